@@ -93,7 +93,9 @@ pub use self::owningorref::OwningOrRef;
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 mod bufferedstream {
-	use std::io::{self, Read, Write};
+	use std::{
+		io::{self, Read, Write}, mem, ptr
+	};
 	#[derive(Debug)]
 	pub struct BufferedStream<T: Read + Write> {
 		stream: io::BufReader<T>,
@@ -135,6 +137,15 @@ mod bufferedstream {
 	pub struct BufferedStreamWriter<'a, T: Read + Write + 'a>(
 		io::BufWriter<&'a mut BufferedStream<T>>,
 	);
+	impl<'a, T: Read + Write + 'a> BufferedStreamWriter<'a, T> {
+		/// Flush, returning rather than panicking on error.
+		pub fn finish(self) -> io::Result<()> {
+			let mut self_ = mem::ManuallyDrop::new(self);
+			let ret = self_.0.flush();
+			unsafe { ptr::drop_in_place(&mut self_.0) };
+			ret
+		}
+	}
 	impl<'a, T: Read + Write + 'a> Write for BufferedStreamWriter<'a, T> {
 		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
 			self.0.write(buf)
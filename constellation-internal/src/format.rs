@@ -1,6 +1,6 @@
 use rand::{self, Rng, SeedableRng};
 use std::{
-	borrow, convert::TryInto, fmt, fs, io::{self, Write}, os::{self, unix::io::IntoRawFd}
+	borrow, collections::HashSet, convert::{Infallible, TryInto}, fmt, fs, io::{self, Write}, os::{self, unix::io::IntoRawFd}, str::FromStr
 };
 
 use super::{DeployOutputEvent, Pid};
@@ -59,6 +59,50 @@ impl<A: Write, B: Write> Writer<A, B> {
 	}
 }
 
+/// Which processes to show the output of, by the label given to them with [`SpawnOptions::label`](crate::SpawnOptions::label).
+///
+/// This is parsed from a comma-separated list of labels, such as `coordinator,-worker`. Labels prefixed with `-` are excluded. If any labels aren't prefixed, only processes with one of them are shown. A label ending in `*` matches any label beginning with what precedes it. The default shows all processes.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct LabelFilter {
+	include: Vec<String>,
+	exclude: Vec<String>,
+}
+impl LabelFilter {
+	/// Whether a process with `label` is shown.
+	pub fn shows(&self, label: Option<&str>) -> bool {
+		let matches = |pattern: &String| {
+			label.map_or(false, |label| {
+				if pattern.ends_with('*') {
+					label.starts_with(&pattern[..pattern.len() - 1])
+				} else {
+					label == pattern
+				}
+			})
+		};
+		(self.include.is_empty() || self.include.iter().any(matches))
+			&& !self.exclude.iter().any(matches)
+	}
+}
+impl FromStr for LabelFilter {
+	type Err = Infallible;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut filter = Self::default();
+		for label in s
+			.split(',')
+			.map(str::trim)
+			.filter(|label| !label.is_empty())
+		{
+			if label.starts_with('-') {
+				filter.exclude.push(label[1..].to_owned());
+			} else {
+				filter.include.push(label.to_owned());
+			}
+		}
+		Ok(filter)
+	}
+}
+
 #[derive(Debug)]
 pub struct Formatter<A: Write, B: Write> {
 	// TODO: if we get half a multi-byte character/combined thing, then something else, then rest of it, it'll be malformatted. deadline cache?
@@ -66,9 +110,13 @@ pub struct Formatter<A: Write, B: Write> {
 	pid: Pid,
 	nl: Option<os::unix::io::RawFd>,
 	style_support: StyleSupport,
+	labels: LabelFilter,
+	hidden: HashSet<Pid>,
 }
 impl<A: Write, B: Write> Formatter<A, B> {
-	pub fn new(pid: Pid, style_support: StyleSupport, stdout: A, stderr: B) -> Self {
+	pub fn new(
+		pid: Pid, style_support: StyleSupport, labels: LabelFilter, stdout: A, stderr: B,
+	) -> Self {
 		eprintln!("{}:", pretty_pid(&pid, true, style_support));
 		Self {
 			writer: Writer {
@@ -79,12 +127,26 @@ impl<A: Write, B: Write> Formatter<A, B> {
 			pid,
 			nl: None,
 			style_support,
+			labels,
+			hidden: HashSet::new(),
 		}
 	}
 
 	#[allow(clippy::too_many_lines)]
 	pub fn write(&mut self, event: &DeployOutputEvent) {
 		match *event {
+			DeployOutputEvent::Started(pid_, ref report) => {
+				if !self.labels.shows(report.label.as_deref()) {
+					let _ = self.hidden.insert(pid_);
+				}
+			}
+			DeployOutputEvent::Spawn(pid_, _)
+			| DeployOutputEvent::Output(pid_, _, _)
+			| DeployOutputEvent::Paused(pid_, _)
+				if self.hidden.contains(&pid_) => {}
+			DeployOutputEvent::Exit(pid_, _) if self.hidden.contains(&pid_) => {
+				let _ = self.hidden.remove(&pid_);
+			}
 			DeployOutputEvent::Spawn(pid_, new_pid) => {
 				assert_ne!(pid_, new_pid);
 				if self.nl.is_some() {
@@ -166,6 +228,30 @@ impl<A: Write, B: Write> Formatter<A, B> {
 					// let fd = unsafe{fs::File::from_raw_fd(fd)};
 				}
 			}
+			DeployOutputEvent::Paused(pid_, paused) => {
+				if self.nl.is_some() {
+					self.writer.write(STDERR, b"\n");
+					self.nl = None;
+				}
+				if pid_ != self.pid {
+					self.pid = pid_;
+					self.writer.write_fmt(
+						STDERR,
+						format_args!("{}:\n", pretty_pid(&self.pid, true, self.style_support)),
+					);
+				}
+				self.writer.write_fmt(
+					STDERR,
+					format_args!(
+						"   {}\n",
+						self.style_support.style().bold().paint(if paused {
+							"paused"
+						} else {
+							"resumed"
+						})
+					),
+				);
+			}
 			DeployOutputEvent::Exit(pid_, exit_code_) => {
 				if self.nl.is_some() {
 					self.writer.write(STDERR, b"\n");
@@ -195,6 +281,7 @@ impl<A: Write, B: Write> Formatter<A, B> {
 				}
 				// self.writer.write_fmt(STDERR, format_args!("   {} {:?}\nremaining: {}\n", self.style_support.style().bold().paint("exited:"), exit_code_, std::slice::SliceConcatExt::join(&*xyz.iter().map(|pid|pretty_pid(pid,false).to_string()).collect::<Vec<_>>(), ",")));
 			}
+			DeployOutputEvent::Summary(_) => (),
 		}
 	}
 }
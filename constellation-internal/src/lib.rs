@@ -27,16 +27,18 @@
 mod ext;
 mod format;
 pub mod msg;
+#[cfg(feature = "protobuf")]
+mod protobuf;
 mod units;
 
 #[cfg(unix)]
-use nix::{fcntl, libc, sys::signal, unistd};
+use nix::{errno, fcntl, libc, sys::signal, unistd};
 use palaver::file::{copy, memfd_create};
 use serde::{Deserialize, Serialize};
 use std::{
-	convert::{TryFrom, TryInto}, env, error::Error, ffi::{CString, OsString}, fmt::{self, Debug, Display}, fs::File, io::{self, Read, Seek, Write}, net::{IpAddr, SocketAddr}, ops, os::unix::{
+	cmp, collections::{BTreeMap, HashMap, HashSet}, convert::{TryFrom, TryInto}, env, error::Error, ffi::{CString, OsString}, fmt::{self, Debug, Display}, fs::File, hash::BuildHasher, io::{self, Read, Seek, Write}, iter, net::{IpAddr, SocketAddr}, ops, os::unix::{
 		ffi::OsStringExt, io::{AsRawFd, FromRawFd, IntoRawFd}
-	}, process::abort, sync::{Arc, Mutex}
+	}, path::PathBuf, process::abort, sync::{Arc, Mutex}, time::{Duration, Instant}
 };
 
 #[cfg(target_family = "unix")]
@@ -121,6 +123,16 @@ pub struct Envs {
 	pub recce: Option<Option<bool>>,
 	pub format: Option<Option<Format>>,
 	pub resources: Option<Option<Resources>>,
+	pub process_limit: Option<Option<usize>>,
+	pub job: Option<Option<u64>>,
+	pub labels: Option<Option<LabelFilter>>,
+	pub check_fds: Option<Option<bool>>,
+	pub seed: Option<Option<u64>>,
+	pub bind: Option<Option<IpAddr>>,
+	pub pool: Option<Option<String>>,
+	pub expected_hash: Option<Option<[u8; 32]>>,
+	pub record: Option<Option<PathBuf>>,
+	pub replay: Option<Option<PathBuf>>,
 }
 impl Envs {
 	pub fn from_env() -> Self {
@@ -152,6 +164,8 @@ impl Envs {
 				.and_then(|x| match &*x.to_ascii_lowercase() {
 					"human" => Some(Format::Human),
 					"json" => Some(Format::Json),
+					#[cfg(feature = "protobuf")]
+					"protobuf" => Some(Format::Protobuf),
 					_ => None,
 				})
 		}); // TODO: use serde?
@@ -160,15 +174,48 @@ impl Envs {
 				.ok()
 				.and_then(|x| serde_json::from_str(&x).ok())
 		});
+		let process_limit = env::var_os("CONSTELLATION_PROCESS_LIMIT")
+			.map(|x| x.into_string().ok().and_then(|x| x.parse().ok()));
+		let job = env::var_os("CONSTELLATION_JOB")
+			.map(|x| x.into_string().ok().and_then(|x| x.parse().ok()));
+		let labels = env::var_os("CONSTELLATION_LABELS")
+			.map(|x| x.into_string().ok().and_then(|x| x.parse().ok()));
+		let seed = env::var_os("CONSTELLATION_SEED")
+			.map(|x| x.into_string().ok().and_then(|x| x.parse().ok()));
+		let bind = env::var_os("CONSTELLATION_BIND")
+			.map(|x| x.into_string().ok().and_then(|x| x.parse().ok()));
+		let pool = env::var_os("CONSTELLATION_POOL").map(|x| x.into_string().ok());
+		let expected_hash = env::var_os("CONSTELLATION_EXPECTED_HASH")
+			.map(|x| x.into_string().ok().and_then(|x| hash_from_hex(&x)));
+		let record = env::var_os("CONSTELLATION_RECORD").map(|x| Some(PathBuf::from(x)));
+		let replay = env::var_os("CONSTELLATION_REPLAY").map(|x| Some(PathBuf::from(x)));
+		let check_fds = env::var_os("CONSTELLATION_CHECK_FDS").map(|x| {
+			x.into_string().ok().and_then(|x| match &*x {
+				"0" => Some(false),
+				"1" => Some(true),
+				_ => None,
+			})
+		});
 		Self {
 			deploy,
 			version,
 			recce,
 			format,
 			resources,
+			process_limit,
+			job,
+			labels,
+			check_fds,
+			seed,
+			bind,
+			pool,
+			expected_hash,
+			record,
+			replay,
 		}
 	}
 
+	#[allow(clippy::too_many_lines)]
 	pub fn from(env: &[(OsString, OsString)]) -> Self {
 		let deploy =
 			env.iter().find_map(|x| {
@@ -212,6 +259,8 @@ impl Envs {
 						|x| match &*x.to_ascii_lowercase() {
 							"human" => Some(Format::Human),
 							"json" => Some(Format::Json),
+							#[cfg(feature = "protobuf")]
+							"protobuf" => Some(Format::Protobuf),
 							_ => None,
 						},
 					))
@@ -231,12 +280,101 @@ impl Envs {
 				None
 			}
 		});
+		let process_limit = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_PROCESS_LIMIT" {
+				Some(x.1.clone().into_string().ok().and_then(|x| x.parse().ok()))
+			} else {
+				None
+			}
+		});
+		let job = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_JOB" {
+				Some(x.1.clone().into_string().ok().and_then(|x| x.parse().ok()))
+			} else {
+				None
+			}
+		});
+		let labels = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_LABELS" {
+				Some(x.1.clone().into_string().ok().and_then(|x| x.parse().ok()))
+			} else {
+				None
+			}
+		});
+		let seed = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_SEED" {
+				Some(x.1.clone().into_string().ok().and_then(|x| x.parse().ok()))
+			} else {
+				None
+			}
+		});
+		let bind = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_BIND" {
+				Some(x.1.clone().into_string().ok().and_then(|x| x.parse().ok()))
+			} else {
+				None
+			}
+		});
+		let pool = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_POOL" {
+				Some(x.1.clone().into_string().ok())
+			} else {
+				None
+			}
+		});
+		let expected_hash = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_EXPECTED_HASH" {
+				Some(
+					x.1.clone()
+						.into_string()
+						.ok()
+						.and_then(|x| hash_from_hex(&x)),
+				)
+			} else {
+				None
+			}
+		});
+		let record = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_RECORD" {
+				Some(Some(PathBuf::from(x.1.clone())))
+			} else {
+				None
+			}
+		});
+		let replay = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_REPLAY" {
+				Some(Some(PathBuf::from(x.1.clone())))
+			} else {
+				None
+			}
+		});
+		let check_fds = env.iter().find_map(|x| {
+			if x.0 == "CONSTELLATION_CHECK_FDS" {
+				Some(x.1.clone().into_string().ok().and_then(|x| match &*x {
+					"0" => Some(false),
+					"1" => Some(true),
+					_ => None,
+				}))
+			} else {
+				None
+			}
+		});
 		Self {
 			deploy,
 			version,
 			recce,
 			format,
 			resources,
+			process_limit,
+			job,
+			labels,
+			check_fds,
+			seed,
+			bind,
+			pool,
+			expected_hash,
+			record,
+			replay,
 		}
 	}
 }
@@ -251,21 +389,28 @@ pub enum Deploy {
 pub enum Format {
 	Human,
 	Json,
+	#[cfg(feature = "protobuf")]
+	Protobuf,
 }
 
-/// Memory and CPU requirements for a process.
+/// Memory, CPU and GPU requirements for a process.
 ///
 /// This is used in allocation of a process, to ensure that sufficient resources are available.
 ///
 /// Best effort is made to enforce these as limits to avoid buggy/greedy processes starving others.
 ///
+/// Prefer constructing it with [`Resources::builder()`] over a struct literal, so that code keeps compiling as new dimensions are added, taking their defaults.
+///
+/// Resources can be added together, and multiplied by a count, to total the requirements of several processes, for example `stage_a + stage_b` or `worker * count`. As with [Mem] and [Cpu], each requirement saturates at the largest quantity representable rather than overflowing, so a total too large for any node is still one no node can fit.
+///
 /// The default is [`RESOURCES_DEFAULT`], which is defined as:
 ///
 /// ```ignore
-/// # use constellation_internal::{Cpu, Mem, Resources};
+/// # use constellation_internal::{Cpu, Gpu, Mem, Resources};
 /// pub const RESOURCES_DEFAULT: Resources = Resources {
 ///     mem: 100 * Mem::MIB, // 100 MiB
 ///     cpu: Cpu::CORE / 16, // 1/16th of a logical CPU core
+///     gpu: 0 * Gpu::DEVICE, // no GPUs
 /// };
 /// ```
 #[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
@@ -274,26 +419,240 @@ pub struct Resources {
 	pub mem: Mem,
 	/// CPU requirement as a fraction of one logical core
 	pub cpu: Cpu,
+	/// GPU requirement as a number of devices
+	///
+	/// A node runs processes requiring at most as many GPUs as it has. Which of the node's devices a process uses is left to it. This is missing from resources serialized before GPUs were supported, in which case it's zero.
+	#[serde(default)]
+	pub gpu: Gpu,
+}
+impl Resources {
+	/// Create a [`ResourcesBuilder`], with each requirement not set on it taken from [`RESOURCES_DEFAULT`].
+	///
+	/// ```ignore
+	/// # use constellation_internal::{Cpu, Mem, Resources};
+	/// let resources = Resources::builder()
+	///     .mem(20 * Mem::MIB)
+	///     .cpu(Cpu::CORE / 4)
+	///     .build();
+	/// ```
+	pub fn builder() -> ResourcesBuilder {
+		ResourcesBuilder {
+			resources: RESOURCES_DEFAULT,
+		}
+	}
 }
 impl Default for Resources {
 	fn default() -> Self {
 		RESOURCES_DEFAULT
 	}
 }
+impl ops::Add for Resources {
+	type Output = Self;
+	fn add(self, other: Self) -> Self {
+		Self {
+			mem: self.mem + other.mem,
+			cpu: self.cpu + other.cpu,
+			gpu: self.gpu + other.gpu,
+		}
+	}
+}
+impl ops::AddAssign for Resources {
+	fn add_assign(&mut self, other: Self) {
+		*self = *self + other;
+	}
+}
+impl ops::Mul<usize> for Resources {
+	type Output = Self;
+	fn mul(self, count: usize) -> Self {
+		Self {
+			mem: self.mem * count,
+			cpu: self.cpu * count,
+			gpu: self.gpu * count,
+		}
+	}
+}
+impl ops::Mul<Resources> for usize {
+	type Output = Resources;
+	fn mul(self, resources: Resources) -> Resources {
+		resources * self
+	}
+}
+impl ops::MulAssign<usize> for Resources {
+	fn mul_assign(&mut self, count: usize) {
+		*self = *self * count;
+	}
+}
+impl iter::Sum for Resources {
+	/// Total the requirements of several processes. This is all zeros, rather than [`RESOURCES_DEFAULT`], if there are none.
+	fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+		iter.fold(
+			Self {
+				mem: Mem(0),
+				cpu: Cpu(0),
+				gpu: Gpu(0),
+			},
+			|a, b| a + b,
+		)
+	}
+}
+
+/// A builder for [Resources], created with [`Resources::builder()`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ResourcesBuilder {
+	resources: Resources,
+}
+impl ResourcesBuilder {
+	/// Set the memory requirement in bytes.
+	#[must_use]
+	pub fn mem(mut self, mem: Mem) -> Self {
+		self.resources.mem = mem;
+		self
+	}
+	/// Set the CPU requirement as a fraction of one logical core.
+	#[must_use]
+	pub fn cpu(mut self, cpu: Cpu) -> Self {
+		self.resources.cpu = cpu;
+		self
+	}
+	/// Set the GPU requirement as a number of devices.
+	#[must_use]
+	pub fn gpu(mut self, gpu: Gpu) -> Self {
+		self.resources.gpu = gpu;
+		self
+	}
+	/// Build the [Resources].
+	pub fn build(self) -> Resources {
+		self.resources
+	}
+}
 /// The [Resources] returned by [`Resources::default()`](Resources::default). Intended to be used as a placeholder in your application until you have a better idea as to resource requirements.
 ///
 /// ```ignore
-/// # use constellation_internal::{Cpu, Mem, Resources};
+/// # use constellation_internal::{Cpu, Gpu, Mem, Resources};
 /// pub const RESOURCES_DEFAULT: Resources = Resources {
 ///     mem: 100 * Mem::MIB, // 100 MiB
 ///     cpu: Cpu::CORE / 16, // 1/16th of a logical CPU core
+///     gpu: 0 * Gpu::DEVICE, // no GPUs
 /// };
 /// ```
 pub const RESOURCES_DEFAULT: Resources = Resources {
 	mem: Mem(100 * 1024 * 2014), // 100 MiB
 	cpu: Cpu(65536 / 16),        // 1/16th of a logical CPU core
+	gpu: Gpu(0),                 // no GPUs
 };
 
+/// Options for spawning a process, beyond its [Resources].
+///
+/// Passed to [`spawn_with()`](spawn_with) and [`try_spawn_with()`](try_spawn_with). The default is what [`spawn()`](spawn) and [`try_spawn()`](try_spawn) use.
+#[derive(Clone, PartialEq, Eq, Default, Serialize, Deserialize, Debug)]
+pub struct SpawnOptions {
+	/// The working directory of the new process, or `None` to inherit it.
+	///
+	/// When running on a fabric, this is the directory on the node the process is allocated to, and `None` means the working directory of the fabric there. If the directory doesn't exist the spawn fails with `CwdNotFound`.
+	pub cwd: Option<PathBuf>,
+	/// Environment variables to set for the new process, on top of those it inherits from the process spawning it.
+	///
+	/// These are applied in order, so a later entry overrides an earlier one with the same key. This suits passing task-specific settings such as a shard index without changing this process's own environment.
+	pub env: Vec<(OsString, OsString)>,
+	/// Inherited environment variables to leave unset for the new process. Variables set with `env` are set regardless.
+	pub env_remove: Vec<OsString>,
+	/// Serialize the closure passed to the new process to a memfd rather than to memory.
+	///
+	/// Otherwise the request to spawn the process, including the serialized closure, is assembled in memory before it's sent. For closures that capture a lot of data, this reduces the peak memory of spawning.
+	pub spill_arg: bool,
+	/// What happens to the new process when the process that spawned it exits.
+	pub on_parent_exit: OnParentExit,
+	/// Existing processes the new process will communicate with.
+	///
+	/// Connections to them are established as the new process starts, before the closure is run, rather than once it creates a `Sender` to or `Receiver` from them. Channels it then creates to them use these connections, so it can start communicating without waiting for connection setup.
+	pub peers: Vec<Pid>,
+	/// Limits on the new process's use of resources not covered by [Resources], applied with `setrlimit`.
+	pub rlimits: RLimits,
+	/// Prefer allocating the new process to the same node as the process spawning it.
+	///
+	/// Processes that communicate a lot benefit from the lower latency. If the node doesn't have capacity for the new process, it's allocated elsewhere as usual. This only has an effect when running on a fabric.
+	pub colocate: bool,
+	/// Prefer allocating the new process to a node matching a [`NodeSelector`], such as the one holding the data it processes.
+	///
+	/// Like `colocate`, which it takes precedence over, this is a hint: if no such node has capacity for the new process, it's allocated elsewhere as usual. This only has an effect when running on a fabric.
+	pub affinity: Option<NodeSelector>,
+	/// Constrain which node the new process is allocated to, relative to other processes.
+	///
+	/// Unlike `colocate` and `affinity` this isn't a hint: if the constraint can't be met by any node the spawn fails with [`Unsatisfiable`](TrySpawnError::Unsatisfiable), and if it can only be met by nodes without capacity the spawn waits or fails as it would for lack of capacity anywhere. It takes precedence over `colocate` and `affinity`, which then only choose among the nodes that meet it. When not running on a fabric there is only one node, so [`Spread`](Placement::Spread) from any processes fails.
+	pub placement: Option<Placement>,
+	/// A label for the new process, such as its role in the job.
+	///
+	/// Human-readable deploy output can be filtered to show only the processes with certain labels, with `--labels` or the `CONSTELLATION_LABELS` env var.
+	pub label: Option<String>,
+	/// The resource pool to allocate the new process from, or `None` to use the pool of the process spawning it, if any.
+	///
+	/// Pools are reserved slices of a cluster's capacity, configured on the master node, so that for example one team's jobs can't starve another's. A pool named like `team/project` is nested within `team`, and draws on its capacity too. If the pool doesn't exist the spawn fails with `UnknownPool`, and if it doesn't have enough free capacity the spawn waits, or fails with `NoCapacity`, just as if the cluster were full. This only has an effect when running on a fabric.
+	pub pool: Option<String>,
+	/// The SHA-256 hash the binary executed for the new process must have, or `None` to use the hash expected for the process spawning it, if any.
+	///
+	/// The node the process is allocated to hashes the binary it received before executing it, and if it doesn't match the spawn fails with `BinaryHashMismatch`, so that a tampered or mismatched binary never runs. A hash can be given for a whole deployment with `deploy --expected-hash`. This only has an effect when running on a fabric.
+	pub expected_hash: Option<[u8; 32]>,
+	/// Forward the new process's stdout and stderr to this process, as well as to the deploy output.
+	///
+	/// This is set by [`spawn_handle()`](spawn_handle), so that the output can be read from the `Child` it returns. Otherwise the forwarded output is discarded.
+	pub forward_output: bool,
+}
+
+/// Limits on a process's use of resources, set with [`SpawnOptions::rlimits`]. `None` leaves a limit as it's inherited.
+///
+/// These are applied with `setrlimit` as the process starts, so as defense in depth they apply regardless of whether the process is in a cgroup. Exceeding them has the usual effect: for example opening a file fails with `EMFILE`, and exceeding the CPU time kills the process with `SIGXCPU`.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Debug)]
+pub struct RLimits {
+	/// The maximum number of open file descriptors, as `RLIMIT_NOFILE`.
+	pub open_files: Option<u64>,
+	/// The maximum size of a core dump in bytes, as `RLIMIT_CORE`. `Some(0)` disables core dumps.
+	pub core_size: Option<u64>,
+	/// The maximum CPU time, as `RLIMIT_CPU`. This is rounded up to whole seconds.
+	pub cpu_time: Option<Duration>,
+	/// The maximum number of threads, as `RLIMIT_NPROC`. This is only applied on Linux, where it counts all threads of the process's user rather than just of this process.
+	pub threads: Option<u64>,
+}
+
+/// The nodes a process would prefer to be allocated to, set with [`SpawnOptions::affinity`].
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum NodeSelector {
+	/// The node with this IP.
+	Host(IpAddr),
+	/// Nodes with this label, as given to them in the fabric's `nodes.toml`.
+	Label(String),
+	/// The node this process is running on.
+	SameAs(Pid),
+}
+
+/// A constraint on which node a process is allocated to, set with [`SpawnOptions::placement`].
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum Placement {
+	/// Allocate to the same node as this process, for example so they can communicate through shared memory.
+	///
+	/// The process must still be running when the new process is scheduled, which for a spawn waiting for capacity is when capacity frees up rather than when it's called. If it has exited the spawn fails with [`Unsatisfiable`](TrySpawnError::Unsatisfiable).
+	CoLocate(Pid),
+	/// Allocate to a node other than those of these processes, for example so a node failing doesn't take down more than one of them.
+	///
+	/// Processes that have exited by the time the new process is scheduled no longer constrain it.
+	Spread(Vec<Pid>),
+}
+
+/// What happens to a process when the process that spawned it exits, set with [`SpawnOptions::on_parent_exit`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum OnParentExit {
+	/// The process is killed. On Linux it is also killed via `PR_SET_PDEATHSIG` if its monitor dies, so it is never left orphaned.
+	Kill,
+	/// The process carries on running, and is still treated as a descendant of its parent's ancestors, so for example cancellation by them still reaches it. This is the default.
+	Detach,
+	/// The process carries on running, and is moved under the bridge, so it's no longer treated as a descendant of its parent's ancestors.
+	Reparent,
+}
+impl Default for OnParentExit {
+	fn default() -> Self {
+		Self::Detach
+	}
+}
+
 /// An error returned by the [`try_spawn()`](try_spawn) method detailing the reason if known.
 #[allow(missing_copy_implementations)]
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -302,6 +661,28 @@ pub enum TrySpawnError {
 	NoCapacity,
 	/// [`try_spawn()`](try_spawn) failed because `constellation::init()` is not called immediately inside main().
 	Recce,
+	/// [`try_spawn()`](try_spawn) failed because the scheduler didn't reply within the timeout set by `set_scheduler_timeout()`.
+	SchedulerTimeout,
+	/// [`try_spawn()`](try_spawn) failed because the connection to the scheduler was lost, and hasn't yet been reestablished. See `set_scheduler_loss_policy()`.
+	SchedulerUnavailable,
+	/// [`try_spawn()`](try_spawn) failed because the working directory given in [`SpawnOptions`] doesn't exist.
+	CwdNotFound,
+	/// [`try_spawn()`](try_spawn) failed because the deployment has already spawned as many processes as its process limit allows.
+	ProcessLimitExceeded,
+	/// [`try_spawn()`](try_spawn) failed because the resource pool given in [`SpawnOptions`] isn't configured on the cluster.
+	UnknownPool,
+	/// [`try_spawn()`](try_spawn) failed because the binary to be executed doesn't have the hash given in [`SpawnOptions`].
+	BinaryHashMismatch,
+	/// [`try_spawn()`](try_spawn) failed because the new process couldn't execute the binary, for example because it has been deleted. `errno` is the error `execve()` failed with.
+	ExecFailed {
+		/// The error number.
+		errno: i32,
+	},
+	/// [`try_spawn()`](try_spawn) failed because no node can meet the [`Placement`] given in [`SpawnOptions`].
+	Unsatisfiable {
+		/// Why the constraint can't be met.
+		reason: String,
+	},
 	/// [`try_spawn()`](try_spawn) failed for unknown reasons.
 	Unknown,
 	#[doc(hidden)]
@@ -314,6 +695,28 @@ pub enum TrySpawnError {
 pub enum SpawnError {
 	/// [`spawn()`](spawn) failed because `constellation::init()` is not called immediately inside main().
 	Recce,
+	/// [`spawn()`](spawn) failed because the scheduler didn't reply within the timeout set by `set_scheduler_timeout()`.
+	SchedulerTimeout,
+	/// [`spawn()`](spawn) failed because the connection to the scheduler was lost, and hasn't yet been reestablished. See `set_scheduler_loss_policy()`.
+	SchedulerUnavailable,
+	/// [`spawn()`](spawn) failed because the working directory given in [`SpawnOptions`] doesn't exist.
+	CwdNotFound,
+	/// [`spawn()`](spawn) failed because the deployment has already spawned as many processes as its process limit allows.
+	ProcessLimitExceeded,
+	/// [`spawn()`](spawn) failed because the resource pool given in [`SpawnOptions`] isn't configured on the cluster.
+	UnknownPool,
+	/// [`spawn()`](spawn) failed because the binary to be executed doesn't have the hash given in [`SpawnOptions`].
+	BinaryHashMismatch,
+	/// [`spawn()`](spawn) failed because the new process couldn't execute the binary, for example because it has been deleted. `errno` is the error `execve()` failed with.
+	ExecFailed {
+		/// The error number.
+		errno: i32,
+	},
+	/// [`spawn()`](spawn) failed because no node can meet the [`Placement`] given in [`SpawnOptions`].
+	Unsatisfiable {
+		/// Why the constraint can't be met.
+		reason: String,
+	},
 	/// [`spawn()`](spawn) failed for unknown reasons.
 	Unknown,
 	#[doc(hidden)]
@@ -323,6 +726,14 @@ impl From<SpawnError> for TrySpawnError {
 	fn from(error: SpawnError) -> Self {
 		match error {
 			SpawnError::Recce => Self::Recce,
+			SpawnError::SchedulerTimeout => Self::SchedulerTimeout,
+			SpawnError::SchedulerUnavailable => Self::SchedulerUnavailable,
+			SpawnError::CwdNotFound => Self::CwdNotFound,
+			SpawnError::ProcessLimitExceeded => Self::ProcessLimitExceeded,
+			SpawnError::UnknownPool => Self::UnknownPool,
+			SpawnError::BinaryHashMismatch => Self::BinaryHashMismatch,
+			SpawnError::ExecFailed { errno } => Self::ExecFailed { errno },
+			SpawnError::Unsatisfiable { reason } => Self::Unsatisfiable { reason },
 			SpawnError::Unknown => Self::Unknown,
 			SpawnError::__Nonexhaustive => unreachable!(),
 		}
@@ -335,6 +746,14 @@ impl TryFrom<TrySpawnError> for SpawnError {
 		match error {
 			TrySpawnError::NoCapacity => Err(()),
 			TrySpawnError::Recce => Ok(Self::Recce),
+			TrySpawnError::SchedulerTimeout => Ok(Self::SchedulerTimeout),
+			TrySpawnError::SchedulerUnavailable => Ok(Self::SchedulerUnavailable),
+			TrySpawnError::CwdNotFound => Ok(Self::CwdNotFound),
+			TrySpawnError::ProcessLimitExceeded => Ok(Self::ProcessLimitExceeded),
+			TrySpawnError::UnknownPool => Ok(Self::UnknownPool),
+			TrySpawnError::BinaryHashMismatch => Ok(Self::BinaryHashMismatch),
+			TrySpawnError::ExecFailed { errno } => Ok(Self::ExecFailed { errno }),
+			TrySpawnError::Unsatisfiable { reason } => Ok(Self::Unsatisfiable { reason }),
 			TrySpawnError::Unknown => Ok(Self::Unknown),
 			TrySpawnError::__Nonexhaustive => unreachable!(),
 		}
@@ -351,6 +770,40 @@ impl Display for TrySpawnError {
 				f,
 				"try_spawn() because constellation::init() is not called immediately inside main()"
 			),
+			Self::SchedulerTimeout => write!(
+				f,
+				"try_spawn() failed because the scheduler didn't reply in time"
+			),
+			Self::SchedulerUnavailable => write!(
+				f,
+				"try_spawn() failed because the connection to the scheduler was lost"
+			),
+			Self::CwdNotFound => write!(
+				f,
+				"try_spawn() failed because the working directory doesn't exist"
+			),
+			Self::ProcessLimitExceeded => write!(
+				f,
+				"try_spawn() failed because the process limit was exceeded"
+			),
+			Self::UnknownPool => write!(
+				f,
+				"try_spawn() failed because the resource pool doesn't exist"
+			),
+			Self::BinaryHashMismatch => write!(
+				f,
+				"try_spawn() failed because the binary doesn't have the expected hash"
+			),
+			Self::ExecFailed { errno } => write!(
+				f,
+				"try_spawn() failed because the new process couldn't be executed: {}",
+				errno::Errno::from_i32(*errno).desc()
+			),
+			Self::Unsatisfiable { reason } => write!(
+				f,
+				"try_spawn() failed because the placement constraint can't be met: {}",
+				reason
+			),
 			Self::Unknown => write!(f, "try_spawn() failed for unknown reasons"),
 			Self::__Nonexhaustive => unreachable!(),
 		}
@@ -368,6 +821,40 @@ impl Display for SpawnError {
 				f,
 				"spawn() because constellation::init() is not called immediately inside main()"
 			),
+			Self::SchedulerTimeout => {
+				write!(
+					f,
+					"spawn() failed because the scheduler didn't reply in time"
+				)
+			}
+			Self::SchedulerUnavailable => write!(
+				f,
+				"spawn() failed because the connection to the scheduler was lost"
+			),
+			Self::CwdNotFound => write!(
+				f,
+				"spawn() failed because the working directory doesn't exist"
+			),
+			Self::ProcessLimitExceeded => {
+				write!(f, "spawn() failed because the process limit was exceeded")
+			}
+			Self::UnknownPool => {
+				write!(f, "spawn() failed because the resource pool doesn't exist")
+			}
+			Self::BinaryHashMismatch => write!(
+				f,
+				"spawn() failed because the binary doesn't have the expected hash"
+			),
+			Self::ExecFailed { errno } => write!(
+				f,
+				"spawn() failed because the new process couldn't be executed: {}",
+				errno::Errno::from_i32(*errno).desc()
+			),
+			Self::Unsatisfiable { reason } => write!(
+				f,
+				"spawn() failed because the placement constraint can't be met: {}",
+				reason
+			),
 			Self::Unknown => write!(f, "spawn() failed for unknown reasons"),
 			Self::__Nonexhaustive => unreachable!(),
 		}
@@ -381,6 +868,51 @@ impl Debug for SpawnError {
 impl Error for TrySpawnError {}
 impl Error for SpawnError {}
 
+/// An error returned by the [`update_resources()`](update_resources) method detailing the reason if known.
+#[allow(missing_copy_implementations)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResourceError {
+	/// [`update_resources()`](update_resources) failed because this process's node, or its resource pool, doesn't have enough free resources for it to grow.
+	NoCapacity,
+	/// [`update_resources()`](update_resources) failed because the connection to the scheduler was lost, and hasn't yet been reestablished. See `set_scheduler_loss_policy()`.
+	SchedulerUnavailable,
+	/// [`update_resources()`](update_resources) failed for unknown reasons.
+	Unknown,
+	#[doc(hidden)]
+	__Nonexhaustive,
+}
+impl Display for ResourceError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::NoCapacity => write!(
+				f,
+				"update_resources() failed because the node doesn't have enough free resources"
+			),
+			Self::SchedulerUnavailable => write!(
+				f,
+				"update_resources() failed because the connection to the scheduler was lost"
+			),
+			Self::Unknown => write!(f, "update_resources() failed for unknown reasons"),
+			Self::__Nonexhaustive => unreachable!(),
+		}
+	}
+}
+impl Debug for ResourceError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		Display::fmt(self, f)
+	}
+}
+impl Error for ResourceError {}
+
+/// The reason a spawn is waiting to be allocated, passed to the callback given to [`spawn_with_progress()`](spawn_with_progress).
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum PendingReason {
+	/// No node has enough free resources. The request is queued at `position`, where 0 is next, until processes exit and free some up.
+	NoCapacity { position: usize },
+	#[doc(hidden)]
+	__Nonexhaustive,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(/*tag = "event", */rename_all = "lowercase")]
 pub enum FabricOutputEvent {
@@ -391,17 +923,140 @@ pub enum FabricOutputEvent {
 	// Exit(Pid, ExitStatus),
 }
 
+/// Details of a process, reported once it has finished initializing.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct StartupReport {
+	/// The process that spawned this one, if any.
+	pub parent: Option<Pid>,
+	/// The resources reserved for this process.
+	pub resources: Resources,
+	/// The label given to this process with [`SpawnOptions::label`], if any.
+	pub label: Option<String>,
+	/// The version of `constellation-rs` the process was built with.
+	pub version: String,
+}
+
+/// A summary of a deployment, emitted as the final [`DeployOutputEvent`] once all of its processes have exited.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct DeploySummary {
+	/// How many processes ran, including the initial one.
+	pub processes: usize,
+	/// How many processes were given each label with [`SpawnOptions::label`]. Unlabelled processes aren't counted.
+	pub labels: BTreeMap<String, usize>,
+	/// How many processes exited with each status, most common first.
+	pub exits: Vec<(ExitStatus, usize)>,
+	/// How long the deployment ran for.
+	pub runtime: Duration,
+	/// The most processes that were running at once.
+	pub peak_processes: usize,
+}
+
+/// Aggregates the [`DeployOutputEvent`]s of a deployment into a [`DeploySummary`].
+#[derive(Debug)]
+pub struct Summarizer {
+	start: Instant,
+	seen: HashSet<Pid>,
+	exited: usize,
+	summary: DeploySummary,
+}
+impl Summarizer {
+	pub fn new() -> Self {
+		Self {
+			start: Instant::now(),
+			seen: HashSet::new(),
+			exited: 0,
+			summary: DeploySummary {
+				processes: 0,
+				labels: BTreeMap::new(),
+				exits: Vec::new(),
+				runtime: Duration::new(0, 0),
+				peak_processes: 0,
+			},
+		}
+	}
+	/// Whether any process that has been observed is still running.
+	pub fn running(&self) -> bool {
+		self.seen.len() > self.exited
+	}
+	pub fn observe(&mut self, event: &DeployOutputEvent) {
+		match event {
+			DeployOutputEvent::Spawn(pid, new_pid) => {
+				self.see(*pid);
+				self.see(*new_pid);
+			}
+			DeployOutputEvent::Started(pid, report) => {
+				self.see(*pid);
+				if let Some(label) = &report.label {
+					*self.summary.labels.entry(label.clone()).or_insert(0) += 1;
+				}
+			}
+			DeployOutputEvent::Output(pid, _, _) | DeployOutputEvent::Paused(pid, _) => {
+				self.see(*pid);
+			}
+			DeployOutputEvent::Exit(pid, exit_code) => {
+				self.see(*pid);
+				self.exited += 1;
+				let exits = &mut self.summary.exits;
+				match exits.iter_mut().find(|(status, _)| status == exit_code) {
+					Some((_, count)) => *count += 1,
+					None => exits.push((*exit_code, 1)),
+				}
+			}
+			DeployOutputEvent::Summary(_) => (),
+		}
+	}
+	fn see(&mut self, pid: Pid) {
+		if self.seen.insert(pid) {
+			self.summary.processes += 1;
+			self.summary.peak_processes = self
+				.summary
+				.peak_processes
+				.max(self.seen.len() - self.exited);
+		}
+	}
+	pub fn finish(mut self) -> DeploySummary {
+		self.summary.runtime = self.start.elapsed();
+		self.summary
+			.exits
+			.sort_by_key(|&(_, count)| cmp::Reverse(count));
+		self.summary
+	}
+}
+impl Default for Summarizer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(/*tag = "event", */rename_all = "lowercase")]
 pub enum DeployOutputEvent {
 	Spawn(Pid, Pid),
+	Started(Pid, StartupReport),
 	Output(Pid, Fd, Vec<u8>),
+	Paused(Pid, bool),
 	Exit(Pid, ExitStatus),
+	Summary(DeploySummary),
+}
+impl DeployOutputEvent {
+	/// Write this event to `writer` in a machine-readable `format`.
+	pub fn write<W: Write>(&self, format: Format, mut writer: W) -> io::Result<()> {
+		match format {
+			Format::Json => {
+				serde_json::to_writer(&mut writer, self)?;
+				writer.write_all(b"\n")
+			}
+			#[cfg(feature = "protobuf")]
+			Format::Protobuf => self.write_protobuf(writer),
+			Format::Human => panic!("DeployOutputEvent::write() called with Format::Human"),
+		}
+	}
 }
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum DeployInputEvent {
 	Input(Pid, Fd, Vec<u8>),
 	Kill(Option<Pid>),
+	Signal(Pid, Signal),
 }
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
 pub enum ExitStatus {
@@ -423,6 +1078,28 @@ impl ExitStatus {
 			None
 		}
 	}
+	/// Whether the process exited successfully, with a status of 0.
+	pub fn is_success(&self) -> bool {
+		self.success()
+	}
+	/// The status the process exited with, or `None` if it was terminated by a signal or its status is indeterminate.
+	#[allow(clippy::cast_possible_wrap)] // as std::process::ExitStatus::code() does on Windows
+	pub fn code(&self) -> Option<i32> {
+		match *self {
+			Self::Success => Some(0),
+			Self::Error(ExitStatusError::Unix(ExitStatusUnix::Status(code))) => Some(code.into()),
+			Self::Error(ExitStatusError::Windows(code)) => Some(code as i32),
+			Self::Error(_) => None,
+		}
+	}
+	/// The number of the signal that terminated the process, for example 9 for `SIGKILL` as is typical of it running out of memory, or `None` if it exited of its own accord.
+	pub fn signal(&self) -> Option<i32> {
+		if let Self::Error(ExitStatusError::Unix(ExitStatusUnix::Signal(signal))) = *self {
+			Some(signal::Signal::from(signal) as i32)
+		} else {
+			None
+		}
+	}
 	pub fn from_unix_status(s: u8) -> Self {
 		if s == 0 {
 			Self::Success
@@ -611,14 +1288,32 @@ impl From<ExitStatus> for i32 {
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum ProcessOutputEvent {
-	Spawn(Pid),
+	Spawn(Pid, OnParentExit, bool), // a process has been spawned, and whether to forward its output to this one
+	Started(StartupReport),
 	Output(Fd, Vec<u8>),
 	Exit(ExitStatus),
+	Cancel,              // cancel all descendants
+	Signal(Pid, Signal), // deliver a signal to another process
+	Pause(Pid),          // stop another process
+	Resume(Pid),         // continue another stopped process
+	Paused(bool),        // this process has been stopped or continued
+	Watch(Pid),          // report another process's exit to this one
+	Kill(Pid, Signal),   // kill another process with a signal
+	Input(Pid, Fd, Vec<u8>), // write to another process's stdin
+	Reach(Pid),              // report when another process has started, or has exited, to this one
 }
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum ProcessInputEvent {
 	Input(Fd, Vec<u8>),
 	Kill,
+	Cancel,
+	Signal(Signal),
+	Pause,
+	Resume,
+	Exited(Pid, ExitStatus), // a process being watched has exited
+	Killed(Pid, bool),       // whether a process this one asked to kill was running
+	Output(Pid, Fd, Vec<u8>), // output of a process whose output is forwarded to this one
+	Reachable(Pid, bool), // whether a process this one asked to reach had started, rather than exited
 }
 
 /////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -686,6 +1381,27 @@ pub fn file_from_reader<R: Read>(
 
 /////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Parse a SHA-256 hash, as passed in `CONSTELLATION_EXPECTED_HASH`, from 64 hex digits.
+pub fn hash_from_hex(hex: &str) -> Option<[u8; 32]> {
+	if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+		return None;
+	}
+	let mut hash = [0; 32];
+	for (i, byte) in hash.iter_mut().enumerate() {
+		*byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+	}
+	Some(hash)
+}
+
+/// Format a SHA-256 hash as 64 hex digits, as parsed by [`hash_from_hex()`].
+pub fn hash_to_hex(hash: &[u8; 32]) -> String {
+	hash.iter()
+		.fold(String::with_capacity(64), |mut hex, byte| {
+			fmt::Write::write_fmt(&mut hex, format_args!("{:02x}", byte)).unwrap();
+			hex
+		})
+}
+
 pub fn map_bincode_err(err: bincode::Error) -> io::Error {
 	match *err {
 		bincode::ErrorKind::Io(err) => err,
@@ -732,6 +1448,19 @@ fn abort_on_unwind_<F: FnOnce() -> T, T>(f: F) -> T {
 	})
 }
 
+/// Whether `ancestor` spawned `pid`, or spawned a process that did, and so on, given a map of each process to its parent.
+pub fn is_descendant<S: BuildHasher>(
+	parents: &HashMap<Pid, Pid, S>, mut pid: Pid, ancestor: Pid,
+) -> bool {
+	while let Some(&parent) = parents.get(&pid) {
+		if parent == ancestor {
+			return true;
+		}
+		pid = parent;
+	}
+	false
+}
+
 #[must_use]
 #[inline]
 pub fn abort_on_unwind<F: FnOnce() -> T, T>(f: F) -> impl FnOnce() -> T {
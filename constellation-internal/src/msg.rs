@@ -1,16 +1,70 @@
 use ::serde::{Deserialize, Serialize};
+#[cfg(feature = "distribute_binaries")]
+use std::collections::VecDeque;
 #[cfg(not(feature = "distribute_binaries"))]
 use std::marker::PhantomData;
 use std::{
-	ffi::OsString, net::{IpAddr, SocketAddr}
+	ffi::OsString, net::{IpAddr, SocketAddr}, path::PathBuf
 };
 
-use crate::{Pid, Resources};
+use crate::{
+	NodeSelector, OnParentExit, PendingReason, Pid, Placement, RLimits, ResourceError, Resources, TrySpawnError
+};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct SchedulerArg {
 	pub ip: IpAddr,
 	pub scheduler: Pid,
+	/// The hash of the binary this process was spawned from. The scheduler keeps it cached while the process is running, so the process's own spawn requests needn't send it.
+	#[cfg(feature = "distribute_binaries")]
+	pub binary: BinaryHash,
+}
+
+/// The SHA-1 of a binary, by which the scheduler and fabric nodes cache it.
+#[cfg(feature = "distribute_binaries")]
+pub type BinaryHash = [u8; 20];
+
+/// The binaries cached by a fabric node, least recently used first.
+///
+/// The scheduler mirrors each node's cache by making the same updates in the same order, that is, one per [`FabricRequest`] sent to the node. That way it knows which binaries the node already has without them having to tell it, and so never sends a request for a binary the node has evicted.
+#[cfg(feature = "distribute_binaries")]
+#[derive(Debug)]
+pub struct BinaryCache<T> {
+	entries: VecDeque<(BinaryHash, T)>,
+}
+#[cfg(feature = "distribute_binaries")]
+impl<T> BinaryCache<T> {
+	const CAPACITY: usize = 8;
+
+	pub fn new() -> Self {
+		Self {
+			entries: VecDeque::with_capacity(Self::CAPACITY),
+		}
+	}
+
+	/// Look up a binary, marking it as most recently used.
+	pub fn get(&mut self, hash: &BinaryHash) -> Option<&T> {
+		let i = self.entries.iter().position(|(hash_, _)| hash_ == hash)?;
+		let entry = self.entries.remove(i).unwrap();
+		self.entries.push_back(entry);
+		self.entries.back().map(|(_, t)| t)
+	}
+
+	/// Insert a binary as most recently used, evicting the least recently used if full.
+	pub fn insert(&mut self, hash: BinaryHash, t: T) {
+		if let Some(i) = self.entries.iter().position(|(hash_, _)| *hash_ == hash) {
+			let _ = self.entries.remove(i).unwrap();
+		} else if self.entries.len() == Self::CAPACITY {
+			let _ = self.entries.pop_front().unwrap();
+		}
+		self.entries.push_back((hash, t));
+	}
+}
+#[cfg(feature = "distribute_binaries")]
+impl<T> Default for BinaryCache<T> {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -21,6 +75,12 @@ pub struct SpawnArg<T> {
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct SpawnArgSub<T> {
 	pub parent: Pid,
+	pub on_parent_exit: OnParentExit,
+	pub peers: Vec<Pid>,
+	pub rlimits: RLimits,
+	pub child_init: Option<Vec<u8>>,
+	pub label: Option<String>,
+	pub seed: u64,
 	pub f: T,
 }
 
@@ -40,15 +100,46 @@ where
 	pub args: Vec<OsString>,
 	/// The environment variables passed to the process.
 	pub vars: Vec<(OsString, OsString)>,
+	/// The working directory of the process, or `None` to use the fabric's.
+	pub cwd: Option<PathBuf>,
+	/// The nodes to prefer allocating the process to, if one has capacity.
+	pub near: Option<NodeSelector>,
+	/// The constraint on which nodes the process may be allocated to, which fails the request if it can't be met.
+	pub placement: Option<Placement>,
 	/// An extra argument passed to the process on a special file descriptor.
 	pub arg: A,
-	/// The process to spawn.
+	/// The hash of `binary`. This is `None` when a full binary is sent to the scheduler, which calculates it.
 	#[cfg(feature = "distribute_binaries")]
-	pub binary: B,
+	pub binary_hash: Option<BinaryHash>,
+	/// The process to spawn, or `None` if the recipient already has it cached under `binary_hash`.
+	#[cfg(feature = "distribute_binaries")]
+	pub binary: Option<B>,
 	#[cfg(not(feature = "distribute_binaries"))]
 	pub binary: PhantomData<B>,
 }
 
+/// This is a request made by a process to the scheduler.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum SchedulerRequest {
+	/// Spawn a process. This is followed by a [`FabricRequest`].
+	Spawn,
+	/// Spawn this many processes, all of them or none. This is followed by a [`FabricRequest`] for each.
+	SpawnMany(usize),
+	/// Change the resources reserved for a running process.
+	UpdateResources(Pid, Resources),
+}
+
+/// This is the scheduler's reply to a [`SchedulerRequest`]. For a spawn, zero or more `Pending` are followed by a `Done`; for a batch, zero or more `Pending` are followed by a `Done` for each process in order.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum SchedulerReply {
+	/// The process is still waiting to be allocated.
+	Pending(PendingReason),
+	/// The process has been spawned, or couldn't be.
+	Done(Result<Pid, TrySpawnError>),
+	/// The process's resources have been updated, or couldn't be.
+	Updated(Result<(), ResourceError>),
+}
+
 /// This is the request made by `deploy` to the `bridge`.
 #[derive(Debug)]
 pub struct BridgeRequest<A, B>
@@ -91,6 +182,13 @@ mod serde {
 		fn next_element_seed<'de, S, R>(
 			self_: &mut S, file_seed: FileSeed<R>,
 		) -> Result<Option<Self>, S::Error>
+		where
+			S: SeqAccess<'de>,
+			R: Read,
+			Self: Sized;
+		fn next_element_seed_option<'de, S, R>(
+			self_: &mut S, file_seed: FileSeed<R>,
+		) -> Result<Option<Option<Self>>, S::Error>
 		where
 			S: SeqAccess<'de>,
 			R: Read,
@@ -111,6 +209,16 @@ mod serde {
 		{
 			self_.next_element_seed(file_seed)
 		}
+		fn next_element_seed_option<'de, S, R>(
+			self_: &mut S, file_seed: FileSeed<R>,
+		) -> Result<Option<Option<Self>>, S::Error>
+		where
+			S: SeqAccess<'de>,
+			R: Read,
+			Self: Sized,
+		{
+			self_.next_element_seed(OptionSeed(file_seed))
+		}
 		fn as_serializer<'a, W: Write>(
 			&'a self, writer: &'a UnsafeCell<W>,
 		) -> PoorGat<FileSerializer<'a, W>, &'a serde_bytes::Bytes> {
@@ -131,6 +239,18 @@ mod serde {
 				.next_element::<serde_bytes::ByteBuf>()
 				.map(|x| x.map(serde_bytes::ByteBuf::into_vec))
 		}
+		fn next_element_seed_option<'de, S, R>(
+			self_: &mut S, _file_seed: FileSeed<R>,
+		) -> Result<Option<Option<Self>>, S::Error>
+		where
+			S: SeqAccess<'de>,
+			R: Read,
+			Self: Sized,
+		{
+			self_
+				.next_element::<Option<serde_bytes::ByteBuf>>()
+				.map(|x| x.map(|x| x.map(serde_bytes::ByteBuf::into_vec)))
+		}
 		fn as_serializer<'a, W: Write>(
 			&'a self, _writer: &'a UnsafeCell<W>,
 		) -> PoorGat<FileSerializer<'a, W>, &'a serde_bytes::Bytes> {
@@ -171,15 +291,27 @@ mod serde {
 		where
 			S: Serializer,
 		{
-			let mut state = serializer.serialize_tuple(7)?;
+			let mut state = serializer.serialize_tuple(11)?;
 			state.serialize_element(&self.block)?;
 			state.serialize_element(&self.resources)?;
 			state.serialize_element(&self.bind)?;
 			state.serialize_element(&self.args)?;
 			state.serialize_element(&self.vars)?;
+			state.serialize_element(&self.cwd)?;
+			state.serialize_element(&self.near)?;
+			state.serialize_element(&self.placement)?;
 			state.serialize_element(&serde_bytes::Bytes::new(&self.arg))?;
 			#[cfg(feature = "distribute_binaries")]
-			state.serialize_element(&serde_bytes::Bytes::new(&self.binary))?;
+			state.serialize_element(&self.binary_hash)?;
+			#[cfg(feature = "distribute_binaries")]
+			state.serialize_element(
+				&self
+					.binary
+					.as_ref()
+					.map(|binary| serde_bytes::Bytes::new(binary)),
+			)?;
+			#[cfg(not(feature = "distribute_binaries"))]
+			state.serialize_element(&PhantomData::<()>)?;
 			#[cfg(not(feature = "distribute_binaries"))]
 			state.serialize_element(&PhantomData::<Vec<u8>>)?;
 			state.end()
@@ -217,15 +349,28 @@ mod serde {
 		where
 			S: Serializer,
 		{
-			let mut state = serializer.serialize_tuple(7)?;
+			let mut state = serializer.serialize_tuple(11)?;
 			state.serialize_element(&self.value.block)?;
 			state.serialize_element(&self.value.resources)?;
 			state.serialize_element(&self.value.bind)?;
 			state.serialize_element(&self.value.args)?;
 			state.serialize_element(&self.value.vars)?;
+			state.serialize_element(&self.value.cwd)?;
+			state.serialize_element(&self.value.near)?;
+			state.serialize_element(&self.value.placement)?;
 			state.serialize_element(&self.value.arg.as_serializer(&self.writer))?;
 			#[cfg(feature = "distribute_binaries")]
-			state.serialize_element(&self.value.binary.as_serializer(&self.writer))?;
+			state.serialize_element(&self.value.binary_hash)?;
+			#[cfg(feature = "distribute_binaries")]
+			state.serialize_element(
+				&self
+					.value
+					.binary
+					.as_ref()
+					.map(|binary| binary.as_serializer(&self.writer)),
+			)?;
+			#[cfg(not(feature = "distribute_binaries"))]
+			state.serialize_element(&PhantomData::<()>)?;
 			#[cfg(not(feature = "distribute_binaries"))]
 			state.serialize_element(&PhantomData::<B>)?;
 			state.end()
@@ -299,7 +444,7 @@ mod serde {
 		where
 			D: Deserializer<'de>,
 		{
-			deserializer.deserialize_tuple(7, FabricRequestVisitor)
+			deserializer.deserialize_tuple(11, FabricRequestVisitor)
 		}
 	}
 	struct FabricRequestVisitor;
@@ -328,22 +473,48 @@ mod serde {
 			let vars = seq
 				.next_element()?
 				.ok_or_else(|| de::Error::invalid_length(4, &self))?;
+			let cwd = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(5, &self))?;
+			let near = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(6, &self))?;
+			let placement = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(7, &self))?;
 			let arg = seq
 				.next_element::<serde_bytes::ByteBuf>()?
-				.ok_or_else(|| de::Error::invalid_length(5, &self))?
+				.ok_or_else(|| de::Error::invalid_length(8, &self))?
 				.into_vec();
-			let binary = seq
+			#[cfg(feature = "distribute_binaries")]
+			let binary_hash = seq
 				.next_element()?
-				.ok_or_else(|| de::Error::invalid_length(6, &self))?;
+				.ok_or_else(|| de::Error::invalid_length(9, &self))?;
+			#[cfg(not(feature = "distribute_binaries"))]
+			let PhantomData::<()> = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(9, &self))?;
 			#[cfg(feature = "distribute_binaries")]
-			let binary = serde_bytes::ByteBuf::into_vec(binary);
+			let binary = seq
+				.next_element::<Option<serde_bytes::ByteBuf>>()?
+				.ok_or_else(|| de::Error::invalid_length(10, &self))?
+				.map(serde_bytes::ByteBuf::into_vec);
+			#[cfg(not(feature = "distribute_binaries"))]
+			let binary = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(10, &self))?;
 			Ok(FabricRequest {
 				block,
 				resources,
 				bind,
 				args,
 				vars,
+				cwd,
+				near,
+				placement,
 				arg,
+				#[cfg(feature = "distribute_binaries")]
+				binary_hash,
 				binary,
 			})
 		}
@@ -420,7 +591,7 @@ mod serde {
 		where
 			D: Deserializer<'de>,
 		{
-			deserializer.deserialize_tuple(7, self)
+			deserializer.deserialize_tuple(11, self)
 		}
 	}
 	impl<'de, R, A, B> Visitor<'de> for FabricRequestSeed<R, A, B>
@@ -453,6 +624,15 @@ mod serde {
 			let vars = seq
 				.next_element()?
 				.ok_or_else(|| de::Error::invalid_length(4, &self))?;
+			let cwd = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(5, &self))?;
+			let near = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(6, &self))?;
+			let placement = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(7, &self))?;
 			let arg = A::next_element_seed(
 				&mut seq,
 				FileSeed {
@@ -462,9 +642,17 @@ mod serde {
 					seal: false,
 				},
 			)?
-			.ok_or_else(|| de::Error::invalid_length(5, &self))?;
+			.ok_or_else(|| de::Error::invalid_length(8, &self))?;
 			#[cfg(feature = "distribute_binaries")]
-			let binary = B::next_element_seed(
+			let binary_hash = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(9, &self))?;
+			#[cfg(not(feature = "distribute_binaries"))]
+			let PhantomData::<()> = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(9, &self))?;
+			#[cfg(feature = "distribute_binaries")]
+			let binary = B::next_element_seed_option(
 				&mut seq,
 				FileSeed {
 					reader: &mut self.reader,
@@ -473,17 +661,22 @@ mod serde {
 					seal: true,
 				},
 			)?
-			.ok_or_else(|| de::Error::invalid_length(6, &self))?;
+			.ok_or_else(|| de::Error::invalid_length(10, &self))?;
 			#[cfg(not(feature = "distribute_binaries"))]
 			let binary = seq.next_element()?
-				.ok_or_else(|| de::Error::invalid_length(6, &self))?;
+				.ok_or_else(|| de::Error::invalid_length(10, &self))?;
 			Ok(FabricRequest {
 				block,
 				resources,
 				bind,
 				args,
 				vars,
+				cwd,
+				near,
+				placement,
 				arg,
+				#[cfg(feature = "distribute_binaries")]
+				binary_hash,
 				binary,
 			})
 		}
@@ -600,6 +793,43 @@ mod serde {
 		}
 	}
 
+	/// Deserialize an `Option` whose `Some` contents are deserialized with the wrapped seed.
+	struct OptionSeed<S>(S);
+	impl<'de, S> DeserializeSeed<'de> for OptionSeed<S>
+	where
+		S: DeserializeSeed<'de>,
+	{
+		type Value = Option<S::Value>;
+		fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+		where
+			D: Deserializer<'de>,
+		{
+			deserializer.deserialize_option(self)
+		}
+	}
+	impl<'de, S> Visitor<'de> for OptionSeed<S>
+	where
+		S: DeserializeSeed<'de>,
+	{
+		type Value = Option<S::Value>;
+		fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+			formatter.write_str("an option")
+		}
+
+		fn visit_none<E>(self) -> Result<Self::Value, E>
+		where
+			E: de::Error,
+		{
+			Ok(None)
+		}
+		fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+		where
+			D: Deserializer<'de>,
+		{
+			self.0.deserialize(deserializer).map(Some)
+		}
+	}
+
 	// #[allow(missing_debug_implementations)]
 	// struct RefCellReader<R>(RefCell<R>);
 	// impl<R> RefCellReader<R> {
@@ -0,0 +1,291 @@
+//! Length-delimited protobuf encoding of [`DeployOutputEvent`]s, for `--format protobuf`.
+//!
+//! The messages below are equivalent to this schema:
+//!
+//! ```proto
+//! syntax = "proto3";
+//! package constellation;
+//!
+//! message Pid {
+//!   bytes key = 1;
+//!   string ip = 2;
+//!   uint32 port = 3;
+//! }
+//! message DeployOutputEvent {
+//!   oneof event {
+//!     Spawn spawn = 1;
+//!     Output output = 2;
+//!     Exit exit = 3;
+//!     Started started = 4;
+//!     Paused paused = 5;
+//!     Summary summary = 6;
+//!   }
+//! }
+//! message Spawn {
+//!   Pid pid = 1;
+//!   Pid new_pid = 2;
+//! }
+//! message Started {
+//!   Pid pid = 1;
+//!   Pid parent = 2; // unset for the initial process
+//!   uint64 mem = 3; // bytes
+//!   uint32 cpu = 4; // 65536ths of a logical core
+//!   string version = 5;
+//!   string label = 6; // empty if none
+//! }
+//! message Paused {
+//!   Pid pid = 1;
+//!   bool paused = 2; // false once resumed
+//! }
+//! message Output {
+//!   Pid pid = 1;
+//!   int32 fd = 2;
+//!   bytes data = 3; // empty for EOF
+//!   bool eof = 4;
+//! }
+//! message Exit {
+//!   Pid pid = 1;
+//!   ExitStatus status = 2;
+//! }
+//! message Summary { // emitted last, once every process has exited
+//!   uint64 processes = 1;
+//!   map<string, uint64> labels = 2;
+//!   repeated ExitCount exits = 3; // most common first
+//!   uint64 runtime_ms = 4;
+//!   uint64 peak_processes = 5;
+//! }
+//! message ExitCount {
+//!   ExitStatus status = 1;
+//!   uint64 count = 2;
+//! }
+//! message ExitStatus {
+//!   oneof status {
+//!     bool success = 1;
+//!     uint32 code = 2;
+//!     string signal = 3;
+//!     uint32 windows = 4;
+//!     bool indeterminate = 5;
+//!   }
+//! }
+//! ```
+
+use std::{
+	collections::BTreeMap, convert::TryInto, io::{self, Write}
+};
+
+use super::{DeployOutputEvent, ExitStatusError, ExitStatusUnix};
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Pid {
+	#[prost(bytes, tag = "1")]
+	key: Vec<u8>,
+	#[prost(string, tag = "2")]
+	ip: String,
+	#[prost(uint32, tag = "3")]
+	port: u32,
+}
+impl From<super::Pid> for Pid {
+	fn from(pid: super::Pid) -> Self {
+		Self {
+			key: pid.key.to_le_bytes().to_vec(),
+			ip: pid.ip.to_string(),
+			port: pid.port.into(),
+		}
+	}
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Event {
+	#[prost(oneof = "event::Event", tags = "1, 2, 3, 4, 5, 6")]
+	event: Option<event::Event>,
+}
+mod event {
+	#[derive(Clone, PartialEq, prost::Oneof)]
+	pub(super) enum Event {
+		#[prost(message, tag = "1")]
+		Spawn(super::Spawn),
+		#[prost(message, tag = "2")]
+		Output(super::Output),
+		#[prost(message, tag = "3")]
+		Exit(super::Exit),
+		#[prost(message, tag = "4")]
+		Started(super::Started),
+		#[prost(message, tag = "5")]
+		Paused(super::Paused),
+		#[prost(message, tag = "6")]
+		Summary(super::Summary),
+	}
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Spawn {
+	#[prost(message, optional, tag = "1")]
+	pid: Option<Pid>,
+	#[prost(message, optional, tag = "2")]
+	new_pid: Option<Pid>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Started {
+	#[prost(message, optional, tag = "1")]
+	pid: Option<Pid>,
+	#[prost(message, optional, tag = "2")]
+	parent: Option<Pid>,
+	#[prost(uint64, tag = "3")]
+	mem: u64,
+	#[prost(uint32, tag = "4")]
+	cpu: u32,
+	#[prost(string, tag = "5")]
+	version: String,
+	#[prost(string, tag = "6")]
+	label: String,
+	#[prost(uint32, tag = "7")]
+	gpu: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Paused {
+	#[prost(message, optional, tag = "1")]
+	pid: Option<Pid>,
+	#[prost(bool, tag = "2")]
+	paused: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Output {
+	#[prost(message, optional, tag = "1")]
+	pid: Option<Pid>,
+	#[prost(int32, tag = "2")]
+	fd: i32,
+	#[prost(bytes, tag = "3")]
+	data: Vec<u8>,
+	#[prost(bool, tag = "4")]
+	eof: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Exit {
+	#[prost(message, optional, tag = "1")]
+	pid: Option<Pid>,
+	#[prost(message, optional, tag = "2")]
+	status: Option<ExitStatus>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Summary {
+	#[prost(uint64, tag = "1")]
+	processes: u64,
+	#[prost(btree_map = "string, uint64", tag = "2")]
+	labels: BTreeMap<String, u64>,
+	#[prost(message, repeated, tag = "3")]
+	exits: Vec<ExitCount>,
+	#[prost(uint64, tag = "4")]
+	runtime_ms: u64,
+	#[prost(uint64, tag = "5")]
+	peak_processes: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ExitCount {
+	#[prost(message, optional, tag = "1")]
+	status: Option<ExitStatus>,
+	#[prost(uint64, tag = "2")]
+	count: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ExitStatus {
+	#[prost(oneof = "exit_status::Status", tags = "1, 2, 3, 4, 5")]
+	status: Option<exit_status::Status>,
+}
+mod exit_status {
+	#[derive(Clone, PartialEq, prost::Oneof)]
+	pub(super) enum Status {
+		#[prost(bool, tag = "1")]
+		Success(bool),
+		#[prost(uint32, tag = "2")]
+		Code(u32),
+		#[prost(string, tag = "3")]
+		Signal(String),
+		#[prost(uint32, tag = "4")]
+		Windows(u32),
+		#[prost(bool, tag = "5")]
+		Indeterminate(bool),
+	}
+}
+impl From<super::ExitStatus> for ExitStatus {
+	fn from(status: super::ExitStatus) -> Self {
+		use exit_status::Status;
+		let status = match status {
+			super::ExitStatus::Success => Status::Success(true),
+			super::ExitStatus::Error(ExitStatusError::Unix(ExitStatusUnix::Status(code))) => {
+				Status::Code(code.into())
+			}
+			super::ExitStatus::Error(ExitStatusError::Unix(ExitStatusUnix::Signal(signal))) => {
+				Status::Signal(format!("{:?}", signal))
+			}
+			super::ExitStatus::Error(ExitStatusError::Windows(code)) => Status::Windows(code),
+			super::ExitStatus::Error(ExitStatusError::Indeterminate) => Status::Indeterminate(true),
+		};
+		Self {
+			status: Some(status),
+		}
+	}
+}
+
+impl DeployOutputEvent {
+	/// Write this event to `writer` as a length-delimited protobuf message.
+	pub fn write_protobuf<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		let event = match self.clone() {
+			Self::Spawn(pid, new_pid) => event::Event::Spawn(Spawn {
+				pid: Some(pid.into()),
+				new_pid: Some(new_pid.into()),
+			}),
+			Self::Started(pid, report) => event::Event::Started(Started {
+				pid: Some(pid.into()),
+				parent: report.parent.map(Into::into),
+				mem: report.resources.mem.0,
+				cpu: report.resources.cpu.0,
+				gpu: report.resources.gpu.0,
+				version: report.version,
+				label: report.label.unwrap_or_default(),
+			}),
+			Self::Output(pid, fd, output) => event::Event::Output(Output {
+				pid: Some(pid.into()),
+				fd,
+				eof: output.is_empty(),
+				data: output,
+			}),
+			Self::Paused(pid, paused) => event::Event::Paused(Paused {
+				pid: Some(pid.into()),
+				paused,
+			}),
+			Self::Exit(pid, status) => event::Event::Exit(Exit {
+				pid: Some(pid.into()),
+				status: Some(status.into()),
+			}),
+			Self::Summary(summary) => event::Event::Summary(Summary {
+				processes: summary.processes as u64,
+				labels: summary
+					.labels
+					.into_iter()
+					.map(|(label, count)| (label, count as u64))
+					.collect(),
+				exits: summary
+					.exits
+					.into_iter()
+					.map(|(status, count)| ExitCount {
+						status: Some(status.into()),
+						count: count as u64,
+					})
+					.collect(),
+				runtime_ms: summary.runtime.as_millis().try_into().unwrap(),
+				peak_processes: summary.peak_processes as u64,
+			}),
+		};
+		let event = Event { event: Some(event) };
+		let mut buf = Vec::with_capacity(prost::Message::encoded_len(&event) + 10);
+		prost::Message::encode_length_delimited(&event, &mut buf).unwrap();
+		writer.write_all(&buf)
+	}
+}
@@ -1,8 +1,11 @@
 use serde::{de::Visitor, Deserialize, Serialize, *};
 use std::{
-	convert::TryInto, fmt::{self, Display}, ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign}, str::FromStr
+	convert::{TryFrom, TryInto}, fmt::{self, Display}, ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign}, str::FromStr
 };
 
+/// A quantity of memory in bytes.
+///
+/// Adding and multiplying saturate at the largest quantity representable rather than overflowing, while subtracting more than there is panics.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct Mem(pub(super) u64);
 impl Mem {
@@ -74,10 +77,7 @@ impl Add for Mem {
 	type Output = Self;
 
 	fn add(self, rhs: Self) -> Self {
-		Mem(self
-			.0
-			.checked_add(rhs.0)
-			.expect("overflow when adding mems"))
+		Mem(self.0.saturating_add(rhs.0))
 	}
 }
 impl AddAssign for Mem {
@@ -104,10 +104,7 @@ impl Mul<u64> for Mem {
 	type Output = Self;
 
 	fn mul(self, rhs: u64) -> Self {
-		Mem(self
-			.0
-			.checked_mul(rhs)
-			.expect("overflow when multiplying mem by scalar"))
+		Mem(self.0.saturating_mul(rhs))
 	}
 }
 impl Mul<Mem> for u64 {
@@ -122,6 +119,18 @@ impl MulAssign<u64> for Mem {
 		*self = *self * rhs;
 	}
 }
+impl Mul<usize> for Mem {
+	type Output = Self;
+
+	fn mul(self, rhs: usize) -> Self {
+		self * u64::try_from(rhs).unwrap_or(u64::MAX)
+	}
+}
+impl MulAssign<usize> for Mem {
+	fn mul_assign(&mut self, rhs: usize) {
+		*self = *self * rhs;
+	}
+}
 impl Div<u64> for Mem {
 	type Output = Self;
 
@@ -186,10 +195,36 @@ impl<'de> Visitor<'de> for MemVisitor {
 	}
 }
 
+/// A quantity of logical CPU cores, which needn't be whole.
+///
+/// This is stored in fixed point, so the minimum granularity is 1/65536 of a core, finer than a millicore. Quantities are exact in arithmetic, allocation, and when serialized, so for example the `Cpu::CORE / 4` requested for a process is exactly what the scheduler accounts for and what [`resources()`](crate::resources) returns in it. As with [Mem], adding and multiplying saturate rather than overflowing.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct Cpu(pub(super) u32);
 impl Cpu {
 	pub const CORE: Cpu = Cpu(65536);
+
+	/// A quantity of thousandths of a core, rounded to the nearest 1/65536 of a core.
+	///
+	/// This panics if `millicores` is too large to represent, which is above 65,535,999: just under 65,536 cores.
+	pub fn from_millicores(millicores: u32) -> Self {
+		Cpu(((u64::from(millicores) * 65536 + 500) / 1000)
+			.try_into()
+			.expect("overflow when converting millicores to cpu"))
+	}
+
+	/// A quantity of cores, rounded to the nearest 1/65536 of a core.
+	///
+	/// This panics if `cores` is negative, NaN, or too large to represent.
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+	pub fn from_cores(cores: f64) -> Self {
+		let units = (cores * 65536.0).round();
+		assert!(
+			units >= 0.0 && units <= f64::from(u32::MAX),
+			"couldn't convert {} cores to cpu",
+			cores
+		);
+		Cpu(units as u32)
+	}
 }
 impl FromStr for Cpu {
 	type Err = ();
@@ -230,9 +265,11 @@ impl FromStr for Cpu {
 			return Err(());
 		}
 		let c = 65536;
-		Ok(Cpu((u64::from(a) * c + b * c / 10_u64.pow(b1))
+		// The fraction of a core as displayed can have 16 significant digits, so b * c can overflow u64
+		let b: u64 = (u128::from(b) * u128::from(c) / 10_u128.pow(b1))
 			.try_into()
-			.unwrap()))
+			.unwrap();
+		Ok(Cpu((u64::from(a) * c + b).try_into().unwrap()))
 	}
 }
 impl Display for Cpu {
@@ -244,10 +281,7 @@ impl Add for Cpu {
 	type Output = Self;
 
 	fn add(self, rhs: Self) -> Self {
-		Cpu(self
-			.0
-			.checked_add(rhs.0)
-			.expect("overflow when adding cpus"))
+		Cpu(self.0.saturating_add(rhs.0))
 	}
 }
 impl AddAssign for Cpu {
@@ -274,10 +308,7 @@ impl Mul<u32> for Cpu {
 	type Output = Self;
 
 	fn mul(self, rhs: u32) -> Self {
-		Cpu(self
-			.0
-			.checked_mul(rhs)
-			.expect("overflow when multiplying cpu by scalar"))
+		Cpu(self.0.saturating_mul(rhs))
 	}
 }
 impl Mul<Cpu> for u32 {
@@ -292,6 +323,18 @@ impl MulAssign<u32> for Cpu {
 		*self = *self * rhs;
 	}
 }
+impl Mul<usize> for Cpu {
+	type Output = Self;
+
+	fn mul(self, rhs: usize) -> Self {
+		self * u32::try_from(rhs).unwrap_or(u32::MAX)
+	}
+}
+impl MulAssign<usize> for Cpu {
+	fn mul_assign(&mut self, rhs: usize) {
+		*self = *self * rhs;
+	}
+}
 impl Div<u32> for Cpu {
 	type Output = Self;
 
@@ -366,12 +409,109 @@ impl<'de> Visitor<'de> for CpuVisitor {
 				.map_err(|_| E::custom(format!("couldn't parse CPU quantity: {}", value)))?,
 		)
 	}
-	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
 	fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
 	where
 		E: de::Error,
 	{
-		Ok(Cpu((value * 65536.0) as u32)) // TODO
+		if value >= 0.0 && value * 65536.0 <= f64::from(u32::MAX) {
+			Ok(Cpu::from_cores(value))
+		} else {
+			Err(E::custom(format!("couldn't parse CPU quantity: {}", value)))
+		}
+	}
+}
+
+/// A number of GPU devices. As with [Mem], adding and multiplying saturate rather than overflowing.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Gpu(pub(super) u32);
+impl Gpu {
+	pub const DEVICE: Gpu = Gpu(1);
+}
+impl FromStr for Gpu {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.parse().map(Gpu).map_err(|_| ())
+	}
+}
+impl Display for Gpu {
+	fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		write!(f, "{}", self.0)
+	}
+}
+impl Add for Gpu {
+	type Output = Self;
+
+	fn add(self, rhs: Self) -> Self {
+		Gpu(self.0.saturating_add(rhs.0))
+	}
+}
+impl AddAssign for Gpu {
+	fn add_assign(&mut self, rhs: Self) {
+		*self = *self + rhs;
+	}
+}
+impl Sub for Gpu {
+	type Output = Self;
+
+	fn sub(self, rhs: Self) -> Self {
+		Gpu(self
+			.0
+			.checked_sub(rhs.0)
+			.expect("overflow when subtracting gpus"))
+	}
+}
+impl SubAssign for Gpu {
+	fn sub_assign(&mut self, rhs: Self) {
+		*self = *self - rhs;
+	}
+}
+impl Mul<u32> for Gpu {
+	type Output = Self;
+
+	fn mul(self, rhs: u32) -> Self {
+		Gpu(self.0.saturating_mul(rhs))
+	}
+}
+impl Mul<Gpu> for u32 {
+	type Output = Gpu;
+
+	fn mul(self, rhs: Gpu) -> Gpu {
+		rhs * self
+	}
+}
+impl MulAssign<u32> for Gpu {
+	fn mul_assign(&mut self, rhs: u32) {
+		*self = *self * rhs;
+	}
+}
+impl Mul<usize> for Gpu {
+	type Output = Self;
+
+	fn mul(self, rhs: usize) -> Self {
+		self * u32::try_from(rhs).unwrap_or(u32::MAX)
+	}
+}
+impl MulAssign<usize> for Gpu {
+	fn mul_assign(&mut self, rhs: usize) {
+		*self = *self * rhs;
+	}
+}
+
+impl Serialize for Gpu {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		self.0.serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for Gpu {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		u32::deserialize(deserializer).map(Gpu)
 	}
 }
 
@@ -381,7 +521,7 @@ impl<'de> Visitor<'de> for CpuVisitor {
 mod tests {
 	use std::str::FromStr;
 
-	use super::{Cpu, Mem};
+	use super::{Cpu, Gpu, Mem};
 
 	#[test]
 	fn parse_mem_size_test() {
@@ -454,4 +594,50 @@ mod tests {
 		// assert_eq!(Cpu::from_str("0.9999847412109374"), Ok(Cpu(65535)));
 		// assert_eq!(Cpu::from_str("65535.9999847412109374"), Ok(Cpu(u32::max_value())));
 	}
+
+	#[test]
+	fn fractional_cpu_test() {
+		assert_eq!(Cpu::from_millicores(1000), Cpu::CORE);
+		assert_eq!(Cpu::from_millicores(250), Cpu::CORE / 4);
+		assert_eq!(Cpu::from_millicores(1), Cpu(66));
+		assert_eq!(Cpu::from_millicores(65_535_999), Cpu(4_294_967_230));
+		assert_eq!(Cpu::from_cores(2.0), 2 * Cpu::CORE);
+		assert_eq!(Cpu::from_cores(0.125), Cpu::CORE / 8);
+		assert_eq!(Cpu::from_cores(0.001), Cpu::from_millicores(1));
+		for cpu in &[
+			Cpu(1),
+			Cpu(65),
+			Cpu::CORE / 16,
+			Cpu::from_millicores(333),
+			3 * Cpu::CORE + Cpu(1),
+		] {
+			let json = serde_json::to_string(cpu).unwrap();
+			assert_eq!(
+				serde_json::from_str::<Cpu>(&json).unwrap(),
+				*cpu,
+				"{}",
+				json
+			);
+		}
+	}
+
+	#[test]
+	fn parse_gpu_test() {
+		assert_eq!(Gpu::from_str("0"), Ok(Gpu(0)));
+		assert_eq!(Gpu::from_str("8"), Ok(Gpu(8)));
+		assert_eq!(Gpu::from_str("0.5"), Err(()));
+		assert_eq!(Gpu::from_str(""), Err(()));
+	}
+
+	#[test]
+	fn saturating_test() {
+		assert_eq!(Mem(u64::MAX) + Mem::B, Mem(u64::MAX));
+		assert_eq!(Mem::EIB * 16_usize, Mem(u64::MAX));
+		assert_eq!(Mem::MIB * 3_usize, 3 * Mem::MIB);
+		assert_eq!(Cpu(u32::MAX) + Cpu(1), Cpu(u32::MAX));
+		assert_eq!(Cpu::CORE * 65536_usize, Cpu(u32::MAX));
+		assert_eq!(Cpu::CORE * usize::MAX, Cpu(u32::MAX));
+		assert_eq!(Cpu(0) * usize::MAX, Cpu(0));
+		assert_eq!(Gpu::DEVICE * usize::MAX, Gpu(u32::MAX));
+	}
 }
@@ -9,8 +9,10 @@
 //!
 //! By default, 10 processes are spawned for the pool. By default, 30 jobs –
 //! which sleep for a couple of seconds before returning a `String` – are
-//! spawned on the pool. As such they are round-robin allocated to the 10
-//! processes of the pool. The initial process collects and prints the `String`s
+//! spawned on the pool. How they're allocated to the 10 processes of the pool
+//! is determined by its `DispatchPolicy`: round-robin, to the process with the
+//! fewest jobs in flight, or to the process with the fewest in flight relative
+//! to its share of CPU. The initial process collects and prints the `String`s
 //! returned by each job.
 //!
 //! This is a simple implementation; a more featureful version is [`amadeus`](https://github.com/alecmocatta/amadeus).
@@ -33,7 +35,7 @@
 
 use rand::Rng;
 use serde_traitobject as st;
-use std::{any, collections::VecDeque, env, marker, mem, thread, time};
+use std::{any, collections::VecDeque, convert::TryFrom, env, marker, mem, thread, time};
 
 use constellation::*;
 
@@ -42,6 +44,7 @@ type Request = st::Box<dyn st::FnOnce<(), Output = Response>>;
 type Response = st::Box<dyn st::Any>;
 
 struct Process {
+	resources: Resources,
 	sender: Sender<Option<Request>>,
 	receiver: Receiver<Response>,
 	queue: VecDeque<Queued<Response>>,
@@ -70,14 +73,32 @@ impl<T> Queued<T> {
 	}
 }
 
+/// How a `ProcessPool` picks the process to run each job.
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+enum DispatchPolicy {
+	/// Each process in turn, regardless of its resources or load.
+	RoundRobin,
+	/// The process with the fewest jobs in flight.
+	LeastLoaded,
+	/// The process with the fewest jobs in flight relative to its `Cpu`, so that processes with more CPU are given proportionally more work.
+	Weighted,
+}
+
 struct ProcessPool {
 	processes: Vec<Process>,
+	policy: DispatchPolicy,
 	i: usize,
 }
 impl ProcessPool {
+	#[allow(dead_code)]
 	fn new(processes: usize, resources: Resources) -> Self {
-		let processes = (0..processes)
-			.map(|_| {
+		Self::with_policy(vec![resources; processes], DispatchPolicy::RoundRobin)
+	}
+	fn with_policy(resources: Vec<Resources>, policy: DispatchPolicy) -> Self {
+		let processes = resources
+			.into_iter()
+			.map(|resources| {
 				// Spawn the following FnOnce closure in a new process
 				let child = spawn(
 					// Use the default resource limits, which are enough for this example
@@ -112,6 +133,7 @@ impl ProcessPool {
 				let (queue, received, tail) = (VecDeque::new(), 0, 0);
 
 				Process {
+					resources,
 					sender,
 					receiver,
 					queue,
@@ -120,7 +142,54 @@ impl ProcessPool {
 				}
 			})
 			.collect::<Vec<_>>();
-		ProcessPool { processes, i: 0 }
+		ProcessPool {
+			processes,
+			policy,
+			i: 0,
+		}
+	}
+	fn next_process(&mut self) -> usize {
+		match self.policy {
+			DispatchPolicy::RoundRobin => {
+				let process_index = self.i;
+				self.i += 1;
+				if self.i == self.processes.len() {
+					self.i = 0;
+				}
+				process_index
+			}
+			DispatchPolicy::LeastLoaded => {
+				self.poll();
+				(0..self.processes.len())
+					.min_by_key(|&i| self.processes[i].in_flight())
+					.unwrap()
+			}
+			DispatchPolicy::Weighted => {
+				self.poll();
+				// Compare (in_flight + 1) / cpu without division: a/b < c/d ⇔ a*d < c*b
+				(0..self.processes.len())
+					.min_by(|&a, &b| {
+						let (a, b) = (&self.processes[a], &self.processes[b]);
+						let load =
+							|process: &Process| u32::try_from(process.in_flight() + 1).unwrap();
+						(b.resources.cpu * load(a)).cmp(&(a.resources.cpu * load(b)))
+					})
+					.unwrap()
+			}
+		}
+	}
+	/// Receive any responses that have already arrived, so that in-flight counts are current.
+	fn poll(&mut self) {
+		for process in &mut self.processes {
+			while process.in_flight() > 0 {
+				let response = match process.receiver.try_recv() {
+					Some(recv) => recv().unwrap(),
+					None => break,
+				};
+				process.queue[process.received - process.tail].received(response);
+				process.received += 1;
+			}
+		}
 	}
 	fn spawn<
 		F: FnOnce() -> T + serde::ser::Serialize + serde::de::DeserializeOwned + 'static,
@@ -128,11 +197,7 @@ impl ProcessPool {
 	>(
 		&mut self, work: F,
 	) -> JoinHandle<T> {
-		let process_index = self.i;
-		self.i += 1;
-		if self.i == self.processes.len() {
-			self.i = 0;
-		}
+		let process_index = self.next_process();
 		let process = &mut self.processes[process_index];
 		process
 			.sender
@@ -173,6 +238,13 @@ impl Drop for ProcessPool {
 	}
 }
 
+impl Process {
+	/// Jobs sent to this process that it hasn't yet responded to.
+	fn in_flight(&self) -> usize {
+		self.tail + self.queue.len() - self.received
+	}
+}
+
 struct JoinHandle<T: any::Any>(usize, usize, marker::PhantomData<fn() -> T>);
 
 fn main() {
@@ -184,7 +256,10 @@ fn main() {
 		.and_then(|arg| arg.parse::<usize>().ok())
 		.unwrap_or(10);
 
-	let mut pool = ProcessPool::new(processes, Resources::default());
+	let mut pool = ProcessPool::with_policy(
+		vec![Resources::default(); processes],
+		DispatchPolicy::LeastLoaded,
+	);
 
 	let handles = (0..processes * 3)
 		.map(|i| {
@@ -0,0 +1,297 @@
+use futures::{
+	future::{self, FutureExt, LocalBoxFuture}, Stream
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	collections::HashSet, fmt, future::Future, ops::{Deref, DerefMut}, pin::Pin, sync::{
+		atomic::{AtomicU64, Ordering}, Arc, Mutex
+	}, task::{Context, Poll, Waker}
+};
+
+use super::{ChannelError, Pid, Receiver, Sender};
+
+/// Confirmation that a message sent with [`AckedSender::send_acked()`] has been acknowledged by the receiving application.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Ack(u64);
+impl Ack {
+	/// Get the position of the acknowledged message among those sent by the [`AckedSender`], starting at 0.
+	pub fn seq(&self) -> u64 {
+		self.0
+	}
+}
+
+/// The sending half of a channel whose messages are acknowledged by the receiving application, for use with an [`AckedReceiver`] at the other end.
+///
+/// Unlike a message having been sent, which only means it's been handed to the remote process, an acknowledgement means the application at the other end has received it and called [`Delivery::ack()`], for example once it's been processed or durably stored. Resending messages whose acknowledgement doesn't arrive gives at-least-once processing.
+///
+/// Acknowledgements travel over a channel in the opposite direction, so this takes both the [Sender] and the [Receiver] to the remote process.
+pub struct AckedSender<T: Serialize> {
+	sender: Sender<(u64, T)>,
+	acks: Receiver<u64>,
+	next: AtomicU64,
+	state: Mutex<AckState>,
+}
+impl<T: Serialize> AckedSender<T> {
+	/// Create a new `AckedSender` with a remote [Pid].
+	pub fn new(remote: Pid) -> Self {
+		Self {
+			sender: Sender::new(remote),
+			acks: Receiver::new(remote),
+			next: AtomicU64::new(0),
+			state: Mutex::new(AckState::default()),
+		}
+	}
+
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.sender.remote_pid()
+	}
+
+	/// Send `t`, resolving once the receiving application has acknowledged it.
+	///
+	/// Several messages can be awaiting acknowledgement at once, and they can be acknowledged in any order. This returns `Err` if the remote exits or the channel fails before acknowledging it.
+	///
+	/// This is an async fn.
+	pub async fn send_acked(&self, t: T) -> Result<Ack, ChannelError>
+	where
+		T: 'static,
+	{
+		let seq = self.next.fetch_add(1, Ordering::Relaxed);
+		self.sender.send((seq, t)).await;
+		Acked {
+			seq,
+			acks: &self.acks,
+			state: &self.state,
+			recv: None,
+		}
+		.await
+	}
+}
+impl<T: Serialize> fmt::Debug for AckedSender<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("AckedSender")
+			.field("sender", &self.sender)
+			.field("acks", &self.acks)
+			.finish()
+	}
+}
+
+/// Acknowledgements received for messages whose [`send_acked()`](AckedSender::send_acked) is yet to resolve.
+#[derive(Default)]
+struct AckState {
+	acked: HashSet<u64>,
+	error: Option<ChannelError>,
+	wakers: Vec<Waker>,
+}
+
+/// Waits for the acknowledgement of message `seq`. Whichever of these is polled receives acknowledgements on behalf of all of them, waking the others.
+struct Acked<'a> {
+	seq: u64,
+	acks: &'a Receiver<u64>,
+	state: &'a Mutex<AckState>,
+	recv: Option<LocalBoxFuture<'a, Result<u64, ChannelError>>>,
+}
+impl Future for Acked<'_> {
+	type Output = Result<Ack, ChannelError>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		let self_ = self.get_mut();
+		loop {
+			{
+				let mut state = self_.state.lock().unwrap();
+				if state.acked.remove(&self_.seq) {
+					return Poll::Ready(Ok(Ack(self_.seq)));
+				}
+				if let Some(err) = &state.error {
+					return Poll::Ready(Err(err.clone()));
+				}
+				if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+					state.wakers.push(cx.waker().clone());
+				}
+			}
+			let acks = self_.acks;
+			let recv = self_.recv.get_or_insert_with(|| acks.recv().boxed_local());
+			let ack = match recv.as_mut().poll(cx) {
+				Poll::Ready(ack) => ack,
+				Poll::Pending => return Poll::Pending,
+			};
+			self_.recv = None;
+			let mut state = self_.state.lock().unwrap();
+			match ack {
+				Ok(seq) => {
+					let _ = state.acked.insert(seq);
+				}
+				Err(err) => state.error = Some(err),
+			}
+			for waker in state.wakers.drain(..) {
+				waker.wake();
+			}
+		}
+	}
+}
+
+/// The receiving half of a channel whose messages are acknowledged by the receiving application, for use with an [`AckedSender`] at the other end.
+///
+/// Acknowledgements travel over a channel in the opposite direction, so this takes both the [Receiver] and the [Sender] to the remote process.
+pub struct AckedReceiver<T: DeserializeOwned> {
+	receiver: Receiver<(u64, T)>,
+	acks: Sender<u64>,
+}
+impl<T: DeserializeOwned> AckedReceiver<T> {
+	/// Create a new `AckedReceiver` with a remote [Pid].
+	pub fn new(remote: Pid) -> Self {
+		Self {
+			receiver: Receiver::new(remote),
+			acks: Sender::new(remote),
+		}
+	}
+
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.receiver.remote_pid()
+	}
+
+	/// Receive a message, which the sender is told about once it's acknowledged with [`Delivery::ack()`].
+	///
+	/// This is an async fn.
+	pub async fn recv(&self) -> Result<Delivery<'_, T>, ChannelError>
+	where
+		T: 'static,
+	{
+		let (seq, value) = self.receiver.recv().await?;
+		Ok(Delivery {
+			acks: &self.acks,
+			seq,
+			value,
+		})
+	}
+
+	/// Receive messages as a stream, each paired with an [`AckToken`] with which to acknowledge it.
+	///
+	/// At most one message is in flight: the next isn't received until the previous one's token has been acknowledged or dropped. A message whose token is dropped without being acknowledged, for example because processing it failed, leaves the sender's [`send_acked()`](AckedSender::send_acked) unresolved so that the sender can resend it. On error the error is yielded and the stream ends.
+	pub fn ackable_stream(&self) -> impl Stream<Item = Result<(T, AckToken<'_>), ChannelError>> + '_
+	where
+		T: 'static,
+	{
+		let in_flight = Arc::new(Mutex::new(InFlight::default()));
+		futures::stream::unfold(Some(in_flight), move |in_flight| async move {
+			let in_flight = in_flight?;
+			future::poll_fn(|cx| {
+				let mut state = in_flight.lock().unwrap();
+				if state.busy {
+					state.waker = Some(cx.waker().clone());
+					Poll::Pending
+				} else {
+					Poll::Ready(())
+				}
+			})
+			.await;
+			match self.receiver.recv().await {
+				Ok((seq, value)) => {
+					in_flight.lock().unwrap().busy = true;
+					let token = AckToken {
+						acks: &self.acks,
+						seq,
+						in_flight: in_flight.clone(),
+					};
+					Some((Ok((value, token)), Some(in_flight)))
+				}
+				Err(err) => Some((Err(err), None)),
+			}
+		})
+	}
+}
+impl<T: DeserializeOwned> fmt::Debug for AckedReceiver<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("AckedReceiver")
+			.field("receiver", &self.receiver)
+			.field("acks", &self.acks)
+			.finish()
+	}
+}
+
+/// A message received by an [`AckedReceiver`]. It derefs to the message.
+///
+/// Dropping it without calling [`ack()`](Delivery::ack) leaves it unacknowledged.
+pub struct Delivery<'a, T> {
+	acks: &'a Sender<u64>,
+	seq: u64,
+	value: T,
+}
+impl<T> Delivery<'_, T> {
+	/// Acknowledge the message, resolving the sender's [`send_acked()`](AckedSender::send_acked), and return it.
+	///
+	/// This is an async fn.
+	pub async fn ack(self) -> T {
+		self.acks.send(self.seq).await;
+		self.value
+	}
+
+	/// Get the position of the message among those sent by the [`AckedSender`], starting at 0.
+	pub fn seq(&self) -> u64 {
+		self.seq
+	}
+}
+impl<T> Deref for Delivery<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		&self.value
+	}
+}
+impl<T> DerefMut for Delivery<'_, T> {
+	fn deref_mut(&mut self) -> &mut T {
+		&mut self.value
+	}
+}
+impl<T: fmt::Debug> fmt::Debug for Delivery<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Delivery")
+			.field("seq", &self.seq)
+			.field("value", &self.value)
+			.finish()
+	}
+}
+
+/// Whether a message yielded by [`AckedReceiver::ackable_stream()`] is awaiting acknowledgement, and the task waiting to yield the next one.
+#[derive(Default)]
+struct InFlight {
+	busy: bool,
+	waker: Option<Waker>,
+}
+
+/// A token with which to acknowledge a message yielded by [`AckedReceiver::ackable_stream()`].
+///
+/// Dropping it without calling [`ack()`](AckToken::ack) leaves the message unacknowledged, and lets the stream yield the next message.
+pub struct AckToken<'a> {
+	acks: &'a Sender<u64>,
+	seq: u64,
+	in_flight: Arc<Mutex<InFlight>>,
+}
+impl AckToken<'_> {
+	/// Acknowledge the message, resolving the sender's [`send_acked()`](AckedSender::send_acked).
+	///
+	/// This is an async fn.
+	pub async fn ack(self) {
+		self.acks.send(self.seq).await;
+	}
+
+	/// Get the position of the message among those sent by the [`AckedSender`], starting at 0.
+	pub fn seq(&self) -> u64 {
+		self.seq
+	}
+}
+impl Drop for AckToken<'_> {
+	fn drop(&mut self) {
+		let mut state = self.in_flight.lock().unwrap();
+		state.busy = false;
+		if let Some(waker) = state.waker.take() {
+			waker.wake();
+		}
+	}
+}
+impl fmt::Debug for AckToken<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("AckToken").field("seq", &self.seq).finish()
+	}
+}
@@ -29,7 +29,7 @@
 
 use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
 use std::{
-	convert::TryInto, env, ffi::{OsStr, OsString}, iter, net::SocketAddr, process
+	convert::TryInto, env, ffi::{OsStr, OsString}, iter, net::SocketAddr, process, time::Duration
 };
 
 use constellation_internal::Format;
@@ -84,7 +84,7 @@ fn main() {
 		.collect();
 	let vars: Vec<(OsString, OsString)> = env::vars_os().collect();
 	let format = Format::Human;
-	constellation::deploy(host, &path, format, args, vars);
+	constellation::deploy(host, &path, format, &args, &vars, 0, Duration::from_secs(1));
 }
 
 fn cli<'a, 'b>() -> App<'a, 'b> {
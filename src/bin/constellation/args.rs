@@ -3,8 +3,8 @@
 use serde::Deserialize;
 use std::{error::Error, fs::File, io::Read, net::SocketAddr};
 
-use super::{Args, Node, Role};
-use constellation_internal::{Cpu, Format, Mem};
+use super::{Args, Node, Pool, Role};
+use constellation_internal::{Cpu, Format, Gpu, Mem};
 
 const DESCRIPTION: &str = r"Run a constellation node.
 ";
@@ -58,7 +58,27 @@ This reads the file "nodes.toml", which should look something like this:
     mem = "5 GiB"
     cpu = 1
 
-This enables the nodes to see and communicate with each other.
+This enables the nodes to see and communicate with each other. Nodes with GPUs
+can declare how many with, for example, gpu = 2. Nodes can also be given
+labels, like labels = ["dataset-a"], which processes can be spawned with an
+affinity for.
+
+Slices of the cluster's capacity can be reserved as named resource pools, for
+example one per team, by adding to "nodes.toml":
+
+    [[pools]]
+    name = "team-a"
+    mem = "4 GiB"
+    cpu = 1
+
+    [[pools]]
+    name = "team-a/nightly"
+    mem = "1 GiB"
+    cpu = 0.5
+
+Processes spawned with a pool are allocated from it, and by default their
+children are too. A pool named like "team-a/nightly" is nested within
+"team-a", and draws on its capacity as well as its own.
 
 Deploying to this cluster might then be:
 
@@ -187,6 +207,7 @@ impl Args {
 			(bind, Some(_)) if bind.parse::<SocketAddr>().is_ok() => {
 				let bind = bind.parse().unwrap();
 				let mut nodes = Vec::new();
+				let mut pools = Vec::new();
 				match (args.next().unwrap(), args.peek()) {
 					(arg, Some(_)) => {
 						let mut arg: Option<String> = Some(arg);
@@ -215,6 +236,8 @@ impl Args {
 										bridge,
 										mem,
 										cpu,
+										gpu: Gpu::default(),
+										labels: Vec::new(),
 									});
 								}
 								_ => {
@@ -227,21 +250,24 @@ impl Args {
 						}
 					}
 					(arg, None) => {
-						nodes = Self::from_toml(&mut File::open(&arg).map_err(|e| {
-							(
-								format!("Can't open the TOML file \"{}\": {}", arg, e),
-								false,
-							)
-						})?)
-						.map_err(|e| {
-							(
-								format!("Can't parse the TOML file \"{}\": {}", arg, e),
-								false,
-							)
-						})?;
+						let (nodes_, pools_) =
+							Self::from_toml(&mut File::open(&arg).map_err(|e| {
+								(
+									format!("Can't open the TOML file \"{}\": {}", arg, e),
+									false,
+								)
+							})?)
+							.map_err(|e| {
+								(
+									format!("Can't parse the TOML file \"{}\": {}", arg, e),
+									false,
+								)
+							})?;
+						nodes = nodes_;
+						pools = pools_;
 					}
 				}
-				Role::Master(bind, nodes)
+				Role::Master(bind, nodes, pools)
 			}
 			(bind, None) if bind.parse::<SocketAddr>().is_ok() => {
 				Role::Worker(bind.parse::<SocketAddr>().unwrap())
@@ -256,10 +282,12 @@ impl Args {
 			role,
 		})
 	}
-	fn from_toml<R: Read>(reader: &mut R) -> Result<Vec<Node>, Box<dyn Error>> {
+	fn from_toml<R: Read>(reader: &mut R) -> Result<(Vec<Node>, Vec<Pool>), Box<dyn Error>> {
 		#[derive(Deserialize)]
 		struct A {
 			nodes: Vec<B>,
+			#[serde(default)]
+			pools: Vec<C>,
 		}
 		#[derive(Deserialize)]
 		struct B {
@@ -267,14 +295,23 @@ impl Args {
 			bridge_bind: Option<SocketAddr>,
 			mem: Mem,
 			cpu: Cpu,
+			#[serde(default)]
+			gpu: Gpu,
+			#[serde(default)]
+			labels: Vec<String>,
+		}
+		#[derive(Deserialize)]
+		struct C {
+			name: String,
+			mem: Mem,
+			cpu: Cpu,
 		}
 
 		let mut toml = Vec::new();
 		let _ = reader.read_to_end(&mut toml)?;
-		let nodes = toml::from_slice::<A>(&toml)
-			.map_err(|e| {
-				format!(
-					r#"{}
+		let A { nodes, pools } = toml::from_slice::<A>(&toml).map_err(|e| {
+			format!(
+				r#"{}
 It should look something like:
 
 [[nodes]]
@@ -288,22 +325,39 @@ fabric_addr = "10.0.0.2:9999"
 mem = "5 GiB"
 cpu = 1
 "#,
-					e
-				)
-			})?
-			.nodes;
+				e
+			)
+		})?;
 		if nodes.is_empty() {
 			return Err("must contain multiple nodes".into());
 		}
-		Ok(nodes
+		if let Some(pool) = pools
+			.iter()
+			.enumerate()
+			.find(|&(i, pool)| pools[..i].iter().any(|pool_| pool_.name == pool.name))
+		{
+			return Err(format!("pool \"{}\" is given more than once", pool.1.name).into());
+		}
+		let nodes = nodes
 			.into_iter()
 			.map(|node| Node {
 				fabric: node.fabric_addr,
 				bridge: node.bridge_bind,
 				mem: node.mem,
 				cpu: node.cpu,
+				gpu: node.gpu,
+				labels: node.labels,
+			})
+			.collect();
+		let pools = pools
+			.into_iter()
+			.map(|pool| Pool {
+				name: pool.name,
+				mem: pool.mem,
+				cpu: pool.cpu,
 			})
-			.collect())
+			.collect();
+		Ok((nodes, pools))
 	}
 }
 
@@ -375,7 +429,10 @@ mod tests {
 						bridge: Some("10.0.0.1:7777".parse().unwrap()),
 						mem: 400 * Mem::GIB,
 						cpu: 34 * Cpu::CORE,
-					}]
+						gpu: Gpu::default(),
+						labels: Vec::new(),
+					}],
+					Vec::new()
 				)
 			})
 		);
@@ -403,16 +460,115 @@ mod tests {
 							bridge: Some("10.0.0.1:7777".parse().unwrap()),
 							mem: 400 * Mem::GIB,
 							cpu: 34 * Cpu::CORE,
+							gpu: Gpu::default(),
+							labels: Vec::new(),
 						},
 						Node {
 							fabric: "10.0.0.1:8888".parse().unwrap(),
 							bridge: None,
 							mem: 400 * Mem::GIB,
 							cpu: 34 * Cpu::CORE,
+							gpu: Gpu::default(),
+							labels: Vec::new(),
 						}
-					]
+					],
+					Vec::new()
 				)
 			})
 		);
 	}
+	#[test]
+	fn toml_pools() {
+		let toml = r#"
+[[nodes]]
+fabric_addr = "10.0.0.1:9999"
+mem = "5 GiB"
+cpu = 1
+
+[[pools]]
+name = "team-a"
+mem = "4 GiB"
+cpu = 1
+
+[[pools]]
+name = "team-a/nightly"
+mem = "1 GiB"
+cpu = 0.5
+"#;
+		let (nodes, pools) = Args::from_toml(&mut toml.as_bytes()).unwrap();
+		assert_eq!(nodes.len(), 1);
+		assert_eq!(
+			pools,
+			vec![
+				Pool {
+					name: String::from("team-a"),
+					mem: 4 * Mem::GIB,
+					cpu: 1 * Cpu::CORE,
+				},
+				Pool {
+					name: String::from("team-a/nightly"),
+					mem: 1 * Mem::GIB,
+					cpu: Cpu::CORE / 2,
+				}
+			]
+		);
+		let toml = r#"
+[[nodes]]
+fabric_addr = "10.0.0.1:9999"
+mem = "5 GiB"
+cpu = 1
+
+[[pools]]
+name = "team-a"
+mem = "4 GiB"
+cpu = 1
+
+[[pools]]
+name = "team-a"
+mem = "1 GiB"
+cpu = 1
+"#;
+		assert!(Args::from_toml(&mut toml.as_bytes()).is_err());
+	}
+	#[test]
+	fn toml_gpus() {
+		let toml = r#"
+[[nodes]]
+fabric_addr = "10.0.0.1:9999"
+mem = "5 GiB"
+cpu = 1
+gpu = 2
+
+[[nodes]]
+fabric_addr = "10.0.0.2:9999"
+mem = "5 GiB"
+cpu = 1
+"#;
+		let (nodes, _pools) = Args::from_toml(&mut toml.as_bytes()).unwrap();
+		assert_eq!(
+			nodes.iter().map(|node| node.gpu).collect::<Vec<_>>(),
+			[2 * Gpu::DEVICE, Gpu::default()]
+		);
+	}
+
+	#[test]
+	fn toml_labels() {
+		let toml = r#"
+[[nodes]]
+fabric_addr = "10.0.0.1:9999"
+mem = "5 GiB"
+cpu = 1
+labels = ["dataset-a", "ssd"]
+
+[[nodes]]
+fabric_addr = "10.0.0.2:9999"
+mem = "5 GiB"
+cpu = 1
+"#;
+		let (nodes, _pools) = Args::from_toml(&mut toml.as_bytes()).unwrap();
+		assert_eq!(
+			nodes.iter().map(|node| &*node.labels).collect::<Vec<_>>(),
+			[&["dataset-a".to_owned(), "ssd".to_owned()][..], &[][..]]
+		);
+	}
 }
@@ -31,18 +31,19 @@ use palaver::{
 	file::{execve, fexecve, move_fds}, process::WaitStatus
 };
 use std::{
-	collections::HashMap, ffi::{CStr, CString, OsString}, fs::File, iter, net::TcpStream, os::unix::{
+	collections::{HashMap, HashSet}, ffi::{CStr, CString, OsString}, fs::File, iter, net::TcpStream, os::unix::{
 		ffi::OsStringExt, io::{AsRawFd, FromRawFd, IntoRawFd}
 	}, sync::{
 		atomic::{self, AtomicUsize}, mpsc, Arc, Mutex
 	}, thread, time::Duration
 };
 
+use super::verify_binary;
 use constellation::FutureExt1;
 use constellation_internal::{
-	abort_on_unwind, abort_on_unwind_1, forbid_alloc, map_bincode_err, msg::{
-		bincode_deserialize_from, bincode_serialize_into, BridgeRequest, FabricRequest, SpawnArg
-	}, BufferedStream, DeployInputEvent, DeployOutputEvent, ExitStatus, Fd, Pid, ProcessInputEvent, ProcessOutputEvent, Resources, TrySpawnError
+	abort_on_unwind, abort_on_unwind_1, forbid_alloc, is_descendant, map_bincode_err, msg::{
+		bincode_deserialize_from, bincode_serialize_into, BridgeRequest, FabricRequest, SchedulerReply, SchedulerRequest, SpawnArg
+	}, BufferedStream, DeployInputEvent, DeployOutputEvent, ExitStatus, Fd, OnParentExit, Pid, ProcessInputEvent, ProcessOutputEvent, Resources, StartupReport, Summarizer, TrySpawnError
 };
 
 const SCHEDULER_FD: Fd = 4;
@@ -50,14 +51,38 @@ const RECCE_TIMEOUT: Duration = Duration::from_secs(10); // Time to allow binary
 
 #[derive(Clone, Debug)]
 enum OutputEventInt {
-	Spawn(Pid, Pid, futures::channel::mpsc::Sender<InputEventInt>),
+	Spawn(
+		Pid,
+		Pid,
+		OnParentExit,
+		bool,
+		futures::channel::mpsc::Sender<InputEventInt>,
+	),
+	Started(Pid, StartupReport),
 	Output(Pid, Fd, Vec<u8>),
 	Exit(Pid, ExitStatus),
+	Cancel(Pid),
+	Signal(Pid, constellation_internal::Signal),
+	Pause(Pid),
+	Resume(Pid),
+	Paused(Pid, bool),
+	Watch(Pid, Pid),
+	Kill(Pid, Pid, constellation_internal::Signal),
+	Input(Pid, Fd, Vec<u8>),
+	Reach(Pid, Pid),
 }
 #[derive(Clone, Debug)]
 enum InputEventInt {
 	Input(Fd, Vec<u8>),
 	Kill,
+	Cancel,
+	Signal(constellation_internal::Signal),
+	Pause,
+	Resume,
+	Exited(Pid, ExitStatus),
+	Killed(Pid, bool),
+	Output(Pid, Fd, Vec<u8>),
+	Reachable(Pid, bool),
 }
 
 static PROCESS_COUNT: AtomicUsize = AtomicUsize::new(0);
@@ -81,14 +106,36 @@ fn monitor_process(
 					.send(match event.unwrap() {
 						InputEventInt::Input(fd, input) => ProcessInputEvent::Input(fd, input),
 						InputEventInt::Kill => ProcessInputEvent::Kill,
+						InputEventInt::Cancel => ProcessInputEvent::Cancel,
+						InputEventInt::Signal(signal) => ProcessInputEvent::Signal(signal),
+						InputEventInt::Pause => ProcessInputEvent::Pause,
+						InputEventInt::Resume => ProcessInputEvent::Resume,
+						InputEventInt::Exited(pid, exit_code) => {
+							ProcessInputEvent::Exited(pid, exit_code)
+						}
+						InputEventInt::Killed(pid, running) => {
+							ProcessInputEvent::Killed(pid, running)
+						}
+						InputEventInt::Output(pid, fd, output) => {
+							ProcessInputEvent::Output(pid, fd, output)
+						}
+						InputEventInt::Reachable(pid, started) => {
+							ProcessInputEvent::Reachable(pid, started)
+						}
 					})
 					.block();
 			}
 			futures::future::Either::Right(event) => match event.unwrap() {
-				ProcessOutputEvent::Spawn(new_pid) => {
+				ProcessOutputEvent::Spawn(new_pid, on_parent_exit, forward_output) => {
 					let (sender1, receiver1) = futures::channel::mpsc::channel(0);
 					sender_
-						.send(OutputEventInt::Spawn(pid, new_pid, sender1))
+						.send(OutputEventInt::Spawn(
+							pid,
+							new_pid,
+							on_parent_exit,
+							forward_output,
+							sender1,
+						))
 						.unwrap();
 					let sender_ = sender_.clone();
 					let _ = thread::Builder::new()
@@ -98,6 +145,9 @@ fn monitor_process(
 						}))
 						.unwrap();
 				}
+				ProcessOutputEvent::Started(report) => {
+					sender_.send(OutputEventInt::Started(pid, report)).unwrap();
+				}
 				ProcessOutputEvent::Output(fd, output) => {
 					sender_
 						.send(OutputEventInt::Output(pid, fd, output))
@@ -107,6 +157,35 @@ fn monitor_process(
 					sender_.send(OutputEventInt::Exit(pid, exit_code)).unwrap();
 					break;
 				}
+				ProcessOutputEvent::Cancel => {
+					sender_.send(OutputEventInt::Cancel(pid)).unwrap();
+				}
+				ProcessOutputEvent::Signal(pid, signal) => {
+					sender_.send(OutputEventInt::Signal(pid, signal)).unwrap();
+				}
+				ProcessOutputEvent::Pause(pid) => {
+					sender_.send(OutputEventInt::Pause(pid)).unwrap();
+				}
+				ProcessOutputEvent::Resume(pid) => {
+					sender_.send(OutputEventInt::Resume(pid)).unwrap();
+				}
+				ProcessOutputEvent::Paused(paused) => {
+					sender_.send(OutputEventInt::Paused(pid, paused)).unwrap();
+				}
+				ProcessOutputEvent::Watch(watched) => {
+					sender_.send(OutputEventInt::Watch(pid, watched)).unwrap();
+				}
+				ProcessOutputEvent::Kill(killed, signal) => {
+					sender_
+						.send(OutputEventInt::Kill(pid, killed, signal))
+						.unwrap();
+				}
+				ProcessOutputEvent::Input(pid, fd, input) => {
+					sender_.send(OutputEventInt::Input(pid, fd, input)).unwrap();
+				}
+				ProcessOutputEvent::Reach(reached) => {
+					sender_.send(OutputEventInt::Reach(pid, reached)).unwrap();
+				}
 			},
 		}
 	}
@@ -277,18 +356,35 @@ fn manage_connection(
 		spawn: None,
 	};
 	bincode::serialize_into(&mut request.arg, &spawn_arg).unwrap();
-	let resources = request
-		.resources
-		.or_else(|| {
-			recce(
-				#[cfg(feature = "distribute_binaries")]
-				&request.binary,
-				&request.args,
-				&request.vars,
-			)
-			.ok()
-		})
-		.ok_or(TrySpawnError::Recce);
+	// Don't run a recce of a binary that the fabric would refuse to spawn
+	#[cfg(feature = "distribute_binaries")]
+	let verified = verify_binary(&request.vars, || request.binary.try_clone());
+	#[cfg(not(feature = "distribute_binaries"))]
+	let verified = verify_binary(&request.vars, || File::open(&request.args[0]));
+	let resources = verified.and_then(|()| {
+		request
+			.resources
+			.or_else(|| {
+				recce(
+					#[cfg(feature = "distribute_binaries")]
+					&request.binary,
+					&request.args,
+					&request.vars,
+				)
+				.ok()
+			})
+			.ok_or(TrySpawnError::Recce)
+	});
+	// Tag the deployment so the scheduler can enforce CONSTELLATION_PROCESS_LIMIT across all its processes, which inherit the var
+	request.vars = request
+		.vars
+		.into_iter()
+		.filter(|(key, _)| key != "CONSTELLATION_JOB")
+		.chain(iter::once((
+			OsString::from("CONSTELLATION_JOB"),
+			OsString::from(rand::random::<u64>().to_string()),
+		)))
+		.collect();
 	let pid: Result<Pid, TrySpawnError> = resources.and_then(|resources| {
 		let (sender_, receiver) = mpsc::sync_channel(0);
 		sender
@@ -299,7 +395,15 @@ fn manage_connection(
 					bind: vec![],
 					args: request.args,
 					vars: request.vars,
+					cwd: None,
+					near: None,
+					placement: None,
 					arg: request.arg,
+					#[cfg(feature = "distribute_binaries")]
+					binary_hash: None,
+					#[cfg(feature = "distribute_binaries")]
+					binary: Some(request.binary),
+					#[cfg(not(feature = "distribute_binaries"))]
 					binary: request.binary,
 				},
 				sender_,
@@ -344,6 +448,12 @@ fn manage_connection(
 						DeployInputEvent::Kill(None) => {
 							break;
 						}
+						DeployInputEvent::Signal(pid, signal) => {
+							if let Some(sender) = hashmap.lock().unwrap().get_mut(&pid) {
+								let _unchecked_error =
+									sender.send(InputEventInt::Signal(signal)).block();
+							}
+						}
 					}
 				}
 				let mut x = hashmap.lock().unwrap();
@@ -351,16 +461,145 @@ fn manage_connection(
 					let _unchecked_error = process.send(InputEventInt::Kill).block();
 				}
 			}));
+			let (mut parents, mut cancelled) = (HashMap::new(), HashSet::new());
+			let mut on_parent_exit = HashMap::new();
+			// Processes waiting to be told of another's exit, and the exit statuses of processes whose parents might yet ask
+			let (mut watchers, mut exited) = (HashMap::<Pid, Vec<Pid>>::new(), HashMap::new());
+			// Processes waiting to be told another has started, and the running processes that have
+			let (mut reachers, mut started) = (HashMap::<Pid, Vec<Pid>>::new(), HashSet::new());
+			// Processes whose output is forwarded to their parent
+			let mut forwarded = HashSet::new();
+			let mut summarizer = Summarizer::new();
 			for event in receiver.iter() {
 				let event = match event {
-					OutputEventInt::Spawn(pid, new_pid, sender) => {
+					OutputEventInt::Spawn(
+						pid,
+						new_pid,
+						on_parent_exit_,
+						forward_output,
+						sender,
+					) => {
 						let x = PROCESS_COUNT.fetch_add(1, atomic::Ordering::Relaxed);
 						trace!("BRIDGE: SPAWN ({})", x);
+						let _ = parents.insert(new_pid, pid);
+						let _ = on_parent_exit.insert(new_pid, on_parent_exit_);
+						if forward_output {
+							let _ = forwarded.insert(new_pid);
+						}
+						if cancelled
+							.iter()
+							.any(|&pid| is_descendant(&parents, new_pid, pid))
+						{
+							let _unchecked_error = sender.clone().try_send(InputEventInt::Cancel);
+						}
 						let x = hashmap.lock().unwrap().insert(new_pid, sender);
 						assert!(x.is_none());
 						DeployOutputEvent::Spawn(pid, new_pid)
 					}
+					OutputEventInt::Cancel(pid) => {
+						let _ = cancelled.insert(pid);
+						// try_send on a fresh clone so as not to block on a monitor thread that's blocked on us
+						for (&pid_, process) in hashmap.lock().unwrap().iter() {
+							if is_descendant(&parents, pid_, pid) {
+								let _unchecked_error =
+									process.clone().try_send(InputEventInt::Cancel);
+							}
+						}
+						continue;
+					}
+					OutputEventInt::Signal(pid, signal) => {
+						if let Some(process) = hashmap.lock().unwrap().get(&pid) {
+							let _unchecked_error =
+								process.clone().try_send(InputEventInt::Signal(signal));
+						}
+						continue;
+					}
+					OutputEventInt::Pause(pid) => {
+						if let Some(process) = hashmap.lock().unwrap().get(&pid) {
+							let _unchecked_error = process.clone().try_send(InputEventInt::Pause);
+						}
+						continue;
+					}
+					OutputEventInt::Resume(pid) => {
+						if let Some(process) = hashmap.lock().unwrap().get(&pid) {
+							let _unchecked_error = process.clone().try_send(InputEventInt::Resume);
+						}
+						continue;
+					}
+					OutputEventInt::Kill(pid, killed, signal) => {
+						let hashmap = hashmap.lock().unwrap();
+						let running = if let Some(process) = hashmap.get(&killed) {
+							let _unchecked_error =
+								process.clone().try_send(InputEventInt::Signal(signal));
+							true
+						} else {
+							false
+						};
+						if let Some(process) = hashmap.get(&pid) {
+							let _unchecked_error = process
+								.clone()
+								.try_send(InputEventInt::Killed(killed, running));
+						}
+						continue;
+					}
+					OutputEventInt::Input(pid, fd, input) => {
+						if let Some(process) = hashmap.lock().unwrap().get(&pid) {
+							let _unchecked_error =
+								process.clone().try_send(InputEventInt::Input(fd, input));
+						}
+						continue;
+					}
+					OutputEventInt::Watch(pid, watched) => {
+						if let Some(&exit_code) = exited.get(&watched) {
+							if let Some(process) = hashmap.lock().unwrap().get(&pid) {
+								let _unchecked_error = process
+									.clone()
+									.try_send(InputEventInt::Exited(watched, exit_code));
+							}
+						} else {
+							watchers.entry(watched).or_default().push(pid);
+						}
+						continue;
+					}
+					OutputEventInt::Paused(pid, paused) => DeployOutputEvent::Paused(pid, paused),
+					OutputEventInt::Reach(pid, reached) => {
+						let hashmap = hashmap.lock().unwrap();
+						let started_ = started.contains(&reached);
+						if started_ || !hashmap.contains_key(&reached) {
+							if let Some(process) = hashmap.get(&pid) {
+								let _unchecked_error = process
+									.clone()
+									.try_send(InputEventInt::Reachable(reached, started_));
+							}
+						} else {
+							reachers.entry(reached).or_default().push(pid);
+						}
+						continue;
+					}
+					OutputEventInt::Started(pid, report) => {
+						let _ = started.insert(pid);
+						for reacher in reachers.remove(&pid).unwrap_or_default() {
+							if let Some(process) = hashmap.lock().unwrap().get(&reacher) {
+								let _unchecked_error = process
+									.clone()
+									.try_send(InputEventInt::Reachable(pid, true));
+							}
+						}
+						DeployOutputEvent::Started(pid, report)
+					}
 					OutputEventInt::Output(pid, fd, output) => {
+						if forwarded.contains(&pid) {
+							if let Some(mut process) = parents
+								.get(&pid)
+								.and_then(|parent| hashmap.lock().unwrap().get(parent).cloned())
+							{
+								let _unchecked_error = process.try_send(InputEventInt::Output(
+									pid,
+									fd,
+									output.clone(),
+								));
+							}
+						}
 						DeployOutputEvent::Output(pid, fd, output)
 					}
 					OutputEventInt::Exit(pid, exit_code) => {
@@ -368,12 +607,60 @@ fn manage_connection(
 						assert_ne!(x, 0);
 						trace!("BRIDGE: KILL ({})", x);
 						let _ = hashmap.lock().unwrap().remove(&pid).unwrap();
+						let _ = forwarded.remove(&pid);
+						let _ = started.remove(&pid);
+						for reacher in reachers.remove(&pid).unwrap_or_default() {
+							if let Some(process) = hashmap.lock().unwrap().get(&reacher) {
+								let _unchecked_error = process
+									.clone()
+									.try_send(InputEventInt::Reachable(pid, false));
+							}
+						}
+						for watcher in watchers.remove(&pid).unwrap_or_default() {
+							if let Some(process) = hashmap.lock().unwrap().get(&watcher) {
+								let _unchecked_error = process
+									.clone()
+									.try_send(InputEventInt::Exited(pid, exit_code));
+							}
+						}
+						if parents
+							.get(&pid)
+							.map_or(false, |parent| hashmap.lock().unwrap().contains_key(parent))
+						{
+							let _ = exited.insert(pid, exit_code);
+						}
+						let children = parents
+							.iter()
+							.filter(|&(_, &parent)| parent == pid)
+							.map(|(&child, _)| child)
+							.collect::<Vec<_>>();
+						for child in children {
+							let _ = exited.remove(&child);
+							match on_parent_exit[&child] {
+								OnParentExit::Kill => {
+									if let Some(process) = hashmap.lock().unwrap().get(&child) {
+										let _unchecked_error =
+											process.clone().try_send(InputEventInt::Kill);
+									}
+								}
+								OnParentExit::Detach => (),
+								OnParentExit::Reparent => {
+									let _ = parents.remove(&child);
+								}
+							}
+						}
 						DeployOutputEvent::Exit(pid, exit_code)
 					}
 				};
+				summarizer.observe(&event);
 				if bincode::serialize_into(&mut stream_write, &event).is_err() {
 					break;
 				}
+				if !summarizer.running() {
+					let summary = DeployOutputEvent::Summary(summarizer.finish());
+					let _ = bincode::serialize_into(&mut stream_write, &summary);
+					break;
+				}
 			}
 			trace!("BRIDGE: KILLED: {:?}", *hashmap.lock().unwrap());
 			let mut x = hashmap.lock().unwrap();
@@ -421,11 +708,19 @@ pub fn main() {
 			BufferedStream::new(&scheduler),
 		);
 
-		bincode_serialize_into(&mut scheduler_write.write(), &request).unwrap();
+		let mut scheduler_write_ = scheduler_write.write();
+		bincode::serialize_into(&mut scheduler_write_, &SchedulerRequest::Spawn).unwrap();
+		bincode_serialize_into(&mut scheduler_write_, &request).unwrap();
+		drop(scheduler_write_);
 
-		let pid: Result<Pid, TrySpawnError> = bincode::deserialize_from(&mut scheduler_read)
-			.map_err(map_bincode_err)
-			.unwrap();
+		let pid: Result<Pid, TrySpawnError> = loop {
+			let reply: SchedulerReply = bincode::deserialize_from(&mut scheduler_read)
+				.map_err(map_bincode_err)
+				.unwrap();
+			if let SchedulerReply::Done(pid) = reply {
+				break pid;
+			}
+		};
 		sender.send(pid).unwrap();
 		drop((scheduler_read, scheduler_write));
 		let _ = scheduler.into_raw_fd();
@@ -9,7 +9,7 @@ use std::{
 };
 
 use super::master;
-use constellation_internal::{abort_on_unwind, Cpu, Mem, Pid, PidInternal};
+use constellation_internal::{abort_on_unwind, Cpu, Gpu, Mem, Pid, PidInternal};
 
 pub fn kube_master(
 	master_bind: SocketAddr, fabric_port: u16, bridge_bind: SocketAddr, mem: Mem, cpu: Cpu,
@@ -71,18 +71,19 @@ pub fn kube_master(
 				.map(|ip| {
 					let fabric = SocketAddr::new(ip, master_bind.port());
 					let bridge = None;
-					(fabric, (bridge, mem, cpu))
+					(fabric, (bridge, mem, cpu, Gpu::default(), Vec::new()))
 				})
 				.collect::<HashMap<_, _>>(); // TODO: error on clash
 			let _ = nodes.insert(
 				SocketAddr::new(master_addr.ip(), fabric_port),
-				(Some(bridge_bind), mem, cpu),
+				(Some(bridge_bind), mem, cpu, Gpu::default(), Vec::new()),
 			);
 
 			master::run(
 				SocketAddr::new(master_bind.ip(), master_addr.port()),
 				Pid::new(master_addr.ip(), master_addr.port()),
 				nodes,
+				master::Pools::new(HashMap::new()),
 			)
 		}))
 		.unwrap();
@@ -84,19 +84,24 @@ use nix::{fcntl, sys::signal, sys::socket, unistd};
 use palaver::{
 	file::{execve, fexecve, move_fd, move_fds}, process::ChildHandle, socket::{socket, SockFlag}, valgrind
 };
+use sha2::{Digest, Sha256};
 use std::{
-	collections::HashMap, convert::{TryFrom, TryInto}, env, io, io::Seek, net::{IpAddr, SocketAddr, TcpListener}, process, sync, sync::Arc, thread
+	collections::HashMap, convert::{TryFrom, TryInto}, env, ffi::OsString, io, io::{Read, Seek}, net::{IpAddr, SocketAddr, TcpListener}, process, sync, sync::Arc, thread
 };
 #[cfg(unix)]
 use std::{
 	ffi::{CStr, CString}, fs::File, os::unix::{ffi::OsStringExt, io::IntoRawFd}
 };
+#[cfg(all(unix, feature = "distribute_binaries"))]
+use std::os::unix::io::AsRawFd;
 
 #[cfg(feature = "kubernetes")]
 use self::kube::kube_master;
 use constellation_internal::{
-	abort_on_unwind, abort_on_unwind_1, forbid_alloc, map_bincode_err, msg::{bincode_deserialize_from, FabricRequest}, BufferedStream, Cpu, FabricOutputEvent, Fd, Format, Mem, Pid, PidInternal, Trace
+	abort_on_unwind, abort_on_unwind_1, forbid_alloc, map_bincode_err, msg::{bincode_deserialize_from, FabricRequest}, BufferedStream, Cpu, Envs, FabricOutputEvent, Fd, Format, Gpu, Mem, Pid, PidInternal, Trace, TrySpawnError
 };
+#[cfg(feature = "distribute_binaries")]
+use constellation_internal::msg::BinaryCache;
 
 #[derive(PartialEq, Debug)]
 struct Args {
@@ -114,7 +119,7 @@ enum Role {
 		cpu: Cpu,
 		replicas: u32,
 	},
-	Master(SocketAddr, Vec<Node>),
+	Master(SocketAddr, Vec<Node>, Vec<Pool>),
 	Worker(SocketAddr),
 	Bridge,
 }
@@ -124,6 +129,14 @@ struct Node {
 	bridge: Option<SocketAddr>,
 	mem: Mem,
 	cpu: Cpu,
+	gpu: Gpu,
+	labels: Vec<String>,
+}
+#[derive(PartialEq, Debug)]
+struct Pool {
+	name: String,
+	mem: Mem,
+	cpu: Cpu,
 }
 
 const LISTENER_FD: Fd = 3;
@@ -169,7 +182,7 @@ fn main() {
 			);
 			(master_bind.ip(), fabric)
 		}
-		Role::Master(listen, mut nodes) => {
+		Role::Master(listen, mut nodes, pools) => {
 			let fabric = TcpListener::bind(SocketAddr::new(listen.ip(), 0)).unwrap();
 			let master_addr = nodes[0].fabric;
 			nodes[0]
@@ -183,9 +196,17 @@ fn main() {
 					     bridge,
 					     mem,
 					     cpu,
-					 }| { (fabric, (bridge, mem, cpu)) },
+					     gpu,
+					     labels,
+					 }| { (fabric, (bridge, mem, cpu, gpu, labels)) },
 				)
 				.collect::<HashMap<_, _>>(); // TODO: error on clash
+			let pools = master::Pools::new(
+				pools
+					.into_iter()
+					.map(|Pool { name, mem, cpu }| (name, (mem, cpu)))
+					.collect(),
+			);
 			let _ = thread::Builder::new()
 				.name(String::from("master"))
 				.spawn(abort_on_unwind(move || {
@@ -194,6 +215,7 @@ fn main() {
 						SocketAddr::new(listen.ip(), master_addr.port()),
 						Pid::new(master_addr.ip(), master_addr.port()),
 						nodes,
+						pools,
 					)
 				}))
 				.unwrap();
@@ -222,11 +244,43 @@ fn main() {
 		let ip = bincode::deserialize_from::<_, IpAddr>(&mut stream_read);
 		let ip = if let Ok(ip) = ip { ip } else { continue };
 		crossbeam::scope(|scope| {
+			// Mirrored by the scheduler, which only sends binaries we don't have cached
+			#[cfg(feature = "distribute_binaries")]
+			let mut binaries = BinaryCache::new();
 			while let Ok(request) =
 				bincode_deserialize_from(&mut stream_read).map_err(map_bincode_err)
 			{
-				let request: FabricRequest<File, File> = request;
-				let (pid, child) = spawn(listen, ip, request);
+				#[cfg_attr(not(feature = "distribute_binaries"), allow(unused_mut))]
+				let mut request: FabricRequest<File, File> = request;
+				#[cfg(feature = "distribute_binaries")]
+				let binary = {
+					let hash = request.binary_hash.unwrap();
+					if let Some(binary) = request.binary.take() {
+						binaries.insert(hash, binary);
+					}
+					binaries.get(&hash).expect("binary not cached")
+				};
+				let (pid, child) = match spawn(
+					listen,
+					ip,
+					request,
+					#[cfg(feature = "distribute_binaries")]
+					binary,
+				) {
+					Ok(spawned) => spawned,
+					Err(err) => {
+						if bincode::serialize_into(
+							*stream_write.lock().unwrap(),
+							&Either::Left::<Result<Pid, TrySpawnError>, Pid>(Err(err)),
+						)
+						.map_err(map_bincode_err)
+						.is_err()
+						{
+							break;
+						}
+						continue;
+					}
+				};
 				let child = Arc::new(child);
 				let x = pending.write().unwrap().insert(pid, child.clone());
 				assert!(x.is_none());
@@ -236,7 +290,7 @@ fn main() {
 				});
 				if bincode::serialize_into(
 					*stream_write.lock().unwrap(),
-					&Either::Left::<Pid, Pid>(pid),
+					&Either::Left::<Result<Pid, TrySpawnError>, Pid>(Ok(pid)),
 				)
 				.map_err(map_bincode_err)
 				.is_err()
@@ -265,7 +319,7 @@ fn main() {
 					});
 					let _unchecked_error = bincode::serialize_into(
 						*stream_write.lock().unwrap(),
-						&Either::Right::<Pid, Pid>(pid),
+						&Either::Right::<Result<Pid, TrySpawnError>, Pid>(pid),
 					)
 					.map_err(map_bincode_err);
 				}));
@@ -283,7 +337,49 @@ fn main() {
 	}
 }
 
-fn spawn(listen: IpAddr, ip: IpAddr, request: FabricRequest<File, File>) -> (Pid, ChildHandle) {
+/// Check the binary opened by `binary` against the hash in `CONSTELLATION_EXPECTED_HASH`, if it's set, so that a tampered or mismatched binary is refused rather than executed.
+pub(crate) fn verify_binary(
+	vars: &[(OsString, OsString)], binary: impl FnOnce() -> io::Result<File>,
+) -> Result<(), TrySpawnError> {
+	let expected_hash = if let Some(expected_hash) = Envs::from(vars).expected_hash {
+		expected_hash
+	} else {
+		return Ok(());
+	};
+	let mut binary = binary().map_err(|_| TrySpawnError::BinaryHashMismatch)?;
+	let mut hasher = Sha256::new();
+	let mut buf = vec![0; 64 * 1024];
+	let _ = binary
+		.seek(io::SeekFrom::Start(0))
+		.map_err(|_| TrySpawnError::Unknown)?;
+	loop {
+		let n = binary.read(&mut buf).map_err(|_| TrySpawnError::Unknown)?;
+		if n == 0 {
+			break;
+		}
+		hasher.input(&buf[..n]);
+	}
+	let mut hash = [0; 32];
+	hash.copy_from_slice(&hasher.result());
+	if expected_hash != Some(hash) {
+		return Err(TrySpawnError::BinaryHashMismatch);
+	}
+	Ok(())
+}
+
+fn spawn(
+	listen: IpAddr, ip: IpAddr, request: FabricRequest<File, File>,
+	#[cfg(feature = "distribute_binaries")] binary: &File,
+) -> Result<(Pid, ChildHandle), TrySpawnError> {
+	if let Some(cwd) = &request.cwd {
+		if !cwd.is_dir() {
+			return Err(TrySpawnError::CwdNotFound);
+		}
+	}
+	#[cfg(feature = "distribute_binaries")]
+	verify_binary(&request.vars, || binary.try_clone())?;
+	#[cfg(not(feature = "distribute_binaries"))]
+	verify_binary(&request.vars, || File::open(&request.args[0]))?;
 	let process_listener = socket(
 		socket::AddressFamily::Inet,
 		socket::SockType::Stream,
@@ -354,11 +450,10 @@ fn spawn(listen: IpAddr, ip: IpAddr, request: FabricRequest<File, File>) -> (Pid
 	let args: Vec<&CStr> = args.iter().map(|x| &**x).collect();
 	let vars: Vec<&CStr> = vars.iter().map(|x| &**x).collect();
 
-	#[cfg(feature = "distribute_binaries")]
-	let binary = request.binary;
 	let mut binary_desired_fd = BOUND_FD_START + Fd::try_from(request.bind.len()).unwrap();
 	let arg = request.arg;
 	let bind = request.bind;
+	let cwd = request.cwd;
 
 	let child = match palaver::process::fork(false).expect("Fork failed") {
 		palaver::process::ForkResult::Child => {
@@ -370,8 +465,8 @@ fn spawn(listen: IpAddr, ip: IpAddr, request: FabricRequest<File, File>) -> (Pid
 				// println!("{:?}", args[0]);
 				unistd::setpgid(unistd::Pid::from_raw(0), unistd::Pid::from_raw(0)).unwrap();
 				#[cfg(feature = "distribute_binaries")]
-				let binary = binary.into_raw_fd(); // These are dropped by parent
-				let arg = arg.into_raw_fd();
+				let binary = binary.as_raw_fd(); // The parent keeps this cached for later spawns
+				let arg = arg.into_raw_fd(); // This is dropped by parent
 				move_fds(
 					&mut [
 						(arg, ARG_FD),
@@ -407,6 +502,9 @@ fn spawn(listen: IpAddr, ip: IpAddr, request: FabricRequest<File, File>) -> (Pid
 					)
 					.unwrap();
 				}
+				if let Some(cwd) = &cwd {
+					unistd::chdir(&**cwd).expect("Failed to chdir for fabric");
+				}
 				if cfg!(feature = "distribute_binaries") {
 					if valgrind::is().unwrap_or(false) {
 						let binary_desired_fd_ = valgrind::start_fd() - 1;
@@ -430,5 +528,5 @@ fn spawn(listen: IpAddr, ip: IpAddr, request: FabricRequest<File, File>) -> (Pid
 		palaver::process::ForkResult::Parent(child) => child,
 	};
 	unistd::close(process_listener).unwrap();
-	(pid, child)
+	Ok((pid, child))
 }
@@ -2,55 +2,172 @@
 
 use either::Either;
 use std::{
-	collections::{HashMap, VecDeque}, env, ffi::OsString, net::{IpAddr, SocketAddr, TcpListener, TcpStream}, sync::mpsc::{sync_channel, SyncSender}, thread, time::{Duration, Instant}
+	collections::{HashMap, VecDeque}, env, ffi::OsString, iter, mem, net::{IpAddr, SocketAddr, TcpListener, TcpStream}, sync::mpsc::{sync_channel, SyncSender}, thread, time::{Duration, Instant}
 };
 
 use constellation_internal::{
-	abort_on_unwind, abort_on_unwind_1, map_bincode_err, msg::{bincode_deserialize_from, FabricRequest, SchedulerArg}, BufferedStream, Cpu, Mem, Pid, PidInternal, Resources, TrySpawnError
+	abort_on_unwind, abort_on_unwind_1, map_bincode_err, msg::{bincode_deserialize_from, FabricRequest, SchedulerArg, SchedulerReply, SchedulerRequest}, BufferedStream, Cpu, Envs, Gpu, Mem, NodeSelector, PendingReason, Pid, Placement, PidInternal, ResourceError, Resources, TrySpawnError
 };
+#[cfg(feature = "distribute_binaries")]
+use constellation_internal::msg::{BinaryCache, BinaryHash};
+
+/// The binaries of running processes, with how many are running each.
+#[cfg(feature = "distribute_binaries")]
+type Binaries = HashMap<BinaryHash, (Vec<u8>, usize)>;
 
 #[derive(Debug)]
 pub struct Node {
 	mem: Mem,
 	cpu: Cpu,
+	gpu: Gpu,
+	labels: Vec<String>,
+	#[cfg(feature = "distribute_binaries")]
+	binaries: BinaryCache<()>,
 }
 impl Node {
 	fn fits(&self, process: &Resources) -> bool {
-		process.mem <= self.mem && process.cpu <= self.cpu
+		process.mem <= self.mem && process.cpu <= self.cpu && process.gpu <= self.gpu
 	}
 
 	fn alloc(&mut self, process: &Resources) {
 		assert!(process.cpu <= self.cpu);
 		self.mem -= process.mem;
 		self.cpu -= process.cpu;
+		self.gpu -= process.gpu;
 	}
 
 	fn free(&mut self, process: &Resources) {
 		self.mem += process.mem;
 		self.cpu += process.cpu;
+		self.gpu += process.gpu;
+	}
+
+	/// The binary to send with a request to this node, or `None` if it already has it cached.
+	#[cfg(feature = "distribute_binaries")]
+	fn binary(&mut self, hash: BinaryHash, binaries: &Binaries) -> Option<Vec<u8>> {
+		if self.binaries.get(&hash).is_some() {
+			return None;
+		}
+		self.binaries.insert(hash, ());
+		Some(binaries[&hash].0.clone())
+	}
+}
+
+/// The resource pools configured on the cluster and their free resources.
+///
+/// A pool named like `team/project` is nested within `team`, if that's also configured, so a process allocated from it counts against both.
+#[derive(Debug)]
+pub struct Pools(HashMap<String, (Mem, Cpu)>);
+impl Pools {
+	pub fn new(pools: HashMap<String, (Mem, Cpu)>) -> Self {
+		Self(pools)
+	}
+
+	fn contains(&self, pool: &str) -> bool {
+		self.0.contains_key(pool)
+	}
+
+	/// `pool` and the configured pools it's nested within.
+	fn chain<'a>(&'a self, pool: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		pool.match_indices('/')
+			.map(move |(i, _)| &pool[..i])
+			.chain(iter::once(pool))
+			.filter(move |pool| self.0.contains_key(*pool))
+	}
+
+	fn fits(&self, pool: Option<&str>, process: &Resources) -> bool {
+		pool.map_or(true, |pool| {
+			self.chain(pool).all(|pool| {
+				let (mem, cpu) = self.0[pool];
+				process.mem <= mem && process.cpu <= cpu
+			})
+		})
+	}
+
+	fn alloc(&mut self, pool: Option<&str>, process: &Resources) {
+		if let Some(pool) = pool {
+			for pool in self.chain(pool).map(String::from).collect::<Vec<_>>() {
+				let (mem, cpu) = self.0.get_mut(&pool).unwrap();
+				assert!(process.mem <= *mem && process.cpu <= *cpu);
+				*mem -= process.mem;
+				*cpu -= process.cpu;
+			}
+		}
+	}
+
+	fn free(&mut self, pool: Option<&str>, process: &Resources) {
+		if let Some(pool) = pool {
+			for pool in self.chain(pool).map(String::from).collect::<Vec<_>>() {
+				let (mem, cpu) = self.0.get_mut(&pool).unwrap();
+				*mem += process.mem;
+				*cpu += process.cpu;
+			}
+		}
 	}
 }
 
+/// A fabric node: the channel to its connection, its free resources, its IP, and the processes it's been asked to spawn that it's yet to reply about.
+type NodeState = (
+	SyncSender<FabricRequest<Vec<u8>, Vec<u8>>>,
+	Node,
+	IpAddr,
+	VecDeque<(SyncSender<SchedulerReply>, Process)>,
+);
+/// The processes that have been spawned and not yet exited, by the index of their node.
+type Processes = HashMap<(usize, Pid), Process>;
+/// The requests of a batch spawned all or nothing, each with the sender for its `Done`.
+type Batch = Vec<(FabricRequest<Vec<u8>, Vec<u8>>, SyncSender<SchedulerReply>)>;
+/// A spawn request waiting for capacity, with its position in the queue, its job and its pool.
+type Blocked = (
+	FabricRequest<Vec<u8>, Vec<u8>>,
+	SyncSender<SchedulerReply>,
+	usize,
+	Option<u64>,
+	Option<String>,
+);
+
+#[derive(Debug)]
+struct Process {
+	resources: Resources,
+	job: Option<u64>,
+	pool: Option<String>,
+	#[cfg(feature = "distribute_binaries")]
+	binary: BinaryHash,
+}
+
 pub fn run(
 	bind_addr: SocketAddr, master_pid: Pid,
-	nodes: HashMap<SocketAddr, (Option<SocketAddr>, Mem, Cpu)>,
+	nodes: HashMap<SocketAddr, (Option<SocketAddr>, Mem, Cpu, Gpu, Vec<String>)>, mut pools: Pools,
 ) {
 	let (sender, receiver) = sync_channel::<
 		Either<
-			(
-				FabricRequest<Vec<u8>, Vec<u8>>,
-				SyncSender<Result<Pid, TrySpawnError>>,
-				Option<usize>,
-			),
-			(usize, Either<Pid, Pid>),
+			Either<
+				Either<
+					(
+						FabricRequest<Vec<u8>, Vec<u8>>,
+						SyncSender<SchedulerReply>,
+						Option<usize>,
+					),
+					(Batch, SyncSender<SchedulerReply>),
+				>,
+				(Pid, Resources, SyncSender<SchedulerReply>),
+			>,
+			(usize, Either<Result<Pid, TrySpawnError>, Pid>),
 		>,
 	>(0);
 
 	let mut nodes = nodes
 		.into_iter()
 		.enumerate()
-		.map(|(i, (fabric, (bridge, mem, cpu)))| {
-			let node = Node { mem, cpu };
+		.map(|(i, (fabric, (bridge, mem, cpu, gpu, labels)))| {
+			let node = Node {
+				mem,
+				cpu,
+				gpu,
+				labels,
+				#[cfg(feature = "distribute_binaries")]
+				binaries: BinaryCache::new(),
+			};
 			let (sender_a, receiver_a) = sync_channel::<FabricRequest<Vec<u8>, Vec<u8>>>(0);
 			let start = Instant::now();
 			let stream = loop {
@@ -82,9 +199,11 @@ pub fn run(
 									.unwrap();
 							}
 						}));
-						while let Ok(done) =
-							bincode::deserialize_from::<_, Either<Pid, Pid>>(&mut stream_read)
-								.map_err(map_bincode_err)
+						while let Ok(done) = bincode::deserialize_from::<
+							_,
+							Either<Result<Pid, TrySpawnError>, Pid>,
+						>(&mut stream_read)
+						.map_err(map_bincode_err)
 						{
 							sender.send(Either::Right((i, done))).unwrap();
 						}
@@ -105,14 +224,15 @@ pub fn run(
 						};
 						#[cfg(not(feature = "distribute_binaries"))]
 						let binary = std::marker::PhantomData;
-						let (sender_, receiver) = sync_channel::<Result<Pid, TrySpawnError>>(0);
+						let (sender_, receiver) = sync_channel::<SchedulerReply>(0);
 						sender
-							.send(Either::Left((
+							.send(Either::Left(Either::Left(Either::Left((
 								FabricRequest {
 									block: false,
 									resources: Resources {
 										mem: 0 * Mem::B,
 										cpu: 0 * Cpu::CORE,
+										gpu: 0 * Gpu::DEVICE,
 									},
 									bind: vec![bridge],
 									args: vec![
@@ -120,14 +240,26 @@ pub fn run(
 										OsString::from("bridge"),
 									],
 									vars: Vec::new(),
+									cwd: None,
+									near: None,
+									placement: None,
+									#[cfg(feature = "distribute_binaries")]
+									binary_hash: None,
+									#[cfg(feature = "distribute_binaries")]
+									binary: Some(binary),
+									#[cfg(not(feature = "distribute_binaries"))]
 									binary,
 									arg: Vec::new(),
 								},
 								sender_,
 								Some(i),
-							)))
+							)))))
 							.unwrap();
-						let _pid: Pid = receiver.recv().unwrap().unwrap();
+						let _pid: Pid = match receiver.recv().unwrap() {
+							SchedulerReply::Done(pid) => pid.unwrap(),
+							// bridges are forced onto their node
+							SchedulerReply::Pending(_) | SchedulerReply::Updated(_) => unreachable!(),
+						};
 						// println!("bridge at {:?}", pid);
 					}))
 					.unwrap();
@@ -151,14 +283,81 @@ pub fn run(
 						let (mut stream_read, mut stream_write) =
 							(BufferedStream::new(&stream), &stream);
 						while let Ok(request) =
-							bincode_deserialize_from(&mut stream_read).map_err(map_bincode_err)
+							bincode::deserialize_from(&mut stream_read).map_err(map_bincode_err)
 						{
+							let (sender_, receiver) = sync_channel::<SchedulerReply>(0);
+							if let SchedulerRequest::UpdateResources(pid, resources) = request {
+								sender
+									.send(Either::Left(Either::Right((pid, resources, sender_))))
+									.unwrap();
+								let reply = receiver.recv().unwrap();
+								if bincode::serialize_into(&mut stream_write, &reply).is_err() {
+									break;
+								}
+								continue;
+							}
+							if let SchedulerRequest::SpawnMany(count) = request {
+								let requests = match (0..count)
+									.map(|_| {
+										bincode_deserialize_from(&mut stream_read)
+											.map_err(map_bincode_err)
+									})
+									.collect::<Result<Vec<_>, _>>()
+								{
+									Ok(requests) => requests,
+									Err(_) => break,
+								};
+								// Each process gets a single Done, buffered so the scheduler needn't wait for them to be forwarded in order
+								let (batch, receivers): (Batch, Vec<_>) = requests
+									.into_iter()
+									.map(|request| {
+										let (sender, receiver) = sync_channel(1);
+										((request, sender), receiver)
+									})
+									.unzip();
+								sender
+									.send(Either::Left(Either::Left(Either::Right((
+										batch, sender_,
+									)))))
+									.unwrap();
+								// Pending until the batch is admitted or rejected, when the scheduler drops `sender_`
+								let mut ok = true;
+								for reply in receiver.iter().chain(
+									receivers.iter().map(|receiver| receiver.recv().unwrap()),
+								) {
+									ok = ok
+										&& bincode::serialize_into(&mut stream_write, &reply)
+											.is_ok();
+								}
+								if !ok {
+									break;
+								}
+								continue;
+							}
+							let request = match bincode_deserialize_from(&mut stream_read)
+								.map_err(map_bincode_err)
+							{
+								Ok(request) => request,
+								Err(_) => break,
+							};
 							// println!("parsed");
-							let (sender_, receiver) = sync_channel::<Result<Pid, TrySpawnError>>(0);
-							sender.send(Either::Left((request, sender_, None))).unwrap();
-							let pid: Result<Pid, TrySpawnError> = receiver.recv().unwrap();
-							// let mut stream_write = stream_write.write();
-							if bincode::serialize_into(&mut stream_write, &pid).is_err() {
+							sender
+								.send(Either::Left(Either::Left(Either::Left((
+									request, sender_, None,
+								)))))
+								.unwrap();
+							// Receive until Done even if the connection fails, so the scheduler doesn't block sending to us
+							let mut ok = true;
+							loop {
+								let reply = receiver.recv().unwrap();
+								// let mut stream_write = stream_write.write();
+								ok = ok
+									&& bincode::serialize_into(&mut stream_write, &reply).is_ok();
+								if let SchedulerReply::Done(_) = reply {
+									break;
+								}
+							}
+							if !ok {
 								break;
 							}
 						}
@@ -168,32 +367,142 @@ pub fn run(
 		}))
 		.unwrap();
 
-	let mut processes: HashMap<(usize, Pid), Resources> = HashMap::new();
+	let mut processes: Processes = HashMap::new();
 
 	let mut blocked = Vec::new();
 
-	for msg in receiver.iter() {
+	// Processes spawned by each deployment: (live, total). Bridges aren't counted.
+	let mut jobs: HashMap<u64, (usize, usize)> = HashMap::new();
+
+	// Requests carry either a binary, or the hash of one we have because the requesting process is running it
+	#[cfg(feature = "distribute_binaries")]
+	let mut binaries: Binaries = HashMap::new();
+
+	// The requests of an admitted batch, spawned before anything else is received so that nothing takes their capacity
+	let mut admitted = VecDeque::new();
+
+	// Batches waiting for capacity for all their processes, with the sender for their `Pending`
+	let mut blocked_batches: Vec<(Batch, SyncSender<SchedulerReply>)> = Vec::new();
+
+	loop {
+		let msg = if let Some((request, sender)) = admitted.pop_front() {
+			Either::Left(Either::Left(Either::Left((request, sender, None))))
+		} else if let Some(batch) = unblock_batch(
+			&mut nodes,
+			&mut pools,
+			&processes,
+			&jobs,
+			&mut blocked_batches,
+			#[cfg(feature = "distribute_binaries")]
+			&binaries,
+		) {
+			admitted.extend(batch);
+			continue;
+		} else if let Ok(msg) = receiver.recv() {
+			msg
+		} else {
+			break;
+		};
 		match msg {
-			Either::Left((mut request, sender, force)) => {
+			Either::Left(Either::Left(Either::Left((mut request, sender, force)))) => {
 				// println!("spawn {:?}", request.resources);
-				let node = force.or_else(|| {
-					nodes
-						.iter()
-						.position(|node| node.1.fits(&request.resources))
-				});
+				#[cfg(feature = "distribute_binaries")]
+				let binary = if let Some(binary) = request.binary.take() {
+					let hash = sha1::Sha1::from(&binary).digest().bytes();
+					binaries.entry(hash).or_insert((binary, 0)).1 += 1;
+					hash
+				} else if let Some(binary) = binaries.get_mut(&request.binary_hash.unwrap()) {
+					binary.1 += 1;
+					request.binary_hash.unwrap()
+				} else {
+					// The requesting process has already exited
+					sender
+						.send(SchedulerReply::Done(Err(TrySpawnError::Unknown)))
+						.unwrap();
+					continue;
+				};
+				#[cfg(feature = "distribute_binaries")]
+				{
+					request.binary_hash = Some(binary);
+				}
+				let envs = Envs::from(&request.vars);
+				let job = envs.job.and_then(|job| job).filter(|_| force.is_none());
+				let pool = envs.pool.and_then(|pool| pool).filter(|_| force.is_none());
+				if pool.as_ref().map_or(false, |pool| !pools.contains(pool)) {
+					#[cfg(feature = "distribute_binaries")]
+					release_binary(&mut binaries, binary);
+					sender
+						.send(SchedulerReply::Done(Err(TrySpawnError::UnknownPool)))
+						.unwrap();
+					continue;
+				}
+				if let Some(job) = job {
+					let count = jobs.entry(job).or_insert((0, 0));
+					if envs
+						.process_limit
+						.and_then(|limit| limit)
+						.map_or(false, |limit| count.1 >= limit)
+					{
+						if count.0 == 0 {
+							let _ = jobs.remove(&job);
+						}
+						#[cfg(feature = "distribute_binaries")]
+						release_binary(&mut binaries, binary);
+						sender
+							.send(SchedulerReply::Done(Err(
+								TrySpawnError::ProcessLimitExceeded,
+							)))
+							.unwrap();
+						continue;
+					}
+					count.0 += 1;
+					count.1 += 1;
+				}
+				let node = match force {
+					Some(node) => Ok(Some(node)),
+					None => place(&nodes, &pools, &processes, &request, pool.as_deref()),
+				};
+				let node = match node {
+					Ok(node) => node,
+					Err(reason) => {
+						release(&mut jobs, job);
+						#[cfg(feature = "distribute_binaries")]
+						release_binary(&mut binaries, binary);
+						sender
+							.send(SchedulerReply::Done(Err(TrySpawnError::Unsatisfiable {
+								reason,
+							})))
+							.unwrap();
+						continue;
+					}
+				};
 				if let Some(node) = node {
 					let node = &mut nodes[node];
 					node.1.alloc(&request.resources);
+					pools.alloc(pool.as_deref(), &request.resources);
+					#[cfg(feature = "distribute_binaries")]
+					{
+						request.binary = node.1.binary(binary, &binaries);
+					}
 
 					bincode::serialize_into(
 						&mut request.arg,
 						&SchedulerArg {
 							ip: node.2,
 							scheduler: master_pid,
+							#[cfg(feature = "distribute_binaries")]
+							binary,
 						},
 					)
 					.unwrap();
-					node.3.push_back((sender, request.resources));
+					let process = Process {
+						resources: request.resources,
+						job,
+						pool,
+						#[cfg(feature = "distribute_binaries")]
+						binary,
+					};
+					node.3.push_back((sender, process));
 					node.0.send(request).unwrap();
 				} else {
 					// println!(
@@ -201,52 +510,365 @@ pub fn run(
 					// 	resources, nodes
 					// );
 					if request.block {
-						blocked.push((request, sender));
+						let position = blocked.len();
+						sender
+							.send(SchedulerReply::Pending(PendingReason::NoCapacity {
+								position,
+							}))
+							.unwrap();
+						blocked.push((request, sender, position, job, pool));
 					} else {
-						sender.send(Err(TrySpawnError::NoCapacity)).unwrap();
+						release(&mut jobs, job);
+						#[cfg(feature = "distribute_binaries")]
+						release_binary(&mut binaries, binary);
+						sender
+							.send(SchedulerReply::Done(Err(TrySpawnError::NoCapacity)))
+							.unwrap();
+					}
+				}
+			}
+			Either::Left(Either::Left(Either::Right((batch, sender)))) => {
+				match fits_batch(
+					&mut nodes,
+					&mut pools,
+					&processes,
+					&jobs,
+					&batch,
+					#[cfg(feature = "distribute_binaries")]
+					&binaries,
+				) {
+					Ok(()) => admitted.extend(batch),
+					Err(None) if batch.first().map_or(false, |(request, _)| request.block) => {
+						let position = blocked_batches.len();
+						sender
+							.send(SchedulerReply::Pending(PendingReason::NoCapacity {
+								position,
+							}))
+							.unwrap();
+						blocked_batches.push((batch, sender));
 					}
+					Err(err) => reject_batch(batch, &err.unwrap_or(TrySpawnError::NoCapacity)),
 				}
 			}
-			Either::Right((node_, Either::Left(pid))) => {
+			Either::Left(Either::Right((pid, resources, sender))) => {
+				let process = processes.iter_mut().find(|&(&(_, pid_), _)| pid_ == pid);
+				let result = if let Some((&(node, _), process)) = process {
+					let node_ = &mut nodes[node].1;
+					let pool = process.pool.as_deref();
+					node_.free(&process.resources);
+					pools.free(pool, &process.resources);
+					let result = if node_.fits(&resources) && pools.fits(pool, &resources) {
+						process.resources = resources;
+						Ok(())
+					} else {
+						Err(ResourceError::NoCapacity)
+					};
+					node_.alloc(&process.resources);
+					pools.alloc(pool, &process.resources);
+					result
+				} else {
+					Err(ResourceError::Unknown)
+				};
+				sender.send(SchedulerReply::Updated(result)).unwrap();
+				// Shrinking may have made room for blocked requests
+				unblock(
+					&mut nodes,
+					&mut pools,
+					&processes,
+					&mut jobs,
+					&mut blocked,
+					master_pid,
+					#[cfg(feature = "distribute_binaries")]
+					&mut binaries,
+				);
+			}
+			Either::Right((node_, Either::Left(Ok(pid)))) => {
 				// println!("init {}:{} ({})", node_, pid, processes.len());
 				let node = &mut nodes[node_];
 				let (sender, process) = node.3.pop_front().unwrap();
 				let x = processes.insert((node_, pid), process);
 				assert!(x.is_none());
-				sender.send(Ok(pid)).unwrap();
+				sender.send(SchedulerReply::Done(Ok(pid))).unwrap();
 			}
-			Either::Right((node, Either::Right(pid))) => {
-				let process = processes.remove(&(node, pid)).unwrap();
+			Either::Right((node, done)) => {
+				let process = match done {
+					Either::Left(Err(err)) => {
+						// The fabric couldn't spawn the process
+						let (sender, process) = nodes[node].3.pop_front().unwrap();
+						sender.send(SchedulerReply::Done(Err(err))).unwrap();
+						process
+					}
+					Either::Right(pid) => processes.remove(&(node, pid)).unwrap(),
+					Either::Left(Ok(_)) => unreachable!(),
+				};
 				// println!("done {}:{} ({})", node, pid, processes.len());
 				let node = &mut nodes[node];
-				node.1.free(&process);
-				blocked = blocked
-					.into_iter()
-					.filter_map(|(mut request, sender)| {
-						if let Some(node) = nodes
-							.iter()
-							.position(|node| node.1.fits(&request.resources))
-						{
-							let node = &mut nodes[node];
-							node.1.alloc(&request.resources);
-
-							bincode::serialize_into(
-								&mut request.arg,
-								&SchedulerArg {
-									ip: node.2,
-									scheduler: master_pid,
-								},
-							)
-							.unwrap();
-							node.3.push_back((sender, request.resources));
-							node.0.send(request).unwrap();
-							None
-						} else {
-							Some((request, sender))
-						}
-					})
-					.collect();
+				node.1.free(&process.resources);
+				pools.free(process.pool.as_deref(), &process.resources);
+				release(&mut jobs, process.job);
+				#[cfg(feature = "distribute_binaries")]
+				release_binary(&mut binaries, process.binary);
+				unblock(
+					&mut nodes,
+					&mut pools,
+					&processes,
+					&mut jobs,
+					&mut blocked,
+					master_pid,
+					#[cfg(feature = "distribute_binaries")]
+					&mut binaries,
+				);
+			}
+		}
+	}
+}
+
+/// The node to allocate `request` to: of those meeting its placement constraint, the first it would be near that has capacity, otherwise the first that does. This is `None` if `pool` doesn't have capacity for it, and an error describing why if no node meets the constraint.
+fn place(
+	nodes: &[NodeState], pools: &Pools, processes: &Processes,
+	request: &FabricRequest<Vec<u8>, Vec<u8>>, pool: Option<&str>,
+) -> Result<Option<usize>, String> {
+	let node_of = |pid: Pid| {
+		processes
+			.keys()
+			.find(|&&(_, pid_)| pid_ == pid)
+			.map(|&(node, _)| node)
+	};
+	let allowed = match &request.placement {
+		None => vec![true; nodes.len()],
+		Some(Placement::CoLocate(pid)) => {
+			let node = node_of(*pid)
+				.ok_or_else(|| format!("process {} to colocate with isn't running", pid))?;
+			(0..nodes.len()).map(|i| i == node).collect()
+		}
+		Some(Placement::Spread(pids)) => {
+			let mut allowed = vec![true; nodes.len()];
+			for node in pids.iter().filter_map(|&pid| node_of(pid)) {
+				allowed[node] = false;
+			}
+			if !allowed.contains(&true) {
+				return Err(format!(
+					"every node is running one of the processes to spread from: {:?}",
+					pids
+				));
 			}
+			allowed
 		}
+	};
+	if !pools.fits(pool, &request.resources) {
+		return Ok(None);
+	}
+	let nodes = nodes
+		.iter()
+		.enumerate()
+		.filter(|&(i, _)| allowed[i])
+		.collect::<Vec<_>>();
+	let fits = |&(_, node): &(usize, &NodeState)| node.1.fits(&request.resources);
+	let matches = |&(_, node): &(usize, &NodeState), near: &NodeSelector| match near {
+		NodeSelector::Host(ip) => node.2 == *ip,
+		NodeSelector::Label(label) => node.1.labels.contains(label),
+		NodeSelector::SameAs(pid) => node.2 == pid.addr().ip(),
+	};
+	Ok(request
+		.near
+		.as_ref()
+		.and_then(|near| nodes.iter().find(|node| matches(node, near) && fits(node)))
+		.or_else(|| nodes.iter().find(|node| fits(node)))
+		.map(|&(i, _)| i))
+}
+
+/// Allocate the blocked requests that now fit, fail those whose placement constraint can no longer be met, and tell the rest if their position in the queue has changed.
+fn unblock(
+	nodes: &mut [NodeState], pools: &mut Pools, processes: &Processes,
+	jobs: &mut HashMap<u64, (usize, usize)>, blocked: &mut Vec<Blocked>, master_pid: Pid,
+	#[cfg(feature = "distribute_binaries")] binaries: &mut Binaries,
+) {
+	*blocked = mem::take(blocked)
+		.into_iter()
+		.filter_map(|(mut request, sender, position, job, pool)| {
+			let node = match place(nodes, pools, processes, &request, pool.as_deref()) {
+				Ok(node) => node,
+				Err(reason) => {
+					// For example the process to colocate with has since exited
+					release(jobs, job);
+					#[cfg(feature = "distribute_binaries")]
+					release_binary(binaries, request.binary_hash.unwrap());
+					sender
+						.send(SchedulerReply::Done(Err(TrySpawnError::Unsatisfiable {
+							reason,
+						})))
+						.unwrap();
+					return None;
+				}
+			};
+			if let Some(node) = node {
+				let node = &mut nodes[node];
+				node.1.alloc(&request.resources);
+				pools.alloc(pool.as_deref(), &request.resources);
+				#[cfg(feature = "distribute_binaries")]
+				let binary = request.binary_hash.unwrap();
+				#[cfg(feature = "distribute_binaries")]
+				{
+					request.binary = node.1.binary(binary, binaries);
+				}
+
+				bincode::serialize_into(
+					&mut request.arg,
+					&SchedulerArg {
+						ip: node.2,
+						scheduler: master_pid,
+						#[cfg(feature = "distribute_binaries")]
+						binary,
+					},
+				)
+				.unwrap();
+				let process = Process {
+					resources: request.resources,
+					job,
+					pool,
+					#[cfg(feature = "distribute_binaries")]
+					binary,
+				};
+				node.3.push_back((sender, process));
+				node.0.send(request).unwrap();
+				None
+			} else {
+				Some((request, sender, position, job, pool))
+			}
+		})
+		.collect();
+	for (position, (_, sender, position_, _, _)) in blocked.iter_mut().enumerate() {
+		if *position_ != position {
+			*position_ = position;
+			sender
+				.send(SchedulerReply::Pending(PendingReason::NoCapacity {
+					position,
+				}))
+				.unwrap();
+		}
+	}
+}
+
+/// A process of `job` has exited, or failed to spawn. Forget the job once it has no processes left.
+/// Whether every process of `batch` can be spawned now, each placed around those before it, otherwise why not: `None` if there isn't capacity for them all.
+fn fits_batch(
+	nodes: &mut [NodeState], pools: &mut Pools, processes: &Processes,
+	jobs: &HashMap<u64, (usize, usize)>, batch: &Batch,
+	#[cfg(feature = "distribute_binaries")] binaries: &Binaries,
+) -> Result<(), Option<TrySpawnError>> {
+	// Allocations made to place the rest of the batch, undone once it's checked
+	let mut allocated = Vec::new();
+	let mut spawned: HashMap<u64, usize> = HashMap::new();
+	let result = batch.iter().try_for_each(|(request, _)| {
+		#[cfg(feature = "distribute_binaries")]
+		{
+			if request.binary.is_none() && !binaries.contains_key(&request.binary_hash.unwrap()) {
+				return Err(Some(TrySpawnError::Unknown));
+			}
+		}
+		let envs = Envs::from(&request.vars);
+		let pool = envs.pool.and_then(|pool| pool);
+		if pool.as_ref().map_or(false, |pool| !pools.contains(pool)) {
+			return Err(Some(TrySpawnError::UnknownPool));
+		}
+		if let Some(job) = envs.job.and_then(|job| job) {
+			let count = spawned.entry(job).or_insert(0);
+			*count += 1;
+			let total = jobs.get(&job).map_or(0, |count| count.1) + *count;
+			if envs
+				.process_limit
+				.and_then(|limit| limit)
+				.map_or(false, |limit| total > limit)
+			{
+				return Err(Some(TrySpawnError::ProcessLimitExceeded));
+			}
+		}
+		let node = place(nodes, pools, processes, request, pool.as_deref())
+			.map_err(|reason| Some(TrySpawnError::Unsatisfiable { reason }))?
+			.ok_or(None)?;
+		nodes[node].1.alloc(&request.resources);
+		pools.alloc(pool.as_deref(), &request.resources);
+		allocated.push((node, request.resources, pool));
+		Ok(())
+	});
+	for (node, resources, pool) in allocated {
+		nodes[node].1.free(&resources);
+		pools.free(pool.as_deref(), &resources);
+	}
+	result
+}
+
+/// Reply to each process of `batch` that it couldn't be spawned.
+fn reject_batch(batch: Batch, err: &TrySpawnError) {
+	for (_, sender) in batch {
+		sender.send(SchedulerReply::Done(Err(err.clone()))).unwrap();
+	}
+}
+
+/// Take the first blocked batch that can now be spawned, rejecting any that never can be.
+fn unblock_batch(
+	nodes: &mut [NodeState], pools: &mut Pools, processes: &Processes,
+	jobs: &HashMap<u64, (usize, usize)>, blocked: &mut Vec<(Batch, SyncSender<SchedulerReply>)>,
+	#[cfg(feature = "distribute_binaries")] binaries: &Binaries,
+) -> Option<Batch> {
+	let mut admitted = None;
+	let len = blocked.len();
+	*blocked = mem::take(blocked)
+		.into_iter()
+		.filter_map(|(batch, sender)| {
+			if admitted.is_some() {
+				return Some((batch, sender));
+			}
+			match fits_batch(
+				nodes,
+				pools,
+				processes,
+				jobs,
+				&batch,
+				#[cfg(feature = "distribute_binaries")]
+				binaries,
+			) {
+				Ok(()) => {
+					admitted = Some(batch);
+					None
+				}
+				Err(None) => Some((batch, sender)),
+				Err(Some(err)) => {
+					// For example the process to colocate with has since exited
+					reject_batch(batch, &err);
+					None
+				}
+			}
+		})
+		.collect();
+	if blocked.len() != len {
+		for (position, (_, sender)) in blocked.iter().enumerate() {
+			sender
+				.send(SchedulerReply::Pending(PendingReason::NoCapacity {
+					position,
+				}))
+				.unwrap();
+		}
+	}
+	admitted
+}
+
+fn release(jobs: &mut HashMap<u64, (usize, usize)>, job: Option<u64>) {
+	if let Some(job) = job {
+		let count = jobs.get_mut(&job).unwrap();
+		count.0 -= 1;
+		if count.0 == 0 {
+			let _ = jobs.remove(&job);
+		}
+	}
+}
+
+/// A process running `binary` has exited, or failed to spawn. Forget the binary once no processes are running it.
+#[cfg(feature = "distribute_binaries")]
+fn release_binary(binaries: &mut Binaries, binary: BinaryHash) {
+	let count = &mut binaries.get_mut(&binary).unwrap().1;
+	*count -= 1;
+	if *count == 0 {
+		let _ = binaries.remove(&binary);
 	}
 }
@@ -10,10 +10,23 @@
 //! ```text
 //! -h --help          Show this screen.
 //! -V --version       Show version.
-//! --format=<fmt>     Output format [possible values: human, json] [defa ult: human]
+//! --format=<fmt>     Output format [possible values: human, json, protobuf] [defa ult: human]
+//! --labels=<labels>  Only show human output from processes with these labels, such as coordinator,-worker
+//! --process-limit=<n>  Maximum number of processes the deployment may spawn in total
+//! --expected-hash=<sha256>  Refuse to run any process of the deployment whose binary doesn't have this SHA-256 hash
+//! --restarts=<n>     Times to restart the job if it exits abnormally [default: 0]
+//! --restart-backoff=<secs>  Delay before the first restart, doubling after each [default: 1]
 //! ```
 //!
-//! Note: --format can also be given as an env var, such as `CONSTELLATION_FORMAT=json`
+//! Note: --format can also be given as an env var, such as `CONSTELLATION_FORMAT=json`. protobuf requires the `protobuf` feature.
+//!
+//! Note: --labels can also be given as an env var, such as `CONSTELLATION_LABELS=coordinator`. Labels prefixed with `-` are excluded, and a trailing `*` matches any suffix. Processes are labelled with `SpawnOptions::label`.
+//!
+//! Note: --process-limit can also be given as an env var, such as `CONSTELLATION_PROCESS_LIMIT=1000`. Spawns beyond it fail with `ProcessLimitExceeded`.
+//!
+//! Note: --expected-hash can also be given as an env var, such as `CONSTELLATION_EXPECTED_HASH=<64 hex digits>`. Spawns whose binary doesn't match fail with `BinaryHashMismatch`.
+//!
+//! Note: --restarts reruns the whole job, all its processes, if any exits abnormally. Only the first run is given stdin.
 
 #![cfg_attr(feature = "nightly", feature(read_initializer))]
 #![warn(
@@ -29,9 +42,9 @@
 )] // from https://github.com/rust-unofficial/patterns/blob/master/anti_patterns/deny-warnings.md
 
 use serde::Deserialize;
-use std::{env, ffi::OsString, iter, net::SocketAddr, path, process};
+use std::{env, ffi::OsString, iter, net::SocketAddr, path, process, time::Duration};
 
-use constellation_internal::{Envs, Format};
+use constellation_internal::{hash_from_hex, hash_to_hex, Envs, Format, LabelFilter};
 
 const USAGE: &str = "Run a binary on a constellation cluster.
 
@@ -41,15 +54,33 @@ USAGE:
 OPTIONS:
     -h --help          Show this screen.
     -V --version       Show version.
-    --format=<fmt>     Output format [possible values: human, json] [defa ult: human]
+    --format=<fmt>     Output format [possible values: human, json, protobuf] [defa ult: human]
+    --labels=<labels>  Only show human output from processes with these labels, such as coordinator,-worker
+    --process-limit=<n>  Maximum number of processes the deployment may spawn in total
+    --expected-hash=<sha256>  Refuse to run any process of the deployment whose binary doesn't have this SHA-256 hash
+    --restarts=<n>     Times to restart the job if it exits abnormally [default: 0]
+    --restart-backoff=<secs>  Delay before the first restart, doubling after each [default: 1]
+
+Note: --format can also be given as an env var, such as CONSTELLATION_FORMAT=json. protobuf requires the protobuf feature.
 
-Note: --format can also be given as an env var, such as CONSTELLATION_FORMAT=json
+Note: --labels can also be given as an env var, such as CONSTELLATION_LABELS=coordinator. Labels prefixed with - are excluded, and a trailing * matches any suffix. Processes are labelled with SpawnOptions::label.
+
+Note: --process-limit can also be given as an env var, such as CONSTELLATION_PROCESS_LIMIT=1000. Spawns beyond it fail with ProcessLimitExceeded.
+
+Note: --expected-hash can also be given as an env var, such as CONSTELLATION_EXPECTED_HASH=<64 hex digits>. Spawns whose binary doesn't match fail with BinaryHashMismatch.
+
+Note: --restarts reruns the whole job, all its processes, if any exits abnormally. Only the first run is given stdin.
 ";
 
 #[derive(Debug, Deserialize)]
 struct Args {
 	flag_version: bool,
 	flag_format: Option<Format>,
+	flag_labels: Option<String>,
+	flag_process_limit: Option<usize>,
+	flag_expected_hash: Option<String>,
+	flag_restarts: usize,
+	flag_restart_backoff: u64,
 	arg_host: String,
 	arg_binary: path::PathBuf,
 	arg_args: Vec<String>, // OsString
@@ -68,18 +99,63 @@ fn main() {
 		.flag_format
 		.or_else(|| {
 			envs.format
-				.map(|x| x.expect("CONSTELLATION_FORMAT must be json or human"))
+				.map(|x| x.expect("CONSTELLATION_FORMAT must be json, human or protobuf"))
 		})
 		.unwrap_or(Format::Human);
+	let process_limit = args.flag_process_limit.or_else(|| {
+		envs.process_limit
+			.map(|x| x.expect("CONSTELLATION_PROCESS_LIMIT must be a number"))
+	});
+	let expected_hash = args
+		.flag_expected_hash
+		.map(|x| hash_from_hex(&x))
+		.or(envs.expected_hash)
+		.map(|x| x.expect("CONSTELLATION_EXPECTED_HASH must be a SHA-256 hash of 64 hex digits"));
+	let labels: LabelFilter = match args.flag_labels {
+		Some(labels) => labels.parse().unwrap(),
+		None => envs
+			.labels
+			.map(|x| x.expect("CONSTELLATION_LABELS must be a comma-separated list of labels"))
+			.unwrap_or_default(),
+	};
 	if version {
 		println!("constellation-deploy {}", env!("CARGO_PKG_VERSION"));
 		process::exit(0);
 	}
+	let (restarts, restart_backoff) = (
+		args.flag_restarts,
+		Duration::from_secs(args.flag_restart_backoff),
+	);
 	let bridge_address: SocketAddr = args.arg_host.parse().unwrap();
 	let path = args.arg_binary;
 	let args: Vec<OsString> = iter::once(OsString::from(&path))
 		.chain(args.arg_args.into_iter().map(OsString::from))
 		.collect();
-	let vars: Vec<(OsString, OsString)> = env::vars_os().collect();
-	constellation::deploy(bridge_address, &path, format, args, vars);
+	let vars: Vec<(OsString, OsString)> = env::vars_os()
+		.filter(|(key, _)| {
+			key != "CONSTELLATION_PROCESS_LIMIT" && key != "CONSTELLATION_EXPECTED_HASH"
+		})
+		.chain(process_limit.map(|limit| {
+			(
+				OsString::from("CONSTELLATION_PROCESS_LIMIT"),
+				OsString::from(limit.to_string()),
+			)
+		}))
+		.chain(expected_hash.map(|hash| {
+			(
+				OsString::from("CONSTELLATION_EXPECTED_HASH"),
+				OsString::from(hash_to_hex(&hash)),
+			)
+		}))
+		.collect();
+	constellation::deploy(
+		bridge_address,
+		&path,
+		format,
+		&labels,
+		&args,
+		&vars,
+		restarts,
+		restart_backoff,
+	);
 }
@@ -0,0 +1,38 @@
+use serde::{ser::SerializeTuple, Serialize, Serializer};
+use std::sync::Arc;
+
+use super::{ChannelError, Pid, Sender};
+
+/// Send `value` to each of `pids`, returning the result of each send in the same order.
+///
+/// `value` is serialized once, and the same bytes are written to each process concurrently, which is considerably cheaper than a [Sender] per process each serializing it, for example to fan out a config update to every worker. It's received as a `T` by a [`Receiver<T>`](super::Receiver).
+///
+/// A failed send, for example with [`ChannelError::Exited`] to a process that has exited, doesn't affect the others. As with [`Sender::new()`], this panics if this process already has a [Sender] to any of `pids`, or they include its own.
+///
+/// This is an async fn.
+pub async fn broadcast<T: Serialize>(pids: &[Pid], value: T) -> Vec<Result<(), ChannelError>> {
+	let serialized = Serialized(bincode::serialize(&value).unwrap().into());
+	let sends = pids.iter().map(|&pid| {
+		let serialized = serialized.clone();
+		async move {
+			let sender = Sender::<Serialized>::new(pid);
+			let mut serialized = Some(serialized);
+			futures::future::poll_fn(|cx| sender.poll_send(cx, &mut serialized)).await
+		}
+	});
+	futures::future::join_all(sends).await
+}
+
+/// A message already serialized with bincode, which `serde_pipe` sends as is.
+#[derive(Clone)]
+struct Serialized(Arc<[u8]>);
+impl Serialize for Serialized {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		// bincode writes a tuple's elements without a length, and each u8 as a byte
+		let mut tuple = serializer.serialize_tuple(self.0.len())?;
+		for byte in self.0.iter() {
+			tuple.serialize_element(byte)?;
+		}
+		tuple.end()
+	}
+}
@@ -0,0 +1,77 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, marker::PhantomData};
+
+use super::{spawn, Pid, Receiver, Resources, Sender, SpawnError};
+
+/// A capability to create the channel between this process and another.
+///
+/// Creating a second [Sender] or [Receiver] to the same process panics at runtime. [`spawn_with_cap()`](spawn_with_cap) instead hands a `ChannelCap` to each of the parent and the child, and as creating the channel consumes it, that mistake is caught at compile time. It isn't `Clone` and can't be serialized, so as long as the channel between the two processes is only ever created from their caps there can't be duplicates.
+pub struct ChannelCap<T> {
+	remote: Pid,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> ChannelCap<T> {
+	fn new(remote: Pid) -> Self {
+		Self {
+			remote,
+			marker: PhantomData,
+		}
+	}
+
+	/// Get the pid of the process on the other end.
+	pub fn remote_pid(&self) -> Pid {
+		self.remote
+	}
+
+	/// Consume the capability, creating a [Sender] to the remote process.
+	pub fn into_sender(self) -> Sender<T>
+	where
+		T: Serialize,
+	{
+		Sender::new(self.remote)
+	}
+
+	/// Consume the capability, creating a [Receiver] from the remote process.
+	pub fn into_receiver(self) -> Receiver<T>
+	where
+		T: DeserializeOwned,
+	{
+		Receiver::new(self.remote)
+	}
+
+	/// Consume the capability, creating both a [Sender] to and a [Receiver] from the remote process.
+	pub fn into_channel(self) -> (Sender<T>, Receiver<T>)
+	where
+		T: Serialize + DeserializeOwned,
+	{
+		(Sender::new(self.remote), Receiver::new(self.remote))
+	}
+}
+impl<T> fmt::Debug for ChannelCap<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ChannelCap")
+			.field("remote", &self.remote)
+			.finish()
+	}
+}
+
+/// Spawn a new process, passing it and the caller a [`ChannelCap`] for the channel between them.
+///
+/// This is like [`spawn()`](spawn), but rather than the [Pid] of its parent `start` is passed a `ChannelCap<T>` to it, and on success the caller gets a `ChannelCap<T>` to the new process.
+///
+/// This is an async fn.
+pub async fn spawn_with_cap<T, F: FnOnce(ChannelCap<T>) + Serialize + DeserializeOwned>(
+	resources: Resources, start: F,
+) -> Result<ChannelCap<T>, SpawnError> {
+	let start: Vec<u8> = bincode::serialize(&start).unwrap();
+	let pid = spawn(
+		resources,
+		serde_closure::FnOnce!(move |parent| {
+			let start: Vec<u8> = start;
+			let start: F = bincode::deserialize(&start).unwrap();
+			start(ChannelCap::new(parent))
+		}),
+	)
+	.await?;
+	Ok(ChannelCap::new(pid))
+}
@@ -24,6 +24,11 @@ impl Inner {
 		InnerConnecting::new(bind, local, remote, incoming, notifier).into()
 	}
 
+	/// An outgoing connection that was refused, waiting to be retried with [`Inner::connect()`].
+	pub fn redialing() -> Self {
+		InnerConnecting::Outgoing(None).into()
+	}
+
 	pub fn poll(&mut self, notifier: &impl Notifier) {
 		*self = match mem::replace(self, Self::Killed) {
 			Self::Connecting(connecting) => connecting.poll(notifier).into(),
@@ -128,6 +133,19 @@ impl Inner {
 		}
 	}
 
+	/// Whether every message sent has been serialized and handed to the connection. This is trivially true of states in which nothing can be pending.
+	pub fn flushed(&self) -> bool {
+		match *self {
+			Self::Connected(ref connected) => connected.flushed(),
+			Self::RemoteClosed(ref remote_closed) => remote_closed.flushed(),
+			Self::LocalClosed(ref local_closed) => local_closed.flushed(),
+			Self::Closing(ref closing) => closing.flushed(),
+			Self::Connecting(_) | Self::ConnectingLocalClosed(_) | Self::Closed | Self::Killed => {
+				true
+			}
+		}
+	}
+
 	pub fn send<T: Serialize + 'static>(&mut self, x: T, notifier: &impl Notifier) {
 		match *self {
 			Self::Connected(ref mut connected) => connected.send(x, notifier),
@@ -143,6 +161,39 @@ impl Inner {
 		}
 	}
 
+	/// Bytes received towards messages that haven't yet been received by the application.
+	pub fn recv_buffered(&self) -> usize {
+		match *self {
+			Self::Connected(ref connected) => connected.recv_buffered(),
+			Self::LocalClosed(ref local_closed) => local_closed.recv_buffered(),
+			_ => 0,
+		}
+	}
+
+	/// Hold off on starting to receive another message while `limited`, finishing any already partly received.
+	pub fn limit_recv(&mut self, limited: bool) {
+		match *self {
+			Self::Connected(ref mut connected) => connected.limit_recv(limited),
+			Self::LocalClosed(ref mut local_closed) => local_closed.limit_recv(limited),
+			_ => (),
+		}
+	}
+
+	/// Abruptly kill the connection, discarding anything buffered.
+	pub fn kill(&mut self, notifier: &impl Notifier) {
+		*self = match mem::replace(self, Self::Killed) {
+			Self::Connected(connected) => {
+				connected.kill(notifier);
+				Self::Killed
+			}
+			Self::LocalClosed(local_closed) => {
+				local_closed.kill(notifier);
+				Self::Killed
+			}
+			inner => inner,
+		};
+	}
+
 	pub fn closed(&self) -> bool {
 		match *self {
 			Self::Closed => true,
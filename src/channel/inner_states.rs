@@ -8,7 +8,7 @@ use std::{
 use tcp_typed::Notifier;
 
 /// Used to determine which side should be connecter/client and which connectee/server/listener.
-fn ord(a: &SocketAddr, b: &SocketAddr) -> bool {
+pub(super) fn ord(a: &SocketAddr, b: &SocketAddr) -> bool {
 	let a = (a.ip(), a.port());
 	let b = (b.ip(), b.port());
 	assert_ne!(a, b);
@@ -162,6 +162,8 @@ impl InnerConnectingLocalClosed {
 							serde_pipe::Serializer::new(),
 							serde_pipe::Deserializer::new(),
 							false,
+							0,
+							false,
 							notifier,
 						) {
 							InnerLocalClosedPoll::LocalClosed(local_closed) => {
@@ -191,6 +193,8 @@ impl InnerConnectingLocalClosed {
 							serde_pipe::Serializer::new(),
 							serde_pipe::Deserializer::new(),
 							false,
+							0,
+							false,
 							notifier,
 						) {
 							InnerLocalClosedPoll::LocalClosed(local_closed) => {
@@ -225,6 +229,10 @@ pub struct InnerConnected {
 	send_serializer: serde_pipe::Serializer,
 	recv_deserializer: serde_pipe::Deserializer,
 	recv_deserializer_given: bool,
+	/// Bytes pushed into `recv_deserializer` since the last message was received.
+	recv_buffered: usize,
+	/// Whether to hold off on starting to receive another message, per [`Inner::limit_recv()`].
+	recv_limited: bool,
 }
 impl InnerConnected {
 	fn new(connection: Connection, notifier: &impl Notifier) -> InnerConnectedPoll {
@@ -233,10 +241,30 @@ impl InnerConnected {
 			send_serializer: serde_pipe::Serializer::new(),
 			recv_deserializer: serde_pipe::Deserializer::new(),
 			recv_deserializer_given: false,
+			recv_buffered: 0,
+			recv_limited: false,
 		}
 		.poll(notifier)
 	}
 
+	pub fn flushed(&self) -> bool {
+		!self.send_serializer.pull_avail()
+	}
+
+	pub fn recv_buffered(&self) -> usize {
+		self.recv_buffered
+	}
+
+	pub fn limit_recv(&mut self, limited: bool) {
+		self.recv_limited = limited;
+	}
+
+	pub fn kill(mut self, notifier: &impl Notifier) {
+		if let Some(kill) = self.connection.kill(notifier) {
+			kill();
+		}
+	}
+
 	pub fn poll(mut self, notifier: &impl Notifier) -> InnerConnectedPoll {
 		assert!(!self.connection.connecting());
 		let mut progress = true;
@@ -251,10 +279,12 @@ impl InnerConnected {
 			if self.connection.recvable() {
 				while self.connection.recv_avail().unwrap() > 0
 					&& self.recv_deserializer.push_avail()
+					&& (!self.recv_limited || self.recv_buffered > 0)
 				{
 					self.recv_deserializer.push().unwrap()(
 						self.connection.recv(notifier).unwrap()(),
 					);
+					self.recv_buffered += 1;
 					progress = true;
 				}
 			}
@@ -302,6 +332,7 @@ impl InnerConnected {
 
 	pub fn recv<T: DeserializeOwned + 'static>(&mut self, notifier: &impl Notifier) -> T {
 		self.recv_deserializer_given = false;
+		self.recv_buffered = 0;
 		let ret = self.recv_deserializer.pull::<T>().unwrap()();
 		notifier.queue();
 		ret
@@ -320,6 +351,8 @@ impl InnerConnected {
 			self.send_serializer,
 			self.recv_deserializer,
 			self.recv_deserializer_given,
+			self.recv_buffered,
+			self.recv_limited,
 			notifier,
 		)
 	}
@@ -349,6 +382,10 @@ impl InnerRemoteClosed {
 		.poll(notifier)
 	}
 
+	pub fn flushed(&self) -> bool {
+		!self.send_serializer.pull_avail()
+	}
+
 	pub fn poll(mut self, notifier: &impl Notifier) -> InnerRemoteClosedPoll {
 		if self.drain && !self.connection.recvable() {
 			self.drain = false;
@@ -416,22 +453,46 @@ pub struct InnerLocalClosed {
 	send_serializer: serde_pipe::Serializer,
 	recv_deserializer: serde_pipe::Deserializer,
 	recv_deserializer_given: bool,
+	/// Bytes pushed into `recv_deserializer` since the last message was received.
+	recv_buffered: usize,
+	/// Whether to hold off on starting to receive another message, per [`Inner::limit_recv()`].
+	recv_limited: bool,
 }
 impl InnerLocalClosed {
 	fn new(
 		connection: Connection, send_serializer: serde_pipe::Serializer,
 		recv_deserializer: serde_pipe::Deserializer, recv_deserializer_given: bool,
-		notifier: &impl Notifier,
+		recv_buffered: usize, recv_limited: bool, notifier: &impl Notifier,
 	) -> InnerLocalClosedPoll {
 		Self {
 			connection,
 			send_serializer,
 			recv_deserializer,
 			recv_deserializer_given,
+			recv_buffered,
+			recv_limited,
 		}
 		.poll(notifier)
 	}
 
+	pub fn flushed(&self) -> bool {
+		!self.send_serializer.pull_avail()
+	}
+
+	pub fn recv_buffered(&self) -> usize {
+		self.recv_buffered
+	}
+
+	pub fn limit_recv(&mut self, limited: bool) {
+		self.recv_limited = limited;
+	}
+
+	pub fn kill(mut self, notifier: &impl Notifier) {
+		if let Some(kill) = self.connection.kill(notifier) {
+			kill();
+		}
+	}
+
 	pub fn poll(mut self, notifier: &impl Notifier) -> InnerLocalClosedPoll {
 		assert!(!self.connection.connecting());
 		let mut progress = true;
@@ -446,10 +507,12 @@ impl InnerLocalClosed {
 			if self.connection.recvable() {
 				while self.connection.recv_avail().unwrap() > 0
 					&& self.recv_deserializer.push_avail()
+					&& (!self.recv_limited || self.recv_buffered > 0)
 				{
 					self.recv_deserializer.push().unwrap()(
 						self.connection.recv(notifier).unwrap()(),
 					);
+					self.recv_buffered += 1;
 					progress = true;
 				}
 			}
@@ -490,6 +553,7 @@ impl InnerLocalClosed {
 
 	pub fn recv<T: DeserializeOwned + 'static>(&mut self, notifier: &impl Notifier) -> T {
 		self.recv_deserializer_given = false;
+		self.recv_buffered = 0;
 		let ret = self.recv_deserializer.pull::<T>().unwrap()();
 		notifier.queue();
 		ret
@@ -528,6 +592,10 @@ impl InnerClosing {
 		.poll(notifier)
 	}
 
+	pub fn flushed(&self) -> bool {
+		!self.send_serializer.pull_avail()
+	}
+
 	pub fn poll(mut self, notifier: &impl Notifier) -> InnerClosingPoll {
 		if self.drain && !self.connection.recvable() {
 			self.drain = false;
@@ -0,0 +1,99 @@
+use std::{
+	collections::VecDeque, fmt, sync::{Arc, Mutex}, task::{Context, Waker}
+};
+
+use super::ChannelError;
+
+/// An in-memory queue shared by a [`Sender`](super::Sender) and [`Receiver`](super::Receiver) that both live in this process.
+///
+/// Values are moved rather than serialized, so nothing touches the reactor or a socket.
+pub struct Local<T> {
+	inner: Mutex<LocalInner<T>>,
+}
+
+struct LocalInner<T> {
+	queue: VecDeque<T>,
+	sender: bool,
+	receiver: bool,
+	receivers_futures: Vec<Waker>,
+}
+
+impl<T> Local<T> {
+	pub fn new() -> Arc<Self>
+	where
+		T: Send,
+	{
+		Arc::new(Self {
+			inner: Mutex::new(LocalInner {
+				queue: VecDeque::new(),
+				sender: true,
+				receiver: true,
+				receivers_futures: Vec::new(),
+			}),
+		})
+	}
+
+	pub fn send(&self, t: T) -> Result<(), ChannelError> {
+		let mut inner = self.inner.lock().unwrap();
+		assert!(inner.sender, ".send() called on a closed Sender");
+		if !inner.receiver {
+			return Err(ChannelError::Exited);
+		}
+		inner.queue.push_back(t);
+		for receiver_future in inner.receivers_futures.drain(..) {
+			receiver_future.wake();
+		}
+		Ok(())
+	}
+
+	pub fn recv_avail(&self, register: Option<&mut Context>) -> bool {
+		let mut inner = self.inner.lock().unwrap();
+		let unblocked = !inner.queue.is_empty() || !inner.sender;
+		if !unblocked {
+			if let Some(cx) = register {
+				inner.receivers_futures.push(cx.waker().clone());
+			}
+		}
+		unblocked
+	}
+
+	pub fn recv(&self) -> Result<T, ChannelError> {
+		let mut inner = self.inner.lock().unwrap();
+		inner.queue.pop_front().ok_or(ChannelError::Exited)
+	}
+
+	/// Whether the receiver hasn't been dropped.
+	pub fn receiver_alive(&self) -> bool {
+		self.inner.lock().unwrap().receiver
+	}
+
+	/// Whether the sender hasn't been dropped, or there are messages from it yet to be received.
+	pub fn sender_alive(&self) -> bool {
+		let inner = self.inner.lock().unwrap();
+		inner.sender || !inner.queue.is_empty()
+	}
+
+	pub fn drop_sender(&self) {
+		let mut inner = self.inner.lock().unwrap();
+		inner.sender = false;
+		for receiver_future in inner.receivers_futures.drain(..) {
+			receiver_future.wake();
+		}
+	}
+
+	pub fn drop_receiver(&self) {
+		let mut inner = self.inner.lock().unwrap();
+		inner.receiver = false;
+		inner.queue.clear();
+	}
+}
+impl<T> fmt::Debug for Local<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let inner = self.inner.lock().unwrap();
+		f.debug_struct("Local")
+			.field("queued", &inner.queue.len())
+			.field("sender", &inner.sender)
+			.field("receiver", &inner.receiver)
+			.finish()
+	}
+}
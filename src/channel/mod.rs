@@ -1,21 +1,24 @@
 mod inner;
 mod inner_states;
+mod local;
 
 use either::Either;
 use log::trace;
 use nix::sys::socket;
-use notifier::{Notifier, Triggerer};
+use notifier::{Notifier, NotifierContext, Triggeree, Triggerer};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-	borrow::Borrow, collections::{hash_map, HashMap}, convert::Infallible, error::Error, fmt, marker, mem, net::{IpAddr, SocketAddr}, pin::Pin, ptr, sync::{Arc, RwLock, RwLockWriteGuard}, task::{Context, Poll, Waker}, thread
+	any::{type_name, Any}, borrow::Borrow, collections::{hash_map, HashMap, VecDeque}, convert::{Infallible, TryInto}, error::Error, fmt, marker, mem, net::{IpAddr, SocketAddr}, pin::Pin, ptr, sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, RwLock, RwLockWriteGuard
+	}, task::{Context, Poll, Waker}, thread, time::{Duration, Instant}
 };
-use tcp_typed::{Connection, Listener};
+use tcp_typed::{Connection, Listener, Notifier as _};
 
 use constellation_internal::abort_on_unwind;
 
 use super::Fd;
 
-pub use self::{inner::*, inner_states::*};
+pub use self::{inner::*, inner_states::*, local::*};
 pub use tcp_typed::{socket_forwarder, SocketForwardee, SocketForwarder};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
@@ -50,6 +53,24 @@ pub struct Reactor {
 	sockets: RwLock<HashMap<SocketAddr, Arc<RwLock<Option<Channel>>>>>,
 	bind: SocketAddr,
 	local: SocketAddr,
+	/// Whether incoming connections are handed over by our monitor process's [`SocketForwarder`] rather than accepted directly.
+	forwardee: bool,
+	/// How long to keep retrying refused outgoing connections for, if at all.
+	connect_timeout: RwLock<Option<Duration>>,
+	/// Whether to time serialization and deserialization on each channel.
+	serde_timing: AtomicBool,
+	/// The most bytes to buffer towards received messages across all channels, and what to do beyond it.
+	buffer_limit: RwLock<Option<(usize, BufferLimitPolicy)>>,
+	/// The total of each channel's [`Channel::buffered`].
+	buffered: AtomicUsize,
+	/// Whether `buffered` has reached the limit, so that channels shouldn't start receiving new messages.
+	buffer_limited: AtomicBool,
+	/// The most bytes a message received on a channel can take, beyond which the channel is killed, unless overridden for the channel.
+	max_message_size: AtomicUsize,
+	/// Called with each message sent and received, if set.
+	inspector: RwLock<Option<Inspector>>,
+	/// Whether `inspector` is set, so the hot paths needn't take its lock.
+	inspecting: AtomicBool,
 }
 impl Reactor {
 	#[allow(dead_code)]
@@ -66,6 +87,15 @@ impl Reactor {
 				sockets,
 				bind,
 				local,
+				forwardee: false,
+				connect_timeout: RwLock::new(None),
+				serde_timing: AtomicBool::new(false),
+				buffer_limit: RwLock::new(None),
+				buffered: AtomicUsize::new(0),
+				buffer_limited: AtomicBool::new(false),
+				max_message_size: AtomicUsize::new(DEFAULT_MAX_MESSAGE_SIZE),
+				inspector: RwLock::new(None),
+				inspecting: AtomicBool::new(false),
 			},
 			port,
 		)
@@ -86,6 +116,15 @@ impl Reactor {
 			sockets,
 			bind,
 			local,
+			forwardee: false,
+			connect_timeout: RwLock::new(None),
+			serde_timing: AtomicBool::new(false),
+			buffer_limit: RwLock::new(None),
+			buffered: AtomicUsize::new(0),
+			buffer_limited: AtomicBool::new(false),
+			max_message_size: AtomicUsize::new(DEFAULT_MAX_MESSAGE_SIZE),
+			inspector: RwLock::new(None),
+			inspecting: AtomicBool::new(false),
 		}
 	}
 
@@ -102,6 +141,186 @@ impl Reactor {
 			sockets,
 			bind,
 			local,
+			forwardee: true,
+			connect_timeout: RwLock::new(None),
+			serde_timing: AtomicBool::new(false),
+			buffer_limit: RwLock::new(None),
+			buffered: AtomicUsize::new(0),
+			buffer_limited: AtomicBool::new(false),
+			max_message_size: AtomicUsize::new(DEFAULT_MAX_MESSAGE_SIZE),
+			inspector: RwLock::new(None),
+			inspecting: AtomicBool::new(false),
+		}
+	}
+
+	/// The remotes this process has a [Sender] to or [Receiver] from, and which of the two it has.
+	pub fn channels(&self) -> Vec<(SocketAddr, bool, bool)> {
+		self.sockets
+			.read()
+			.unwrap()
+			.iter()
+			.filter_map(|(&remote, channel)| {
+				let channel = channel.read().unwrap();
+				let channel = channel.as_ref()?;
+				let (sender, receiver) = (channel.senders_count > 0, channel.receivers_count > 0);
+				if sender || receiver {
+					Some((remote, sender, receiver))
+				} else {
+					None
+				}
+			})
+			.collect()
+	}
+
+	/// The remotes this process has a connection to, how long [Send] and [Recv] futures on it have been blocked for in total, and how long has been spent serializing and deserializing its messages if [`Reactor::set_serde_timing()`] is enabled.
+	pub fn stats(&self) -> Vec<(SocketAddr, Duration, Duration, Duration, Duration)> {
+		self.sockets
+			.read()
+			.unwrap()
+			.iter()
+			.filter_map(|(&remote, channel)| {
+				let channel = channel.read().unwrap();
+				let channel = channel.as_ref()?;
+				Some((
+					remote,
+					channel.send_blocked,
+					channel.recv_blocked,
+					channel.serialize,
+					channel.deserialize,
+				))
+			})
+			.collect()
+	}
+
+	/// The remotes whose open connection to this process was handed over by our monitor process's [`SocketForwarder`].
+	pub fn forwarded(&self) -> Vec<SocketAddr> {
+		self.sockets
+			.read()
+			.unwrap()
+			.iter()
+			.filter_map(|(&remote, channel)| {
+				let channel = channel.read().unwrap();
+				let channel = channel.as_ref()?;
+				if channel.forwarded && channel.inner.valid() && !channel.inner.closed() {
+					Some(remote)
+				} else {
+					None
+				}
+			})
+			.collect()
+	}
+
+	/// Whether every message sent on each channel with a [Sender] has been serialized and handed to the connection, registering to be woken on the next progress of any that haven't.
+	pub fn poll_flushed(&self, cx: &mut Context) -> Poll<()> {
+		let mut flushed = true;
+		for channel in self.sockets.read().unwrap().values() {
+			let mut channel = channel.write().unwrap();
+			if let Some(channel) = channel.as_mut() {
+				if (channel.senders_count > 0 || channel.send_window.is_some())
+					&& !channel.flushed()
+				{
+					channel.senders_futures.push(cx.waker().clone());
+					flushed = false;
+				}
+			}
+		}
+		if flushed {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	}
+
+	/// Retry refused outgoing connections with exponential backoff until `timeout` has elapsed since the first attempt, or don't retry if `None`.
+	pub fn set_connect_timeout(&self, timeout: Option<Duration>) {
+		*self.connect_timeout.write().unwrap() = timeout;
+	}
+
+	/// Time serialization and deserialization on each channel, for [`Reactor::stats()`]. This is off by default as it adds a clock read around each message.
+	pub fn set_serde_timing(&self, enabled: bool) {
+		self.serde_timing.store(enabled, Ordering::Relaxed);
+	}
+
+	/// Cap the bytes buffered towards received messages across all channels at `limit`, applying `policy` when it's reached, or don't if `None`.
+	pub fn set_buffer_limit(&self, limit: Option<usize>, policy: BufferLimitPolicy) {
+		*self.buffer_limit.write().unwrap() = limit.map(|limit| (limit, policy));
+		self.queue_all(&self.sockets.read().unwrap());
+	}
+
+	/// Kill any channel on which a message being received takes more than `max` bytes, unless overridden for the channel by [`Receiver::set_max_message_size()`].
+	pub fn set_max_message_size(&self, max: usize) {
+		self.max_message_size.store(max, Ordering::Relaxed);
+		self.queue_all(&self.sockets.read().unwrap());
+	}
+
+	/// Pass each message sent and received on any channel to `inspector`, or stop if `None`.
+	pub fn set_inspector(&self, inspector: Option<Inspector>) {
+		let inspecting = inspector.is_some();
+		*self.inspector.write().unwrap() = inspector;
+		self.inspecting.store(inspecting, Ordering::Relaxed);
+	}
+
+	/// Pass a message to the inspector set with [`Reactor::set_inspector()`], if any.
+	fn inspect(&self, direction: Direction, remote: SocketAddr, type_name: &str, len: usize) {
+		if let Some(inspector) = &*self.inspector.read().unwrap() {
+			inspector(direction, remote, type_name, len);
+		}
+	}
+
+	/// Apply the limit set by [`Reactor::set_buffer_limit()`] after a channel's buffer has changed, killing the channel with the largest buffer if over it per [`BufferLimitPolicy::Disconnect`], and letting channels start receiving again once back under it.
+	fn govern_buffers(&self, sockets: &HashMap<SocketAddr, Arc<RwLock<Option<Channel>>>>) {
+		let limit = *self.buffer_limit.read().unwrap();
+		if let Some((limit, BufferLimitPolicy::Disconnect)) = limit {
+			if self.buffered.load(Ordering::SeqCst) > limit {
+				let worst = sockets.values().max_by_key(|channel| {
+					channel
+						.read()
+						.unwrap()
+						.as_ref()
+						.map_or(0, |channel| channel.buffered)
+				});
+				if let Some(worst) = worst {
+					let notifier_key: *const RwLock<Option<Channel>> = &**worst;
+					let notifier = &self.notifier.context(Key(notifier_key as *const ()));
+					let mut channel = worst.write().unwrap();
+					let channel = channel.as_mut().unwrap();
+					if channel.buffered > 0 {
+						channel.kill(self, notifier);
+					}
+				}
+			}
+		}
+		let limited = limit.map_or(false, |(limit, _)| {
+			self.buffered.load(Ordering::SeqCst) >= limit
+		});
+		if self.buffer_limited.swap(limited, Ordering::SeqCst) && !limited {
+			self.queue_all(sockets);
+		}
+	}
+
+	/// Poll every channel, for example so that those held back by the buffer limit can resume.
+	fn queue_all(&self, sockets: &HashMap<SocketAddr, Arc<RwLock<Option<Channel>>>>) {
+		for channel in sockets.values() {
+			let notifier_key: *const RwLock<Option<Channel>> = &**channel;
+			self.notifier
+				.context(Key(notifier_key as *const ()))
+				.queue();
+		}
+	}
+
+	/// Start connecting to `remote` ahead of a [Sender] to or [Receiver] from it being created, which will then use this connection.
+	pub fn connect(&self, remote: SocketAddr) {
+		let (notifier, sockets, bind, local) =
+			(&self.notifier, &self.sockets, &self.bind, &self.local);
+		let sockets = &mut *sockets.write().unwrap();
+		if let hash_map::Entry::Vacant(vacant) = sockets.entry(remote) {
+			let channel = Arc::new(RwLock::new(None));
+			let notifier_key: *const RwLock<Option<Channel>> = &*channel;
+			let notifier = &notifier.context(Key(notifier_key as *const ()));
+			let inner = Channel::new(Inner::connect(*bind, *local, remote, None, notifier));
+			*channel.try_write().unwrap() = Some(inner);
+			let _ = vacant.insert(channel);
+			trace!("connect {:?}", notifier_key);
 		}
 	}
 
@@ -128,12 +347,14 @@ impl Reactor {
 				let context = context();
 				let context = context.borrow();
 				let mut listener = context.listener.try_write().unwrap();
-				let (notifier, listener, sockets, bind, local) = (
+				let (notifier, listener, sockets, bind, local, forwardee, connect_timeout) = (
 					&context.notifier,
 					listener.as_mut().unwrap(),
 					&context.sockets,
 					&context.bind,
 					&context.local,
+					context.forwardee,
+					&context.connect_timeout,
 				);
 				let mut done: Option<
 					RwLockWriteGuard<HashMap<SocketAddr, Arc<RwLock<Option<Channel>>>>>,
@@ -254,6 +475,7 @@ impl Reactor {
 										let connectee: Connection = connection(notifier).into();
 										let mut channel = channel_.write().unwrap();
 										let channel = channel.as_mut().unwrap();
+										channel.forwarded |= forwardee;
 										if channel.inner.add_incoming(notifier).is_some() {
 											channel.inner.add_incoming(notifier).unwrap()(
 												connectee,
@@ -280,7 +502,13 @@ impl Reactor {
 										} else {
 											panic!("{:?} {:?} {:?}", channel, local, remote);
 										}
+										channel.inner.limit_recv(
+											context.buffer_limited.load(Ordering::SeqCst),
+										);
 										channel.inner.poll(notifier);
+										channel.limit_message_size(context, notifier);
+										channel.fill_windows(notifier);
+										channel.account(context);
 										if channel.inner.closable()
 											&& !channel.inner.connecting() && !channel
 											.inner
@@ -329,8 +557,9 @@ impl Reactor {
 											}
 										}
 										if !inner.closed() {
-											*channel.try_write().unwrap() =
-												Some(Channel::new(inner));
+											let mut new = Channel::new(inner);
+											new.forwarded = forwardee;
+											*channel.try_write().unwrap() = Some(new);
 											let _ = vacant.insert(channel);
 										}
 									}
@@ -349,11 +578,11 @@ impl Reactor {
 							// 	notifier_key2 == notifier_key
 							// }));
 							// let mut channel = unsafe{&*notifier_key}.write().unwrap();
-							let channel_arc = sockets.values().find(|&channel| {
+							let channel_arc = sockets.iter().find(|&(_, channel)| {
 								let notifier_key2: *const RwLock<Option<Channel>> = &**channel;
 								notifier_key2 == notifier_key
 							});
-							if let Some(channel_arc) = channel_arc {
+							if let Some((&remote, channel_arc)) = channel_arc {
 								let mut channel = channel_arc.write().unwrap();
 								assert_eq!(
 									Arc::strong_count(&channel_arc),
@@ -362,12 +591,60 @@ impl Reactor {
 								);
 								let finished = {
 									let channel: &mut Channel = channel.as_mut().unwrap();
+									if let Some(triggeree) = channel
+										.dialing
+										.as_mut()
+										.and_then(|dialing| dialing.triggeree.take())
+									{
+										// The backoff has elapsed
+										drop(triggeree);
+										if !is_done {
+											channel.inner = Inner::connect(
+												*bind, *local, remote, None, notifier,
+											);
+										}
+									}
+									channel
+										.inner
+										.limit_recv(context.buffer_limited.load(Ordering::SeqCst));
+									channel.inner.poll(notifier);
+									channel.limit_message_size(context, notifier);
+									channel.fill_windows(notifier);
+									channel.account(context);
 									let inner: &mut Inner = &mut channel.inner;
-									inner.poll(notifier);
 									if inner.closable() && !inner.connecting() && !inner.recvable()
 									{
 										inner.close(notifier); // if the other end's process is ending; this could be given sooner
 									}
+									if !inner.valid() && !is_done {
+										if let Some(dialing) = &mut channel.dialing {
+											let timeout = *connect_timeout.read().unwrap();
+											let dialer = ord(local, &remote);
+											if dialer
+												&& timeout.map_or(false, |timeout| {
+													dialing.since.elapsed() + dialing.backoff
+														< timeout
+												}) {
+												*inner = Inner::redialing();
+												let (triggerer, triggeree) = notifier.add_trigger();
+												dialing.triggeree = Some(triggeree);
+												let backoff = dialing.backoff;
+												dialing.backoff = (backoff * 2).min(MAX_BACKOFF);
+												let _ = thread::Builder::new()
+													.name(String::from("redial"))
+													.spawn(abort_on_unwind(move || {
+														thread::sleep(backoff);
+														drop(triggerer);
+													}))
+													.unwrap();
+											} else {
+												channel.timed_out = dialer && timeout.is_some();
+												channel.dialing = None;
+											}
+										}
+									} else if !inner.connecting() {
+										channel.dialing = None;
+									}
 									if !is_done {
 										for sender_future in channel.senders_futures.drain(..) {
 											sender_future.wake();
@@ -407,6 +684,9 @@ impl Reactor {
 									assert!(x.get_mut().unwrap().is_none());
 								}
 							}
+							if !is_done {
+								context.govern_buffers(&sockets);
+							}
 						} else {
 							assert!(done.is_none());
 							// trace!("\\close"); // called after rust runtime exited, not sure what trace does
@@ -458,6 +738,25 @@ pub struct Channel {
 	receivers_count: usize,
 	senders_futures: Vec<Waker>,
 	receivers_futures: Vec<Waker>,
+	send_blocked: Duration,
+	recv_blocked: Duration,
+	/// Time spent serializing sent and deserializing received messages, if enabled.
+	serialize: Duration,
+	deserialize: Duration,
+	/// Whether an incoming connection from the remote was handed over by our monitor process.
+	forwarded: bool,
+	/// Set until the connection is established, for retrying it if it's refused.
+	dialing: Option<Dialing>,
+	/// Whether the connection was still refused once the connect timeout elapsed.
+	timed_out: bool,
+	/// This channel's contribution to [`Reactor::buffered`], as of when it was last accounted.
+	buffered: usize,
+	/// Messages sent ahead of the connection, per [`Sender::set_capacity()`].
+	send_window: Option<SendWindow>,
+	/// Messages received ahead of the application, per [`Receiver::set_capacity()`].
+	recv_window: Option<RecvWindow>,
+	/// Overrides [`Reactor::max_message_size`] for this channel, per [`Receiver::set_max_message_size()`].
+	max_message_size: Option<usize>,
 }
 impl Channel {
 	fn new(inner: Inner) -> Self {
@@ -467,8 +766,193 @@ impl Channel {
 			receivers_count: 0,
 			senders_futures: Vec::new(),
 			receivers_futures: Vec::new(),
+			send_blocked: Duration::new(0, 0),
+			recv_blocked: Duration::new(0, 0),
+			serialize: Duration::new(0, 0),
+			deserialize: Duration::new(0, 0),
+			forwarded: false,
+			dialing: Some(Dialing {
+				since: Instant::now(),
+				backoff: MIN_BACKOFF,
+				triggeree: None,
+			}),
+			timed_out: false,
+			buffered: 0,
+			send_window: None,
+			recv_window: None,
+			max_message_size: None,
+		}
+	}
+
+	/// Whether every message sent has been serialized and handed to the connection, including any waiting in the [`SendWindow`].
+	fn flushed(&self) -> bool {
+		self.inner.flushed()
+			&& self
+				.send_window
+				.as_ref()
+				.map_or(true, |window| window.queued.is_empty())
+	}
+
+	/// Hand messages waiting in the [`SendWindow`] to the connection as it has room for them, and receive messages into the [`RecvWindow`] until it's full.
+	fn fill_windows(&mut self, notifier: &NotifierContext<'_, Key>) {
+		let inner = &mut self.inner;
+		if let Some(window) = &mut self.send_window {
+			if inner.valid() && (inner.connecting() || inner.sendable()) {
+				while !window.queued.is_empty() && inner.send_avail() == Some(true) {
+					(window.queued.pop_front().unwrap().0)(inner, notifier);
+				}
+			} else {
+				// They can no longer be sent
+				window.queued.clear();
+			}
+			if window.capacity == 0 && window.queued.is_empty() {
+				self.send_window = None;
+			}
+		}
+		if let Some(window) = &mut self.recv_window {
+			while window.received.len() < window.capacity {
+				match (window.recv)(inner, notifier) {
+					Some(received) => window.received.push_back(received),
+					None => break,
+				}
+			}
+		}
+	}
+
+	/// Update [`Reactor::buffered`] with the change in this channel's receive buffer.
+	fn account(&mut self, context: &Reactor) {
+		let buffered = self.inner.recv_buffered();
+		if buffered >= self.buffered {
+			let _ = context
+				.buffered
+				.fetch_add(buffered - self.buffered, Ordering::SeqCst);
+		} else {
+			let _ = context
+				.buffered
+				.fetch_sub(self.buffered - buffered, Ordering::SeqCst);
+		}
+		self.buffered = buffered;
+	}
+
+	/// Kill the channel if the message being received is over the maximum size. A remote could otherwise claim an arbitrarily large message and have it buffered.
+	fn limit_message_size(&mut self, context: &Reactor, notifier: &NotifierContext<'_, Key>) {
+		let max = self
+			.max_message_size
+			.unwrap_or_else(|| context.max_message_size.load(Ordering::Relaxed));
+		if self.inner.recv_buffered() > max {
+			self.kill(context, notifier);
 		}
 	}
+
+	/// Abruptly kill the connection, waking any sends and receives so they return [`ChannelError::Unknown`].
+	fn kill(&mut self, context: &Reactor, notifier: &NotifierContext<'_, Key>) {
+		self.inner.kill(notifier);
+		self.account(context);
+		for sender_future in self.senders_futures.drain(..) {
+			sender_future.wake();
+		}
+		for receiver_future in self.receivers_futures.drain(..) {
+			receiver_future.wake();
+		}
+	}
+}
+
+/// A message, or the sending of one, held by a [`SendWindow`] or [`RecvWindow`]. Its type is erased as [Channel] isn't generic.
+struct Held<T: ?Sized>(Box<T>);
+/// They're only created for a `T: Send`, per [`Sender::set_capacity()`] and [`Receiver::set_capacity()`], and only accessed with the [Channel] locked for writing.
+unsafe impl<T: ?Sized> marker::Send for Held<T> {}
+unsafe impl<T: ?Sized> Sync for Held<T> {}
+
+/// Messages sent while the connection has no room for them, to be handed to it by the reactor.
+struct SendWindow {
+	/// How many messages can wait. This is 0 once the [Sender] that set it has been replaced by one without a capacity, leaving the window to drain.
+	capacity: usize,
+	queued: VecDeque<Held<dyn FnOnce(&mut Inner, &NotifierContext<'_, Key>)>>,
+}
+impl fmt::Debug for SendWindow {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SendWindow")
+			.field("capacity", &self.capacity)
+			.field("queued", &self.queued.len())
+			.finish()
+	}
+}
+
+/// Messages received by the reactor ahead of the application, with the bytes each took.
+struct RecvWindow {
+	capacity: usize,
+	received: VecDeque<(Held<dyn Any>, usize)>,
+	/// Receive a message of the [Receiver]'s type, if one is available.
+	recv: fn(&mut Inner, &NotifierContext<'_, Key>) -> Option<(Held<dyn Any>, usize)>,
+}
+impl fmt::Debug for RecvWindow {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("RecvWindow")
+			.field("capacity", &self.capacity)
+			.field("received", &self.received.len())
+			.finish()
+	}
+}
+
+/// Encodes messages for a [Sender] whose payloads are encoded by a codec other than the channel's own bincode.
+pub type Encode<T> = Box<dyn Fn(&T) -> Vec<u8> + marker::Send + Sync>;
+/// Decodes messages for a [Receiver] whose payloads are encoded by a codec other than the channel's own bincode.
+pub type Decode<T> = Box<dyn Fn(&[u8]) -> T + marker::Send + Sync>;
+
+/// A message, or its encoding by a codec, which is then sent as a byte vector so that the framing is unchanged.
+enum Encoded<T> {
+	Value(T),
+	Bytes(Vec<u8>),
+}
+impl<T: Serialize> Serialize for Encoded<T> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match self {
+			Self::Value(t) => t.serialize(serializer),
+			Self::Bytes(bytes) => bytes.serialize(serializer),
+		}
+	}
+}
+
+fn recv_ahead<T: DeserializeOwned + 'static>(
+	inner: &mut Inner, notifier: &NotifierContext<'_, Key>,
+) -> Option<(Held<dyn Any>, usize)> {
+	if inner.recv_avail::<T, _>(notifier) == Some(true) {
+		let len = inner.recv_buffered();
+		Some((Held(Box::new(inner.recv::<T>(notifier))), len))
+	} else {
+		None
+	}
+}
+
+/// The time now, if serialization is being timed per [`Reactor::set_serde_timing()`].
+fn serde_timing_start(context: &Reactor) -> Option<Instant> {
+	if context.serde_timing.load(Ordering::Relaxed) {
+		Some(Instant::now())
+	} else {
+		None
+	}
+}
+
+/// The default for [`Reactor::set_max_message_size()`].
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+const MIN_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The state of retrying a refused outgoing connection, for [`Reactor::set_connect_timeout()`].
+struct Dialing {
+	since: Instant,
+	backoff: Duration,
+	/// Set while waiting out the backoff before redialing, triggered once it has elapsed.
+	triggeree: Option<Triggeree>,
+}
+impl fmt::Debug for Dialing {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Dialing")
+			.field("since", &self.since)
+			.field("backoff", &self.backoff)
+			.field("waiting", &self.triggeree.is_some())
+			.finish()
+	}
 }
 
 /// Channel operation error modes.
@@ -479,6 +963,8 @@ pub enum ChannelError {
 	Exited,
 	/// The remote process terminated abruptly, or the channel was killed by the OS or hardware.
 	Unknown,
+	/// The operation didn't complete within its timeout.
+	Timeout,
 	#[doc(hidden)]
 	__Nonexhaustive,
 }
@@ -487,14 +973,39 @@ impl fmt::Display for ChannelError {
 		match *self {
 			Self::Exited => write!(f, "remote process already exited"),
 			Self::Unknown => write!(f, "remote process died or channel killed by OS/hardware"), //(ref err) => err.fmt(f),
+			Self::Timeout => write!(f, "timed out"),
 			Self::__Nonexhaustive => unreachable!(),
 		}
 	}
 }
 impl Error for ChannelError {}
 
+/// What to do when the limit set by [`set_buffer_limit()`](crate::set_buffer_limit) is reached.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum BufferLimitPolicy {
+	/// Stop receiving new messages from any process until messages already buffered have been received by the application, leaving the rest in the network and so blocking their senders.
+	Backpressure,
+	/// As with `Backpressure`, and if messages still being received take the total over the limit, kill the channel with the most buffered. Its [Receiver](crate::Receiver) then returns [`ChannelError::Unknown`].
+	Disconnect,
+}
+
+/// Which way a message passed to the inspector set with [`set_message_inspector()`](crate::set_message_inspector) was going.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum Direction {
+	/// Sent by this process.
+	Sent,
+	/// Received by this process.
+	Received,
+}
+
+/// A callback passed the direction, remote, type name and serialized length of each message.
+pub type Inspector = Box<dyn Fn(Direction, SocketAddr, &str, usize) + marker::Send + Sync>;
+
 pub struct Sender<T: Serialize> {
 	channel: Option<Arc<RwLock<Option<Channel>>>>,
+	remote: SocketAddr,
+	local: Option<Arc<Local<T>>>,
+	encode: Option<Encode<T>>,
 	_marker: marker::PhantomData<fn(T)>,
 }
 impl<T: Serialize> Sender<T> {
@@ -524,6 +1035,10 @@ impl<T: Serialize> Sender<T> {
 					return None;
 				}
 				channel.write().unwrap().as_mut().unwrap().senders_count += 1;
+				if let Some(window) = &mut channel.write().unwrap().as_mut().unwrap().send_window {
+					// Let what the previous Sender left drain, without queueing any more
+					window.capacity = 0;
+				}
 				let notifier_key: *const RwLock<Option<Channel>> = &**channel;
 				trace!("retain sender {:?}", notifier_key);
 				channel.clone()
@@ -538,45 +1053,138 @@ impl<T: Serialize> Sender<T> {
 		);
 		Some(Self {
 			channel: Some(channel),
+			remote,
+			local: None,
+			encode: None,
 			_marker: marker::PhantomData,
 		})
 	}
 
+	pub fn with_local(local: Arc<Local<T>>, remote: SocketAddr) -> Self {
+		Self {
+			channel: None,
+			remote,
+			local: Some(local),
+			encode: None,
+			_marker: marker::PhantomData,
+		}
+	}
+
+	/// Encode messages with `encode` rather than the channel's own bincode. The [Receiver] at the other end must decode them to match.
+	pub fn set_encode(&mut self, encode: Encode<T>) {
+		self.encode = Some(encode);
+	}
+
+	/// Let up to `capacity` messages wait to be handed to the connection, rather than sends blocking as soon as it has no room.
+	pub fn set_capacity(&self, capacity: usize)
+	where
+		T: marker::Send,
+	{
+		if let Some(channel) = &self.channel {
+			let mut channel = channel.write().unwrap();
+			channel
+				.as_mut()
+				.unwrap()
+				.send_window
+				.get_or_insert_with(|| SendWindow {
+					capacity,
+					queued: VecDeque::new(),
+				})
+				.capacity = capacity;
+		}
+	}
+
 	pub fn try_send<'a, C: Borrow<Reactor> + 'a>(
 		&'a self, context: C, register: Option<&mut Context>,
-	) -> Option<impl FnOnce(T) + 'a>
+	) -> Option<impl FnOnce(T) -> Result<(), ChannelError> + 'a>
 	where
 		T: 'static,
 	{
-		let mut channel = self.channel.as_ref().unwrap().write().unwrap();
-		let unblocked = {
+		let mut channel = self
+			.channel
+			.as_ref()
+			.map(|channel| channel.write().unwrap());
+		let unblocked = channel.as_mut().map_or(true, |channel| {
 			// let notifier = &context.borrow().notifier;
 			// let notifier_key: *const RwLock<Option<Channel>> =
 			// 	&**self.channel.as_ref().unwrap();
 			// let notifier = &notifier.context(Key(notifier_key as *const ()));
 			// assert_eq!(Arc::strong_count(&self.channel.as_ref().unwrap()), 1+channel.as_ref().unwrap().senders_count+channel.as_ref().unwrap().receivers_count);
-			let inner = &mut channel.as_mut().unwrap().inner;
-			inner.send_avail().unwrap_or(!inner.valid()) // || inner.closed()
-		};
+			let channel = channel.as_mut().unwrap();
+			let inner = &mut channel.inner;
+			match &channel.send_window {
+				Some(window) if inner.valid() && (inner.connecting() || inner.sendable()) => {
+					window.queued.len() < window.capacity
+						|| (window.queued.is_empty() && inner.send_avail() == Some(true))
+				}
+				_ => inner.send_avail().unwrap_or(!inner.valid()), // || inner.closed()
+			}
+		});
 		if unblocked {
 			Some(move |t| {
+				let mut channel = if let Some(channel) = channel {
+					channel
+				} else {
+					if context.borrow().inspecting.load(Ordering::Relaxed) {
+						context
+							.borrow()
+							.inspect(Direction::Sent, self.remote, type_name::<T>(), 0);
+					}
+					return self.local.as_ref().unwrap().send(t);
+				};
 				let notifier = &context.borrow().notifier;
 				let notifier_key: *const RwLock<Option<Channel>> =
 					&**self.channel.as_ref().unwrap();
 				let notifier = &notifier.context(Key(notifier_key as *const ()));
-				let inner = &mut channel.as_mut().unwrap().inner;
+				let start = serde_timing_start(context.borrow());
+				let channel = channel.as_mut().unwrap();
+				let inner = &mut channel.inner;
 				if !inner.valid() {
-					panic!(".send() called on killed Sender");
+					return Err(if channel.timed_out {
+						ChannelError::Timeout
+					} else {
+						ChannelError::Unknown
+					});
 				}
-				if !inner.sendable() {
-					panic!(".send() called on a closed Sender");
+				// Hold it in the window if the connection has no room for it, or there are messages ahead of it. A window left draining by a previous Sender takes no more.
+				let queue = channel.send_window.as_ref().map_or(false, |window| {
+					window.capacity > 0
+						&& (inner.connecting()
+							|| (inner.sendable()
+								&& (!window.queued.is_empty() || inner.send_avail() != Some(true))))
+				});
+				if !queue && !inner.sendable() {
+					return Err(ChannelError::Exited);
+				}
+				let t = match &self.encode {
+					Some(encode) => Encoded::Bytes(encode(&t)),
+					None => Encoded::Value(t),
+				};
+				if context.borrow().inspecting.load(Ordering::Relaxed) {
+					let len = bincode::serialized_size(&t).unwrap().try_into().unwrap();
+					context
+						.borrow()
+						.inspect(Direction::Sent, self.remote, type_name::<T>(), len);
+				}
+				if queue {
+					let send = move |inner: &mut Inner, notifier: &NotifierContext<'_, Key>| {
+						inner.send(t, notifier)
+					};
+					let window = channel.send_window.as_mut().unwrap();
+					window.queued.push_back(Held(Box::new(send)));
+					return Ok(());
 				}
 				inner.send(t, notifier);
+				if let Some(start) = start {
+					channel.serialize += start.elapsed();
+				}
+				Ok(())
 				// TODO: unpark queue?
 			})
 		} else {
 			if let Some(cx) = register {
 				channel
+					.unwrap()
 					.as_mut()
 					.unwrap()
 					.senders_futures
@@ -586,14 +1194,48 @@ impl<T: Serialize> Sender<T> {
 		}
 	}
 
+	/// Panic if a send failed, for the methods that don't return the error. A local channel whose receiver has been dropped discards the message instead.
+	pub fn expect_sent(&self, result: &Result<(), ChannelError>) {
+		if let Err(err) = result {
+			if self.local.is_none() {
+				if *err == ChannelError::Exited {
+					panic!(".send() called on a closed Sender");
+				}
+				panic!(".send() called on killed Sender");
+			}
+		}
+	}
+
 	pub fn send<'a, F: FnOnce() -> T + 'a>(&'a self, f: F) -> Send<'a, T, F>
 	where
 		T: 'static,
 	{
-		Send(self, RwLock::new(Some(f)))
+		Send(self, RwLock::new(Some(f)), RwLock::new(None))
+	}
+
+	/// Record that a [Send] on this was blocked for `duration`.
+	fn blocked(&self, duration: Duration) {
+		if let Some(channel) = &self.channel {
+			channel.write().unwrap().as_mut().unwrap().send_blocked += duration;
+		}
+	}
+
+	/// Whether the connection is being made or can still be sent on.
+	pub fn is_connected(&self) -> bool {
+		if let Some(channel) = &self.channel {
+			let channel = channel.read().unwrap();
+			let inner = &channel.as_ref().unwrap().inner;
+			inner.valid() && (inner.connecting() || inner.sendable())
+		} else {
+			self.local.as_ref().unwrap().receiver_alive()
+		}
 	}
 
 	pub fn drop(mut self, context: &Reactor) {
+		if let Some(local) = self.local.take() {
+			mem::forget(self);
+			return local.drop_sender();
+		}
 		let mut sockets = context.sockets.write().unwrap();
 		let channel_arc = self.channel.take().unwrap();
 		mem::forget(self);
@@ -631,31 +1273,42 @@ impl<T: Serialize> Sender<T> {
 		}
 	}
 }
-impl<T: Serialize> Sender<Option<T>> {
-	pub fn futures_poll_ready(
-		&self, cx: &mut Context, context: &Reactor,
-	) -> Poll<Result<(), Infallible>>
+impl<T: Serialize> Sender<T> {
+	pub fn futures_poll_ready(&self, cx: &mut Context, context: &Reactor) -> Poll<()>
 	where
 		T: 'static,
 	{
 		if let Some(_send) = self.try_send(context, Some(cx)) {
-			Poll::Ready(Ok(()))
+			Poll::Ready(())
 		} else {
 			Poll::Pending
 		}
 	}
 
-	pub fn futures_start_send(&self, item: T, context: &Reactor) -> Result<(), Infallible>
+	pub fn futures_start_send(&self, item: T, context: &Reactor) -> Result<(), ChannelError>
 	where
 		T: 'static,
 	{
 		// TODO: Race
 		self.try_send(context, None).expect(
 			"called futures::Sink::start_send without the go-ahead from futures::Sink::poll_ready OR another thread has beaten us to it (!)",
-		)(Some(item));
-		Ok(())
+		)(item)
 	}
 
+	/// Whether every message sent has been serialized and handed to the connection, registering `cx` to be woken on its next progress if not.
+	pub fn futures_poll_flush(&self, cx: &mut Context) -> Poll<()> {
+		if let Some(channel) = &self.channel {
+			let mut channel = channel.write().unwrap();
+			let channel = channel.as_mut().unwrap();
+			if !channel.flushed() {
+				channel.senders_futures.push(cx.waker().clone());
+				return Poll::Pending;
+			}
+		}
+		Poll::Ready(())
+	}
+}
+impl<T: Serialize> Sender<Option<T>> {
 	pub fn futures_poll_close(
 		&self, cx: &mut Context, context: &Reactor,
 	) -> Poll<Result<(), Infallible>>
@@ -663,7 +1316,8 @@ impl<T: Serialize> Sender<Option<T>> {
 		T: 'static,
 	{
 		if let Some(send) = self.try_send(context, Some(cx)) {
-			send(None);
+			let sent = send(None);
+			self.expect_sent(&sent);
 			Poll::Ready(Ok(()))
 		} else {
 			Poll::Pending
@@ -678,6 +1332,7 @@ impl<T: Serialize> Drop for Sender<T> {
 pub struct Send<'a, T: Serialize + 'static, F: FnOnce() -> T>(
 	pub &'a Sender<T>,
 	pub RwLock<Option<F>>,
+	pub RwLock<Option<Instant>>, // when this first blocked
 );
 impl<'a, T: Serialize + 'static, F: FnOnce() -> T> fmt::Debug for Send<'a, T, F> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -687,9 +1342,14 @@ impl<'a, T: Serialize + 'static, F: FnOnce() -> T> fmt::Debug for Send<'a, T, F>
 impl<'a, T: Serialize + 'static, F: FnOnce() -> T> Send<'a, T, F> {
 	pub fn futures_poll(self: Pin<&mut Self>, cx: &mut Context, context: &Reactor) -> Poll<()> {
 		if let Some(send) = self.0.try_send(context, Some(cx)) {
-			send(self.as_ref().1.write().unwrap().take().unwrap()());
+			let sent = send(self.as_ref().1.write().unwrap().take().unwrap()());
+			self.0.expect_sent(&sent);
+			if let Some(blocked) = self.2.write().unwrap().take() {
+				self.0.blocked(blocked.elapsed());
+			}
 			Poll::Ready(())
 		} else {
+			let _ = self.2.write().unwrap().get_or_insert_with(Instant::now);
 			Poll::Pending
 		}
 	}
@@ -699,12 +1359,16 @@ impl<T: Serialize> fmt::Debug for Sender<T> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_struct("Sender")
 			.field("inner", &self.channel)
+			.field("local", &self.local)
 			.finish()
 	}
 }
 
 pub struct Receiver<T: DeserializeOwned> {
 	channel: Option<Arc<RwLock<Option<Channel>>>>,
+	remote: SocketAddr,
+	local: Option<Arc<Local<T>>>,
+	decode: Option<Decode<T>>,
 	_marker: marker::PhantomData<fn() -> T>,
 }
 impl<T: DeserializeOwned> Receiver<T> {
@@ -748,46 +1412,178 @@ impl<T: DeserializeOwned> Receiver<T> {
 		);
 		Some(Self {
 			channel: Some(channel),
+			remote,
+			local: None,
+			decode: None,
 			_marker: marker::PhantomData,
 		})
 	}
 
+	pub fn with_local(local: Arc<Local<T>>, remote: SocketAddr) -> Self {
+		Self {
+			channel: None,
+			remote,
+			local: Some(local),
+			decode: None,
+			_marker: marker::PhantomData,
+		}
+	}
+
+	/// Kill the channel if a message being received takes more than `max` bytes, overriding [`Reactor::set_max_message_size()`].
+	pub fn set_max_message_size(&self, max: usize, context: &Reactor) {
+		if let Some(channel) = &self.channel {
+			channel.write().unwrap().as_mut().unwrap().max_message_size = Some(max);
+			let notifier_key: *const RwLock<Option<Channel>> = &**channel;
+			context
+				.notifier
+				.context(Key(notifier_key as *const ()))
+				.queue();
+		}
+	}
+
+	/// Decode messages with `decode` rather than the channel's own bincode. The [Sender] at the other end must encode them to match.
+	pub fn set_decode(&mut self, decode: Decode<T>) {
+		self.decode = Some(decode);
+	}
+
+	/// Have the reactor receive up to `capacity` messages ahead of the application, rather than holding off once it has received one.
+	pub fn set_capacity(&self, capacity: usize, context: &Reactor)
+	where
+		T: marker::Send + 'static,
+	{
+		if let Some(channel) = &self.channel {
+			channel.write().unwrap().as_mut().unwrap().recv_window = Some(RecvWindow {
+				capacity,
+				received: VecDeque::new(),
+				recv: if self.decode.is_some() {
+					recv_ahead::<Vec<u8>>
+				} else {
+					recv_ahead::<T>
+				},
+			});
+			let notifier_key: *const RwLock<Option<Channel>> = &**channel;
+			context
+				.notifier
+				.context(Key(notifier_key as *const ()))
+				.queue();
+		}
+	}
+
+	#[allow(clippy::too_many_lines)]
 	pub fn try_recv<'a, C: Borrow<Reactor> + 'a>(
-		&'a self, context: C, register: Option<&mut Context>,
+		&'a self, context: C, mut register: Option<&mut Context>,
 	) -> Option<impl FnOnce() -> Result<T, ChannelError> + 'a>
 	where
 		T: 'static,
 	{
-		let mut channel = self.channel.as_ref().unwrap().write().unwrap();
-		let unblocked = {
+		let mut channel = self
+			.channel
+			.as_ref()
+			.map(|channel| channel.write().unwrap());
+		let unblocked = if let Some(channel) = &mut channel {
 			let notifier = &context.borrow().notifier;
 			let notifier_key: *const RwLock<Option<Channel>> = &**self.channel.as_ref().unwrap();
 			let notifier = &notifier.context(Key(notifier_key as *const ()));
 			// assert_eq!(Arc::strong_count(&self.channel.as_ref().unwrap()), 1+channel.as_ref().unwrap().senders_count+channel.as_ref().unwrap().receivers_count);
-			let inner = &mut channel.as_mut().unwrap().inner;
-			inner.recv_avail::<T, _>(notifier).unwrap_or(!inner.valid()) // || inner.closed()
+			let start = serde_timing_start(context.borrow());
+			let channel = channel.as_mut().unwrap();
+			let inner = &mut channel.inner;
+			let unblocked = channel
+				.recv_window
+				.as_ref()
+				.map_or(false, |window| !window.received.is_empty())
+				|| if self.decode.is_some() {
+					inner.recv_avail::<Vec<u8>, _>(notifier)
+				} else {
+					inner.recv_avail::<T, _>(notifier)
+				}
+				.unwrap_or(!inner.valid()); // || inner.closed()
+			if let Some(start) = start {
+				channel.deserialize += start.elapsed();
+			}
+			unblocked
+		} else {
+			self.local.as_ref().unwrap().recv_avail(register.take())
 		};
 		if unblocked {
 			Some(move || {
+				let mut channel = if let Some(channel) = channel {
+					channel
+				} else {
+					let t = self.local.as_ref().unwrap().recv();
+					if t.is_ok() && context.borrow().inspecting.load(Ordering::Relaxed) {
+						context.borrow().inspect(
+							Direction::Received,
+							self.remote,
+							type_name::<T>(),
+							0,
+						);
+					}
+					return t;
+				};
 				let notifier = &context.borrow().notifier;
 				let notifier_key: *const RwLock<Option<Channel>> =
 					&**self.channel.as_ref().unwrap();
 				let notifier = &notifier.context(Key(notifier_key as *const ()));
 				// let mut channel = self.channel.as_ref().unwrap().write().unwrap();
 				// assert_eq!(Arc::strong_count(&self.channel.as_ref().unwrap()), 1+channel.as_ref().unwrap().senders_count+channel.as_ref().unwrap().receivers_count);
-				let inner = &mut channel.as_mut().unwrap().inner;
+				let channel = channel.as_mut().unwrap();
+				if let Some((t, len)) = channel
+					.recv_window
+					.as_mut()
+					.and_then(|window| window.received.pop_front())
+				{
+					if context.borrow().inspecting.load(Ordering::Relaxed) {
+						context.borrow().inspect(
+							Direction::Received,
+							self.remote,
+							type_name::<T>(),
+							len,
+						);
+					}
+					// So that the reactor receives another in its place
+					notifier.queue();
+					return Ok(match &self.decode {
+						Some(decode) => decode(&t.0.downcast::<Vec<u8>>().unwrap()),
+						None => *t.0.downcast().unwrap(),
+					});
+				}
+				let inner = &mut channel.inner;
 				if !inner.valid() {
-					return Err(ChannelError::Unknown);
+					return Err(if channel.timed_out {
+						ChannelError::Timeout
+					} else {
+						ChannelError::Unknown
+					});
 				}
 				if !inner.recvable() {
 					return Err(ChannelError::Exited);
 				}
-				Ok(inner.recv(notifier))
+				let len = inner.recv_buffered();
+				let start = serde_timing_start(context.borrow());
+				let t = match &self.decode {
+					Some(decode) => decode(&inner.recv::<Vec<u8>>(notifier)),
+					None => inner.recv(notifier),
+				};
+				if context.borrow().inspecting.load(Ordering::Relaxed) {
+					context.borrow().inspect(
+						Direction::Received,
+						self.remote,
+						type_name::<T>(),
+						len,
+					);
+				}
+				if let Some(start) = start {
+					channel.deserialize += start.elapsed();
+				}
+				channel.account(context.borrow());
+				Ok(t)
 				// TODO: unpark queue?
 			})
 		} else {
 			if let Some(cx) = register {
 				channel
+					.unwrap()
 					.as_mut()
 					.unwrap()
 					.receivers_futures
@@ -801,10 +1597,37 @@ impl<T: DeserializeOwned> Receiver<T> {
 	where
 		T: 'static,
 	{
-		Recv(self, RwLock::new(Some(f)))
+		Recv(self, RwLock::new(Some(f)), RwLock::new(None))
+	}
+
+	/// Record that a [Recv] on this was blocked for `duration`.
+	fn blocked(&self, duration: Duration) {
+		if let Some(channel) = &self.channel {
+			channel.write().unwrap().as_mut().unwrap().recv_blocked += duration;
+		}
+	}
+
+	/// Whether the connection is being made or can still be received on. Messages the remote sent before closing are received before it stops being recvable.
+	pub fn is_connected(&self) -> bool {
+		if let Some(channel) = &self.channel {
+			let channel = channel.read().unwrap();
+			let channel = channel.as_ref().unwrap();
+			let inner = &channel.inner;
+			(inner.valid() && (inner.connecting() || inner.recvable()))
+				|| channel
+					.recv_window
+					.as_ref()
+					.map_or(false, |window| !window.received.is_empty())
+		} else {
+			self.local.as_ref().unwrap().sender_alive()
+		}
 	}
 
 	pub fn drop(mut self, context: &Reactor) {
+		if let Some(local) = self.local.take() {
+			mem::forget(self);
+			return local.drop_receiver();
+		}
 		let mut sockets = context.sockets.write().unwrap();
 		let channel_arc = self.channel.take().unwrap();
 		mem::forget(self);
@@ -818,6 +1641,7 @@ impl<T: DeserializeOwned> Receiver<T> {
 			let channel = channel.as_mut().unwrap();
 			channel.receivers_count -= 1;
 			assert_eq!(channel.receivers_count, 0);
+			channel.recv_window = None;
 			trace!("release receiver {:?}", notifier_key);
 			channel.senders_count == 0 && channel.receivers_count == 0 && channel.inner.closed()
 		};
@@ -868,6 +1692,7 @@ impl<T: DeserializeOwned> Drop for Receiver<T> {
 pub struct Recv<'a, T: DeserializeOwned + 'static, F: FnOnce(Result<T, ChannelError>)>(
 	pub &'a Receiver<T>,
 	pub RwLock<Option<F>>,
+	pub RwLock<Option<Instant>>, // when this first blocked
 );
 impl<'a, T: DeserializeOwned + 'static, F: FnOnce(Result<T, ChannelError>)> fmt::Debug
 	for Recv<'a, T, F>
@@ -883,8 +1708,12 @@ impl<'a, T: DeserializeOwned + 'static, F: FnOnce(Result<T, ChannelError>)> Recv
 	{
 		if let Some(recv) = self.0.try_recv(context, Some(cx)) {
 			self.as_ref().1.write().unwrap().take().unwrap()(recv());
+			if let Some(blocked) = self.2.write().unwrap().take() {
+				self.0.blocked(blocked.elapsed());
+			}
 			Poll::Ready(())
 		} else {
+			let _ = self.2.write().unwrap().get_or_insert_with(Instant::now);
 			Poll::Pending
 		}
 	}
@@ -894,6 +1723,7 @@ impl<T: DeserializeOwned> fmt::Debug for Receiver<T> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_struct("Receiver")
 			.field("inner", &self.channel)
+			.field("local", &self.local)
 			.finish()
 	}
 }
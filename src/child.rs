@@ -0,0 +1,327 @@
+use futures::{channel::oneshot, Sink, Stream};
+use nix::libc;
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	collections::{HashMap, VecDeque}, convert::{Infallible, TryInto}, pin::Pin, sync::Mutex, task::{Context, Poll, Waker}
+};
+
+use super::{
+	monitor_event, send_signal, spawn, spawn_many_deployed, spawn_with, ChannelError, ExitStatus, Pid, Resources, Signal, SpawnError, CONTROL, DEPLOYED
+};
+use constellation_internal::{Fd, ProcessOutputEvent, SpawnOptions};
+
+/// The processes being watched by a [Child], with their exit status once they've exited, and their output yet to be read.
+static EXITS: Lazy<Mutex<HashMap<Pid, Exit>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Calls to [`kill()`] and [`terminate()`] waiting to hear from the bridge whether the process was running, in the order they were made.
+static KILLS: Lazy<Mutex<HashMap<Pid, VecDeque<oneshot::Sender<bool>>>>> =
+	Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Exit {
+	status: Option<ExitStatus>,
+	wakers: Vec<Waker>,
+	stdout: Output,
+	stderr: Output,
+	/// The [Child] has been dropped before the process exited, so the entry lingers until it does to discard its output.
+	dropped: bool,
+}
+impl Exit {
+	fn new() -> Self {
+		Self {
+			status: None,
+			wakers: Vec::new(),
+			stdout: Output::default(),
+			stderr: Output::default(),
+			dropped: false,
+		}
+	}
+
+	fn output(&mut self, fd: Fd) -> &mut Output {
+		match fd {
+			libc::STDOUT_FILENO => &mut self.stdout,
+			libc::STDERR_FILENO => &mut self.stderr,
+			_ => unreachable!(),
+		}
+	}
+}
+
+#[derive(Default)]
+struct Output {
+	chunks: VecDeque<Vec<u8>>,
+	eof: bool,
+	waker: Option<Waker>,
+}
+
+/// Note that a process being watched has exited, as reported by our monitor process.
+pub(crate) fn exited(pid: Pid, exit_code: ExitStatus) {
+	let mut exits = EXITS.lock().unwrap();
+	if let Some(exit) = exits.get_mut(&pid) {
+		if exit.dropped {
+			let _ = exits.remove(&pid);
+			return;
+		}
+		exit.status = Some(exit_code);
+		for waker in exit.wakers.drain(..) {
+			waker.wake();
+		}
+	}
+}
+
+/// Start watching a process spawned by [`spawn_handle()`], before our monitor process is told of it, so that output arriving before the [Child] has been returned is buffered rather than discarded.
+pub(crate) fn watch(pid: Pid) {
+	let _ = EXITS.lock().unwrap().entry(pid).or_insert_with(Exit::new);
+}
+
+/// Note output written by a process whose output is forwarded to this one, as reported by our monitor process. An empty `output` is EOF.
+///
+/// Output of a process not being watched, for example because its [Child] was dropped and it has since exited, is discarded.
+pub(crate) fn output(pid: Pid, fd: Fd, output: Vec<u8>) {
+	let mut exits = EXITS.lock().unwrap();
+	let exit = match exits.get_mut(&pid) {
+		Some(exit) if !exit.dropped => exit,
+		_ => return,
+	};
+	let output_ = exit.output(fd);
+	if output.is_empty() {
+		output_.eof = true;
+	} else {
+		output_.chunks.push_back(output);
+	}
+	if let Some(waker) = output_.waker.take() {
+		waker.wake();
+	}
+}
+
+/// Note whether a process this one asked to kill was running, as reported by our monitor process.
+pub(crate) fn killed(pid: Pid, running: bool) {
+	let mut kills = KILLS.lock().unwrap();
+	let waiting = kills.get_mut(&pid).unwrap();
+	let _ = waiting.pop_front().unwrap().send(running);
+	if waiting.is_empty() {
+		let _ = kills.remove(&pid);
+	}
+}
+
+/// A handle to a process spawned with [`spawn_handle()`], to wait for it to exit or kill it.
+///
+/// This is akin to [`std::process::Child`], for the common pattern of spawning a process and waiting for it.
+#[derive(Debug)]
+pub struct Child {
+	pid: Pid,
+}
+impl Child {
+	/// Get the pid of the process.
+	pub fn pid(&self) -> Pid {
+		self.pid
+	}
+
+	/// Wait for the process to exit, returning its exit status.
+	///
+	/// This is an async fn.
+	pub async fn join(self) -> ExitStatus {
+		futures::future::poll_fn(|cx| {
+			let mut exits = EXITS.lock().unwrap();
+			let exit = exits.get_mut(&self.pid).unwrap();
+			if let Some(exit_code) = exit.status {
+				Poll::Ready(exit_code)
+			} else {
+				exit.wakers.push(cx.waker().clone());
+				Poll::Pending
+			}
+		})
+		.await
+	}
+
+	/// Kill the process, by delivering it `SIGKILL`. Like [`send_signal()`], this is ignored if it has already exited.
+	pub fn kill(&self) {
+		send_signal(self.pid, Signal::SIGKILL);
+	}
+
+	/// A [Stream] of what the process writes to its stdout, in the chunks it was read in, ending once the process closes it.
+	///
+	/// The output is still printed as usual too. It's buffered from when the process is spawned until it's read, and is shared between all the streams returned, so each chunk is yielded by only one of them.
+	pub fn stdout(&self) -> impl Stream<Item = Vec<u8>> + '_ {
+		self.output(libc::STDOUT_FILENO)
+	}
+
+	/// A [Stream] of what the process writes to its stderr, in the chunks it was read in, ending once the process closes it.
+	///
+	/// Like [`stdout()`](Child::stdout), the output is still printed as usual, and is buffered until it's read.
+	pub fn stderr(&self) -> impl Stream<Item = Vec<u8>> + '_ {
+		self.output(libc::STDERR_FILENO)
+	}
+
+	/// A [Sink] that writes to the process's stdin.
+	///
+	/// Each item is written in the order it's sent, once the process's monitor receives it. Closing the sink closes the process's stdin, so that it reads EOF; merely dropping it doesn't. Like [`send_signal()`], writes are ignored once the process has exited, and empty items are skipped as they'd otherwise be taken as EOF.
+	pub fn stdin(&self) -> impl Sink<Vec<u8>, Error = Infallible> {
+		Stdin {
+			pid: self.pid,
+			closed: false,
+		}
+	}
+
+	fn output(&self, fd: Fd) -> impl Stream<Item = Vec<u8>> + '_ {
+		futures::stream::poll_fn(move |cx| {
+			let mut exits = EXITS.lock().unwrap();
+			let output = exits.get_mut(&self.pid).unwrap().output(fd);
+			if let Some(chunk) = output.chunks.pop_front() {
+				Poll::Ready(Some(chunk))
+			} else if output.eof {
+				Poll::Ready(None)
+			} else {
+				output.waker = Some(cx.waker().clone());
+				Poll::Pending
+			}
+		})
+	}
+}
+struct Stdin {
+	pid: Pid,
+	/// EOF has been sent, after which the monitor no longer accepts input.
+	closed: bool,
+}
+impl Sink<Vec<u8>> for Stdin {
+	type Error = Infallible;
+
+	fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		Poll::Ready(Ok(()))
+	}
+	fn start_send(self: Pin<&mut Self>, item: Vec<u8>) -> Result<(), Self::Error> {
+		assert!(
+			!self.closed,
+			"Child::stdin() sink sent to after being closed"
+		);
+		if !item.is_empty() {
+			monitor_event(&ProcessOutputEvent::Input(
+				self.pid,
+				libc::STDIN_FILENO,
+				item,
+			));
+		}
+		Ok(())
+	}
+	fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		Poll::Ready(Ok(()))
+	}
+	fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		if !self.closed {
+			monitor_event(&ProcessOutputEvent::Input(
+				self.pid,
+				libc::STDIN_FILENO,
+				Vec::new(),
+			));
+			self.closed = true;
+		}
+		Poll::Ready(Ok(()))
+	}
+}
+
+impl Drop for Child {
+	fn drop(&mut self) {
+		let mut exits = EXITS.lock().unwrap();
+		let exit = exits.get_mut(&self.pid).unwrap();
+		if exit.status.is_some() {
+			let _ = exits.remove(&self.pid);
+		} else {
+			exit.dropped = true;
+			exit.stdout = Output::default();
+			exit.stderr = Output::default();
+		}
+	}
+}
+
+/// Spawn a new process, as with [`spawn()`], returning a [Child] handle to it.
+///
+/// The [Child] can be [joined](Child::join) to wait for the process to exit and get its exit status, without setting up a channel to learn of it, and its output can be read with [`stdout()`](Child::stdout) and [`stderr()`](Child::stderr).
+///
+/// This is an async fn.
+pub async fn spawn_handle<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
+	resources: Resources, start: T,
+) -> Result<Child, SpawnError> {
+	let () = *Lazy::force(&CONTROL);
+	let options = SpawnOptions {
+		forward_output: true,
+		..SpawnOptions::default()
+	};
+	// Watched from when it was spawned, per watch()
+	let pid = spawn_with(resources, options, start).await?;
+	monitor_event(&ProcessOutputEvent::Watch(pid));
+	Ok(Child { pid })
+}
+
+/// Kill the process identified by `pid`, by delivering it `SIGKILL`.
+///
+/// Like [`send_signal()`], this is routed via the bridge to the process's monitor. It resolves once the bridge has passed it on, returning `Err(ChannelError::Exited)` if the process had already exited. Delivery is otherwise best-effort: it resolving `Ok` doesn't mean the process has yet been killed, nor that it didn't exit of its own accord in the meantime.
+///
+/// This is an async fn.
+pub async fn kill(pid: Pid) -> Result<(), ChannelError> {
+	kill_with(pid, Signal::SIGKILL).await
+}
+
+/// Ask the process identified by `pid` to shut down, by delivering it `SIGTERM`.
+///
+/// Unlike [`kill()`], this gives the process the chance to run a shutdown handler, for example to flush its state, though it's up to the process whether and when it exits. Delivery and the result are as for [`kill()`].
+///
+/// This is an async fn.
+pub async fn terminate(pid: Pid) -> Result<(), ChannelError> {
+	kill_with(pid, Signal::SIGTERM).await
+}
+
+async fn kill_with(pid: Pid, signal: Signal) -> Result<(), ChannelError> {
+	let (sender, receiver) = oneshot::channel();
+	KILLS
+		.lock()
+		.unwrap()
+		.entry(pid)
+		.or_default()
+		.push_back(sender);
+	let () = *Lazy::force(&CONTROL);
+	monitor_event(&ProcessOutputEvent::Kill(pid, signal));
+	if receiver.await.unwrap() {
+		Ok(())
+	} else {
+		Err(ChannelError::Exited)
+	}
+}
+
+/// Spawn `count` processes with the same `resources`, the `i`th running the closure returned by `start(i)`.
+///
+/// This is for spawning a group of workers that are only useful together, so either all of them are running or none are. When deployed to a cluster they're spawned as a single batch that the scheduler allocates all or nothing, waiting until there's capacity for them all. Otherwise they're spawned one after another, and if any fails to spawn, those already spawned are [killed](kill) and the error returned; they'll have started running before they're killed. The same goes for a process of a batch that the fabric fails to start.
+///
+/// This is an async fn.
+pub async fn spawn_many<F, T>(
+	resources: Resources, count: usize, mut start: F,
+) -> Result<Vec<Pid>, SpawnError>
+where
+	F: FnMut(usize) -> T,
+	T: FnOnce(Pid) + Serialize + DeserializeOwned,
+{
+	let deployed = *DEPLOYED.get().unwrap_or_else(|| {
+		panic!("You must call init() immediately inside your application's main() function")
+	});
+	if deployed {
+		let pids = spawn_many_deployed(resources, (0..count).map(start).collect()).await;
+		if let Some(err) = pids.iter().find_map(|pid| pid.as_ref().err()).cloned() {
+			for pid in pids.into_iter().filter_map(Result::ok) {
+				let _ = kill(pid).await;
+			}
+			return Err(err.try_into().unwrap());
+		}
+		return Ok(pids.into_iter().map(Result::unwrap).collect());
+	}
+	let mut pids = Vec::with_capacity(count);
+	for i in 0..count {
+		match spawn(resources, start(i)).await {
+			Ok(pid) => pids.push(pid),
+			Err(err) => {
+				for pid in pids {
+					let _ = kill(pid).await;
+				}
+				return Err(err);
+			}
+		}
+	}
+	Ok(pids)
+}
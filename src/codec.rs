@@ -0,0 +1,50 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::error::Error;
+
+/// How the messages on a channel created with [`Sender::new_with_codec()`](crate::Sender::new_with_codec) and [`Receiver::new_with_codec()`](crate::Receiver::new_with_codec) are encoded.
+///
+/// Messages are otherwise encoded with bincode. Another codec is useful for interop with services not written in Rust, or to make the traffic on a channel readable when debugging. Only the payload of each message is encoded by the codec, so the framing is unchanged, and both ends must use the same codec.
+pub trait Codec: Send + Sync + 'static {
+	/// Encode `t`.
+	fn encode<T: Serialize>(&self, t: &T) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+	/// Decode a `T` from `bytes`, as encoded by [`encode()`](Codec::encode).
+	fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn Error + Send + Sync>>;
+}
+
+/// Encode messages with [bincode](https://github.com/servo/bincode), as channels do by default.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Bincode;
+impl Codec for Bincode {
+	fn encode<T: Serialize>(&self, t: &T) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+		Ok(bincode::serialize(t)?)
+	}
+	fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn Error + Send + Sync>> {
+		Ok(bincode::deserialize(bytes)?)
+	}
+}
+
+/// Encode messages as JSON.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Json;
+impl Codec for Json {
+	fn encode<T: Serialize>(&self, t: &T) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+		Ok(serde_json::to_vec(t)?)
+	}
+	fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn Error + Send + Sync>> {
+		Ok(serde_json::from_slice(bytes)?)
+	}
+}
+
+/// Encode messages as [MessagePack](https://msgpack.org/).
+#[cfg(feature = "msgpack")]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct MessagePack;
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePack {
+	fn encode<T: Serialize>(&self, t: &T) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+		Ok(rmp_serde::to_vec(t)?)
+	}
+	fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn Error + Send + Sync>> {
+		Ok(rmp_serde::from_slice(bytes)?)
+	}
+}
@@ -0,0 +1,64 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::error::Error;
+
+use super::Codec;
+
+/// How the messages of a channel created with [`Sender::with_compression()`](crate::Sender::with_compression) are compressed.
+///
+/// Each message is serialized with bincode as usual, then compressed, and sent with a one-byte header naming the algorithm. A [`Receiver::with_compression()`](crate::Receiver::with_compression) decompresses according to the header, so messages compressed differently, or not at all, can be mixed. This suits large messages, at the cost of CPU time on both ends. Algorithms other than [`None`](Compression::None) are enabled by the feature of the same name.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum Compression {
+	/// Don't compress.
+	None,
+	/// Compress with [LZ4](https://lz4.github.io/lz4/), which is fast with a moderate ratio. This requires the `lz4` feature.
+	#[cfg(feature = "lz4")]
+	Lz4,
+	/// Compress with [Zstandard](https://facebook.github.io/zstd/), which is slower with a better ratio. This requires the `zstd` feature.
+	#[cfg(feature = "zstd")]
+	Zstd,
+}
+
+const NONE: u8 = 0;
+const LZ4: u8 = 1;
+const ZSTD: u8 = 2;
+
+impl Compression {
+	#[allow(clippy::unnecessary_wraps)] // only fallible with the zstd feature
+	fn compress(self, bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+		let (header, compressed) = match self {
+			Self::None => (NONE, bytes.to_vec()),
+			#[cfg(feature = "lz4")]
+			Self::Lz4 => (LZ4, lz4_flex::compress_prepend_size(bytes)),
+			#[cfg(feature = "zstd")]
+			Self::Zstd => (ZSTD, zstd::encode_all(bytes, 0)?),
+		};
+		let mut message = Vec::with_capacity(1 + compressed.len());
+		message.push(header);
+		message.extend(compressed);
+		Ok(message)
+	}
+
+	fn decompress(message: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+		let (&header, compressed) = message.split_first().ok_or("empty message")?;
+		match header {
+			NONE => Ok(compressed.to_vec()),
+			#[cfg(feature = "lz4")]
+			LZ4 => Ok(lz4_flex::decompress_size_prepended(compressed)?),
+			#[cfg(feature = "zstd")]
+			ZSTD => Ok(zstd::decode_all(compressed)?),
+			#[cfg(not(feature = "lz4"))]
+			LZ4 => Err("message compressed with LZ4, which requires the `lz4` feature".into()),
+			#[cfg(not(feature = "zstd"))]
+			ZSTD => Err("message compressed with Zstandard, which requires the `zstd` feature".into()),
+			_ => Err(format!("unknown compression algorithm {}", header).into()),
+		}
+	}
+}
+impl Codec for Compression {
+	fn encode<T: Serialize>(&self, t: &T) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+		self.compress(&bincode::serialize(t)?)
+	}
+	fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn Error + Send + Sync>> {
+		Ok(bincode::deserialize(&Self::decompress(bytes)?)?)
+	}
+}
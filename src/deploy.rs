@@ -1,18 +1,51 @@
 use either::Either;
 use std::{
-	collections::HashSet, ffi, fs, io::{self, Read, Write}, mem::MaybeUninit, net, path, process
+	collections::HashSet, ffi, fs, io::{self, Read}, mem::MaybeUninit, net, path, process, thread, time::Duration
 };
 
 use constellation_internal::{
-	abort_on_unwind_1, map_bincode_err, msg::{bincode_serialize_into, BridgeRequest}, BufferedStream, DeployInputEvent, DeployOutputEvent, ExitStatus, Format, Formatter, Pid, StyleSupport, TrySpawnError
+	abort_on_unwind, map_bincode_err, msg::{bincode_serialize_into, BridgeRequest}, BufferedStream, DeployInputEvent, DeployOutputEvent, ExitStatus, Format, Formatter, LabelFilter, Pid, StyleSupport, TrySpawnError
 };
 
 /// Unstable
 #[doc(hidden)]
+#[allow(clippy::too_many_arguments)]
 pub fn deploy(
-	bridge_address: net::SocketAddr, path: &path::PathBuf, format: Format,
-	args: Vec<ffi::OsString>, vars: Vec<(ffi::OsString, ffi::OsString)>,
+	bridge_address: net::SocketAddr, path: &path::PathBuf, format: Format, labels: &LabelFilter,
+	args: &[ffi::OsString], vars: &[(ffi::OsString, ffi::OsString)], max_restarts: usize,
+	mut backoff: Duration,
 ) {
+	let mut restarts = 0;
+	loop {
+		// Only the first run gets our stdin, as what it's read can't be replayed
+		let exit_code = run(
+			bridge_address,
+			path,
+			format,
+			labels.clone(),
+			args.to_vec(),
+			vars.to_vec(),
+			restarts == 0,
+		);
+		if exit_code == ExitStatus::Success || restarts == max_restarts {
+			process::exit(exit_code.into());
+		}
+		restarts += 1;
+		eprintln!(
+			"Job exited with {:?}; restarting in {:?} ({} of {})",
+			exit_code, backoff, restarts, max_restarts
+		);
+		thread::sleep(backoff);
+		backoff *= 2;
+	}
+}
+
+/// Run the job once, returning the aggregate exit status of its processes.
+#[allow(clippy::too_many_lines)]
+fn run(
+	bridge_address: net::SocketAddr, path: &path::PathBuf, format: Format, labels: LabelFilter,
+	args: Vec<ffi::OsString>, vars: Vec<(ffi::OsString, ffi::OsString)>, stdin: bool,
+) -> ExitStatus {
 	let stream = net::TcpStream::connect(&bridge_address)
 		.unwrap_or_else(|e| panic!("Could not connect to {:?}: {:?}", bridge_address, e));
 	let (mut stream_read, mut stream_write) =
@@ -37,80 +70,95 @@ pub fn deploy(
 		.map_err(map_bincode_err)
 		.unwrap_or_else(|e| panic!("Couldn't communicate with bridge: {:?}", e));
 	let pid = pid.unwrap_or_else(|e| panic!("Deploy failed due to {}", e)); // TODO get resources from bridge
-	crossbeam::scope(|scope| {
-		let _ = scope.spawn(abort_on_unwind_1(|_scope| {
-			let mut stdin = io::stdin();
-			loop {
-				let mut buf = MaybeUninit::<[u8; 1024]>::uninit();
-				#[cfg(feature = "nightly")]
-				unsafe {
-					stdin.initializer().initialize(&mut *buf.as_mut_ptr());
-				}
-				let n = stdin.read(unsafe { &mut *buf.as_mut_ptr() }).unwrap();
-				bincode::serialize_into(
-					&mut stream_write.write(),
-					&DeployInputEvent::Input(pid, 0, unsafe { &(&*buf.as_ptr())[..n] }.to_owned()),
-				)
-				.unwrap();
-				if n == 0 {
-					break;
+	if stdin {
+		let stream = stream.try_clone().unwrap();
+		let _ = thread::Builder::new()
+			.name(String::from("deploy-stdin"))
+			.spawn(abort_on_unwind(move || {
+				let mut stream_write = BufferedStream::new(&stream);
+				let mut stdin = io::stdin();
+				loop {
+					let mut buf = MaybeUninit::<[u8; 1024]>::uninit();
+					#[cfg(feature = "nightly")]
+					unsafe {
+						stdin.initializer().initialize(&mut *buf.as_mut_ptr());
+					}
+					let n = stdin.read(unsafe { &mut *buf.as_mut_ptr() }).unwrap();
+					if bincode::serialize_into(
+						&mut stream_write.write(),
+						&DeployInputEvent::Input(
+							pid,
+							0,
+							unsafe { &(&*buf.as_ptr())[..n] }.to_owned(),
+						),
+					)
+					.is_err() || n == 0
+					{
+						break; // the job has exited, or our stdin has
+					}
 				}
+			}))
+			.unwrap();
+	} else {
+		bincode::serialize_into(
+			&mut stream_write.write(),
+			&DeployInputEvent::Input(pid, 0, Vec::new()),
+		)
+		.unwrap();
+	}
+	let mut exit_code = ExitStatus::Success;
+	let mut ref_count = 1;
+	let mut pids = HashSet::new();
+	let _ = pids.insert(pid);
+	let (stdout, stderr) = (io::stdout(), io::stderr());
+	let mut formatter = if let Format::Human = format {
+		Either::Left(Formatter::new(
+			pid,
+			if atty::is(atty::Stream::Stderr) {
+				StyleSupport::EightBit
+			} else {
+				StyleSupport::None
+			},
+			labels,
+			stdout.lock(),
+			stderr.lock(),
+		))
+	} else {
+		Either::Right(stdout.lock())
+	};
+	loop {
+		let event: DeployOutputEvent = bincode::deserialize_from(&mut stream_read)
+			.map_err(map_bincode_err)
+			.expect("Bridge died");
+		match formatter {
+			Either::Left(ref mut formatter) => formatter.write(&event),
+			Either::Right(ref mut stdout) => event.write(format, &mut *stdout).unwrap(),
+		}
+		match event {
+			DeployOutputEvent::Spawn(pid, new_pid) => {
+				assert_ne!(pid, new_pid);
+				assert!(pids.contains(&pid));
+				ref_count += 1;
+				let x = pids.insert(new_pid);
+				assert!(x);
 			}
-		}));
-		let mut exit_code = ExitStatus::Success;
-		let mut ref_count = 1;
-		let mut pids = HashSet::new();
-		let _ = pids.insert(pid);
-		let (stdout, stderr) = (io::stdout(), io::stderr());
-		let mut formatter = if let Format::Human = format {
-			Either::Left(Formatter::new(
-				pid,
-				if atty::is(atty::Stream::Stderr) {
-					StyleSupport::EightBit
-				} else {
-					StyleSupport::None
-				},
-				stdout.lock(),
-				stderr.lock(),
-			))
-		} else {
-			Either::Right(stdout.lock())
-		};
-		loop {
-			let event: DeployOutputEvent = bincode::deserialize_from(&mut stream_read)
-				.map_err(map_bincode_err)
-				.expect("Bridge died");
-			match formatter {
-				Either::Left(ref mut formatter) => formatter.write(&event),
-				Either::Right(ref mut stdout) => {
-					serde_json::to_writer(&mut *stdout, &event).unwrap();
-					stdout.write_all(b"\n").unwrap()
-				}
+			DeployOutputEvent::Started(pid, _)
+			| DeployOutputEvent::Output(pid, _, _)
+			| DeployOutputEvent::Paused(pid, _) => {
+				assert!(pids.contains(&pid));
 			}
-			match event {
-				DeployOutputEvent::Spawn(pid, new_pid) => {
-					assert_ne!(pid, new_pid);
-					assert!(pids.contains(&pid));
-					ref_count += 1;
-					let x = pids.insert(new_pid);
-					assert!(x);
-				}
-				DeployOutputEvent::Output(pid, _fd, _output) => {
-					assert!(pids.contains(&pid));
-				}
-				DeployOutputEvent::Exit(pid, exit_code_) => {
-					exit_code += exit_code_;
-					ref_count -= 1;
-					let x = pids.remove(&pid);
-					assert!(x);
-					// printer.eprint(format_args!("   {} {:?}\nremaining: {}\n", ansi_term::Style::new().bold().paint("exited:"), exit_code_, std::slice::SliceConcatExt::join(&*pids.iter().map(|pid|pretty_pid(pid,false).to_string()).collect::<Vec<_>>(), ",")));
-				}
+			DeployOutputEvent::Exit(pid, exit_code_) => {
+				exit_code += exit_code_;
+				ref_count -= 1;
+				let x = pids.remove(&pid);
+				assert!(x);
+				// printer.eprint(format_args!("   {} {:?}\nremaining: {}\n", ansi_term::Style::new().bold().paint("exited:"), exit_code_, std::slice::SliceConcatExt::join(&*pids.iter().map(|pid|pretty_pid(pid,false).to_string()).collect::<Vec<_>>(), ",")));
 			}
-			if ref_count == 0 {
+			DeployOutputEvent::Summary(_) => {
+				assert_eq!(ref_count, 0);
 				break;
 			}
 		}
-		process::exit(exit_code.into());
-	})
-	.unwrap();
+	}
+	exit_code
 }
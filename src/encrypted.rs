@@ -0,0 +1,104 @@
+use chacha20poly1305::{
+	aead::{Aead, NewAead}, XChaCha20Poly1305
+};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{convert::TryInto, fmt, marker::PhantomData};
+
+use super::{ChannelError, Pid, Receiver, Sender};
+
+const NONCE_LEN: usize = 24;
+
+/// A symmetric key shared by two processes, used to encrypt the messages between them. It should be generated randomly and distributed out of band, for example in the closure passed to [`spawn()`](crate::spawn).
+pub type EncryptionKey = [u8; 32];
+
+/// The sending half of a channel whose messages are encrypted end to end, for use with an [`EncryptedReceiver`] at the other end.
+///
+/// Each message is serialized and then encrypted and authenticated with XChaCha20-Poly1305 under a key shared with the remote process, so that it can't be read or altered by anything relaying the connection between the two.
+pub struct EncryptedSender<T: Serialize> {
+	sender: Sender<Vec<u8>>,
+	cipher: XChaCha20Poly1305,
+	marker: PhantomData<fn(T)>,
+}
+impl<T: Serialize> EncryptedSender<T> {
+	/// Create a new `EncryptedSender` with a remote [Pid], encrypting with `key`.
+	pub fn new(remote: Pid, key: &EncryptionKey) -> Self {
+		Self {
+			sender: Sender::new(remote),
+			cipher: XChaCha20Poly1305::new(&(*key).into()),
+			marker: PhantomData,
+		}
+	}
+
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.sender.remote_pid()
+	}
+
+	/// Encrypt and send `t`.
+	///
+	/// This is an async fn.
+	pub async fn send(&self, t: T) {
+		let plaintext = bincode::serialize(&t).unwrap();
+		let mut nonce = [0; NONCE_LEN];
+		rand::thread_rng().fill(&mut nonce);
+		let mut message = nonce.to_vec();
+		message.extend(self.cipher.encrypt(&nonce.into(), &*plaintext).unwrap());
+		self.sender.send(message).await;
+	}
+}
+impl<T: Serialize> fmt::Debug for EncryptedSender<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("EncryptedSender")
+			.field("sender", &self.sender)
+			.finish()
+	}
+}
+
+/// The receiving half of a channel whose messages are encrypted end to end, for use with an [`EncryptedSender`] at the other end.
+pub struct EncryptedReceiver<T: DeserializeOwned> {
+	receiver: Receiver<Vec<u8>>,
+	cipher: XChaCha20Poly1305,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T: DeserializeOwned> EncryptedReceiver<T> {
+	/// Create a new `EncryptedReceiver` with a remote [Pid], decrypting with `key`.
+	pub fn new(remote: Pid, key: &EncryptionKey) -> Self {
+		Self {
+			receiver: Receiver::new(remote),
+			cipher: XChaCha20Poly1305::new(&(*key).into()),
+			marker: PhantomData,
+		}
+	}
+
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.receiver.remote_pid()
+	}
+
+	/// Receive and decrypt a message.
+	///
+	/// A message that fails authentication, because it was encrypted with a different key or altered in transit, gives [`ChannelError::Unknown`].
+	///
+	/// This is an async fn.
+	pub async fn recv(&self) -> Result<T, ChannelError> {
+		let message = self.receiver.recv().await?;
+		if message.len() < NONCE_LEN {
+			return Err(ChannelError::Unknown);
+		}
+		let (nonce, ciphertext) = message.split_at(NONCE_LEN);
+		let nonce: [u8; NONCE_LEN] = nonce.try_into().unwrap();
+		let plaintext = self
+			.cipher
+			.decrypt(&nonce.into(), ciphertext)
+			.map_err(|_| ChannelError::Unknown)?;
+		Ok(bincode::deserialize(&plaintext).unwrap())
+	}
+}
+impl<T: DeserializeOwned> fmt::Debug for EncryptedReceiver<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("EncryptedReceiver")
+			.field("receiver", &self.receiver)
+			.finish()
+	}
+}
@@ -0,0 +1,97 @@
+use std::{
+	convert::TryInto, fmt, io::{self, Read, Write}, net::{SocketAddr, TcpStream}
+};
+
+/// How messages are delimited on a connection to an external service, for use with [`ExternalConnection`].
+pub trait Framing {
+	/// Append `message`, framed, to `buf`.
+	fn encode(&mut self, message: &[u8], buf: &mut Vec<u8>);
+
+	/// Remove and return the first whole message from the front of `buf`, or `None` if more bytes are needed.
+	fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// Framing that prefixes each message with its length as a big-endian `u32`, as used by many existing protocols.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct LengthPrefixed;
+impl Framing for LengthPrefixed {
+	fn encode(&mut self, message: &[u8], buf: &mut Vec<u8>) {
+		let len: u32 = message.len().try_into().unwrap();
+		buf.extend_from_slice(&len.to_be_bytes());
+		buf.extend_from_slice(message);
+	}
+
+	fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+		if buf.len() < 4 {
+			return Ok(None);
+		}
+		let len = u32::from_be_bytes(buf[..4].try_into().unwrap()) as usize;
+		if buf.len() < 4 + len {
+			return Ok(None);
+		}
+		let message = buf[4..4 + len].to_vec();
+		let _ = buf.drain(..4 + len);
+		Ok(Some(message))
+	}
+}
+
+/// A connection to a TCP service that isn't a constellation process, with messages delimited by a user-supplied [`Framing`].
+///
+/// This lets a process be a client of an existing service without setting up its own socket handling. [Sender](crate::Sender)s and [Receiver](crate::Receiver)s speak constellation's own protocol, which begins with a handshake between the two processes, so this instead connects directly to `addr` and sends and receives exactly the bytes produced and consumed by the framing.
+pub struct ExternalConnection<F: Framing> {
+	stream: TcpStream,
+	framing: F,
+	buf: Vec<u8>,
+}
+impl<F: Framing> ExternalConnection<F> {
+	/// Connect to the service listening at `addr`, delimiting messages with `framing`.
+	pub fn connect(addr: SocketAddr, framing: F) -> io::Result<Self> {
+		let stream = TcpStream::connect(addr)?;
+		stream.set_nodelay(true)?;
+		Ok(Self {
+			stream,
+			framing,
+			buf: Vec::new(),
+		})
+	}
+
+	/// Get the address of the service.
+	pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+		self.stream.peer_addr()
+	}
+
+	/// Send `message` as a single frame, blocking until it has been written.
+	pub fn write_message(&mut self, message: &[u8]) -> io::Result<()> {
+		let mut buf = Vec::new();
+		self.framing.encode(message, &mut buf);
+		self.stream.write_all(&buf)
+	}
+
+	/// Receive a single frame, blocking until all of it has arrived. This returns an `UnexpectedEof` error if the service closes the connection partway through a frame, or before sending one.
+	pub fn read_message(&mut self) -> io::Result<Vec<u8>> {
+		loop {
+			if let Some(message) = self.framing.decode(&mut self.buf)? {
+				return Ok(message);
+			}
+			let mut chunk = [0; 4096];
+			let n = self.stream.read(&mut chunk)?;
+			if n == 0 {
+				return Err(io::ErrorKind::UnexpectedEof.into());
+			}
+			self.buf.extend_from_slice(&chunk[..n]);
+		}
+	}
+
+	/// Unwrap the underlying [`TcpStream`]. Any bytes received but not yet returned as a message are discarded.
+	pub fn into_inner(self) -> TcpStream {
+		self.stream
+	}
+}
+impl<F: Framing> fmt::Debug for ExternalConnection<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ExternalConnection")
+			.field("stream", &self.stream)
+			.field("buffered", &self.buf.len())
+			.finish()
+	}
+}
@@ -0,0 +1,113 @@
+use serde::de::DeserializeOwned;
+use std::{sync::Mutex, task::Poll};
+
+use super::{ChannelError, Pid, Receiver};
+
+/// How a [`FanIn`] chooses which of its [Receiver]s to receive from next.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FanInPolicy {
+	/// Keep receiving from the same [Receiver] while it has messages ready, moving on to the next only once it's empty. This is the cheapest, but lets a sender that sends faster than the messages are consumed starve the others.
+	Drain,
+	/// Take turns between the [Receiver]s that have messages ready, receiving up to each one's weight in messages before moving on to the next. This interleaves messages from different senders, so that one noisy sender can't monopolise the consumer.
+	Fair,
+}
+
+/// Receive from several [Receiver]s as one, for a process consuming from many senders.
+///
+/// Each [Receiver] is to a single remote process, so fan-in is a matter of which of them to receive from next, which is set by the [`FanInPolicy`].
+#[derive(Debug)]
+pub struct FanIn<T: DeserializeOwned> {
+	receivers: Vec<(Receiver<T>, usize)>,
+	policy: FanInPolicy,
+	turn: Mutex<Turn>,
+}
+#[derive(Debug)]
+struct Turn {
+	/// The index of the receiver whose turn it is.
+	current: usize,
+	/// How many messages have been received from it this turn.
+	received: usize,
+}
+impl<T: DeserializeOwned> FanIn<T> {
+	/// Create a `FanIn` with no [Receiver]s, that receives according to `policy`.
+	pub fn new(policy: FanInPolicy) -> Self {
+		Self {
+			receivers: Vec::new(),
+			policy,
+			turn: Mutex::new(Turn {
+				current: 0,
+				received: 0,
+			}),
+		}
+	}
+
+	/// Add `receiver`, which with [`FanInPolicy::Fair`] gets a turn of up to `weight` messages at a time.
+	pub fn add(&mut self, receiver: Receiver<T>, weight: usize) {
+		assert_ne!(weight, 0, "FanIn::add() called with a weight of 0");
+		self.receivers.push((receiver, weight));
+	}
+
+	/// Remove and return the [Receiver] from `remote`, for example once it has returned an error.
+	pub fn remove(&mut self, remote: Pid) -> Option<Receiver<T>> {
+		let index = self
+			.receivers
+			.iter()
+			.position(|(receiver, _)| receiver.remote_pid() == remote)?;
+		let mut turn = self.turn.lock().unwrap();
+		if index < turn.current {
+			turn.current -= 1;
+		} else if index == turn.current {
+			turn.received = 0;
+		}
+		Some(self.receivers.remove(index).0)
+	}
+
+	/// The number of [Receiver]s.
+	pub fn len(&self) -> usize {
+		self.receivers.len()
+	}
+
+	/// Whether there are no [Receiver]s.
+	pub fn is_empty(&self) -> bool {
+		self.receivers.is_empty()
+	}
+
+	/// Receive from whichever [Receiver] is next according to the policy, returning the [Pid] it's from, or `None` if there are no [Receiver]s.
+	///
+	/// An error counts as a message, and a [Receiver] that has errored will typically keep doing so, so it should be [removed](FanIn::remove).
+	///
+	/// This is an async fn.
+	pub async fn recv(&self) -> Option<(Pid, Result<T, ChannelError>)>
+	where
+		T: 'static,
+	{
+		futures::future::poll_fn(|cx| {
+			if self.receivers.is_empty() {
+				return Poll::Ready(None);
+			}
+			let mut turn = self.turn.lock().unwrap();
+			if turn.current >= self.receivers.len() {
+				turn.current = 0;
+				turn.received = 0;
+			}
+			if self.policy == FanInPolicy::Fair && turn.received >= self.receivers[turn.current].1 {
+				turn.current = (turn.current + 1) % self.receivers.len();
+				turn.received = 0;
+			}
+			for i in 0..self.receivers.len() {
+				let index = (turn.current + i) % self.receivers.len();
+				let receiver = &self.receivers[index].0;
+				if let Poll::Ready(t) = receiver.poll_recv(cx) {
+					if index != turn.current {
+						turn.current = index;
+						turn.received = 0;
+					}
+					turn.received += 1;
+					return Poll::Ready(Some((receiver.remote_pid(), t)));
+				}
+			}
+			Poll::Pending
+		})
+		.await
+	}
+}
@@ -0,0 +1,113 @@
+//! Fault injection for testing how applications cope with unreliable channels.
+//!
+//! This module requires the `fault_injection` feature. A [`FaultPolicy`] set with [`set_policy()`] applies to messages this process sends to a given remote process with [`Sender::send()`](crate::Sender::send) and [`Sender::send_with()`](crate::Sender::send_with), dropping, delaying or reordering them. [`sever()`] simulates the connection to a remote process being lost.
+//!
+//! Faults are injected in the process that sets them, so to inject faults on both halves of a channel, set them in both processes.
+
+use once_cell::sync::Lazy;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use super::{idle::Delay, Pid};
+
+static FAULTS: Lazy<Mutex<HashMap<Pid, Faults>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The faults to inject into messages sent to a remote process.
+///
+/// The default policy injects no faults.
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct FaultPolicy {
+	/// The probability, between 0 and 1, that each message is silently dropped.
+	pub drop: f64,
+	/// The probability, between 0 and 1, that each message is held back and sent after the next one.
+	pub reorder: f64,
+	/// How long to delay each message by before sending it.
+	pub delay: Option<Duration>,
+	/// The seed for the random choice of which messages to drop and reorder, so that a failing test can be reproduced.
+	pub seed: u64,
+}
+
+struct Faults {
+	policy: FaultPolicy,
+	rng: StdRng,
+	severed: bool,
+}
+impl Faults {
+	fn new(policy: FaultPolicy) -> Self {
+		let rng = StdRng::seed_from_u64(policy.seed);
+		Self {
+			policy,
+			rng,
+			severed: false,
+		}
+	}
+}
+
+/// Inject the faults described by `policy` into messages sent to `remote`, replacing any previous policy.
+pub fn set_policy(remote: Pid, policy: FaultPolicy) {
+	let mut faults = FAULTS.lock().unwrap();
+	let severed = faults.get(&remote).map_or(false, |faults| faults.severed);
+	let mut new = Faults::new(policy);
+	new.severed = severed;
+	let _ = faults.insert(remote, new);
+}
+
+/// Simulate the connection to `remote` being lost.
+///
+/// Subsequent messages sent to `remote` are dropped, and [`Receiver::recv()`](crate::Receiver::recv) on the channel from `remote` returns [`ChannelError::Unknown`](crate::ChannelError::Unknown), as it would were the connection really lost. A `recv()` that's already waiting returns the error once it receives a message. `remote` itself isn't notified.
+pub fn sever(remote: Pid) {
+	FAULTS
+		.lock()
+		.unwrap()
+		.entry(remote)
+		.or_insert_with(|| Faults::new(FaultPolicy::default()))
+		.severed = true;
+}
+
+/// Stop injecting faults into the channels to and from `remote`, including restoring it if it was severed.
+pub fn clear(remote: Pid) {
+	let _ = FAULTS.lock().unwrap().remove(&remote);
+}
+
+/// What to do with a message about to be sent.
+pub(crate) enum Action {
+	Send,
+	Drop,
+	Hold,
+}
+
+/// Decide the fate of a message about to be sent to `remote`, first waiting out any delay.
+pub(crate) async fn on_send(remote: Pid) -> Action {
+	let (action, delay) = {
+		let mut faults = FAULTS.lock().unwrap();
+		let faults = match faults.get_mut(&remote) {
+			Some(faults) => faults,
+			None => return Action::Send,
+		};
+		if faults.severed {
+			return Action::Drop;
+		}
+		let x: f64 = faults.rng.gen();
+		let action = if x < faults.policy.drop {
+			Action::Drop
+		} else if x < faults.policy.drop + faults.policy.reorder {
+			Action::Hold
+		} else {
+			Action::Send
+		};
+		(action, faults.policy.delay)
+	};
+	if let Some(delay) = delay {
+		Delay::new(delay).await;
+	}
+	action
+}
+
+/// Whether the connection to `remote` has been severed with [`sever()`].
+pub(crate) fn is_severed(remote: Pid) -> bool {
+	FAULTS
+		.lock()
+		.unwrap()
+		.get(&remote)
+		.map_or(false, |faults| faults.severed)
+}
@@ -0,0 +1,74 @@
+use std::{
+	convert::TryInto, io::{self, Read, Write}
+};
+
+use super::{Pid, Receiver, Sender};
+
+/// A [Sender] of bytes that preserves message boundaries, for use with a [`FramedReceiver`] at the other end.
+///
+/// Each message is prefixed with its length as a little-endian `u64`, so this can be used to port length-prefixed protocols onto a byte channel.
+#[derive(Debug)]
+pub struct FramedSender(Sender<u8>);
+impl FramedSender {
+	/// Create a new `FramedSender` with a remote [Pid]. This is equivalent to `FramedSender::from(Sender::new(remote))`.
+	pub fn new(remote: Pid) -> Self {
+		Self(Sender::new(remote))
+	}
+
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.0.remote_pid()
+	}
+
+	/// Send `message` as a single frame, blocking until it has been sent.
+	pub fn write_message(&self, message: &[u8]) -> io::Result<()> {
+		let len: u64 = message.len().try_into().unwrap();
+		(&self.0).write_all(&len.to_le_bytes())?;
+		(&self.0).write_all(message)
+	}
+
+	/// Unwrap the underlying [Sender].
+	pub fn into_inner(self) -> Sender<u8> {
+		self.0
+	}
+}
+impl From<Sender<u8>> for FramedSender {
+	fn from(sender: Sender<u8>) -> Self {
+		Self(sender)
+	}
+}
+
+/// A [Receiver] of bytes that preserves message boundaries, for use with a [`FramedSender`] at the other end.
+#[derive(Debug)]
+pub struct FramedReceiver(Receiver<u8>);
+impl FramedReceiver {
+	/// Create a new `FramedReceiver` with a remote [Pid]. This is equivalent to `FramedReceiver::from(Receiver::new(remote))`.
+	pub fn new(remote: Pid) -> Self {
+		Self(Receiver::new(remote))
+	}
+
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.0.remote_pid()
+	}
+
+	/// Receive a single frame, blocking until all of it has arrived. This returns exactly the bytes passed to the corresponding [`write_message()`](FramedSender::write_message).
+	pub fn read_message(&self) -> io::Result<Vec<u8>> {
+		let mut len = [0; 8];
+		(&self.0).read_exact(&mut len)?;
+		let len = u64::from_le_bytes(len);
+		let mut message = vec![0; len.try_into().unwrap()];
+		(&self.0).read_exact(&mut message)?;
+		Ok(message)
+	}
+
+	/// Unwrap the underlying [Receiver].
+	pub fn into_inner(self) -> Receiver<u8> {
+		self.0
+	}
+}
+impl From<Receiver<u8>> for FramedReceiver {
+	fn from(receiver: Receiver<u8>) -> Self {
+		Self(receiver)
+	}
+}
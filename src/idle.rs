@@ -0,0 +1,100 @@
+use futures::future::{self, Either, FutureExt};
+use serde::de::DeserializeOwned;
+use std::{
+	future::Future, pin::Pin, sync::{
+		atomic::{AtomicBool, Ordering}, Arc, Mutex
+	}, task::{Context, Poll, Waker}, thread, time::Duration
+};
+
+use super::{ChannelError, Receiver};
+use constellation_internal::abort_on_unwind;
+
+/// A [Receiver] that waits indefinitely for the first message, but once one has arrived times out if the remote goes quiet.
+///
+/// This suits request/response servers, where a new connection may take any amount of time to send its first request, but a conversation that's underway should be abandoned if it stalls. [`recv()`](IdleReceiver::recv) returns `Err(ChannelError::Timeout)` if more than `idle` passes between messages after the first.
+#[derive(Debug)]
+pub struct IdleReceiver<T: DeserializeOwned> {
+	receiver: Receiver<T>,
+	idle: Duration,
+	active: AtomicBool,
+}
+impl<T: DeserializeOwned> IdleReceiver<T> {
+	/// Wrap `receiver`, timing out after `idle` without a message once the first has been received.
+	pub fn new(receiver: Receiver<T>, idle: Duration) -> Self {
+		Self {
+			receiver,
+			idle,
+			active: AtomicBool::new(false),
+		}
+	}
+
+	/// Whether the first message has been received, and so whether [`recv()`](IdleReceiver::recv) will time out.
+	pub fn is_active(&self) -> bool {
+		self.active.load(Ordering::Relaxed)
+	}
+
+	/// Go back to waiting indefinitely for the next message, for example at the end of a conversation.
+	pub fn reset(&self) {
+		self.active.store(false, Ordering::Relaxed);
+	}
+
+	/// Receive, timing out if this isn't the first message and none arrives within `idle`.
+	///
+	/// This is an async fn.
+	pub async fn recv(&self) -> Result<T, ChannelError>
+	where
+		T: 'static,
+	{
+		let t = if !self.is_active() {
+			self.receiver.recv().await
+		} else {
+			match future::select(self.receiver.recv().boxed_local(), Delay::new(self.idle)).await {
+				Either::Left((t, _)) => t,
+				Either::Right(((), _)) => Err(ChannelError::Timeout),
+			}
+		};
+		if t.is_ok() {
+			self.active.store(true, Ordering::Relaxed);
+		}
+		t
+	}
+
+	/// Unwrap the underlying [Receiver].
+	pub fn into_inner(self) -> Receiver<T> {
+		self.receiver
+	}
+}
+
+/// A future that resolves once `duration` has passed.
+pub(crate) struct Delay(Arc<Mutex<(bool, Option<Waker>)>>);
+impl Delay {
+	pub(crate) fn new(duration: Duration) -> Self {
+		let state = Arc::new(Mutex::new((false, None::<Waker>)));
+		let state1 = state.clone();
+		let _ = thread::Builder::new()
+			.name(String::from("delay"))
+			.spawn(abort_on_unwind(move || {
+				thread::sleep(duration);
+				let mut state = state1.lock().unwrap();
+				state.0 = true;
+				if let Some(waker) = state.1.take() {
+					waker.wake();
+				}
+			}))
+			.unwrap();
+		Self(state)
+	}
+}
+impl Future for Delay {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		let mut state = self.0.lock().unwrap();
+		if state.0 {
+			Poll::Ready(())
+		} else {
+			state.1 = Some(cx.waker().clone());
+			Poll::Pending
+		}
+	}
+}
@@ -35,12 +35,37 @@
 #[cfg(doctest)]
 doc_comment::doctest!("../README.md");
 
+mod ack;
+mod broadcast;
+mod cap;
 mod channel;
+mod child;
+mod codec;
+mod compression;
 mod deploy;
+#[cfg(feature = "encryption")]
+mod encrypted;
+mod external;
+mod fan_in;
+#[cfg(feature = "fault_injection")]
+pub mod fault;
+mod framed;
+mod idle;
+mod metrics;
+mod oneshot;
+mod probe;
+mod pubsub;
+mod replay;
+mod retry;
+mod select;
+mod session;
+mod stream_result;
+pub mod time;
+mod topology;
 
 use either::Either;
 use futures::{
-	future::{FutureExt, TryFutureExt}, sink::{Sink, SinkExt}, stream::{Stream, StreamExt}
+	future::{BoxFuture, FutureExt, TryFutureExt}, sink::{Sink, SinkExt}, stream::{Stream, StreamExt}
 };
 use log::trace;
 use nix::{
@@ -53,27 +78,76 @@ use palaver::{
 	env, file::{execve, fd_path, fexecve}, socket::{socket as palaver_socket, SockFlag}, valgrind
 };
 use pin_utils::pin_mut;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-	any::type_name, borrow, convert::{Infallible, TryInto}, ffi::{CStr, CString, OsString}, fmt, fs, future::Future, io::{self, Read, Write}, iter, marker, mem::MaybeUninit, net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream}, ops, os::unix::{
+	any::{type_name, Any}, borrow, collections::{HashMap, HashSet, VecDeque}, convert::{Infallible, TryInto}, ffi::{CStr, CString, OsString}, fmt, fs, future::Future, hash::Hash, io::{self, Read, Seek, Write}, iter, marker, mem::{ManuallyDrop, MaybeUninit}, net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream}, ops, os::unix::{
 		ffi::OsStringExt, io::{AsRawFd, FromRawFd, IntoRawFd}
-	}, path, pin::Pin, process, sync::{mpsc, Arc, Mutex, RwLock}, task::{Context, Poll}, thread::{self, Thread}
+	}, path, pin::Pin, process, sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering}, mpsc, Arc, Mutex, MutexGuard, RwLock
+	}, task::{Context, Poll, Waker}, thread::{self, Thread}, time::{Duration, Instant}
 };
 
 use constellation_internal::{
-	abort_on_unwind, file_from_reader, forbid_alloc, map_bincode_err, msg::{bincode_serialize_into, FabricRequest, SchedulerArg, SpawnArg, SpawnArgSub}, BufferedStream, Deploy, DeployOutputEvent, Envs, ExitStatus, Fd, Format, Formatter, OwningOrRef, PidInternal, ProcessInputEvent, ProcessOutputEvent, StyleSupport
+	abort_on_unwind, file_from_reader, forbid_alloc, hash_to_hex, is_descendant, map_bincode_err, msg::{bincode_serialize_into, FabricRequest, FileOrVec, SchedulerArg, SchedulerReply, SchedulerRequest, SpawnArg, SpawnArgSub}, BufferedStream, Deploy, DeployOutputEvent, Envs, Fd, Format, Formatter, LabelFilter, OwningOrRef, PidInternal, ProcessInputEvent, ProcessOutputEvent, StartupReport, StyleSupport, Summarizer
 };
 
+#[cfg(feature = "bytes")]
+#[doc(no_inline)]
+pub use bytes::Bytes;
 #[doc(inline)]
-pub use channel::ChannelError;
+pub use ack::{Ack, AckToken, AckedReceiver, AckedSender, Delivery};
+#[doc(inline)]
+pub use broadcast::broadcast;
+#[doc(inline)]
+pub use cap::{spawn_with_cap, ChannelCap};
+#[cfg(feature = "encryption")]
+#[doc(inline)]
+pub use encrypted::{EncryptedReceiver, EncryptedSender, EncryptionKey};
+#[doc(inline)]
+pub use external::{ExternalConnection, Framing, LengthPrefixed};
+#[doc(inline)]
+pub use channel::{BufferLimitPolicy, ChannelError, Direction};
+#[doc(inline)]
+pub use child::{kill, spawn_handle, spawn_many, terminate, Child};
+#[doc(inline)]
+pub use codec::{Bincode, Codec, Json};
+#[cfg(feature = "msgpack")]
+#[doc(inline)]
+pub use codec::MessagePack;
+#[doc(inline)]
+pub use compression::Compression;
 #[doc(inline)]
 pub use constellation_internal::{
-	Cpu, Mem, Pid, Resources, SpawnError, TrySpawnError, RESOURCES_DEFAULT
+	Cpu, ExitStatus, Gpu, Mem, NodeSelector, OnParentExit, PendingReason, Pid, Placement, RLimits, ResourceError, Resources, ResourcesBuilder, Signal, SpawnError, SpawnOptions, TrySpawnError, RESOURCES_DEFAULT
 };
 #[doc(inline)]
 pub use deploy::deploy;
 #[doc(inline)]
+pub use fan_in::{FanIn, FanInPolicy};
+#[doc(inline)]
+pub use framed::{FramedReceiver, FramedSender};
+#[doc(inline)]
+pub use idle::IdleReceiver;
+#[doc(inline)]
+pub use metrics::serve_metrics;
+#[doc(inline)]
+pub use oneshot::{oneshot, oneshot_result, Oneshot, OneshotResult, SpawnedError};
+#[doc(inline)]
+pub use probe::{spawn_with_probes, Probe, Probed};
+#[doc(inline)]
+pub use pubsub::Broker;
+#[doc(inline)]
+pub use retry::{spawn_with_retry, RetryPolicy};
+#[doc(inline)]
+pub use select::Select;
+#[doc(inline)]
 pub use serde_closure::{Fn, FnMut, FnOnce};
+#[doc(inline)]
+pub use session::{AwaitingResponse, Requester, Responder, Responding};
+#[doc(inline)]
+pub use stream_result::{spawn_stream_result, StreamSender};
+#[doc(inline)]
+pub use topology::{Instance, Topology, TopologyError};
 
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
@@ -120,16 +194,87 @@ const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
 const LISTENER_FD: Fd = 3; // from fabric
 const ARG_FD: Fd = 4; // from fabric
 const SCHEDULER_FD: Fd = 4;
+/// How often a process that has lost its connection to the scheduler tries to reconnect, with [`SchedulerLossPolicy::Reconnect`].
+const SCHEDULER_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
 const MONITOR_FD: Fd = 5;
+const CONTROL_FD: Fd = 6;
 
 static PID: OnceCell<Pid> = OnceCell::new();
+static PARENT: OnceCell<Option<Pid>> = OnceCell::new();
 static BRIDGE: OnceCell<Pid> = OnceCell::new();
 static DEPLOYED: OnceCell<bool> = OnceCell::new();
-static RESOURCES: OnceCell<Resources> = OnceCell::new();
-static SCHEDULER: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+static RESOURCES: OnceCell<RwLock<Resources>> = OnceCell::new();
+static SEED: OnceCell<u64> = OnceCell::new();
+static SPAWN_INDEX: AtomicU64 = AtomicU64::new(0);
+static CHILD_INIT: Lazy<RwLock<Option<Vec<u8>>>> = Lazy::new(|| RwLock::new(None));
+#[cfg(feature = "distribute_binaries")]
+static BINARY: OnceCell<constellation_internal::msg::BinaryHash> = OnceCell::new();
+static SCHEDULER: Lazy<Mutex<Scheduler>> = Lazy::new(|| {
+	Mutex::new(Scheduler {
+		addr: None,
+		policy: SchedulerLossPolicy::Exit,
+		lost: false,
+		timeout: None,
+		owed: VecDeque::new(),
+		initialised: false,
+		init_result: None,
+	})
+});
 static REACTOR: Lazy<RwLock<Option<channel::Reactor>>> = Lazy::new(|| RwLock::new(None));
 static HANDLE: Lazy<RwLock<Option<channel::Handle>>> = Lazy::new(|| RwLock::new(None));
 
+struct Scheduler {
+	addr: Option<SocketAddr>, // the scheduler to reconnect to, when deployed
+	policy: SchedulerLossPolicy,
+	lost: bool, // whether the connection has been lost and not yet reestablished
+	timeout: Option<Duration>,
+	owed: VecDeque<OnParentExit>, // replies to requests that timed out and are yet to be received, with their `OnParentExit`
+	initialised: bool,            // whether `try_init()` has been called, successfully or not
+	init_result: Option<Result<(), String>>, // how the first `try_init()` ended: `Ok` once the runtime is fully set up, or the error it failed with
+}
+impl Scheduler {
+	/// Handle the connection to the scheduler having been lost, according to the [`SchedulerLossPolicy`].
+	fn lose(&mut self, err: &io::Error) {
+		match self.policy {
+			SchedulerLossPolicy::Exit => {
+				eprintln!("constellation: lost connection to the scheduler: {}", err);
+				process::exit(1)
+			}
+			SchedulerLossPolicy::Reconnect => {
+				// Replies owed on the lost connection will never arrive
+				self.owed.clear();
+				if !self.lost {
+					self.lost = true;
+					let addr = self.addr.unwrap();
+					let _ = thread::Builder::new()
+						.name(String::from("scheduler-reconnect"))
+						.spawn(abort_on_unwind(move || reconnect_scheduler(addr)))
+						.unwrap();
+				}
+			}
+		}
+	}
+}
+
+/// Reconnect to the scheduler at `addr`, retrying every [`SCHEDULER_RECONNECT_INTERVAL`] until it's reachable.
+fn reconnect_scheduler(addr: SocketAddr) {
+	let stream = loop {
+		thread::sleep(SCHEDULER_RECONNECT_INTERVAL);
+		if let Ok(stream) = TcpStream::connect(addr) {
+			break stream;
+		}
+	};
+	let mut scheduler = SCHEDULER.lock().unwrap();
+	palaver::file::move_fd(
+		stream.into_raw_fd(),
+		SCHEDULER_FD,
+		Some(fcntl::FdFlag::empty()),
+		false,
+	)
+	.unwrap();
+	scheduler.lost = false;
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// The sending half of a channel.
@@ -137,7 +282,9 @@ static HANDLE: Lazy<RwLock<Option<channel::Handle>>> = Lazy::new(|| RwLock::new(
 /// It has an async method [`send(value)`](Sender::send) and a nonblocking method [`try_send()`](Sender::try_send).
 ///
 /// For blocking behaviour use [`.send(value).block()`](FutureExt1::block).
-pub struct Sender<T: Serialize>(Option<channel::Sender<T>>, Pid);
+///
+/// With the `bytes` feature, a `Sender<`[`Bytes`]`>` writes each buffer to the connection as a whole, and the matching `Receiver<Bytes>` takes ownership of the received buffer without copying it again. This is considerably cheaper for large binary payloads than `Sender<Vec<u8>>`, which serializes element by element.
+pub struct Sender<T: Serialize>(Option<channel::Sender<T>>, Pid, Held<T>, Mutex<Latest<T>>);
 impl<T: Serialize> Sender<T> {
 	/// Create a new `Sender<T>` with a remote [Pid]. This method returns instantly.
 	pub fn new(remote: Pid) -> Self {
@@ -151,7 +298,12 @@ impl<T: Serialize> Sender<T> {
 				panic!("You must call init() immediately inside your application's main() function")
 			}),
 		) {
-			Self(Some(sender), remote)
+			Self(
+				Some(sender),
+				remote,
+				Held::default(),
+				Mutex::new(Latest::default()),
+			)
 		} else {
 			panic!(
 				"Sender::<{}>::new() called for pid {} when a Sender to this pid already exists",
@@ -161,16 +313,63 @@ impl<T: Serialize> Sender<T> {
 		}
 	}
 
+	/// Create a new `Sender<T>` with a remote [Pid] that can send up to `messages` messages ahead of the connection before sends block. This method returns instantly.
+	///
+	/// A [`new()`](Sender::new) `Sender` blocks as soon as the connection has no room for the message being sent, which for bursts of small messages means waiting on the remote for each. The messages held here are handed to the connection in order as it drains, which suits bursty workloads at the cost of memory for up to `messages` messages. Messages still held when the connection fails are discarded, as are those buffered in the connection itself.
+	pub fn with_capacity(remote: Pid, messages: usize) -> Self
+	where
+		T: Send + 'static,
+	{
+		assert_ne!(
+			messages, 0,
+			"Sender::with_capacity() called with a capacity of 0"
+		);
+		let sender = Self::new(remote);
+		sender.0.as_ref().unwrap().set_capacity(messages);
+		sender
+	}
+
+	/// Create a new `Sender<T>` with a remote [Pid] whose messages are compressed with `compression`. This method returns instantly.
+	///
+	/// The [Receiver] at the other end must be created with [`Receiver::with_compression()`].
+	pub fn with_compression(remote: Pid, compression: Compression) -> Self {
+		Self::new_with_codec(remote, compression)
+	}
+
+	/// Create a new `Sender<T>` with a remote [Pid] whose messages are encoded with `codec` rather than bincode. This method returns instantly.
+	///
+	/// The [Receiver] at the other end must be created with [`Receiver::new_with_codec()`] and the same codec. Sending panics if `codec` can't encode a message.
+	pub fn new_with_codec<C: Codec>(remote: Pid, codec: C) -> Self {
+		let mut sender = Self::new(remote);
+		sender.0.as_mut().unwrap().set_encode(Box::new(move |t| {
+			codec.encode(t).unwrap_or_else(|err| {
+				panic!(
+					"Sender::<{}>::new_with_codec(): couldn't encode message: {}",
+					type_name::<T>(),
+					err
+				)
+			})
+		}));
+		sender
+	}
+
 	/// Get the pid of the remote end of this Sender.
 	pub fn remote_pid(&self) -> Pid {
 		self.1
 	}
 
+	/// Whether the channel to the remote process is still live, without sending anything.
+	///
+	/// This is `true` while the connection is being made and once it's established, and `false` once the remote process has exited or the connection has failed, after which sends would fail. This suits supervisors pruning senders to processes that have gone away.
+	pub fn is_connected(&self) -> bool {
+		self.0.as_ref().unwrap().is_connected()
+	}
+
 	/// Nonblocking send.
 	///
-	/// If sending would not block, `Some` is returned with a `FnOnce` that accepts a `T` to send.
+	/// If sending would not block, `Some` is returned with a `FnOnce` that accepts a `T` to send, and returns a `Result<(), ChannelError>`. This is `Err`, and `T` is dropped unsent, if the channel has failed, for example with `ChannelError::Exited` if the remote process has exited since.
 	/// If sending would block, `None` is returned.
-	pub fn try_send<'a>(&'a self) -> Option<impl FnOnce(T) + 'a>
+	pub fn try_send<'a>(&'a self) -> Option<impl FnOnce(T) -> Result<(), ChannelError> + 'a>
 	where
 		T: 'static,
 	{
@@ -188,7 +387,7 @@ impl<T: Serialize> Sender<T> {
 	where
 		T: 'static,
 	{
-		self.0.as_ref().unwrap().send(|| t).await;
+		self.send_with(|| t).await;
 	}
 
 	/// Send
@@ -198,15 +397,122 @@ impl<T: Serialize> Sender<T> {
 	where
 		T: 'static,
 	{
+		#[cfg(feature = "fault_injection")]
+		let held = match fault::on_send(self.1).await {
+			fault::Action::Send => self.2.lock().unwrap().take(),
+			fault::Action::Drop => return,
+			fault::Action::Hold => {
+				let mut held = self.2.lock().unwrap();
+				if held.is_none() {
+					*held = Some(f());
+					return;
+				}
+				held.take()
+			}
+		};
 		self.0.as_ref().unwrap().send(f).await;
+		#[cfg(feature = "fault_injection")]
+		{
+			if let Some(held) = held {
+				self.0.as_ref().unwrap().send(|| held).await;
+			}
+		}
+	}
+
+	/// Send `t`, replacing any message previously passed to `send_latest()` with an equal `key` of the same type that is still waiting to be sent.
+	///
+	/// This suits channels of state updates where only the most recent value matters, such as a progress percentage or a config version: if the receiver falls behind, it gets the latest value for each key rather than a backlog of stale ones. Messages go out in the order their keys were first buffered. This completes once `t` has been sent or superseded by a later value for the same key.
+	///
+	/// This is an async fn.
+	pub async fn send_latest<K: Hash + Eq + Send + 'static>(&self, key: K, t: T)
+	where
+		T: 'static,
+	{
+		let seq = {
+			let mut latest = self.3.lock().unwrap();
+			let seq = latest.next;
+			latest.next += 1;
+			if let Some(pending) = latest
+				.pending
+				.iter_mut()
+				.find(|pending| pending.0.downcast_ref::<K>() == Some(&key))
+			{
+				pending.1 = seq;
+				pending.2 = t;
+			} else {
+				latest.pending.push_back((Box::new(key), seq, t));
+			}
+			seq
+		};
+		futures::future::poll_fn(|cx| {
+			let mut latest = self.3.lock().unwrap();
+			loop {
+				if latest.pending.iter().all(|pending| pending.1 != seq) {
+					return Poll::Ready(());
+				}
+				let context = REACTOR.read().unwrap();
+				if let Some(send) = self
+					.0
+					.as_ref()
+					.unwrap()
+					.try_send(BorrowMap::new(context, borrow_unwrap_option), Some(cx))
+				{
+					let sent = send(latest.pending.pop_front().unwrap().2);
+					self.0.as_ref().unwrap().expect_sent(&sent);
+				} else {
+					return Poll::Pending;
+				}
+			}
+		})
+		.await
+	}
+
+	/// Poll to send the message in `t`, registering `cx` to be woken if sending would block, for combinators over several senders.
+	fn poll_send(&self, cx: &mut Context, t: &mut Option<T>) -> Poll<Result<(), ChannelError>>
+	where
+		T: 'static,
+	{
+		let context = REACTOR.read().unwrap();
+		match self
+			.0
+			.as_ref()
+			.unwrap()
+			.try_send(BorrowMap::new(context, borrow_unwrap_option), Some(cx))
+		{
+			Some(send) => Poll::Ready(send(t.take().unwrap())),
+			None => Poll::Pending,
+		}
+	}
+}
+
+/// Messages passed to [`Sender::send_latest()`] that are waiting to be sent, as `(key, seq, message)`. Keys of different types are never equal.
+struct Latest<T> {
+	next: u64,
+	pending: VecDeque<(Box<dyn Any + Send>, u64, T)>,
+}
+impl<T> Default for Latest<T> {
+	fn default() -> Self {
+		Self {
+			next: 0,
+			pending: VecDeque::new(),
+		}
 	}
 }
 
+/// A message held back by [`fault::FaultPolicy::reorder`], to be sent after the next one.
+#[cfg(feature = "fault_injection")]
+type Held<T> = Mutex<Option<T>>;
+#[cfg(not(feature = "fault_injection"))]
+type Held<T> = marker::PhantomData<fn(T)>;
+
 #[doc(hidden)] // noise
 impl<T: Serialize> Drop for Sender<T> {
 	fn drop(&mut self) {
-		let context = REACTOR.read().unwrap();
-		self.0.take().unwrap().drop(context.as_ref().unwrap())
+		// Already dropped if closed as a Sink
+		if let Some(sender) = self.0.take() {
+			let context = REACTOR.read().unwrap();
+			sender.drop(context.as_ref().unwrap())
+		}
 	}
 }
 impl<'a> Write for &'a Sender<u8> {
@@ -220,10 +526,9 @@ impl<'a> Write for &'a Sender<u8> {
 			return Ok(1);
 		}
 		for (i, buf) in (1..buf.len()).zip(buf[1..].iter().cloned()) {
-			if let Some(send) = self.try_send() {
-				send(buf);
-			} else {
-				return Ok(i);
+			match self.try_send().map(|send| send(buf)) {
+				Some(Ok(())) => (),
+				Some(Err(_)) | None => return Ok(i),
 			}
 		}
 		Ok(buf.len())
@@ -258,11 +563,14 @@ impl Write for Sender<u8> {
 		(&*self).flush()
 	}
 }
+// Messages held for sending are never pinned
+impl<T: Serialize> Unpin for Sender<T> {}
 impl<T: Serialize> fmt::Debug for Sender<T> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		self.0.fmt(f)
 	}
 }
+/// Sending `None` signals the end to a [`Receiver<Option<T>>`](Receiver), whose [Stream] then ends.
 impl<T: 'static + Serialize> Sink<T> for Sender<Option<T>> {
 	type Error = Infallible;
 
@@ -272,14 +580,15 @@ impl<T: 'static + Serialize> Sink<T> for Sender<Option<T>> {
 			.as_ref()
 			.unwrap()
 			.futures_poll_ready(cx, context.as_ref().unwrap())
+			.map(Ok)
 	}
 
 	fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
 		let context = REACTOR.read().unwrap();
-		self.0
-			.as_ref()
-			.unwrap()
-			.futures_start_send(item, context.as_ref().unwrap())
+		let sender = self.0.as_ref().unwrap();
+		let sent = sender.futures_start_send(Some(item), context.as_ref().unwrap());
+		sender.expect_sent(&sent);
+		Ok(())
 	}
 
 	fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
@@ -295,6 +604,43 @@ impl<T: 'static + Serialize> Sink<T> for Sender<Option<T>> {
 	}
 }
 
+/// Closing waits for the messages sent to be handed to the connection, then drops this end of the channel, so the [Receiver] sees it end. The `Sender` can't be used after it's closed.
+impl<T: 'static + Serialize> Sink<T> for Sender<T> {
+	type Error = ChannelError;
+
+	fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		let context = REACTOR.read().unwrap();
+		self.0
+			.as_ref()
+			.unwrap()
+			.futures_poll_ready(cx, context.as_ref().unwrap())
+			.map(Ok)
+	}
+
+	fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+		let context = REACTOR.read().unwrap();
+		self.0
+			.as_ref()
+			.unwrap()
+			.futures_start_send(item, context.as_ref().unwrap())
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		self.0.as_ref().unwrap().futures_poll_flush(cx).map(Ok)
+	}
+
+	fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+		if let Some(sender) = &self.0 {
+			if sender.futures_poll_flush(cx).is_pending() {
+				return Poll::Pending;
+			}
+			let context = REACTOR.read().unwrap();
+			self.0.take().unwrap().drop(context.as_ref().unwrap());
+		}
+		Poll::Ready(Ok(()))
+	}
+}
+
 impl<'a, T: Serialize + 'static, F: FnOnce() -> T> Future for channel::Send<'a, T, F> {
 	type Output = ();
 
@@ -309,7 +655,12 @@ impl<'a, T: Serialize + 'static, F: FnOnce() -> T> Future for channel::Send<'a,
 /// It has an async method [`recv()`](Receiver::recv) and a nonblocking method [`try_recv()`](Receiver::try_recv).
 ///
 /// For blocking behaviour use [`.recv().block()`](FutureExt1::block).
-pub struct Receiver<T: DeserializeOwned>(Option<channel::Receiver<T>>, Pid);
+pub struct Receiver<T: DeserializeOwned>(
+	Option<channel::Receiver<T>>,
+	Pid,
+	Mutex<VecDeque<Vec<u8>>>,
+	Mutex<Option<Result<T, ChannelError>>>, // the message returned by `peek()`, or the error hit by `recv_batch()`, yet to be received
+);
 impl<T: DeserializeOwned> Receiver<T> {
 	/// Create a new `Receiver<T>` with a remote [Pid]. This method returns instantly.
 	pub fn new(remote: Pid) -> Self {
@@ -323,7 +674,13 @@ impl<T: DeserializeOwned> Receiver<T> {
 				panic!("You must call init() immediately inside your application's main() function")
 			}),
 		) {
-			Self(Some(receiver), remote)
+			replay::register(remote);
+			Self(
+				Some(receiver),
+				remote,
+				Mutex::new(VecDeque::new()),
+				Mutex::new(None),
+			)
 		} else {
 			panic!(
 				"Receiver::<{}>::new() called for pid {} when a Receiver to this pid already exists",
@@ -333,11 +690,103 @@ impl<T: DeserializeOwned> Receiver<T> {
 		}
 	}
 
+	/// Create a new `Receiver<T>` with a remote [Pid] that receives up to `messages` messages ahead of the application. This method returns instantly.
+	///
+	/// A [`new()`](Receiver::new) `Receiver` receives one message ahead, and stops reading from the connection until it has been taken. Receiving more ahead keeps the remote's sends flowing through bursts, at the cost of memory for up to `messages` messages. Messages received ahead are discarded if the `Receiver` is dropped.
+	pub fn with_capacity(remote: Pid, messages: usize) -> Self
+	where
+		T: Send + 'static,
+	{
+		assert_ne!(
+			messages, 0,
+			"Receiver::with_capacity() called with a capacity of 0"
+		);
+		let receiver = Self::new(remote);
+		let context = REACTOR.read().unwrap();
+		receiver
+			.0
+			.as_ref()
+			.unwrap()
+			.set_capacity(messages, context.as_ref().unwrap());
+		receiver
+	}
+
+	/// Create a new `Receiver<T>` with a remote [Pid] whose messages are decompressed, for a [Sender] at the other end created with [`Sender::with_compression()`]. This method returns instantly.
+	///
+	/// Each message is decompressed according to the algorithm it was compressed with. Receiving panics if that algorithm's feature isn't enabled.
+	pub fn with_compression(remote: Pid) -> Self {
+		Self::new_with_codec(remote, Compression::None)
+	}
+
+	/// Create a new `Receiver<T>` with a remote [Pid] whose messages are decoded with `codec` rather than bincode. This method returns instantly.
+	///
+	/// The [Sender] at the other end must be created with [`Sender::new_with_codec()`] and the same codec. Receiving panics if a message can't be decoded as a `T`.
+	pub fn new_with_codec<C: Codec>(remote: Pid, codec: C) -> Self {
+		let mut receiver = Self::new(remote);
+		receiver
+			.0
+			.as_mut()
+			.unwrap()
+			.set_decode(Box::new(move |bytes| {
+				codec.decode(bytes).unwrap_or_else(|err| {
+					panic!(
+						"Receiver::<{}>::new_with_codec(): couldn't decode message from {}: {}",
+						type_name::<T>(),
+						remote,
+						err
+					)
+				})
+			}));
+		receiver
+	}
+
+	/// Create a new `Receiver<T>` with a remote [Pid] that first yields the messages of a [`snapshot()`](Receiver::snapshot), for example one taken by a process this one is taking over from.
+	pub fn from_snapshot(remote: Pid, snapshot: Vec<T>) -> Self
+	where
+		T: Serialize,
+	{
+		let receiver = Self::new(remote);
+		*receiver.2.lock().unwrap() = snapshot
+			.into_iter()
+			.map(|t| bincode::serialize(&t).unwrap())
+			.collect();
+		receiver
+	}
+
 	/// Get the pid of the remote end of this Receiver.
 	pub fn remote_pid(&self) -> Pid {
 		self.1
 	}
 
+	/// Kill the channel if a message being received from the remote takes more than `max` bytes, overriding [`set_max_message_size()`] for this channel.
+	///
+	/// Receiving then returns [`ChannelError::Unknown`]. This suits a channel expected to carry larger messages than the rest, or one from a less trusted remote.
+	pub fn set_max_message_size(&self, max: usize) {
+		self.0
+			.as_ref()
+			.unwrap()
+			.set_max_message_size(max, REACTOR.read().unwrap().as_ref().unwrap());
+	}
+
+	/// Whether the channel from the remote process is still live, without receiving anything.
+	///
+	/// This is `true` while the connection is being made and once it's established, and `false` once the connection has failed, or the remote process has exited and every message it sent has been received.
+	pub fn is_connected(&self) -> bool {
+		self.3.lock().unwrap().as_ref().map_or(false, Result::is_ok)
+			|| !self.2.lock().unwrap().is_empty()
+			|| self.0.as_ref().unwrap().is_connected()
+	}
+
+	/// Take the messages that have been received but not yet consumed, so that they can be restored with [`from_snapshot()`](Receiver::from_snapshot).
+	///
+	/// This drains every message that can be received without blocking. Messages still in flight from the remote process aren't included.
+	pub fn snapshot(&self) -> Vec<T>
+	where
+		T: 'static,
+	{
+		iter::from_fn(|| self.try_recv().and_then(|recv| recv().ok())).collect()
+	}
+
 	/// Nonblocking recv.
 	///
 	/// If receiving would not block, `Some` is returned with a `FnOnce` that returns a `Result<T, ChannelError>`.
@@ -346,11 +795,73 @@ impl<T: DeserializeOwned> Receiver<T> {
 	where
 		T: 'static,
 	{
-		let context = REACTOR.read().unwrap();
-		self.0
-			.as_ref()
-			.unwrap()
-			.try_recv(BorrowMap::new(context, borrow_unwrap_option), None)
+		let peeked = self.3.lock().unwrap().take();
+		let recv = if peeked.is_none() {
+			Some(self.try_recv_unpeeked()?)
+		} else {
+			None
+		};
+		Some(move || match peeked {
+			Some(peeked) => peeked,
+			None => recv.unwrap()(),
+		})
+	}
+
+	/// [`try_recv()`](Receiver::try_recv), ignoring any message held by [`peek()`](Receiver::peek).
+	fn try_recv_unpeeked<'a>(&'a self) -> Option<impl FnOnce() -> Result<T, ChannelError> + 'a>
+	where
+		T: 'static,
+	{
+		if !replay::is_turn(self.1, None) {
+			return None;
+		}
+		let restored = self.2.lock().unwrap().pop_front();
+		let recv = if restored.is_none() {
+			let context = REACTOR.read().unwrap();
+			Some(
+				self.0
+					.as_ref()
+					.unwrap()
+					.try_recv(BorrowMap::new(context, borrow_unwrap_option), None)?,
+			)
+		} else {
+			None
+		};
+		Some(move || {
+			let t = match restored {
+				Some(restored) => Ok(bincode::deserialize(&restored).unwrap()),
+				None => recv.unwrap()(),
+			};
+			if t.is_ok() {
+				replay::received(self.1);
+			}
+			t
+		})
+	}
+
+	/// Nonblocking look at the next message, without receiving it.
+	///
+	/// If receiving would not block, `Some` is returned with a `FnOnce` that returns a `Result<Peeked<T>, ChannelError>`, and the message is left in place to be returned by the next receive. If receiving would block, `None` is returned.
+	///
+	/// Unlike [`try_recv()`](Receiver::try_recv), this receives and deserializes the message straight away so as to hold onto it, so an error may be returned here rather than by a later receive.
+	///
+	/// The message is borrowed through a [`Peeked`] rather than a `&T`, as it's held behind a lock that receiving takes; receiving from this `Receiver` waits until the [`Peeked`] is dropped.
+	pub fn peek<'a>(&'a self) -> Option<impl FnOnce() -> Result<Peeked<'a, T>, ChannelError> + 'a>
+	where
+		T: 'static,
+	{
+		// Held throughout, so that a concurrent peek can't replace the message
+		let mut slot = self.3.lock().unwrap();
+		let peeked = match slot.take() {
+			Some(peeked) => peeked,
+			None => self.try_recv_unpeeked()?(),
+		};
+		Some(move || {
+			peeked.map(|t| {
+				*slot = Some(Ok(t));
+				Peeked(slot)
+			})
+		})
 	}
 
 	/// Receive.
@@ -360,57 +871,254 @@ impl<T: DeserializeOwned> Receiver<T> {
 	where
 		T: 'static,
 	{
+		if let Some(peeked) = self.3.lock().unwrap().take() {
+			return peeked;
+		}
+		#[cfg(feature = "fault_injection")]
+		{
+			if fault::is_severed(self.1) {
+				return Err(ChannelError::Unknown);
+			}
+		}
+		replay::turn(self.1).await;
+		if let Some(restored) = self.2.lock().unwrap().pop_front() {
+			replay::received(self.1);
+			return Ok(bincode::deserialize(&restored).unwrap());
+		}
 		let mut x = None;
 		self.0.as_ref().unwrap().recv(|y| x = Some(y)).await;
-		x.unwrap()
-	}
-}
-#[doc(hidden)] // noise
-impl<T: DeserializeOwned> Drop for Receiver<T> {
-	fn drop(&mut self) {
-		let context = REACTOR.read().unwrap();
-		self.0.take().unwrap().drop(context.as_ref().unwrap())
-	}
-}
-impl<'a> Read for &'a Receiver<u8> {
-	#[inline(always)]
-	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-		if buf.is_empty() {
-			return Ok(0);
+		#[cfg(feature = "fault_injection")]
+		{
+			if fault::is_severed(self.1) {
+				return Err(ChannelError::Unknown);
+			}
 		}
-		buf[0] = self.recv().block().map_err(|e| match e {
-			ChannelError::Exited => io::ErrorKind::UnexpectedEof,
-			ChannelError::Unknown => io::ErrorKind::ConnectionReset,
-			ChannelError::__Nonexhaustive => unreachable!(),
-		})?;
-		if buf.len() == 1 {
-			return Ok(1);
+		let t = x.unwrap();
+		if t.is_ok() {
+			replay::received(self.1);
 		}
-		for (i, buf) in (1..buf.len()).zip(buf[1..].iter_mut()) {
-			if let Some(recv) = self.try_recv() {
-				if let Ok(t) = recv() {
-					*buf = t;
-				} else {
-					return Ok(i);
+		t
+	}
+
+	/// Receive the messages that can be received without blocking, up to `max`, or if there are none, wait for one and then take any others that have arrived with it.
+	///
+	/// This suits consumers that batch adaptively: under load they process large batches, and when idle they're woken by the first message rather than waiting for a batch to fill. If an error occurs after some messages have been received, those are returned, and the error is held onto and returned by the next receive.
+	///
+	/// This is an async fn.
+	pub async fn recv_batch(&self, max: usize) -> Result<Vec<T>, ChannelError>
+	where
+		T: 'static,
+	{
+		assert_ne!(max, 0, "recv_batch() called with a max of 0");
+		let mut batch = vec![self.recv().await?];
+		while batch.len() < max {
+			match self.try_recv().map(|recv| recv()) {
+				Some(Ok(t)) => batch.push(t),
+				Some(Err(err)) => {
+					*self.3.lock().unwrap() = Some(Err(err));
+					break;
 				}
-			} else {
-				return Ok(i);
+				None => break,
 			}
 		}
-		Ok(buf.len())
+		Ok(batch)
 	}
 
-	#[inline(always)]
-	fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-		for byte in buf {
-			*byte = self.recv().block().map_err(|e| match e {
-				ChannelError::Exited => io::ErrorKind::UnexpectedEof,
-				ChannelError::Unknown => io::ErrorKind::ConnectionReset,
-				ChannelError::__Nonexhaustive => unreachable!(),
-			})?;
-		}
-		Ok(())
-	}
+	/// Blocking [`recv_batch()`](Receiver::recv_batch).
+	pub fn recv_batch_blocking(&self, max: usize) -> Result<Vec<T>, ChannelError>
+	where
+		T: 'static,
+	{
+		self.recv_batch(max).block()
+	}
+
+	/// Poll for a message, registering `cx` to be woken if there isn't one, for combinators over several receivers.
+	fn poll_recv(&self, cx: &mut Context) -> Poll<Result<T, ChannelError>>
+	where
+		T: 'static,
+	{
+		if let Some(peeked) = self.3.lock().unwrap().take() {
+			return Poll::Ready(peeked);
+		}
+		#[cfg(feature = "fault_injection")]
+		{
+			if fault::is_severed(self.1) {
+				return Poll::Ready(Err(ChannelError::Unknown));
+			}
+		}
+		if !replay::is_turn(self.1, Some(cx)) {
+			return Poll::Pending;
+		}
+		if let Some(restored) = self.2.lock().unwrap().pop_front() {
+			replay::received(self.1);
+			return Poll::Ready(Ok(bincode::deserialize(&restored).unwrap()));
+		}
+		let context = REACTOR.read().unwrap();
+		let recv = self
+			.0
+			.as_ref()
+			.unwrap()
+			.try_recv(context.as_ref().unwrap(), Some(cx));
+		let t = match recv {
+			Some(recv) => recv(),
+			None => return Poll::Pending,
+		};
+		if t.is_ok() {
+			replay::received(self.1);
+		}
+		Poll::Ready(t)
+	}
+
+	/// Receive messages as a [Stream], which ends once the remote process has exited and every message it sent has been received.
+	///
+	/// Unlike the [Stream] implemented by `Receiver<Option<T>>`, this needs no `None` sent to mark the end, so suits `receiver.into_stream().for_each(...)` over any `Receiver<T>`. Errors other than [`ChannelError::Exited`] are yielded, after which the stream ends.
+	pub fn into_stream(self) -> impl Stream<Item = Result<T, ChannelError>>
+	where
+		T: 'static,
+	{
+		let mut done = false;
+		futures::stream::poll_fn(move |cx| {
+			if done {
+				return Poll::Ready(None);
+			}
+			self.poll_recv(cx).map(|t| {
+				done = t.is_err();
+				match t {
+					Err(ChannelError::Exited) => None,
+					t => Some(t),
+				}
+			})
+		})
+	}
+
+	/// Batch received messages into chunks of up to `n`, as a [Stream] built on [`into_stream()`](Receiver::into_stream).
+	///
+	/// Each chunk waits indefinitely for its first message, and is then yielded once it has `n` messages or `max_delay` has passed since that first message, whichever is sooner. Messages accumulated when the remote process exits are yielded as a final, partial chunk, after which the stream ends. On any other error the accumulated messages are likewise yielded and the stream ends; use [`into_stream()`](Receiver::into_stream) to observe the error itself.
+	pub fn chunks(self, n: usize, max_delay: Duration) -> impl Stream<Item = Vec<T>>
+	where
+		T: 'static,
+	{
+		assert_ne!(n, 0, "chunks() called with a chunk size of 0");
+		futures::stream::unfold(
+			Some(Box::pin(self.into_stream())),
+			move |stream| async move {
+				let mut stream = stream?;
+				let mut chunk = Vec::with_capacity(n);
+				match stream.next().await {
+					Some(Ok(t)) => chunk.push(t),
+					Some(Err(_)) | None => return None,
+				}
+				let mut delay = idle::Delay::new(max_delay);
+				while chunk.len() < n {
+					match futures::future::select(stream.next(), &mut delay).await {
+						futures::future::Either::Left((Some(Ok(t)), _)) => chunk.push(t),
+						futures::future::Either::Left((Some(Err(_)), _)) => {
+							return Some((chunk, None))
+						}
+						futures::future::Either::Left((None, _)) => return Some((chunk, None)),
+						futures::future::Either::Right(((), _)) => break,
+					}
+				}
+				Some((chunk, Some(stream)))
+			},
+		)
+	}
+
+	/// Receive only the messages for which `f` returns `Some`, as a stream of what it returns.
+	///
+	/// Messages for which `f` returns `None` are dropped. On error the error is yielded and the stream ends.
+	pub fn filter_map_recv<U, F: FnMut(T) -> Option<U>>(
+		self, f: F,
+	) -> impl Stream<Item = Result<U, ChannelError>>
+	where
+		T: 'static,
+	{
+		futures::stream::unfold(
+			(Some(self), f),
+			|(receiver, mut f): (Option<Self>, F)| async move {
+				let receiver = receiver?;
+				loop {
+					match receiver.recv().await {
+						Ok(t) => {
+							if let Some(u) = f(t) {
+								return Some((Ok(u), (Some(receiver), f)));
+							}
+						}
+						Err(err) => return Some((Err(err), (None, f))),
+					}
+				}
+			},
+		)
+	}
+}
+/// A message looked at with [`Receiver::peek()`], which is left in place to be returned by the next receive.
+///
+/// Receiving from the [Receiver] waits until this is dropped, so it shouldn't be held by the thread receiving.
+pub struct Peeked<'a, T>(MutexGuard<'a, Option<Result<T, ChannelError>>>);
+impl<T> ops::Deref for Peeked<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		match &*self.0 {
+			Some(Ok(t)) => t,
+			_ => unreachable!(),
+		}
+	}
+}
+impl<T: fmt::Debug> fmt::Debug for Peeked<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Peeked").field(&**self).finish()
+	}
+}
+
+#[doc(hidden)] // noise
+impl<T: DeserializeOwned> Drop for Receiver<T> {
+	fn drop(&mut self) {
+		let context = REACTOR.read().unwrap();
+		self.0.take().unwrap().drop(context.as_ref().unwrap())
+	}
+}
+impl<'a> Read for &'a Receiver<u8> {
+	#[inline(always)]
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		buf[0] = self.recv().block().map_err(|e| match e {
+			ChannelError::Exited => io::ErrorKind::UnexpectedEof,
+			ChannelError::Unknown => io::ErrorKind::ConnectionReset,
+			ChannelError::Timeout => io::ErrorKind::TimedOut,
+			ChannelError::__Nonexhaustive => unreachable!(),
+		})?;
+		if buf.len() == 1 {
+			return Ok(1);
+		}
+		for (i, buf) in (1..buf.len()).zip(buf[1..].iter_mut()) {
+			if let Some(recv) = self.try_recv() {
+				if let Ok(t) = recv() {
+					*buf = t;
+				} else {
+					return Ok(i);
+				}
+			} else {
+				return Ok(i);
+			}
+		}
+		Ok(buf.len())
+	}
+
+	#[inline(always)]
+	fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+		for byte in buf {
+			*byte = self.recv().block().map_err(|e| match e {
+				ChannelError::Exited => io::ErrorKind::UnexpectedEof,
+				ChannelError::Unknown => io::ErrorKind::ConnectionReset,
+				ChannelError::Timeout => io::ErrorKind::TimedOut,
+				ChannelError::__Nonexhaustive => unreachable!(),
+			})?;
+		}
+		Ok(())
+	}
 
 	#[cfg(feature = "nightly")]
 	#[inline(always)]
@@ -435,6 +1143,8 @@ impl Read for Receiver<u8> {
 		(&&*self).initializer()
 	}
 }
+// The message held by `peek()` is never pinned
+impl<T: DeserializeOwned> Unpin for Receiver<T> {}
 impl<T: DeserializeOwned> fmt::Debug for Receiver<T> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		self.0.fmt(f)
@@ -444,11 +1154,30 @@ impl<T: 'static + DeserializeOwned> Stream for Receiver<Option<T>> {
 	type Item = Result<T, ChannelError>;
 
 	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		if let Some(peeked) = self.3.lock().unwrap().take() {
+			return Poll::Ready(peeked.transpose());
+		}
+		if !replay::is_turn(self.1, Some(cx)) {
+			return Poll::Pending;
+		}
+		if let Some(restored) = self.2.lock().unwrap().pop_front() {
+			replay::received(self.1);
+			return Poll::Ready(
+				bincode::deserialize::<Option<T>>(&restored)
+					.unwrap()
+					.map(Ok),
+			);
+		}
 		let context = REACTOR.read().unwrap();
-		self.0
+		let t = self
+			.0
 			.as_ref()
 			.unwrap()
-			.futures_poll_next(cx, context.as_ref().unwrap())
+			.futures_poll_next(cx, context.as_ref().unwrap());
+		if let Poll::Ready(Some(Ok(_))) = t {
+			replay::received(self.1);
+		}
+		t
 	}
 }
 
@@ -463,8 +1192,217 @@ impl<'a, T: DeserializeOwned + 'static, F: FnOnce(Result<T, ChannelError>)> Futu
 	}
 }
 
+/// Create a [Sender] and [Receiver] pair with both ends in the current process.
+///
+/// Values are moved through an in-memory queue rather than serialized and sent over a socket, so this is useful for benchmarking and testing pipeline logic without network overhead. The [`remote_pid()`](Sender::remote_pid) of both halves is this process's own [`pid()`](pid).
+pub fn channel_local<T: Serialize + DeserializeOwned + Send + 'static>() -> (Sender<T>, Receiver<T>)
+{
+	let pid = pid();
+	let local = channel::Local::new();
+	(
+		Sender(
+			Some(channel::Sender::with_local(local.clone(), pid.addr())),
+			pid,
+			Held::default(),
+			Mutex::new(Latest::default()),
+		),
+		Receiver(
+			Some(channel::Receiver::with_local(local, pid.addr())),
+			pid,
+			Mutex::new(VecDeque::new()),
+			Mutex::new(None),
+		),
+	)
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A token for cooperatively cancelling the current process and its descendants.
+///
+/// Each process has one, returned by [`cancellation_token()`](cancellation_token). Calling [`cancel()`](CancellationToken::cancel) on it cancels the token of the current process as well as those of all processes it spawned, their children, and so on, including any spawned after the call. Processes can check [`is_cancelled()`](CancellationToken::is_cancelled) or await [`cancelled()`](CancellationToken::cancelled) to stop cleanly.
+#[derive(Copy, Clone, Debug)]
+pub struct CancellationToken(());
+impl CancellationToken {
+	/// Cancel the current process and all of its descendants.
+	pub fn cancel(&self) {
+		if !CANCELLATION.cancel() {
+			monitor_event(&ProcessOutputEvent::Cancel);
+		}
+	}
+
+	/// Whether the current process has been cancelled, either by itself or an ancestor.
+	pub fn is_cancelled(&self) -> bool {
+		CANCELLATION.cancelled.load(Ordering::SeqCst)
+	}
+
+	/// Wait until the current process has been cancelled.
+	///
+	/// This is an async fn.
+	pub async fn cancelled(&self) {
+		futures::future::poll_fn(|cx| {
+			let mut wakers = CANCELLATION.wakers.lock().unwrap();
+			if self.is_cancelled() {
+				Poll::Ready(())
+			} else {
+				wakers.push(cx.waker().clone());
+				Poll::Pending
+			}
+		})
+		.await
+	}
+}
+
+/// Get the [`CancellationToken`] of the current process.
+pub fn cancellation_token() -> CancellationToken {
+	let _ = pid();
+	let _ = Lazy::force(&CANCELLATION);
+	CancellationToken(())
+}
+
+struct Cancellation {
+	cancelled: AtomicBool,
+	wakers: Mutex<Vec<Waker>>,
+}
+impl Cancellation {
+	/// Returns whether we were already cancelled.
+	fn cancel(&self) -> bool {
+		let wakers = &mut *self.wakers.lock().unwrap();
+		let cancelled = self.cancelled.swap(true, Ordering::SeqCst);
+		for waker in wakers.drain(..) {
+			waker.wake();
+		}
+		cancelled
+	}
+}
+static CANCELLATION: Lazy<Cancellation> = Lazy::new(|| {
+	let () = *Lazy::force(&CONTROL);
+	Cancellation {
+		cancelled: AtomicBool::new(false),
+		wakers: Mutex::new(Vec::new()),
+	}
+});
+
+/// Receive the events our monitor process writes to `CONTROL_FD`: an ancestor cancelling, the exits of processes being watched, whether processes being reached have started, and the output of processes whose output is forwarded to us.
+static CONTROL: Lazy<()> = Lazy::new(|| {
+	let _ = thread::Builder::new()
+		.name(String::from("control"))
+		.spawn(abort_on_unwind(|| {
+			let file = unsafe { fs::File::from_raw_fd(CONTROL_FD) };
+			while let Ok(event) = bincode::deserialize_from(&mut &file) {
+				match event {
+					ProcessInputEvent::Cancel => {
+						let _ = CANCELLATION.cancel();
+					}
+					ProcessInputEvent::Exited(pid, exit_code) => child::exited(pid, exit_code),
+					ProcessInputEvent::Killed(pid, running) => child::killed(pid, running),
+					ProcessInputEvent::Reachable(pid, started) => reached(pid, started),
+					ProcessInputEvent::Output(pid, fd, output) => child::output(pid, fd, output),
+					_ => unreachable!(),
+				}
+			}
+			let _ = file.into_raw_fd();
+		}))
+		.unwrap();
+});
+
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Deliver a Unix signal to a process.
+///
+/// The signal is routed via the bridge to the monitor of the process identified by `pid`, which delivers it with `kill()`. This enables processes with their own signal handlers, for example reloading on `SIGHUP`, to be controlled in the usual way. Signals to processes that have exited are ignored.
+pub fn send_signal(pid: Pid, signal: Signal) {
+	let _ = self::pid();
+	monitor_event(&ProcessOutputEvent::Signal(pid, signal));
+}
+
+/// Pause a process, by delivering it `SIGSTOP`.
+///
+/// Like [`send_signal()`], this is routed via the bridge to the process's monitor. The pause is reported to `deploy` as a distinct event, so it shows up in its output. Pausing a process that has exited is ignored.
+pub fn pause(pid: Pid) {
+	let _ = self::pid();
+	monitor_event(&ProcessOutputEvent::Pause(pid));
+}
+
+/// Resume a process previously paused with [`pause()`], by delivering it `SIGCONT`.
+pub fn resume(pid: Pid) {
+	let _ = self::pid();
+	monitor_event(&ProcessOutputEvent::Resume(pid));
+}
+
+/// Calls to [`wait_reachable()`](PidExt::wait_reachable) waiting to hear from the bridge whether the process started, in the order they were made.
+static REACHES: Lazy<Mutex<HashMap<Pid, VecDeque<futures::channel::oneshot::Sender<bool>>>>> =
+	Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Note whether a process this one asked to reach had started, as reported by our monitor process.
+fn reached(pid: Pid, started: bool) {
+	let mut reaches = REACHES.lock().unwrap();
+	let waiting = reaches.get_mut(&pid).unwrap();
+	let _ = waiting.pop_front().unwrap().send(started);
+	if waiting.is_empty() {
+		let _ = reaches.remove(&pid);
+	}
+}
+
+/// Extension trait to provide the [`wait_reachable()`](PidExt::wait_reachable) method on [Pid]s.
+pub trait PidExt {
+	/// Wait until the process is accepting connections, so that a [Sender] or [Receiver] to it created afterwards connects straight away.
+	///
+	/// A newly spawned process, particularly on a remote node, may not be accepting connections straight away. This gives a single await point between spawning it and creating channels to it, rather than retry loops or sleeps. Each process tells the bridge once it has finished [`init()`], by which point it's accepting connections; like [`kill()`], this asks the bridge, via our monitor process, to report when that has happened. It errors with `Exited` if the process exits first, or with `Timeout` if `timeout` passes first.
+	fn wait_reachable(&self, timeout: Duration) -> BoxFuture<'static, Result<(), ReachError>>;
+}
+impl PidExt for Pid {
+	fn wait_reachable(&self, timeout: Duration) -> BoxFuture<'static, Result<(), ReachError>> {
+		let pid = *self;
+		async move {
+			if pid == self::pid() {
+				return Ok(());
+			}
+			let (sender, receiver) = futures::channel::oneshot::channel();
+			REACHES
+				.lock()
+				.unwrap()
+				.entry(pid)
+				.or_default()
+				.push_back(sender);
+			let () = *Lazy::force(&CONTROL);
+			monitor_event(&ProcessOutputEvent::Reach(pid));
+			match futures::future::select(receiver, idle::Delay::new(timeout)).await {
+				futures::future::Either::Left((started, _)) => {
+					if started.unwrap() {
+						Ok(())
+					} else {
+						Err(ReachError::Exited)
+					}
+				}
+				futures::future::Either::Right(((), _)) => Err(ReachError::Timeout),
+			}
+		}
+		.boxed()
+	}
+}
+
+/// An error returned by [`wait_reachable()`](PidExt::wait_reachable).
+#[allow(missing_copy_implementations)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum ReachError {
+	/// The process has exited.
+	Exited,
+	/// The process wasn't accepting connections by the timeout.
+	Timeout,
+	#[doc(hidden)]
+	__Nonexhaustive,
+}
+impl fmt::Display for ReachError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Self::Exited => write!(f, "process already exited"),
+			Self::Timeout => write!(f, "timed out waiting for process to accept connections"),
+			Self::__Nonexhaustive => unreachable!(),
+		}
+	}
+}
+impl std::error::Error for ReachError {}
+
 /// Get the [Pid] of the current process.
 #[inline(always)]
 pub fn pid() -> Pid {
@@ -473,29 +1411,232 @@ pub fn pid() -> Pid {
 	})
 }
 
-/// Get the memory and CPU requirements configured at initialisation of the current process.
-pub fn resources() -> Resources {
-	*RESOURCES.get().unwrap_or_else(|| {
+/// Get this process's seed, for deriving its randomness from so that a run can be reproduced.
+///
+/// The initial process's seed is taken from the `CONSTELLATION_SEED` environment variable if it's set, and otherwise chosen at random. Each spawned process's seed is derived from its parent's seed and how many processes its parent had spawned before it, so as long as processes are spawned in the same order, rerunning with the same `CONSTELLATION_SEED` gives every process the same seed as before.
+pub fn seed() -> u64 {
+	*SEED.get().unwrap_or_else(|| {
+		panic!("You must call init() immediately inside your application's main() function")
+	})
+}
+
+/// Derive the seed for the next process spawned by this one. This is [SplitMix64](http://prng.di.unimi.it/splitmix64.c) seeded with this process's seed, so it's stable across platforms and versions.
+///
+/// The index is only advanced by [`spawn_inner()`] once the spawn has gone through, so failed spawns don't shift the seeds of later ones. `offset` is the position of the process among those spawned by a single request.
+fn child_seed(offset: u64) -> u64 {
+	let index = SPAWN_INDEX.load(Ordering::Relaxed) + offset;
+	let mut z = seed().wrapping_add((index + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
+
+/// Get the [Pid] of the process that spawned the current process, or `None` if it's the initial process.
+pub fn parent_pid() -> Option<Pid> {
+	*PARENT.get().unwrap_or_else(|| {
 		panic!("You must call init() immediately inside your application's main() function")
 	})
 }
 
+/// Render this process and the channels it has open as a [Graphviz](https://graphviz.org/) DOT graph.
+///
+/// Processes are identified by address, so the graphs of several processes can be combined into a view of the whole topology by concatenating their statements. An edge from one process to another means that the first has a [Sender] to the second, or the second a [Receiver] from the first.
+pub fn topology_dot() -> String {
+	use std::fmt::Write as _;
+	let local = pid();
+	let context = REACTOR.read().unwrap();
+	let channels = context
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.channels();
+	let mut dot = String::from("digraph constellation {\n");
+	writeln!(dot, "\t\"{}\" [label=\"{}\"];", local.addr(), local).unwrap();
+	for (remote, sender, receiver) in channels {
+		if sender {
+			writeln!(dot, "\t\"{}\" -> \"{}\";", local.addr(), remote).unwrap();
+		}
+		if receiver {
+			writeln!(dot, "\t\"{}\" -> \"{}\";", remote, local.addr()).unwrap();
+		}
+	}
+	dot.push_str("}\n");
+	dot
+}
+
+/// How long this process has spent blocked on and serializing for a channel with a remote process, returned by [`channel_stats()`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ChannelStats {
+	/// The remote process.
+	pub remote: Pid,
+	/// The total time [`Sender::send()`] to `remote` has waited for it to receive.
+	pub send_blocked: Duration,
+	/// The total time [`Receiver::recv()`] from `remote` has waited for it to send.
+	pub recv_blocked: Duration,
+	/// The total time spent serializing messages sent to `remote`. This is only measured while [`set_serialization_timing()`] is enabled.
+	pub serialize: Duration,
+	/// The total time spent deserializing messages received from `remote`. This is only measured while [`set_serialization_timing()`] is enabled.
+	pub deserialize: Duration,
+}
+
+/// Get how long this process has spent blocked sending to and receiving from each process it has a channel with.
+///
+/// In a pipeline, the bottleneck stage is the one whose upstream processes are blocked sending to it while it isn't itself blocked receiving. Comparing these across processes pinpoints where backpressure originates. Only sends and receives that have completed are counted, and only while the connection to the remote is open.
+pub fn channel_stats() -> Vec<ChannelStats> {
+	let context = REACTOR.read().unwrap();
+	context
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.stats()
+		.into_iter()
+		.map(
+			|(remote, send_blocked, recv_blocked, serialize, deserialize)| ChannelStats {
+				remote: Pid::new(remote.ip(), remote.port()),
+				send_blocked,
+				recv_blocked,
+				serialize,
+				deserialize,
+			},
+		)
+		.collect()
+}
+
+/// Get the remote processes whose open connections to this process were relayed to it by its monitor.
+///
+/// Connections to a process are accepted by the monitor process that supervises it, which hands over all but those from the bridge. These are the sources of such connections, the destination of each being this process. Connections this process made itself aren't included.
+pub fn forwarded_connections() -> Vec<Pid> {
+	let context = REACTOR.read().unwrap();
+	context
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.forwarded()
+		.into_iter()
+		.map(|remote| Pid::new(remote.ip(), remote.port()))
+		.collect()
+}
+
+/// Wait until every message sent by this process has been flushed to the transport.
+///
+/// This covers every open [Sender] in the process, without the caller having to track them, so it suits a barrier step before taking a consistent checkpoint of a distributed job. Messages count once their `send()` has completed; this then waits for each to have been serialized and handed to the connection to its remote. It doesn't wait for the remote to receive or process them – for that, use an [`AckedSender`].
+///
+/// This is an async fn.
+pub async fn flush_all() {
+	let _ = pid();
+	futures::future::poll_fn(|cx| {
+		REACTOR
+			.read()
+			.unwrap()
+			.as_ref()
+			.unwrap_or_else(|| {
+				panic!("You must call init() immediately inside your application's main() function")
+			})
+			.poll_flushed(cx)
+	})
+	.await
+}
+
+/// Get the memory and CPU requirements of the current process, as configured at initialisation or since changed with [`update_resources()`](update_resources).
+pub fn resources() -> Resources {
+	*RESOURCES
+		.get()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.read()
+		.unwrap()
+}
+
+/// Change the memory and CPU requirements of the current process, for example as its working set grows or shrinks.
+///
+/// When deployed to a cluster this asks the scheduler to resize this process's reservation on its node, failing with `NoCapacity` if the node doesn't have enough free resources for it to grow. Shrinking frees resources for other processes. The new requirements are accounted for by the scheduler but not enforced on the process.
+///
+/// This blocks until the scheduler replies.
+pub fn update_resources(resources: Resources) -> Result<(), ResourceError> {
+	let current = RESOURCES.get().unwrap_or_else(|| {
+		panic!("You must call init() immediately inside your application's main() function")
+	});
+	let mut scheduler = SCHEDULER.lock().unwrap();
+	if *DEPLOYED.get().unwrap() {
+		let stream = ManuallyDrop::new(unsafe { TcpStream::from_raw_fd(SCHEDULER_FD) });
+		let mut stream_write = BufferedStream::new(&*stream);
+		while let Some(&on_parent_exit) = scheduler.owed.front() {
+			let _ = scheduler_reply(
+				&stream,
+				None,
+				on_parent_exit,
+				false,
+				&mut scheduler,
+				&|_| (),
+			);
+			let _ = scheduler.owed.pop_front();
+		}
+		if scheduler.lost {
+			return Err(ResourceError::SchedulerUnavailable);
+		}
+		let mut stream_write_ = stream_write.write();
+		let request = bincode::serialize_into(
+			&mut stream_write_,
+			&SchedulerRequest::UpdateResources(pid(), resources),
+		)
+		.map_err(map_bincode_err);
+		let reply = request.and(stream_write_.finish()).and_then(|()| {
+			stream.set_read_timeout(None).unwrap();
+			// Unbuffered, so as not to consume the start of any later reply
+			bincode::deserialize_from(&*stream).map_err(map_bincode_err)
+		});
+		let reply: SchedulerReply = match reply {
+			Ok(reply) => reply,
+			Err(err) => {
+				scheduler.lose(&err);
+				return Err(ResourceError::SchedulerUnavailable);
+			}
+		};
+		match reply {
+			SchedulerReply::Updated(result) => result?,
+			_ => unreachable!(),
+		}
+	}
+	*current.write().unwrap() = resources;
+	Ok(())
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// What the child forked by [`spawn_native()`] writes to say it failed to change directory or to exec, followed by the errno.
+const SPAWN_FAILED_CHDIR: u8 = 0;
+const SPAWN_FAILED_EXEC: u8 = 1;
+
 #[allow(clippy::too_many_lines)]
 fn spawn_native(
-	resources: Resources, f: &(dyn serde_traitobject::FnOnce<(Pid,), Output = ()> + 'static),
-	_block: bool,
+	resources: Resources, options: &SpawnOptions,
+	f: &(dyn serde_traitobject::FnOnce<(Pid,), Output = ()> + 'static), _block: bool,
 ) -> Result<Pid, TrySpawnError> {
 	trace!("spawn_native");
+	if let Some(cwd) = &options.cwd {
+		if !cwd.is_dir() {
+			return Err(TrySpawnError::CwdNotFound);
+		}
+	}
+	// There's only this node, which the processes to spread from are running on
+	if let Some(Placement::Spread(pids)) = &options.placement {
+		if !pids.is_empty() {
+			return Err(TrySpawnError::Unsatisfiable {
+				reason: String::from("not running on a fabric, so there are no other nodes"),
+			});
+		}
+	}
 	let args: Vec<CString> = env::args_os()
 		.expect("Couldn't get argv")
 		.iter()
 		.map(|x| CString::new(OsStringExt::into_vec(x.clone())).unwrap())
 		.collect(); // args.split('\0').map(|x|CString::new(x).unwrap()).collect();
 	let args: Vec<&CStr> = args.iter().map(|x| &**x).collect();
-	let vars: Vec<CString> = env::vars_os()
-		.expect("Couldn't get envp")
+	let vars: Vec<CString> = spawn_vars(options)
 		.iter()
 		.map(|&(ref x, ref y)| {
 			(
@@ -519,27 +1660,37 @@ fn spawn_native(
 		.collect(); //vars.split('\0').map(|x|{let (a,b) = x.split_at(x.chars().position(|x|x=='=').unwrap_or_else(||panic!("invalid vars {:?}", x)));(CString::new(a).unwrap(),CString::new(&b[1..]).unwrap())}).collect();
 	let vars: Vec<&CStr> = vars.iter().map(|x| &**x).collect();
 
-	let (process_listener, new_pid) = native_process_listener();
+	let (process_listener, new_pid) = native_process_listener(pid().addr().ip()).unwrap();
 
 	let bridge_pid: Pid = *BRIDGE.get().unwrap();
 	let spawn_arg = SpawnArg::<Start> {
 		bridge: bridge_pid,
 		spawn: Some(SpawnArgSub {
 			parent: pid(),
+			on_parent_exit: options.on_parent_exit,
+			peers: options.peers.clone(),
+			rlimits: options.rlimits,
+			child_init: CHILD_INIT.read().unwrap().clone(),
+			label: options.label.clone(),
+			seed: child_seed(0),
 			f: OwningOrRef::Ref(f),
 		}),
 	};
-	let mut arg: Vec<u8> = Vec::new();
-	bincode::serialize_into(&mut arg, &spawn_arg).unwrap();
-	bincode::serialize_into(&mut arg, &new_pid).unwrap();
-
-	let arg = file_from_reader(
-		&mut &*arg,
-		arg.len().try_into().unwrap(),
-		&env::args_os().unwrap()[0],
-		false,
-	)
-	.unwrap();
+	let arg = if !options.spill_arg {
+		let mut arg: Vec<u8> = Vec::new();
+		bincode::serialize_into(&mut arg, &spawn_arg).unwrap();
+		bincode::serialize_into(&mut arg, &new_pid).unwrap();
+
+		file_from_reader(
+			&mut &*arg,
+			arg.len().try_into().unwrap(),
+			&env::args_os().unwrap()[0],
+			false,
+		)
+		.unwrap()
+	} else {
+		spill(&(&spawn_arg, &new_pid), false)
+	};
 
 	let exe = CString::new(<OsString as OsStringExt>::into_vec(
 		env::exe_path().unwrap().into(),
@@ -547,6 +1698,10 @@ fn spawn_native(
 	))
 	.unwrap();
 
+	// The child writes which step failed and its errno here if changing directory or exec fails; it's CLOEXEC so a successful exec closes it.
+	let (exec_reader, exec_writer) = unistd::pipe2(fcntl::OFlag::O_CLOEXEC).unwrap();
+	assert!(exec_writer > ARG_FD);
+
 	if let palaver::process::ForkResult::Child = palaver::process::fork(true).expect("Fork failed")
 	{
 		forbid_alloc(|| {
@@ -565,6 +1720,7 @@ fn spawn_native(
 				fd >= 3
 					&& fd != process_listener
 					&& fd != arg.as_raw_fd()
+					&& fd != exec_writer
 					&& (valgrind_start_fd.is_none() || fd < valgrind_start_fd.unwrap())
 			}) {
 				let _ = unistd::close(fd); //.unwrap();
@@ -583,10 +1739,22 @@ fn spawn_native(
 				palaver::file::move_fd(arg.as_raw_fd(), ARG_FD, Some(fcntl::FdFlag::empty()), true)
 					.unwrap();
 			}
+			if let Some(cwd) = &options.cwd {
+				if let Err(err) = unistd::chdir(&**cwd) {
+					let errno = if let nix::Error::Sys(errno) = err {
+						errno as i32
+					} else {
+						0
+					};
+					let mut failed = [SPAWN_FAILED_CHDIR; 5];
+					failed[1..].copy_from_slice(&errno.to_le_bytes());
+					let _ = unistd::write(exec_writer, &failed);
+					unsafe { libc::_exit(1) };
+				}
+			}
 
-			if !valgrind::is().unwrap_or(false) {
-				execve(&exe, &args, &vars)
-					.expect("Failed to execve /proc/self/exe for spawn_native");
+			let err = if !valgrind::is().unwrap_or(false) {
+				execve(&exe, &args, &vars).err().unwrap()
 			} else {
 				let fd = fcntl::open::<path::PathBuf>(
 					&fd_path(valgrind_start_fd.unwrap()).unwrap(),
@@ -598,88 +1766,343 @@ fn spawn_native(
 				assert!(binary_desired_fd_ > fd);
 				palaver::file::move_fd(fd, binary_desired_fd_, Some(fcntl::FdFlag::empty()), true)
 					.unwrap();
-				fexecve(binary_desired_fd_, &args, &vars)
-					.expect("Failed to fexecve /proc/self/fd/n for spawn_native");
-			}
-			unreachable!();
+				fexecve(binary_desired_fd_, &args, &vars).err().unwrap()
+			};
+			let errno = if let nix::Error::Sys(errno) = err {
+				errno as i32
+			} else {
+				0
+			};
+			let mut failed = [SPAWN_FAILED_EXEC; 5];
+			failed[1..].copy_from_slice(&errno.to_le_bytes());
+			let _ = unistd::write(exec_writer, &failed);
+			unsafe { libc::_exit(1) };
 		})
 	}
+	unistd::close(exec_writer).unwrap();
 	unistd::close(process_listener).unwrap();
 	drop(arg);
+	let mut failed = [0; 5];
+	let exec_reader = unsafe { fs::File::from_raw_fd(exec_reader) };
+	if (&exec_reader).read_exact(&mut failed).is_ok() {
+		let mut errno = [0; 4];
+		errno.copy_from_slice(&failed[1..]);
+		return Err(match failed[0] {
+			// The directory was removed since we checked it
+			SPAWN_FAILED_CHDIR => TrySpawnError::CwdNotFound,
+			SPAWN_FAILED_EXEC => TrySpawnError::ExecFailed {
+				errno: i32::from_le_bytes(errno),
+			},
+			_ => unreachable!(),
+		});
+	}
+	drop(exec_reader);
 	// *BRIDGE.get().as_ref().unwrap().0.send(ProcessOutputEvent::Spawn(new_pid)).unwrap();
+	if options.forward_output {
+		child::watch(new_pid);
+	}
 	{
 		let file = unsafe { fs::File::from_raw_fd(MONITOR_FD) };
-		bincode::serialize_into(&mut &file, &ProcessOutputEvent::Spawn(new_pid)).unwrap();
+		bincode::serialize_into(
+			&mut &file,
+			&ProcessOutputEvent::Spawn(new_pid, options.on_parent_exit, options.forward_output),
+		)
+		.unwrap();
 		let _ = file.into_raw_fd();
 	}
 	Ok(new_pid)
 }
 
 fn spawn_deployed(
-	resources: Resources, f: &(dyn serde_traitobject::FnOnce<(Pid,), Output = ()> + 'static),
-	block: bool,
+	resources: Resources, options: &SpawnOptions,
+	f: &(dyn serde_traitobject::FnOnce<(Pid,), Output = ()> + 'static), block: bool,
+	scheduler: &mut Scheduler, on_pending: &dyn Fn(PendingReason),
 ) -> Result<Pid, TrySpawnError> {
 	trace!("spawn_deployed");
-	let stream = unsafe { TcpStream::from_raw_fd(SCHEDULER_FD) };
-	let (mut stream_read, mut stream_write) =
-		(BufferedStream::new(&stream), BufferedStream::new(&stream));
-	let mut arg: Vec<u8> = Vec::new();
+	let stream = ManuallyDrop::new(unsafe { TcpStream::from_raw_fd(SCHEDULER_FD) });
+	scheduler_owed(&stream, scheduler)?;
+	let mut stream_write = BufferedStream::new(&*stream);
+	let mut stream_write_ = stream_write.write();
+	let request = bincode::serialize_into(&mut stream_write_, &SchedulerRequest::Spawn)
+		.map_err(map_bincode_err)
+		.and_then(|()| write_fabric_request(&mut stream_write_, block, resources, options, f, 0));
+	if let Err(err) = request.and(stream_write_.finish()) {
+		scheduler.lose(&err);
+		return Err(TrySpawnError::SchedulerUnavailable);
+	}
+	let pid = scheduler_reply(
+		&stream,
+		scheduler.timeout,
+		options.on_parent_exit,
+		options.forward_output,
+		scheduler,
+		on_pending,
+	)
+	.unwrap_or_else(|| {
+		scheduler.owed.push_back(options.on_parent_exit);
+		Err(TrySpawnError::SchedulerTimeout)
+	});
+	trace!("{} spawned? {:?}", self::pid(), pid);
+	pid
+}
+
+/// Spawn a process running each of `fs` as a single request, which the scheduler allocates all or nothing.
+///
+/// The result for each process is as for [`spawn_deployed()`]. Some can be `Err` while others are `Ok` if the fabric then fails to start them, or if the scheduler's replies time out or the connection is lost partway through.
+fn spawn_deployed_many(
+	resources: Resources, options: &SpawnOptions,
+	fs: &[&(dyn serde_traitobject::FnOnce<(Pid,), Output = ()> + 'static)], block: bool,
+	scheduler: &mut Scheduler, on_pending: &dyn Fn(PendingReason),
+) -> Vec<Result<Pid, TrySpawnError>> {
+	trace!("spawn_deployed_many");
+	let stream = ManuallyDrop::new(unsafe { TcpStream::from_raw_fd(SCHEDULER_FD) });
+	if let Err(err) = scheduler_owed(&stream, scheduler) {
+		return vec![Err(err); fs.len()];
+	}
+	let mut stream_write = BufferedStream::new(&*stream);
+	let mut stream_write_ = stream_write.write();
+	let request =
+		bincode::serialize_into(&mut stream_write_, &SchedulerRequest::SpawnMany(fs.len()))
+			.map_err(map_bincode_err)
+			.and_then(|()| {
+				fs.iter().zip(0..).try_for_each(|(f, offset)| {
+					write_fabric_request(&mut stream_write_, block, resources, options, *f, offset)
+				})
+			});
+	if let Err(err) = request.and(stream_write_.finish()) {
+		scheduler.lose(&err);
+		return vec![Err(TrySpawnError::SchedulerUnavailable); fs.len()];
+	}
+	let mut timed_out = false;
+	fs.iter()
+		.map(|_| {
+			if scheduler.lost {
+				return Err(TrySpawnError::SchedulerUnavailable);
+			}
+			if !timed_out {
+				if let Some(pid) = scheduler_reply(
+					&stream,
+					scheduler.timeout,
+					options.on_parent_exit,
+					options.forward_output,
+					scheduler,
+					on_pending,
+				) {
+					return pid;
+				}
+				timed_out = true;
+			}
+			scheduler.owed.push_back(options.on_parent_exit);
+			Err(TrySpawnError::SchedulerTimeout)
+		})
+		.collect()
+}
+
+/// Receive the replies owed to earlier requests that timed out, so that the next reply received is to the next request made.
+fn scheduler_owed(stream: &TcpStream, scheduler: &mut Scheduler) -> Result<(), TrySpawnError> {
+	while let Some(&on_parent_exit) = scheduler.owed.front() {
+		let _ = scheduler_reply(
+			stream,
+			scheduler.timeout,
+			on_parent_exit,
+			false,
+			scheduler,
+			&|_| (),
+		)
+		.ok_or(TrySpawnError::SchedulerTimeout)?;
+		let _ = scheduler.owed.pop_front();
+	}
+	if scheduler.lost {
+		return Err(TrySpawnError::SchedulerUnavailable);
+	}
+	Ok(())
+}
+
+/// Write the [`FabricRequest`] for a process running `f`, the `offset`th spawned by this request.
+fn write_fabric_request<W: Write>(
+	stream_write: &mut W, block: bool, resources: Resources, options: &SpawnOptions,
+	f: &(dyn serde_traitobject::FnOnce<(Pid,), Output = ()> + 'static), offset: u64,
+) -> Result<(), io::Error> {
 	let bridge_pid: Pid = *BRIDGE.get().unwrap();
 	let spawn_arg = SpawnArg::<Start> {
 		bridge: bridge_pid,
 		spawn: Some(SpawnArgSub {
 			parent: pid(),
+			on_parent_exit: options.on_parent_exit,
+			peers: options.peers.clone(),
+			rlimits: options.rlimits,
+			child_init: CHILD_INIT.read().unwrap().clone(),
+			label: options.label.clone(),
+			seed: child_seed(offset),
 			f: OwningOrRef::Ref(f),
 		}),
 	};
-	bincode::serialize_into(&mut arg, &spawn_arg).unwrap();
-	#[cfg(feature = "distribute_binaries")]
-	let binary = if !valgrind::is().unwrap_or(false) {
-		env::exe().unwrap()
+	if !options.spill_arg {
+		let mut arg: Vec<u8> = Vec::new();
+		bincode::serialize_into(&mut arg, &spawn_arg).unwrap();
+		bincode_serialize_into(
+			stream_write,
+			&fabric_request(block, resources, options, arg),
+		)
 	} else {
-		unsafe {
-			fs::File::from_raw_fd(
-				fcntl::open(
-					&fd_path(valgrind::start_fd()).unwrap(),
-					fcntl::OFlag::O_RDONLY | fcntl::OFlag::O_CLOEXEC,
-					stat::Mode::empty(),
-				)
-				.unwrap(),
-			)
-		}
-	};
+		// The spilled arg is streamed from the memfd straight to the scheduler
+		let arg = spill(&spawn_arg, true);
+		bincode_serialize_into(
+			stream_write,
+			&fabric_request(block, resources, options, arg),
+		)
+	}
+	.map_err(map_bincode_err)
+}
+
+/// The environment of a process spawned with `options`: this process's, less [`SpawnOptions::env_remove`], plus [`SpawnOptions::env`].
+fn spawn_vars(options: &SpawnOptions) -> Vec<(OsString, OsString)> {
+	let mut vars = env::vars_os().expect("Couldn't get envp");
+	vars.retain(|(key, _)| !options.env_remove.contains(key));
+	for (key, value) in &options.env {
+		vars.retain(|(key_, _)| key_ != key);
+		vars.push((key.clone(), value.clone()));
+	}
+	vars
+}
+
+/// Build the request to the scheduler to spawn a copy of this process with `arg`.
+fn fabric_request<A: FileOrVec>(
+	block: bool, resources: Resources, options: &SpawnOptions, arg: A,
+) -> FabricRequest<A, fs::File> {
+	// The scheduler has our binary cached for as long as we're running, so we needn't send it
+	#[cfg(feature = "distribute_binaries")]
+	let binary = None;
 	#[cfg(not(feature = "distribute_binaries"))]
-	let binary = std::marker::PhantomData::<fs::File>;
-	let request = FabricRequest {
+	let binary = std::marker::PhantomData;
+	// The scheduler allocates from the pool in CONSTELLATION_POOL, which children otherwise inherit
+	let mut vars = spawn_vars(options);
+	if let Some(pool) = &options.pool {
+		vars.retain(|(key, _)| key != "CONSTELLATION_POOL");
+		vars.push((OsString::from("CONSTELLATION_POOL"), OsString::from(pool)));
+	}
+	// Likewise the fabric verifies the binary against CONSTELLATION_EXPECTED_HASH
+	if let Some(expected_hash) = &options.expected_hash {
+		vars.retain(|(key, _)| key != "CONSTELLATION_EXPECTED_HASH");
+		vars.push((
+			OsString::from("CONSTELLATION_EXPECTED_HASH"),
+			OsString::from(hash_to_hex(expected_hash)),
+		));
+	}
+	FabricRequest {
 		block,
 		resources,
 		bind: vec![],
 		args: env::args_os().expect("Couldn't get argv"),
-		vars: env::vars_os().expect("Couldn't get envp"),
+		vars,
+		cwd: options.cwd.clone(),
+		near: options
+			.affinity
+			.clone()
+			.or_else(|| Some(NodeSelector::Host(pid().addr().ip())).filter(|_| options.colocate)),
+		placement: options.placement.clone(),
 		arg,
+		#[cfg(feature = "distribute_binaries")]
+		binary_hash: Some(*BINARY.get().unwrap()),
 		binary,
-	};
-	bincode_serialize_into(&mut stream_write.write(), &request)
-		.map_err(map_bincode_err)
-		.unwrap();
-	let pid: Result<Pid, TrySpawnError> = bincode::deserialize_from(&mut stream_read)
-		.map_err(map_bincode_err)
-		.unwrap();
-	drop(stream_read);
-	trace!("{} spawned? {}", self::pid(), pid.as_ref().unwrap());
-	if let Ok(pid) = pid {
-		let file = unsafe { fs::File::from_raw_fd(MONITOR_FD) };
-		bincode::serialize_into(&mut &file, &ProcessOutputEvent::Spawn(pid)).unwrap();
-		let _ = file.into_raw_fd();
 	}
-	let _ = stream.into_raw_fd();
-	pid
+}
+
+/// Serialize `value` to a memfd rather than memory, for [`SpawnOptions::spill_arg`].
+fn spill<T: Serialize>(value: &T, cloexec: bool) -> fs::File {
+	let mut file =
+		file_from_reader(&mut io::empty(), 0, &env::args_os().unwrap()[0], cloexec).unwrap();
+	let mut writer = io::BufWriter::new(&file);
+	bincode::serialize_into(&mut writer, value).unwrap();
+	writer.flush().unwrap();
+	drop(writer);
+	let x = file.seek(io::SeekFrom::Start(0)).unwrap();
+	assert_eq!(x, 0);
+	file
+}
+
+/// Receive the scheduler's reply to a spawn request, passing any progress updates to `on_pending`, or `None` if it timed out.
+///
+/// Replies that arrive after their request timed out are still received, as the process has been spawned regardless.
+///
+/// Replies are read from `stream` unbuffered: a request can get several replies, and waiting for each with `peek()` only works if none have been read ahead.
+fn scheduler_reply(
+	stream: &TcpStream, timeout: Option<Duration>, on_parent_exit: OnParentExit,
+	forward_output: bool, scheduler: &mut Scheduler, on_pending: &dyn Fn(PendingReason),
+) -> Option<Result<Pid, TrySpawnError>> {
+	let deadline = timeout.map(|timeout| Instant::now() + timeout);
+	loop {
+		let remaining = match deadline {
+			Some(deadline) => Some(
+				deadline
+					.checked_duration_since(Instant::now())
+					.filter(|remaining| *remaining > Duration::new(0, 0))?,
+			),
+			None => None,
+		};
+		// Wait for the start of a reply without consuming any of it, so that timing out leaves the stream at a frame boundary for the reply to be received later
+		stream.set_read_timeout(remaining).unwrap();
+		match stream.peek(&mut [0]) {
+			Ok(0) => {
+				scheduler.lose(&io::ErrorKind::UnexpectedEof.into());
+				return Some(Err(TrySpawnError::SchedulerUnavailable));
+			}
+			Ok(_) => (),
+			Err(ref err)
+				if err.kind() == io::ErrorKind::WouldBlock
+					|| err.kind() == io::ErrorKind::TimedOut =>
+			{
+				return None
+			}
+			Err(err) => {
+				scheduler.lose(&err);
+				return Some(Err(TrySpawnError::SchedulerUnavailable));
+			}
+		}
+		// Having started, the rest of the reply is due promptly. If it isn't, the stream is mid-frame and can't be reused
+		stream.set_read_timeout(timeout).unwrap();
+		let reply: SchedulerReply = match bincode::deserialize_from(stream).map_err(map_bincode_err)
+		{
+			Ok(reply) => reply,
+			Err(err) => {
+				scheduler.lose(&err);
+				return Some(Err(TrySpawnError::SchedulerUnavailable));
+			}
+		};
+		match reply {
+			SchedulerReply::Pending(reason) => on_pending(reason),
+			SchedulerReply::Done(pid) => {
+				if let Ok(pid) = pid {
+					if forward_output {
+						child::watch(pid);
+					}
+					monitor_event(&ProcessOutputEvent::Spawn(
+						pid,
+						on_parent_exit,
+						forward_output,
+					));
+				}
+				break Some(pid);
+			}
+			SchedulerReply::Updated(_) => unreachable!(), // update_resources() receives its own reply
+		}
+	}
+}
+
+/// Send an event to our monitor process. Events are written with a single `write()` so that those from different threads aren't interleaved.
+fn monitor_event(event: &ProcessOutputEvent) {
+	let event = bincode::serialize(event).unwrap();
+	let file = unsafe { fs::File::from_raw_fd(MONITOR_FD) };
+	(&file).write_all(&event).unwrap();
+	let _ = file.into_raw_fd();
 }
 
 async fn spawn_inner<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
-	resources: Resources, start: T, block: bool,
+	resources: Resources, options: SpawnOptions, start: T, block: bool,
+	on_pending: &dyn Fn(PendingReason),
 ) -> Result<Pid, TrySpawnError> {
-	let _scheduler = SCHEDULER.lock().unwrap();
+	// There are deliberately no await points, so that a spawn future that's dropped has either done nothing or finished
+	let mut scheduler = SCHEDULER.lock().unwrap();
 	let deployed = *DEPLOYED.get().unwrap_or_else(|| {
 		panic!("You must call init() immediately inside your application's main() function")
 	});
@@ -690,41 +2113,293 @@ async fn spawn_inner<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
 		let closure: T = bincode::deserialize(&arg).unwrap();
 		closure(parent)
 	});
-	if !deployed {
-		spawn_native(resources, &start, block)
+	let pid = if !deployed {
+		spawn_native(resources, &options, &start, block)
 	} else {
-		spawn_deployed(resources, &start, block)
+		spawn_deployed(
+			resources,
+			&options,
+			&start,
+			block,
+			&mut scheduler,
+			on_pending,
+		)
+	};
+	// Holding the scheduler lock, so no other spawn has taken this index. A timed out spawn may yet go through, so its index isn't reused.
+	if let Ok(_) | Err(TrySpawnError::SchedulerTimeout) = pid {
+		let _ = SPAWN_INDEX.fetch_add(1, Ordering::Relaxed);
 	}
+	pid
+}
+
+/// Spawn a process running each of `starts` as a batch the scheduler allocates all or nothing, waiting for capacity for them all. This is only for when deployed; the result for each process is as for [`spawn_deployed_many()`].
+async fn spawn_many_deployed<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
+	resources: Resources, starts: Vec<T>,
+) -> Vec<Result<Pid, TrySpawnError>> {
+	// As for spawn_inner(), there are deliberately no await points
+	let mut scheduler = SCHEDULER.lock().unwrap();
+	let starts = starts
+		.into_iter()
+		.map(|start| {
+			let arg: Vec<u8> = bincode::serialize(&start).unwrap();
+			FnOnce!(move |parent| {
+				let arg: Vec<u8> = arg;
+				let closure: T = bincode::deserialize(&arg).unwrap();
+				closure(parent)
+			})
+		})
+		.collect::<Vec<_>>();
+	let fs = starts
+		.iter()
+		.map(|start| {
+			let f: &(dyn serde_traitobject::FnOnce<(Pid,), Output = ()> + 'static) = start;
+			f
+		})
+		.collect::<Vec<_>>();
+	let pids = spawn_deployed_many(
+		resources,
+		&SpawnOptions::default(),
+		&fs,
+		true,
+		&mut scheduler,
+		&|_| (),
+	);
+	// Each process was given the seed for its index past the current one, so advance past the last that went through or may yet
+	let spawned = pids
+		.iter()
+		.rposition(|pid| pid.is_ok() || *pid == Err(TrySpawnError::SchedulerTimeout))
+		.map_or(0, |last| last + 1);
+	let _ = SPAWN_INDEX.fetch_add(spawned as u64, Ordering::Relaxed);
+	pids
+}
+
+/// Spawn a new process if it can be allocated immediately.
+///
+/// `try_spawn()` takes 2 arguments:
+///  * `resources`: memory and CPU resource requirements of the new process
+///  * `start`: the closure to be run in the new process
+///
+/// `try_spawn()` on success returns the [Pid] of the new process.
+pub async fn try_spawn<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
+	resources: Resources, start: T,
+) -> Result<Pid, TrySpawnError> {
+	spawn_inner(resources, SpawnOptions::default(), start, false, &|_| ()).await
+}
+
+/// Spawn a new process with [`SpawnOptions`] if it can be allocated immediately.
+///
+/// This is like [`try_spawn()`](try_spawn), with `options` such as the working directory of the new process.
+pub async fn try_spawn_with<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
+	resources: Resources, options: SpawnOptions, start: T,
+) -> Result<Pid, TrySpawnError> {
+	spawn_inner(resources, options, start, false, &|_| ()).await
+}
+
+/// Spawn a new process.
+///
+/// `spawn()` takes 2 arguments:
+///  * `resources`: memory and CPU resource requirements of the new process
+///  * `start`: the closure to be run in the new process
+///
+/// `spawn()` on success returns the [Pid] of the new process.
+///
+/// The request is made, and its reply waited for, within the first poll of the returned future, so dropping the future, for example when it loses a `select`, is safe: either it was never polled and no process is spawned, or it has already completed. Conversely, once polled it can't be interrupted, as it blocks until the scheduler replies or the timeout set by [`set_scheduler_timeout()`](set_scheduler_timeout) passes. When not running on a fabric, the new process is forked in that first poll.
+pub async fn spawn<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
+	resources: Resources, start: T,
+) -> Result<Pid, SpawnError> {
+	spawn_with(resources, SpawnOptions::default(), start).await
+}
+
+/// Spawn a new process with [`SpawnOptions`].
+///
+/// This is like [`spawn()`](spawn), with `options` such as the working directory of the new process.
+pub async fn spawn_with<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
+	resources: Resources, options: SpawnOptions, start: T,
+) -> Result<Pid, SpawnError> {
+	spawn_inner(resources, options, start, true, &|_| ())
+		.map_err(|err| err.try_into().unwrap())
+		.await
+}
+
+/// Spawn a new process if it can be allocated immediately, blocking the calling thread until it's been spawned.
+///
+/// This is [`try_spawn()`](try_spawn) for synchronous code that isn't running in an async context. It mustn't be called from within an async task: it blocks the thread the task is running on, which can deadlock if the spawn depends on other tasks on that thread making progress.
+pub fn try_spawn_blocking<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
+	resources: Resources, start: T,
+) -> Result<Pid, TrySpawnError> {
+	try_spawn(resources, start).block()
 }
 
-/// Spawn a new process if it can be allocated immediately.
-///
-/// `try_spawn()` takes 2 arguments:
-///  * `resources`: memory and CPU resource requirements of the new process
-///  * `start`: the closure to be run in the new process
+/// Spawn a new process, blocking the calling thread until it's been spawned.
 ///
-/// `try_spawn()` on success returns the [Pid] of the new process.
-pub async fn try_spawn<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
+/// This is [`spawn()`](spawn) for synchronous code that isn't running in an async context. It mustn't be called from within an async task: it blocks the thread the task is running on, which can deadlock if the spawn depends on other tasks on that thread making progress.
+pub fn spawn_blocking<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
 	resources: Resources, start: T,
-) -> Result<Pid, TrySpawnError> {
-	spawn_inner(resources, start, false).await
+) -> Result<Pid, SpawnError> {
+	spawn(resources, start).block()
 }
 
-/// Spawn a new process.
+/// Spawn a new process, calling `on_pending` with the reason whenever it's waiting to be allocated.
 ///
-/// `spawn()` takes 2 arguments:
-///  * `resources`: memory and CPU resource requirements of the new process
-///  * `start`: the closure to be run in the new process
+/// This is like [`spawn()`](spawn), but gives visibility into a spawn that's blocked, for example because the cluster is at capacity, rather than it appearing to hang. `on_pending` is called when the request is first queued and again as its position in the queue changes. It's called on the spawning thread and so shouldn't block. When not deployed to a cluster processes are spawned immediately and `on_pending` is never called.
 ///
-/// `spawn()` on success returns the [Pid] of the new process.
-pub async fn spawn<T: FnOnce(Pid) + Serialize + DeserializeOwned>(
-	resources: Resources, start: T,
+/// This is an async fn.
+pub async fn spawn_with_progress<
+	T: FnOnce(Pid) + Serialize + DeserializeOwned,
+	P: Fn(PendingReason),
+>(
+	resources: Resources, start: T, on_pending: P,
 ) -> Result<Pid, SpawnError> {
-	spawn_inner(resources, start, true)
+	spawn_inner(resources, SpawnOptions::default(), start, true, &on_pending)
 		.map_err(|err| err.try_into().unwrap())
 		.await
 }
 
+/// Set a closure to be run in every process subsequently spawned by this process or its descendants, after [`init()`](init) and before the spawned closure.
+///
+/// This is for setup that every process needs, like installing a logger or panic hook, so that it needn't be repeated at the start of every closure passed to [`spawn()`](spawn). Calling it again replaces the closure for processes spawned afterwards.
+pub fn set_child_init<F: Fn() + Serialize + DeserializeOwned + 'static>(f: F) {
+	let _ = pid();
+	let arg: Vec<u8> = bincode::serialize(&f).unwrap();
+	let child_init: Box<dyn serde_traitobject::FnOnce<(Pid,), Output = ()>> =
+		Box::new(FnOnce!(move |_parent| {
+			let arg: Vec<u8> = arg;
+			let closure: F = bincode::deserialize(&arg).unwrap();
+			closure()
+		}));
+	*CHILD_INIT.write().unwrap() = Some(bincode::serialize(&child_init).unwrap());
+}
+
+/// Set a timeout on the round-trip to the scheduler made by [`spawn()`](spawn) and [`try_spawn()`](try_spawn).
+///
+/// If the scheduler doesn't reply within `timeout`, `SchedulerTimeout` is returned rather than blocking indefinitely. The default, `None`, is no timeout.
+///
+/// Note that [`spawn()`](spawn) waits for capacity to become available, which counts towards the timeout. If the scheduler does go on to allocate a process for a request that timed out, that process still runs. This has no effect when not running on a fabric.
+pub fn set_scheduler_timeout(timeout: Option<Duration>) {
+	assert_ne!(
+		timeout,
+		Some(Duration::new(0, 0)),
+		"set_scheduler_timeout() called with a zero Duration"
+	);
+	SCHEDULER.lock().unwrap().timeout = timeout;
+}
+
+/// What a process does if its connection to the scheduler is lost, set with [`set_scheduler_loss_policy()`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SchedulerLossPolicy {
+	/// Exit the process with a nonzero exit code. This is the default.
+	Exit,
+	/// Keep running, and reconnect to the scheduler in the background. Channels are unaffected, as they don't go through the scheduler. Until the connection is reestablished, spawns fail with `SchedulerUnavailable`, as does [`update_resources()`](update_resources).
+	Reconnect,
+}
+
+/// Set what this process does if its connection to the scheduler is lost, for example because the node running it is restarted.
+///
+/// The default, [`SchedulerLossPolicy::Exit`], tears the process down, so that a job doesn't run on unable to spawn. With [`SchedulerLossPolicy::Reconnect`] the process degrades gracefully, continuing to serve its existing channels while it retries the scheduler every second. The loss is noticed the next time the process spawns or updates its resources. This has no effect when not running on a fabric.
+pub fn set_scheduler_loss_policy(policy: SchedulerLossPolicy) {
+	SCHEDULER.lock().unwrap().policy = policy;
+}
+
+/// Measure the time spent serializing and deserializing messages on each channel, for [`channel_stats()`].
+///
+/// This tells whether a slow pipeline is bound by the network or by serialization, which might be addressed by restructuring its messages. It's off by default, as it reads the clock around each message sent and received.
+pub fn set_serialization_timing(enabled: bool) {
+	REACTOR
+		.read()
+		.unwrap()
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.set_serde_timing(enabled);
+}
+
+/// Limit the size of a message this process will receive on any channel to `max` bytes.
+///
+/// A remote, whether malformed or malicious, could otherwise claim an arbitrarily large message and have this process buffer it. A channel on which a message being received takes more than `max` bytes is killed, and its [Receiver] returns [`ChannelError::Unknown`]. The default is 256 MiB. It can be overridden for a particular channel with [`Receiver::set_max_message_size()`].
+pub fn set_max_message_size(max: usize) {
+	REACTOR
+		.read()
+		.unwrap()
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.set_max_message_size(max);
+}
+
+/// Limit the memory this process spends buffering messages received from other processes, across all of its channels.
+///
+/// Messages are buffered as they arrive until the application receives them, so a process that isn't keeping up, or a remote that sends a very large message, can otherwise exhaust memory. With a limit of `limit` bytes set, once it's reached no channel starts receiving a new message until enough buffered messages have been received; [`BufferLimitPolicy::Disconnect`] additionally kills the channel with the most buffered if messages that were already being received take the total over the limit. The default, `None`, is not to limit.
+pub fn set_buffer_limit(limit: Option<usize>, policy: BufferLimitPolicy) {
+	REACTOR
+		.read()
+		.unwrap()
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.set_buffer_limit(limit, policy);
+}
+
+/// Observe every message this process sends and receives, on any channel.
+///
+/// `inspector` is called with the [`Direction`] of each message, the remote process, the name of its type as given by [`type_name()`](std::any::type_name), and its serialized length in bytes. Unlike [`channel_stats()`], which aggregates, this is per message, so suits tracing and auditing the flow of messages through a complex topology. It's called on the sending or receiving thread while the channel is locked, so it should be quick and mustn't itself use channels. Messages on channels made with [`channel_local()`] aren't serialized, so are reported with a length of 0.
+///
+/// This replaces any inspector already set. With none set, as by default, the only overhead is a flag check per message.
+pub fn set_message_inspector(
+	inspector: impl Fn(Direction, Pid, &str, usize) + Send + Sync + 'static,
+) {
+	REACTOR
+		.read()
+		.unwrap()
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.set_inspector(Some(Box::new(move |direction, remote, type_name, len| {
+			inspector(
+				direction,
+				Pid::new(remote.ip(), remote.port()),
+				type_name,
+				len,
+			)
+		})));
+}
+
+/// Stop observing messages with the inspector set by [`set_message_inspector()`].
+pub fn clear_message_inspector() {
+	REACTOR
+		.read()
+		.unwrap()
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.set_inspector(None);
+}
+
+/// Retry connections to remote processes that are refused, for up to `timeout`.
+///
+/// A connection to a process that isn't yet accepting them, for example because it's still starting up on a remote node, otherwise fails straight away. With a timeout set, it's retried with exponential backoff, and if it's still refused once `timeout` has elapsed [`Receiver::recv()`] returns `Err(ChannelError::Timeout)`. The default, `None`, is not to retry.
+///
+/// Of the two processes at either end of a channel, only one makes the connection, so to cover both directions set this in both.
+pub fn set_connect_timeout(timeout: Option<Duration>) {
+	assert_ne!(
+		timeout,
+		Some(Duration::new(0, 0)),
+		"set_connect_timeout() called with a zero Duration"
+	);
+	REACTOR
+		.read()
+		.unwrap()
+		.as_ref()
+		.unwrap_or_else(|| {
+			panic!("You must call init() immediately inside your application's main() function")
+		})
+		.set_connect_timeout(timeout);
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 extern "C" fn at_exit() {
@@ -776,12 +2451,16 @@ pub fn bridge_init() -> TcpListener {
 	listener
 }
 
-fn native_bridge(format: Format, our_pid: Pid) -> Pid {
-	let (bridge_process_listener, bridge_pid) = native_process_listener();
+#[allow(clippy::too_many_lines)]
+fn native_bridge(format: Format, labels: LabelFilter, our_pid: Pid) -> Result<Pid, InitError> {
+	let (bridge_process_listener, bridge_pid) = native_process_listener(our_pid.addr().ip())
+		.map_err(|err| InitError::Socket(io_error(err)))?;
 
 	// No threads spawned between init and here so we're good
 	assert_eq!(palaver::thread::count(), 1);
-	if let palaver::process::ForkResult::Parent(_) = palaver::process::fork(true).unwrap() {
+	if let palaver::process::ForkResult::Parent(_) =
+		palaver::process::fork(true).map_err(|err| InitError::Fork(io_error(err)))?
+	{
 		// trace!("parent");
 
 		palaver::file::move_fd(
@@ -815,6 +2494,7 @@ fn native_bridge(format: Format, our_pid: Pid) -> Pid {
 				} else {
 					StyleSupport::None
 				},
+				labels,
 				stdout.lock(),
 				stderr.lock(),
 			))
@@ -825,78 +2505,312 @@ fn native_bridge(format: Format, our_pid: Pid) -> Pid {
 			Sender::<ProcessInputEvent>::new(our_pid),
 			Receiver::<ProcessOutputEvent>::new(our_pid),
 		)];
+		let (mut parents, mut cancelled) = (HashMap::new(), HashSet::new());
+		let mut on_parent_exit = HashMap::new();
+		// Processes waiting to be told of another's exit, and the exit statuses of processes whose parents might yet ask
+		let (mut watchers, mut exited) = (HashMap::<Pid, Vec<Pid>>::new(), HashMap::new());
+		// Processes waiting to be told another has started, and the running processes that have
+		let (mut reachers, mut started) = (HashMap::<Pid, Vec<Pid>>::new(), HashSet::new());
+		// Processes whose output is forwarded to their parent
+		let mut forwarded = HashSet::new();
+		// Events waiting to be sent to each process, sent as its channel has room so that one process not reading its events doesn't hold up the others
+		let mut queued = HashMap::<Pid, VecDeque<ProcessInputEvent>>::new();
+		let mut summarizer = Summarizer::new();
 		while !processes.is_empty() {
-			let (event, i, _): (ProcessOutputEvent, usize, _) = futures::future::select_all(
+			let received = futures::future::select_all(
 				processes
 					.iter()
 					.map(|&(_, ref receiver)| receiver.recv().map(Result::unwrap).boxed_local()),
-			)
-			.block();
+			);
+			let sent = futures::future::poll_fn(|cx| {
+				for (sender, _) in &processes {
+					if let Some(queued) = queued.get_mut(&sender.remote_pid()) {
+						while let Some(event) = queued.pop_front() {
+							let mut event = Some(event);
+							// A process that has exited has its events dropped
+							if sender.poll_send(cx, &mut event).is_pending() {
+								queued.push_front(event.unwrap());
+								break;
+							}
+						}
+					}
+				}
+				Poll::<()>::Pending
+			});
+			let (event, i, _): (ProcessOutputEvent, usize, _) =
+				match futures::future::select(received, sent).block() {
+					futures::future::Either::Left((received, _)) => received,
+					futures::future::Either::Right(((), _)) => unreachable!(),
+				};
 			let pid = processes[i].0.remote_pid();
 			let event = match event {
-				ProcessOutputEvent::Spawn(new_pid) => {
+				ProcessOutputEvent::Spawn(new_pid, on_parent_exit_, forward_output) => {
 					processes.push((
 						Sender::<ProcessInputEvent>::new(new_pid),
 						Receiver::<ProcessOutputEvent>::new(new_pid),
 					));
+					let _ = parents.insert(new_pid, pid);
+					let _ = on_parent_exit.insert(new_pid, on_parent_exit_);
+					if forward_output {
+						let _ = forwarded.insert(new_pid);
+					}
+					if cancelled
+						.iter()
+						.any(|&pid| is_descendant(&parents, new_pid, pid))
+					{
+						queued
+							.entry(new_pid)
+							.or_default()
+							.push_back(ProcessInputEvent::Cancel);
+					}
 					DeployOutputEvent::Spawn(pid, new_pid)
 				}
+				ProcessOutputEvent::Started(report) => {
+					let _ = started.insert(pid);
+					for reacher in reachers.remove(&pid).unwrap_or_default() {
+						if let Some((sender, _)) = processes
+							.iter()
+							.find(|(sender, _)| sender.remote_pid() == reacher)
+						{
+							queued
+								.entry(sender.remote_pid())
+								.or_default()
+								.push_back(ProcessInputEvent::Reachable(pid, true));
+						}
+					}
+					DeployOutputEvent::Started(pid, report)
+				}
+				ProcessOutputEvent::Cancel => {
+					let _ = cancelled.insert(pid);
+					for (sender, _) in &processes {
+						if is_descendant(&parents, sender.remote_pid(), pid) {
+							queued
+								.entry(sender.remote_pid())
+								.or_default()
+								.push_back(ProcessInputEvent::Cancel);
+						}
+					}
+					continue;
+				}
+				ProcessOutputEvent::Signal(pid, signal) => {
+					if let Some((sender, _)) = processes
+						.iter()
+						.find(|(sender, _)| sender.remote_pid() == pid)
+					{
+						queued
+							.entry(sender.remote_pid())
+							.or_default()
+							.push_back(ProcessInputEvent::Signal(signal));
+					}
+					continue;
+				}
+				ProcessOutputEvent::Pause(pid) => {
+					if let Some((sender, _)) = processes
+						.iter()
+						.find(|(sender, _)| sender.remote_pid() == pid)
+					{
+						queued
+							.entry(sender.remote_pid())
+							.or_default()
+							.push_back(ProcessInputEvent::Pause);
+					}
+					continue;
+				}
+				ProcessOutputEvent::Resume(pid) => {
+					if let Some((sender, _)) = processes
+						.iter()
+						.find(|(sender, _)| sender.remote_pid() == pid)
+					{
+						queued
+							.entry(sender.remote_pid())
+							.or_default()
+							.push_back(ProcessInputEvent::Resume);
+					}
+					continue;
+				}
+				ProcessOutputEvent::Input(pid, fd, input) => {
+					if let Some((sender, _)) = processes
+						.iter()
+						.find(|(sender, _)| sender.remote_pid() == pid)
+					{
+						queued
+							.entry(sender.remote_pid())
+							.or_default()
+							.push_back(ProcessInputEvent::Input(fd, input));
+					}
+					continue;
+				}
+				ProcessOutputEvent::Kill(killed, signal) => {
+					let running = if let Some((sender, _)) = processes
+						.iter()
+						.find(|(sender, _)| sender.remote_pid() == killed)
+					{
+						queued
+							.entry(sender.remote_pid())
+							.or_default()
+							.push_back(ProcessInputEvent::Signal(signal));
+						true
+					} else {
+						false
+					};
+					queued
+						.entry(pid)
+						.or_default()
+						.push_back(ProcessInputEvent::Killed(killed, running));
+					continue;
+				}
+				ProcessOutputEvent::Watch(watched) => {
+					if let Some(&exit_code_) = exited.get(&watched) {
+						queued
+							.entry(pid)
+							.or_default()
+							.push_back(ProcessInputEvent::Exited(watched, exit_code_));
+					} else {
+						watchers.entry(watched).or_default().push(pid);
+					}
+					continue;
+				}
+				ProcessOutputEvent::Reach(reached) => {
+					let started_ = started.contains(&reached);
+					if started_
+						|| !processes
+							.iter()
+							.any(|(sender, _)| sender.remote_pid() == reached)
+					{
+						queued
+							.entry(pid)
+							.or_default()
+							.push_back(ProcessInputEvent::Reachable(reached, started_));
+					} else {
+						reachers.entry(reached).or_default().push(pid);
+					}
+					continue;
+				}
+				ProcessOutputEvent::Paused(paused) => DeployOutputEvent::Paused(pid, paused),
 				ProcessOutputEvent::Output(fd, output) => {
 					// sender_.send(OutputEventInt::Output(pid, fd, output)).expect("send failed 1");
 					// trace!("output: {:?} {:?}", fd, output);
 					// print!("{}", output);
+					if forwarded.contains(&pid) {
+						if let Some((sender, _)) = processes
+							.iter()
+							.find(|(sender, _)| Some(&sender.remote_pid()) == parents.get(&pid))
+						{
+							queued
+								.entry(sender.remote_pid())
+								.or_default()
+								.push_back(ProcessInputEvent::Output(pid, fd, output.clone()));
+						}
+					}
 					DeployOutputEvent::Output(pid, fd, output)
 				}
 				ProcessOutputEvent::Exit(exit_code_) => {
 					exit_code += exit_code_;
 					let _ = processes.remove(i);
+					let _ = queued.remove(&pid);
+					let _ = forwarded.remove(&pid);
+					let _ = started.remove(&pid);
+					for reacher in reachers.remove(&pid).unwrap_or_default() {
+						if let Some((sender, _)) = processes
+							.iter()
+							.find(|(sender, _)| sender.remote_pid() == reacher)
+						{
+							queued
+								.entry(sender.remote_pid())
+								.or_default()
+								.push_back(ProcessInputEvent::Reachable(pid, false));
+						}
+					}
+					for watcher in watchers.remove(&pid).unwrap_or_default() {
+						if let Some((sender, _)) = processes
+							.iter()
+							.find(|(sender, _)| sender.remote_pid() == watcher)
+						{
+							queued
+								.entry(sender.remote_pid())
+								.or_default()
+								.push_back(ProcessInputEvent::Exited(pid, exit_code_));
+						}
+					}
+					if parents.get(&pid).map_or(false, |&parent| {
+						processes
+							.iter()
+							.any(|(sender, _)| sender.remote_pid() == parent)
+					}) {
+						let _ = exited.insert(pid, exit_code_);
+					}
+					let children = parents
+						.iter()
+						.filter(|&(_, &parent)| parent == pid)
+						.map(|(&child, _)| child)
+						.collect::<Vec<_>>();
+					for child in children {
+						let _ = exited.remove(&child);
+						match on_parent_exit[&child] {
+							OnParentExit::Kill => {
+								if let Some((sender, _)) = processes
+									.iter()
+									.find(|(sender, _)| sender.remote_pid() == child)
+								{
+									queued
+										.entry(sender.remote_pid())
+										.or_default()
+										.push_back(ProcessInputEvent::Kill);
+								}
+							}
+							OnParentExit::Detach => (),
+							OnParentExit::Reparent => {
+								let _ = parents.remove(&child);
+							}
+						}
+					}
 					DeployOutputEvent::Exit(pid, exit_code_)
 				}
 			};
+			summarizer.observe(&event);
 			match formatter {
 				Either::Left(ref mut formatter) => formatter.write(&event),
-				Either::Right(ref mut stdout) => {
-					serde_json::to_writer(&mut *stdout, &event).unwrap();
-					stdout.write_all(b"\n").unwrap()
-				}
+				Either::Right(ref mut stdout) => event.write(format, &mut *stdout).unwrap(),
 			}
 		}
+		let event = DeployOutputEvent::Summary(summarizer.finish());
+		match formatter {
+			Either::Left(ref mut formatter) => formatter.write(&event),
+			Either::Right(ref mut stdout) => event.write(format, &mut *stdout).unwrap(),
+		}
 		process::exit(exit_code.into());
 	}
 	unistd::close(bridge_process_listener).unwrap();
-	bridge_pid
+	Ok(bridge_pid)
 }
 
-fn native_process_listener() -> (Fd, Pid) {
+fn native_process_listener(ip: IpAddr) -> nix::Result<(Fd, Pid)> {
 	let process_listener = palaver_socket(
 		socket::AddressFamily::Inet,
 		socket::SockType::Stream,
 		SockFlag::SOCK_NONBLOCK,
 		socket::SockProtocol::Tcp,
-	)
-	.unwrap();
-	socket::setsockopt(process_listener, sockopt::ReuseAddr, &true).unwrap();
+	)?;
+	socket::setsockopt(process_listener, sockopt::ReuseAddr, &true)?;
 	socket::bind(
 		process_listener,
-		&socket::SockAddr::Inet(socket::InetAddr::from_std(&SocketAddr::new(LOCALHOST, 0))),
-	)
-	.unwrap();
-	socket::setsockopt(process_listener, sockopt::ReusePort, &true).unwrap();
-	let process_id =
-		if let socket::SockAddr::Inet(inet) = socket::getsockname(process_listener).unwrap() {
-			inet.to_std()
-		} else {
-			panic!()
-		};
-	assert_eq!(process_id.ip(), LOCALHOST);
+		&socket::SockAddr::Inet(socket::InetAddr::from_std(&SocketAddr::new(ip, 0))),
+	)?;
+	socket::setsockopt(process_listener, sockopt::ReusePort, &true)?;
+	let process_id = if let socket::SockAddr::Inet(inet) = socket::getsockname(process_listener)? {
+		inet.to_std()
+	} else {
+		panic!()
+	};
+	assert_eq!(process_id.ip(), ip);
 
-	(process_listener, Pid::new(LOCALHOST, process_id.port()))
+	Ok((process_listener, Pid::new(ip, process_id.port())))
 }
 
-#[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_lines, clippy::match_same_arms)]
 fn monitor_process(
-	bridge: Pid, deployed: bool,
-) -> (channel::SocketForwardee, Fd, Fd, Option<Fd>, Fd) {
+	bridge: Pid, deployed: bool, on_parent_exit: OnParentExit, rlimits: RLimits,
+) -> Result<(channel::SocketForwardee, Fd, Fd, Option<Fd>, Fd, Fd), InitError> {
 	const FORWARD_STDERR: bool = true;
 
 	let (socket_forwarder, socket_forwardee) = channel::socket_forwarder();
@@ -911,6 +2825,7 @@ fn monitor_process(
 		(None, None)
 	};
 	let (stdin_reader, stdin_writer) = unistd::pipe().unwrap();
+	let (control_reader, control_writer) = unistd::pipe().unwrap();
 
 	let (reader, writer) = unistd::pipe().unwrap(); // unistd::pipe2(fcntl::OFlag::empty())
 
@@ -923,7 +2838,9 @@ fn monitor_process(
 		signal::SigSet::empty(),
 	);
 	let old = unsafe { signal::sigaction(signal::SIGCHLD, &new).unwrap() };
-	if let palaver::process::ForkResult::Parent(child) = palaver::process::fork(false).unwrap() {
+	if let palaver::process::ForkResult::Parent(child) =
+		palaver::process::fork(false).map_err(|err| InitError::Fork(io_error(err)))?
+	{
 		unistd::close(reader).unwrap();
 		unistd::close(monitor_writer).unwrap();
 		unistd::close(stdout_writer).unwrap();
@@ -931,6 +2848,8 @@ fn monitor_process(
 			unistd::close(stderr_writer).unwrap();
 		}
 		unistd::close(stdin_reader).unwrap();
+		unistd::close(control_reader).unwrap();
+		let control_writer = unsafe { fs::File::from_raw_fd(control_writer) };
 		let (mut bridge_outbound_sender, mut bridge_outbound_receiver) =
 			futures::channel::mpsc::channel::<ProcessOutputEvent>(0);
 		let (bridge_inbound_sender, bridge_inbound_receiver) =
@@ -1062,6 +2981,40 @@ fn monitor_process(
 										assert_eq!(e, nix::Error::Sys(errno::Errno::ESRCH))
 									});
 								}
+								event @ ProcessInputEvent::Cancel => {
+									let _ = bincode::serialize_into(&control_writer, &event); // fails if the child has exited
+								}
+								event @ ProcessInputEvent::Exited(_, _) => {
+									let _ = bincode::serialize_into(&control_writer, &event); // fails if the child has exited
+								}
+								event @ ProcessInputEvent::Killed(_, _) => {
+									let _ = bincode::serialize_into(&control_writer, &event); // fails if the child has exited
+								}
+								event @ ProcessInputEvent::Reachable(_, _) => {
+									let _ = bincode::serialize_into(&control_writer, &event); // fails if the child has exited
+								}
+								event @ ProcessInputEvent::Output(_, _, _) => {
+									let _ = bincode::serialize_into(&control_writer, &event); // fails if the child has exited
+								}
+								ProcessInputEvent::Signal(signal) => {
+									child1.signal(signal::Signal::from(signal)).unwrap_or_else(
+										|e| assert_eq!(e, nix::Error::Sys(errno::Errno::ESRCH)),
+									);
+								}
+								ProcessInputEvent::Pause => match child1
+									.signal(signal::Signal::SIGSTOP)
+								{
+									Ok(()) => sender.send(ProcessOutputEvent::Paused(true)).block(),
+									Err(e) => assert_eq!(e, nix::Error::Sys(errno::Errno::ESRCH)),
+								},
+								ProcessInputEvent::Resume => match child1
+									.signal(signal::Signal::SIGCONT)
+								{
+									Ok(()) => {
+										sender.send(ProcessOutputEvent::Paused(false)).block()
+									}
+									Err(e) => assert_eq!(e, nix::Error::Sys(errno::Errno::ESRCH)),
+								},
 							}
 						}
 					}
@@ -1132,9 +3085,21 @@ fn monitor_process(
 		// thread::sleep(std::time::Duration::from_millis(100));
 		process::exit(0);
 	}
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	{
+		if on_parent_exit == OnParentExit::Kill {
+			// Die with our monitor rather than be orphaned
+			let err = unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) };
+			assert_eq!(err, 0);
+		}
+	}
+	#[cfg(not(any(target_os = "android", target_os = "linux")))]
+	let _ = on_parent_exit;
+	set_rlimits(rlimits);
 	unistd::close(monitor_reader).unwrap();
 	unistd::close(writer).unwrap();
 	unistd::close(stdin_writer).unwrap();
+	unistd::close(control_writer).unwrap();
 	if FORWARD_STDERR {
 		unistd::close(stderr_reader.unwrap()).unwrap();
 	}
@@ -1149,35 +3114,227 @@ fn monitor_process(
 	unistd::close(reader).unwrap();
 	trace!("ready");
 
-	(
+	Ok((
 		socket_forwardee,
 		monitor_writer,
 		stdout_writer,
 		stderr_writer,
 		stdin_reader,
-	)
+		control_reader,
+	))
+}
+
+/// What an fd inherited at startup is expected to be, as checked by [`check_fds()`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum FdKind {
+	Socket,
+	FileOrPipe,
+}
+
+/// Check that each of the fds a process inherits from its spawner is open and of the expected kind, panicking with a message naming the offending fd if not. This is enabled by `CONSTELLATION_CHECK_FDS=1`, and turns what would otherwise be an opaque failure deep inside `init()` into something actionable.
+fn check_fds(expected: &[(Fd, &str, FdKind)]) {
+	for &(fd, name, kind) in expected {
+		let stat = stat::fstat(fd).unwrap_or_else(|err| {
+			panic!(
+				"CONSTELLATION_CHECK_FDS: expected fd {} ({}) to be inherited, but it isn't open: {}",
+				fd, name, err
+			)
+		});
+		let type_ = stat::SFlag::from_bits_truncate(stat.st_mode) & stat::SFlag::S_IFMT;
+		let actual = if type_ == stat::SFlag::S_IFSOCK {
+			"a socket"
+		} else if type_ == stat::SFlag::S_IFIFO {
+			"a pipe"
+		} else if type_ == stat::SFlag::S_IFREG {
+			"a file"
+		} else {
+			"neither a socket, pipe nor file"
+		};
+		let (expected, matches) = match kind {
+			FdKind::Socket => ("a socket", type_ == stat::SFlag::S_IFSOCK),
+			FdKind::FileOrPipe => (
+				"a file or pipe",
+				type_ == stat::SFlag::S_IFREG || type_ == stat::SFlag::S_IFIFO,
+			),
+		};
+		if !matches {
+			panic!(
+				"CONSTELLATION_CHECK_FDS: expected fd {} ({}) to be {}, but it is {}",
+				fd, name, expected, actual
+			);
+		}
+	}
+}
+
+/// An error returned by [`try_init()`] detailing why the runtime couldn't be initialised.
+#[derive(Debug)]
+pub enum InitError {
+	/// A `CONSTELLATION_*` environment variable is malformed. The message names it and what it should be.
+	BadEnv(String),
+	/// Forking the bridge or monitor process failed.
+	Fork(io::Error),
+	/// Setting up this process's listener, or connecting to the scheduler, failed.
+	Socket(io::Error),
+	/// Reading the argument passed to this process by the process that spawned it failed.
+	Io(io::Error),
+	/// The runtime has already been initialised in this process.
+	AlreadyInitialised,
+	#[doc(hidden)]
+	__Nonexhaustive,
+}
+impl fmt::Display for InitError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::BadEnv(msg) => write!(f, "{}", msg),
+			Self::Fork(err) => write!(f, "couldn't fork: {}", err),
+			Self::Socket(err) => write!(f, "couldn't set up socket: {}", err),
+			Self::Io(err) => write!(f, "couldn't read spawn argument: {}", err),
+			Self::AlreadyInitialised => write!(f, "init() has already been called"),
+			Self::__Nonexhaustive => unreachable!(),
+		}
+	}
+}
+impl std::error::Error for InitError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Fork(err) | Self::Socket(err) | Self::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+fn io_error(err: nix::Error) -> io::Error {
+	match err {
+		nix::Error::Sys(errno) => io::Error::from_raw_os_error(errno as i32),
+		err => io::Error::new(io::ErrorKind::Other, err),
+	}
 }
 
 /// Initialise the [constellation](self) runtime. This must be called immediately inside your application's `main()` function.
 ///
 /// The `resources` argument describes memory and CPU requirements for the initial process.
-#[allow(clippy::too_many_lines)]
+///
+/// This panics if the runtime can't be initialised; use [`try_init()`] to handle that instead. Calling it again once the runtime has been initialised, for example from a test harness that re-enters `main()`, does nothing; calling it again after an earlier call failed panics with that error again.
+///
+/// If the environment variable `CONSTELLATION_CHECK_FDS=1` is set, each process checks that the fds it inherits are present and of the expected kind before using them, and panics naming the offending fd if not.
+///
+/// # Record and replay
+///
+/// To debug a distributed program that only misbehaves sometimes, set the environment variable `CONSTELLATION_RECORD` to a directory, and each process logs the order in which it receives messages across its [Receiver]s to a file in it named after its [`seed()`](seed). Rerunning with `CONSTELLATION_REPLAY` set to that directory instead re-drives the same processes with the recorded schedule: each process's receives are held up until it's their turn in its log, so that nondeterminism from the order messages arrive in is removed. The seed of the recorded run is reused, so a program that takes its randomness from [`seed()`](seed) behaves the same too. The directory must be accessible at the same path to every process, for example on a shared filesystem when running distributed.
+///
+/// Processes and receivers are identified by the order they were spawned and created in, so replay relies on the program doing the same given the same messages. Once a process has replayed its log, its receives proceed as normal. Messages through a [`channel_local()`] aren't recorded.
+///
+/// # Native clusters
+///
+/// When run natively, processes listen on `127.0.0.1` and so can only communicate with processes on the same host. As an advanced alternative to deploying to a [`constellation`](https://github.com/constellation-rs/constellation) cluster, the environment variable `CONSTELLATION_BIND` can be set to a routable IP address of this host, in which case the initial process and all processes it spawns listen on that address instead. Independently started native processes on different hosts can then create [Sender]s and [Receiver]s with each other, given each other's [Pid]s – for example by serializing them with serde into a static config file that each host reads. Spawned processes still run on the host that spawned them, and there is no scheduler or fabric to handle node failure.
 pub fn init(resources: Resources) {
+	match try_init(resources) {
+		Ok(()) => (),
+		// Only a no-op if the first call got all the way through, rather than failing partway
+		Err(InitError::AlreadyInitialised) => {
+			let init_result = SCHEDULER.lock().unwrap().init_result.clone();
+			match init_result {
+				Some(Ok(())) => (),
+				Some(Err(err)) => panic!("{}", err),
+				None => panic!("{}", InitError::AlreadyInitialised),
+			}
+		}
+		Err(err) => panic!("{}", err),
+	}
+}
+
+/// Initialise the [constellation](self) runtime, returning an error rather than panicking if it can't be. This must be called immediately inside your application's `main()` function.
+///
+/// This is otherwise the same as [`init()`], and lets the application report a misconfiguration in its own way, for example printing a message and exiting with a chosen code.
+///
+/// Only the first call in a process initialises the runtime; any later call, including from another thread and including after the first failed, returns [`InitError::AlreadyInitialised`].
+pub fn try_init(resources: Resources) -> Result<(), InitError> {
+	{
+		let mut scheduler = SCHEDULER.lock().unwrap();
+		if scheduler.initialised {
+			return Err(InitError::AlreadyInitialised);
+		}
+		scheduler.initialised = true;
+	}
+	let result = init_inner(resources);
+	if let Err(err) = &result {
+		SCHEDULER.lock().unwrap().init_result = Some(Err(err.to_string()));
+	}
+	result
+}
+
+#[allow(clippy::too_many_lines)]
+fn init_inner(resources: Resources) -> Result<(), InitError> {
+	fn var<T>(x: Option<T>, msg: &str) -> Result<T, InitError> {
+		x.ok_or_else(|| InitError::BadEnv(msg.to_owned()))
+	}
+	fn read_arg<T: DeserializeOwned>(file: &fs::File) -> Result<T, InitError> {
+		bincode::deserialize_from(&mut &*file).map_err(|err| {
+			InitError::Io(match *err {
+				bincode::ErrorKind::Io(err) => err,
+				err => io::Error::new(io::ErrorKind::InvalidData, err),
+			})
+		})
+	}
+	fn read_arg_end(file: &fs::File) -> Result<(), InitError> {
+		match (&*file).read(&mut [0]).map_err(InitError::Io)? {
+			0 => Ok(()),
+			_ => Err(InitError::Io(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"trailing data after spawn argument",
+			))),
+		}
+	}
 	assert_eq!(palaver::thread::count(), 1);
 	if valgrind::is().unwrap_or(false) {
 		let _ = unistd::close(valgrind::start_fd() - 1 - 12); // close non CLOEXEC'd fd of this binary
 	}
-	let envs = Envs::from(&env::vars_os().expect("Couldn't get envp"));
-	let version = envs
-		.version
-		.map_or(false, |x| x.expect("CONSTELLATION_VERSION must be 0 or 1"));
+	let envs = Envs::from(
+		&env::vars_os().map_err(|()| InitError::BadEnv(String::from("Couldn't get envp")))?,
+	);
+	let version = envs.version.map_or(Ok(false), |x| {
+		var(x, "CONSTELLATION_VERSION must be 0 or 1")
+	})?;
 	let recce = envs
 		.recce
-		.map_or(false, |x| x.expect("CONSTELLATION_RECCE must be 0 or 1"));
-	let format = envs.format.map_or(Format::Human, |x| {
-		x.expect("CONSTELLATION_FORMAT must be json or human")
-	});
-	let deployed = envs.deploy == Some(Some(Deploy::Fabric));
+		.map_or(Ok(false), |x| var(x, "CONSTELLATION_RECCE must be 0 or 1"))?;
+	let format = envs.format.map_or(Ok(Format::Human), |x| {
+		var(x, "CONSTELLATION_FORMAT must be json or human")
+	})?;
+	let labels = envs.labels.map_or_else(
+		|| Ok(LabelFilter::default()),
+		|x| {
+			var(
+				x,
+				"CONSTELLATION_LABELS must be a comma-separated list of labels",
+			)
+		},
+	)?;
+	let record = envs.record.map(Option::unwrap);
+	let replay = envs.replay.map(Option::unwrap);
+	let root_seed = envs.seed.map_or_else(
+		|| {
+			Ok(replay
+				.as_deref()
+				.map_or_else(rand::random, replay::recorded_seed))
+		},
+		|x| var(x, "CONSTELLATION_SEED must be a number"),
+	)?;
+	let bind = envs.bind.map_or(Ok(LOCALHOST), |x| {
+		var(x, "CONSTELLATION_BIND must be an IP address")
+	})?;
+	if bind.is_unspecified() {
+		return Err(InitError::BadEnv(format!(
+			"CONSTELLATION_BIND must be an address other processes can connect to, not {}",
+			bind
+		)));
+	}
+	let check = envs.check_fds.map_or(Ok(false), |x| {
+		var(x, "CONSTELLATION_CHECK_FDS must be 0 or 1")
+	})?;
+	let deployed = envs.deploy.map_or(Ok(false), |x| {
+		var(x, "CONSTELLATION must be fabric").map(|deploy| deploy == Deploy::Fabric)
+	})?;
 	if version {
 		assert!(!recce);
 		println!("constellation-lib {}", env!("CARGO_PKG_VERSION"));
@@ -1189,11 +3346,34 @@ pub fn init(resources: Resources) {
 		drop(file);
 		process::exit(0);
 	}
+	let spawned_resources = envs
+		.resources
+		.map(|x| {
+			var(
+				x,
+				"CONSTELLATION_RESOURCES must be JSON-serialized Resources",
+			)
+		})
+		.transpose()?;
+	if check && (deployed || spawned_resources.is_some()) {
+		check_fds(&[
+			(LISTENER_FD, "the process listener", FdKind::Socket),
+			(ARG_FD, "the spawn argument", FdKind::FileOrPipe),
+		]);
+	}
 	let (resources, argument, scheduler, our_pid) = {
 		if !deployed {
-			let (resources, spawn_arg, our_pid) = if envs.resources.is_none() {
+			let (resources, spawn_arg, our_pid) = if let Some(resources) = spawned_resources {
+				// native subprocess
+				let arg = unsafe { fs::File::from_raw_fd(ARG_FD) };
+				let spawn_arg: SpawnArg<Start> = read_arg(&arg)?;
+				let our_pid: Pid = read_arg(&arg)?;
+				read_arg_end(&arg)?;
+				(resources, spawn_arg, our_pid)
+			} else {
 				// We're in native topprocess
-				let (our_process_listener, our_pid) = native_process_listener();
+				let (our_process_listener, our_pid) = native_process_listener(bind)
+					.map_err(|err| InitError::Socket(io_error(err)))?;
 				if our_process_listener != LISTENER_FD {
 					palaver::file::move_fd(
 						our_process_listener,
@@ -1203,49 +3383,48 @@ pub fn init(resources: Resources) {
 					)
 					.unwrap();
 				}
-				let bridge = native_bridge(format, our_pid);
+				let bridge = native_bridge(format, labels, our_pid)?;
 				let spawn_arg = SpawnArg {
 					bridge,
 					spawn: None,
 				};
 				(resources, spawn_arg, our_pid)
-			} else {
-				// native subprocess
-				let arg = unsafe { fs::File::from_raw_fd(ARG_FD) };
-				let spawn_arg: SpawnArg<Start> = bincode::deserialize_from(&mut &arg)
-					.map_err(map_bincode_err)
-					.unwrap();
-				let our_pid: Pid = bincode::deserialize_from(&mut &arg)
-					.map_err(map_bincode_err)
-					.unwrap();
-				assert_eq!((&arg).read(&mut [0]).unwrap(), 0);
-				(envs.resources.unwrap().unwrap(), spawn_arg, our_pid)
 			};
 			(resources, spawn_arg, None, our_pid)
 		} else {
+			let resources = var(
+				spawned_resources,
+				"CONSTELLATION_RESOURCES must be set when CONSTELLATION is fabric",
+			)?;
 			let arg = unsafe { fs::File::from_raw_fd(ARG_FD) };
-			let spawn_arg: SpawnArg<Start> = bincode::deserialize_from(&mut &arg)
-				.map_err(map_bincode_err)
-				.unwrap();
-			let sched_arg: SchedulerArg = bincode::deserialize_from(&mut &arg)
-				.map_err(map_bincode_err)
-				.unwrap();
-			let our_pid: Pid = bincode::deserialize_from(&mut &arg)
-				.map_err(map_bincode_err)
-				.unwrap();
-			assert_eq!((&arg).read(&mut [0]).unwrap(), 0);
-			(
-				envs.resources.unwrap().unwrap(),
-				spawn_arg,
-				Some(sched_arg.scheduler),
-				our_pid,
-			)
+			let spawn_arg: SpawnArg<Start> = read_arg(&arg)?;
+			let sched_arg: SchedulerArg = read_arg(&arg)?;
+			let our_pid: Pid = read_arg(&arg)?;
+			read_arg_end(&arg)?;
+			#[cfg(feature = "distribute_binaries")]
+			BINARY.set(sched_arg.binary).unwrap();
+			(resources, spawn_arg, Some(sched_arg.scheduler), our_pid)
 		}
 	};
 
 	PID.set(our_pid).unwrap();
+	PARENT
+		.set(argument.spawn.as_ref().map(|spawn| spawn.parent))
+		.unwrap();
+	SEED.set(
+		argument
+			.spawn
+			.as_ref()
+			.map_or(root_seed, |spawn| spawn.seed),
+	)
+	.unwrap();
+	replay::init(
+		record.as_deref(),
+		replay.as_deref(),
+		argument.spawn.is_none(),
+	);
 	DEPLOYED.set(deployed).unwrap();
-	RESOURCES.set(resources).unwrap();
+	RESOURCES.set(RwLock::new(resources)).unwrap();
 	BRIDGE.set(argument.bridge).unwrap();
 
 	trace!(
@@ -1260,9 +3439,27 @@ pub fn init(resources: Resources) {
 		palaver::file::move_fd(fd, SCHEDULER_FD, Some(fcntl::FdFlag::empty()), true).unwrap();
 	}
 	palaver::file::copy_fd(SCHEDULER_FD, MONITOR_FD, Some(fcntl::FdFlag::empty()), true).unwrap();
+	palaver::file::copy_fd(SCHEDULER_FD, CONTROL_FD, Some(fcntl::FdFlag::empty()), true).unwrap();
 
-	let (socket_forwardee, monitor_writer, stdout_writer, stderr_writer, stdin_reader) =
-		monitor_process(argument.bridge, deployed);
+	let (
+		socket_forwardee,
+		monitor_writer,
+		stdout_writer,
+		stderr_writer,
+		stdin_reader,
+		control_reader,
+	) = monitor_process(
+		argument.bridge,
+		deployed,
+		argument
+			.spawn
+			.as_ref()
+			.map_or(OnParentExit::Detach, |spawn| spawn.on_parent_exit),
+		argument
+			.spawn
+			.as_ref()
+			.map_or_else(RLimits::default, |spawn| spawn.rlimits),
+	)?;
 	assert_ne!(monitor_writer, MONITOR_FD);
 	palaver::file::move_fd(
 		monitor_writer,
@@ -1271,6 +3468,14 @@ pub fn init(resources: Resources) {
 		false,
 	)
 	.unwrap();
+	assert_ne!(control_reader, CONTROL_FD);
+	palaver::file::move_fd(
+		control_reader,
+		CONTROL_FD,
+		Some(fcntl::FdFlag::empty()),
+		false,
+	)
+	.unwrap();
 	palaver::file::move_fd(
 		stdout_writer,
 		libc::STDOUT_FILENO,
@@ -1296,8 +3501,10 @@ pub fn init(resources: Resources) {
 	.unwrap();
 
 	if deployed {
-		let scheduler = TcpStream::connect(scheduler.unwrap().addr())
-			.unwrap()
+		let addr = scheduler.unwrap().addr();
+		SCHEDULER.lock().unwrap().addr = Some(addr);
+		let scheduler = TcpStream::connect(addr)
+			.map_err(InitError::Socket)?
 			.into_raw_fd();
 		assert_ne!(scheduler, SCHEDULER_FD);
 		palaver::file::move_fd(scheduler, SCHEDULER_FD, Some(fcntl::FdFlag::empty()), false)
@@ -1318,9 +3525,26 @@ pub fn init(resources: Resources) {
 	);
 	*HANDLE.try_write().unwrap() = Some(handle);
 
+	if let Some(spawn) = &argument.spawn {
+		let reactor = REACTOR.read().unwrap();
+		for &peer in spawn.peers.iter().filter(|&&peer| peer != pid()) {
+			reactor.as_ref().unwrap().connect(peer.addr());
+		}
+	}
+
 	let err = unsafe { libc::atexit(at_exit) };
 	assert_eq!(err, 0);
 
+	monitor_event(&ProcessOutputEvent::Started(StartupReport {
+		parent: *PARENT.get().unwrap(),
+		resources: *RESOURCES.get().unwrap().read().unwrap(),
+		label: argument
+			.spawn
+			.as_ref()
+			.and_then(|spawn| spawn.label.clone()),
+		version: env!("CARGO_PKG_VERSION").to_owned(),
+	}));
+
 	trace!(
 		"PROCESS {}:{}: done setup; pid: {}; bridge: {:?}",
 		unistd::getppid(),
@@ -1328,11 +3552,59 @@ pub fn init(resources: Resources) {
 		pid(),
 		argument.bridge
 	);
-
-	if let Some(SpawnArgSub { parent, f }) = argument.spawn {
+	SCHEDULER.lock().unwrap().init_result = Some(Ok(()));
+
+	if let Some(SpawnArgSub {
+		parent,
+		child_init,
+		f,
+		..
+	}) = argument.spawn
+	{
+		if let Some(child_init) = child_init {
+			let init: Box<dyn serde_traitobject::FnOnce<(Pid,), Output = ()>> =
+				bincode::deserialize(&child_init).unwrap();
+			*CHILD_INIT.write().unwrap() = Some(child_init);
+			init(parent);
+		}
 		f.into_inner().unwrap()(parent);
 		process::exit(0);
 	}
+	Ok(())
+}
+
+/// Apply the limits given in [`SpawnOptions::rlimits`] to this process.
+fn set_rlimits(rlimits: RLimits) {
+	let RLimits {
+		open_files,
+		core_size,
+		cpu_time,
+		threads,
+	} = rlimits;
+	let cpu_time =
+		cpu_time.map(|cpu_time| cpu_time.as_secs() + u64::from(cpu_time.subsec_nanos() > 0));
+	let mut limits = vec![
+		(libc::RLIMIT_NOFILE, open_files),
+		(libc::RLIMIT_CORE, core_size),
+		(libc::RLIMIT_CPU, cpu_time),
+	];
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	limits.push((libc::RLIMIT_NPROC, threads));
+	#[cfg(not(any(target_os = "android", target_os = "linux")))]
+	let _ = threads;
+	for (resource, limit) in limits {
+		if let Some(limit) = limit {
+			let mut rlimit = MaybeUninit::<libc::rlimit>::uninit();
+			let err = unsafe { libc::getrlimit(resource, rlimit.as_mut_ptr()) };
+			assert_eq!(err, 0, "getrlimit failed: {}", io::Error::last_os_error());
+			let mut rlimit = unsafe { rlimit.assume_init() };
+			// Unprivileged processes can't raise the hard limit, so cap at it
+			rlimit.rlim_cur = limit.min(rlimit.rlim_max);
+			rlimit.rlim_max = rlimit.rlim_cur;
+			let err = unsafe { libc::setrlimit(resource, &rlimit) };
+			assert_eq!(err, 0, "setrlimit failed: {}", io::Error::last_os_error());
+		}
+	}
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////
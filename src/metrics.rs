@@ -0,0 +1,53 @@
+use serde_json::json;
+use std::{
+	io::{self, Write}, os::unix::net::UnixListener, path::Path, thread
+};
+
+use super::{channel_stats, forwarded_connections, pid, resources};
+use constellation_internal::abort_on_unwind;
+
+/// Serve a snapshot of this process's metrics to each connection made to a Unix domain socket at `path`, for a sidecar to scrape.
+///
+/// Each connection is sent a single line of JSON and then closed. The line has the fields `pid`, this process's [Pid](crate::Pid) as displayed; `resources`, as returned by [`resources()`]; `channels`, an array of the [`channel_stats()`] with durations in seconds; and `forwarded`, the processes returned by [`forwarded_connections()`].
+///
+/// This fails if `path` can't be bound, for example if it already exists. Connections are served on a background thread, for the life of the process.
+pub fn serve_metrics<P: AsRef<Path>>(path: P) -> io::Result<()> {
+	let _ = pid();
+	let listener = UnixListener::bind(path)?;
+	let _ = thread::Builder::new()
+		.name(String::from("metrics"))
+		.spawn(abort_on_unwind(move || {
+			for mut stream in listener.incoming().flatten() {
+				let _ = stream.write_all(snapshot().as_bytes()); // fails if the scraper hung up
+			}
+		}))?;
+	Ok(())
+}
+
+fn snapshot() -> String {
+	let channels = channel_stats()
+		.into_iter()
+		.map(|stats| {
+			json!({
+				"remote": stats.remote.to_string(),
+				"send_blocked": stats.send_blocked.as_secs_f64(),
+				"recv_blocked": stats.recv_blocked.as_secs_f64(),
+				"serialize": stats.serialize.as_secs_f64(),
+				"deserialize": stats.deserialize.as_secs_f64(),
+			})
+		})
+		.collect::<Vec<_>>();
+	let forwarded = forwarded_connections()
+		.into_iter()
+		.map(|remote| remote.to_string())
+		.collect::<Vec<_>>();
+	let mut line = json!({
+		"pid": pid().to_string(),
+		"resources": resources(),
+		"channels": channels,
+		"forwarded": forwarded,
+	})
+	.to_string();
+	line.push('\n');
+	line
+}
@@ -0,0 +1,169 @@
+use futures::future::{FutureExt, LocalBoxFuture};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	any::Any, error::Error, fmt, future::Future, panic::{self, AssertUnwindSafe}, pin::Pin, task::{Context, Poll}
+};
+
+use super::{spawn, ChannelError, FutureExt1, Pid, Receiver, Resources, Sender, SpawnError};
+
+/// A future that resolves to the value returned by a process spawned with [`oneshot()`](oneshot).
+///
+/// It holds the [Receiver] from the process until the value has been received, so creating another `Receiver` from it in the meantime panics.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Oneshot<R> {
+	pid: Pid,
+	recv: LocalBoxFuture<'static, Result<R, ChannelError>>,
+}
+impl<R> Oneshot<R> {
+	/// Get the pid of the process that will send the value.
+	pub fn remote_pid(&self) -> Pid {
+		self.pid
+	}
+}
+impl<R> Future for Oneshot<R> {
+	type Output = Result<R, ChannelError>;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		self.recv.as_mut().poll(cx)
+	}
+}
+impl<R> fmt::Debug for Oneshot<R> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Oneshot").field("pid", &self.pid).finish()
+	}
+}
+
+/// Spawn a new process that runs `start` and sends its return value back.
+///
+/// This is for the common case of a process that computes a single result, without setting up a [Sender] and [Receiver] by hand. On success a [`Oneshot<R>`](Oneshot) is returned, which resolves to the value once it's received, or a [`ChannelError`] if the process exits without sending it. The channel is torn down once the value has been delivered.
+///
+/// This is an async fn.
+pub async fn oneshot<
+	R: Serialize + DeserializeOwned + 'static,
+	F: FnOnce(Pid) -> R + Serialize + DeserializeOwned,
+>(
+	resources: Resources, start: F,
+) -> Result<Oneshot<R>, SpawnError> {
+	let start: Vec<u8> = bincode::serialize(&start).unwrap();
+	let pid = spawn(
+		resources,
+		serde_closure::FnOnce!(move |parent| {
+			let start: Vec<u8> = start;
+			let start: F = bincode::deserialize(&start).unwrap();
+			let r = start(parent);
+			Sender::<R>::new(parent).send(r).block();
+		}),
+	)
+	.await?;
+	let receiver = Receiver::<R>::new(pid);
+	let recv = async move { receiver.recv().await }.boxed_local();
+	Ok(Oneshot { pid, recv })
+}
+
+/// The ways a process spawned with [`oneshot_result()`](oneshot_result) can fail to produce its value.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum SpawnedError<E> {
+	/// The process returned `Err` with this error.
+	Returned(E),
+	/// The process panicked, with this message.
+	Panicked(String),
+	/// The process exited without sending its result, or the channel to it failed.
+	Channel(ChannelError),
+}
+impl<E: fmt::Display> fmt::Display for SpawnedError<E> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Returned(err) => err.fmt(f),
+			Self::Panicked(message) => write!(f, "spawned process panicked: {}", message),
+			Self::Channel(err) => err.fmt(f),
+		}
+	}
+}
+impl<E: Error + 'static> Error for SpawnedError<E> {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Returned(err) => Some(err),
+			Self::Panicked(_) => None,
+			Self::Channel(err) => Some(err),
+		}
+	}
+}
+
+/// A future that resolves to the result of a process spawned with [`oneshot_result()`](oneshot_result).
+///
+/// Like [`Oneshot`], it holds the [Receiver] from the process until the result has been received.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct OneshotResult<T, E> {
+	pid: Pid,
+	recv: LocalBoxFuture<'static, Result<T, SpawnedError<E>>>,
+}
+impl<T, E> OneshotResult<T, E> {
+	/// Get the pid of the process that will send the result.
+	pub fn remote_pid(&self) -> Pid {
+		self.pid
+	}
+}
+impl<T, E> Future for OneshotResult<T, E> {
+	type Output = Result<T, SpawnedError<E>>;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		self.recv.as_mut().poll(cx)
+	}
+}
+impl<T, E> fmt::Debug for OneshotResult<T, E> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("OneshotResult")
+			.field("pid", &self.pid)
+			.finish()
+	}
+}
+
+/// Spawn a new process that runs the fallible `start` and sends its result back, with the error type preserved.
+///
+/// This is [`oneshot()`](oneshot) for computations that return a `Result`, so that they compose with `?` rather than failing with an opaque exit status. The returned [`OneshotResult`] resolves to `Ok` with the value, or to a [`SpawnedError`]: [`Returned`](SpawnedError::Returned) with the process's error, [`Panicked`](SpawnedError::Panicked) if it panicked, or [`Channel`](SpawnedError::Channel) if it exited without sending its result.
+///
+/// This is an async fn.
+pub async fn oneshot_result<
+	T: Serialize + DeserializeOwned + 'static,
+	E: Serialize + DeserializeOwned + 'static,
+	F: FnOnce(Pid) -> Result<T, E> + Serialize + DeserializeOwned,
+>(
+	resources: Resources, start: F,
+) -> Result<OneshotResult<T, E>, SpawnError> {
+	let start: Vec<u8> = bincode::serialize(&start).unwrap();
+	let pid = spawn(
+		resources,
+		serde_closure::FnOnce!(move |parent| {
+			let start: Vec<u8> = start;
+			let start: F = bincode::deserialize(&start).unwrap();
+			let r = match panic::catch_unwind(AssertUnwindSafe(|| start(parent))) {
+				Ok(Ok(t)) => Ok(t),
+				Ok(Err(err)) => Err(SpawnedError::Returned(err)),
+				Err(payload) => Err(SpawnedError::Panicked(panic_message(&*payload))),
+			};
+			Sender::<Result<T, SpawnedError<E>>>::new(parent)
+				.send(r)
+				.block();
+		}),
+	)
+	.await?;
+	let receiver = Receiver::<Result<T, SpawnedError<E>>>::new(pid);
+	let recv = async move {
+		receiver
+			.recv()
+			.await
+			.unwrap_or_else(|err| Err(SpawnedError::Channel(err)))
+	}
+	.boxed_local();
+	Ok(OneshotResult { pid, recv })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+	if let Some(message) = payload.downcast_ref::<&str>() {
+		String::from(*message)
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message.clone()
+	} else {
+		String::from("Box<Any>")
+	}
+}
@@ -0,0 +1,182 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	fmt, io::{Read, Write}, net::{SocketAddr, TcpListener, TcpStream}, sync::{Arc, Mutex}, thread, time::Duration
+};
+
+use super::{pid, send_signal, spawn, FutureExt1, Pid, Resources, Signal, SpawnError};
+use constellation_internal::{abort_on_unwind, PidInternal};
+
+const READY: u8 = 0;
+const FAILED: u8 = 1;
+
+/// A check run periodically inside a process spawned with [`spawn_with_probes()`](spawn_with_probes) to determine whether it's ready or alive.
+///
+/// By default `check` is run every second, and the probe fails after 3 consecutive failed checks.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Probe<F> {
+	check: F,
+	period: Duration,
+	failure_threshold: usize,
+}
+impl<F: Fn() -> bool> Probe<F> {
+	/// Create a probe that passes when `check` returns `true`.
+	pub fn new(check: F) -> Self {
+		Self {
+			check,
+			period: Duration::from_secs(1),
+			failure_threshold: 3,
+		}
+	}
+
+	/// Run the check every `period`.
+	#[must_use]
+	pub fn period(self, period: Duration) -> Self {
+		Self { period, ..self }
+	}
+
+	/// Fail the probe after `failure_threshold` consecutive failed checks.
+	#[must_use]
+	pub fn failure_threshold(self, failure_threshold: usize) -> Self {
+		assert_ne!(failure_threshold, 0, "failure_threshold must be at least 1");
+		Self {
+			failure_threshold,
+			..self
+		}
+	}
+
+	/// Run until the check passes, returning `false` if it fails too many times first.
+	fn ready(&self) -> bool {
+		let mut failures = 0;
+		while !(self.check)() {
+			failures += 1;
+			if failures == self.failure_threshold {
+				return false;
+			}
+			thread::sleep(self.period);
+		}
+		true
+	}
+
+	/// Run until the check fails too many times in a row.
+	fn alive(&self) {
+		let mut failures = 0;
+		while failures < self.failure_threshold {
+			thread::sleep(self.period);
+			failures = if (self.check)() { 0 } else { failures + 1 };
+		}
+	}
+}
+impl<F> fmt::Debug for Probe<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Probe")
+			.field("period", &self.period)
+			.field("failure_threshold", &self.failure_threshold)
+			.finish()
+	}
+}
+
+/// A process spawned with [`spawn_with_probes()`](spawn_with_probes), which is restarted if its probes fail.
+#[derive(Clone, Debug)]
+pub struct Probed(Arc<Mutex<(Pid, usize)>>);
+impl Probed {
+	/// Get the [Pid] of the process. This changes when it's restarted.
+	pub fn pid(&self) -> Pid {
+		self.0.lock().unwrap().0
+	}
+
+	/// Get the number of times the process has been restarted.
+	pub fn restarts(&self) -> usize {
+		self.0.lock().unwrap().1
+	}
+}
+
+/// Spawn a new process with readiness and liveness probes, restarting it if they fail.
+///
+/// This is like [`spawn()`](spawn), but `readiness` and `liveness` are run in a thread in the new process alongside `start`. This returns once `readiness` has passed, so the caller doesn't use the process before it's ready. `liveness` then runs for as long as the process does.
+///
+/// If either probe fails, the process is killed and a new one spawned in its place, again waiting for it to be ready. This happens in the background once the process is first ready; [`Probed::pid()`] gives the current process. A process that exits without a probe failing isn't restarted, and if the first process exits before becoming ready this returns `Unknown`.
+///
+/// This is an async fn.
+pub async fn spawn_with_probes<
+	T: FnOnce(Pid) + Serialize + DeserializeOwned,
+	R: Fn() -> bool + Serialize + DeserializeOwned + Send + 'static,
+	L: Fn() -> bool + Serialize + DeserializeOwned + Send + 'static,
+>(
+	resources: Resources, readiness: Probe<R>, liveness: Probe<L>, start: T,
+) -> Result<Probed, SpawnError> {
+	let listener = TcpListener::bind(SocketAddr::new(pid().addr().ip(), 0)).unwrap();
+	let addr = listener.local_addr().unwrap();
+	let arg: Vec<u8> = bincode::serialize(&(readiness, liveness, start)).unwrap();
+	let spawn_probed = move || -> Result<(Pid, Option<TcpStream>), SpawnError> {
+		let arg = arg.clone();
+		let pid = spawn(
+			resources,
+			serde_closure::FnOnce!(move |parent| {
+				let arg: Vec<u8> = arg;
+				let (readiness, liveness, start): (Probe<R>, Probe<L>, T) =
+					bincode::deserialize(&arg).unwrap();
+				let mut stream = TcpStream::connect(addr).unwrap();
+				let _ = thread::Builder::new()
+					.name(String::from("probe"))
+					.spawn(abort_on_unwind(move || {
+						if readiness.ready() {
+							if stream.write_all(&[READY]).is_err() {
+								return;
+							}
+							liveness.alive();
+						}
+						let _ = stream.write_all(&[FAILED]);
+					}))
+					.unwrap();
+				start(parent)
+			}),
+		)
+		.block()?;
+		// Wait for the new process to be ready, killing it if it's not
+		let (mut stream, _) = listener.accept().unwrap();
+		let mut state = [FAILED];
+		stream
+			.read_exact(&mut state)
+			.map_err(|_| SpawnError::Unknown)?;
+		if state[0] == READY {
+			Ok((pid, Some(stream)))
+		} else {
+			send_signal(pid, Signal::SIGKILL);
+			Ok((pid, None))
+		}
+	};
+	let (mut pid, mut stream) = spawn_probed()?;
+	let mut restarts = 0;
+	while stream.is_none() {
+		let spawned = spawn_probed()?;
+		pid = spawned.0;
+		stream = spawned.1;
+		restarts += 1;
+	}
+	let probed = Probed(Arc::new(Mutex::new((pid, restarts))));
+	let probed1 = probed.clone();
+	let _ = thread::Builder::new()
+		.name(String::from("probe-supervisor"))
+		.spawn(abort_on_unwind(move || {
+			let mut stream = stream;
+			while let Some(mut stream_) = stream.take() {
+				let mut state = [READY];
+				if stream_.read_exact(&mut state).is_err() {
+					break; // the process exited without a probe failing
+				}
+				send_signal(probed1.pid(), Signal::SIGKILL);
+				while stream.is_none() {
+					if let Ok((pid, stream_)) = spawn_probed() {
+						let mut probed = probed1.0.lock().unwrap();
+						probed.0 = pid;
+						probed.1 += 1;
+						stream = stream_;
+					} else {
+						break;
+					}
+				}
+			}
+		}))
+		.unwrap();
+	Ok(probed)
+}
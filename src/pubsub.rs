@@ -0,0 +1,237 @@
+use futures::channel::oneshot;
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	collections::{HashMap, HashSet}, sync::{mpsc, Arc, Mutex}, thread, time::Duration
+};
+
+use super::{
+	channel_local, channel_stats, spawn_with, ChannelError, FutureExt1, OnParentExit, Pid, Receiver, Resources, Sender, SpawnError, SpawnOptions
+};
+use constellation_internal::abort_on_unwind;
+
+/// How often the broker checks for processes that have newly connected to it.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(10);
+
+static CLIENTS: Lazy<Mutex<HashMap<Pid, Arc<Client>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize, Deserialize)]
+enum Request {
+	Subscribe(String),
+	Publish(String, Vec<u8>),
+}
+
+#[derive(Serialize, Deserialize)]
+enum Delivery {
+	Subscribed(String),
+	Published(String, Vec<u8>),
+}
+
+/// A broker process that relays messages published to named topics to the processes subscribed to them.
+///
+/// Publishers send to a topic without knowing who, if anyone, is subscribed, and subscribers receive from a topic without knowing who publishes to it, so event-driven jobs needn't wire up a channel between every pair of processes. A `Broker` is a handle to the broker process, and can be passed to the processes that use it.
+///
+/// The broker relays messages one at a time, so a subscriber that isn't receiving holds up delivery to the others.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct Broker(Pid);
+impl Broker {
+	/// Spawn a broker process with `resources`. It's killed when this process exits.
+	///
+	/// This is an async fn.
+	pub async fn spawn(resources: Resources) -> Result<Self, SpawnError> {
+		let options = SpawnOptions {
+			on_parent_exit: OnParentExit::Kill,
+			label: Some(String::from("broker")),
+			..SpawnOptions::default()
+		};
+		spawn_with(
+			resources,
+			options,
+			serde_closure::FnOnce!(|_parent| run_broker()),
+		)
+		.await
+		.map(Self)
+	}
+
+	/// Get the pid of the broker process.
+	pub fn pid(&self) -> Pid {
+		self.0
+	}
+
+	/// Publish `msg` to `topic`, to be delivered to each process subscribed to it.
+	///
+	/// Messages published by one process to one topic are delivered in the order they were published. They're delivered to processes whose subscription the broker has processed by the time it relays them.
+	///
+	/// This is an async fn.
+	pub async fn publish<T: Serialize>(&self, topic: &str, msg: T) {
+		let payload = bincode::serialize(&msg).unwrap();
+		client(self.0)
+			.sender
+			.send(Request::Publish(topic.to_owned(), payload))
+			.await;
+	}
+
+	/// Subscribe to `topic`, returning a [Receiver] of the messages then published to it.
+	///
+	/// This completes once the broker has processed the subscription, so messages published after that are received. Messages that don't deserialize as `T` are skipped. Dropping the `Receiver` stops messages being delivered to it.
+	///
+	/// This is an async fn.
+	pub async fn subscribe<T: Serialize + DeserializeOwned + Send + 'static>(
+		&self, topic: &str,
+	) -> Receiver<T> {
+		let client = client(self.0);
+		let (sender, receiver) = channel_local::<T>();
+		let (subscribed, subscribed_wait) = oneshot::channel();
+		{
+			let mut subscriptions = client.subscriptions.lock().unwrap();
+			if !subscriptions.receiving {
+				subscriptions.receiving = true;
+				let (broker, subscriptions) = (self.0, client.subscriptions.clone());
+				let _ = thread::Builder::new()
+					.name(String::from("pubsub"))
+					.spawn(abort_on_unwind(move || demux(broker, &subscriptions)))
+					.unwrap();
+			}
+			subscriptions
+				.topics
+				.entry(topic.to_owned())
+				.or_default()
+				.push(Subscription {
+					deliver: Box::new(move |payload| {
+						if let Ok(msg) = bincode::deserialize(payload) {
+							sender.send(msg).block();
+						}
+					}),
+					subscribed: Some(subscribed),
+				});
+		}
+		client
+			.sender
+			.send(Request::Subscribe(topic.to_owned()))
+			.await;
+		subscribed_wait.await.unwrap();
+		receiver
+	}
+}
+
+/// This process's connection to a broker, shared by all its publishes and subscriptions.
+struct Client {
+	sender: Sender<Request>,
+	subscriptions: Arc<Mutex<Subscriptions>>,
+}
+
+struct Subscriptions {
+	/// Whether the thread receiving deliveries from the broker has been started.
+	receiving: bool,
+	topics: HashMap<String, Vec<Subscription>>,
+}
+
+struct Subscription {
+	deliver: Box<dyn Fn(&[u8]) + Send>,
+	/// Notified once the broker has processed the subscription.
+	subscribed: Option<oneshot::Sender<()>>,
+}
+
+fn client(broker: Pid) -> Arc<Client> {
+	CLIENTS
+		.lock()
+		.unwrap()
+		.entry(broker)
+		.or_insert_with(|| {
+			Arc::new(Client {
+				sender: Sender::new(broker),
+				subscriptions: Arc::new(Mutex::new(Subscriptions {
+					receiving: false,
+					topics: HashMap::new(),
+				})),
+			})
+		})
+		.clone()
+}
+
+/// Receive deliveries from `broker`, handing each to the subscriptions to its topic.
+fn demux(broker: Pid, subscriptions: &Mutex<Subscriptions>) {
+	let receiver = Receiver::<Delivery>::new(broker);
+	while let Ok(delivery) = receiver.recv().block() {
+		let mut subscriptions = subscriptions.lock().unwrap();
+		match delivery {
+			Delivery::Subscribed(topic) => {
+				// The broker processes subscriptions in the order they were made
+				let subscription = subscriptions
+					.topics
+					.get_mut(&topic)
+					.and_then(|topic| topic.iter_mut().find(|sub| sub.subscribed.is_some()))
+					.unwrap();
+				let _ = subscription.subscribed.take().unwrap().send(());
+			}
+			Delivery::Published(topic, payload) => {
+				for subscription in subscriptions.topics.get(&topic).into_iter().flatten() {
+					if subscription.subscribed.is_none() {
+						(subscription.deliver)(&payload);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// The broker process: accept connections from publishers and subscribers, and relay published messages.
+fn run_broker() {
+	let (events_sender, events) = mpsc::channel::<(Pid, Result<Request, ChannelError>)>();
+	let mut accepted = HashSet::new();
+	let mut topics: HashMap<String, Vec<Pid>> = HashMap::new();
+	let mut subscribers: HashMap<Pid, Sender<Delivery>> = HashMap::new();
+	loop {
+		// Channels have no way to accept an unknown process, so look for new connections
+		for remote in channel_stats().into_iter().map(|stats| stats.remote) {
+			if accepted.insert(remote) {
+				let events_sender = events_sender.clone();
+				let _ = thread::Builder::new()
+					.name(String::from("pubsub"))
+					.spawn(abort_on_unwind(move || {
+						let receiver = Receiver::<Request>::new(remote);
+						loop {
+							let request = receiver.recv().block();
+							let done = request.is_err();
+							events_sender.send((remote, request)).unwrap();
+							if done {
+								break;
+							}
+						}
+					}))
+					.unwrap();
+			}
+		}
+		let (remote, request) = match events.recv_timeout(ACCEPT_INTERVAL) {
+			Ok(event) => event,
+			Err(mpsc::RecvTimeoutError::Timeout) => continue,
+			Err(mpsc::RecvTimeoutError::Disconnected) => unreachable!(),
+		};
+		match request {
+			Ok(Request::Subscribe(topic)) => {
+				let subscribers_ = topics.entry(topic.clone()).or_default();
+				if !subscribers_.contains(&remote) {
+					subscribers_.push(remote);
+				}
+				subscribers
+					.entry(remote)
+					.or_insert_with(|| Sender::new(remote))
+					.send(Delivery::Subscribed(topic))
+					.block();
+			}
+			Ok(Request::Publish(topic, payload)) => {
+				for subscriber in topics.get(&topic).into_iter().flatten() {
+					subscribers[subscriber]
+						.send(Delivery::Published(topic.clone(), payload.clone()))
+						.block();
+				}
+			}
+			Err(_) => {
+				for subscribers_ in topics.values_mut() {
+					subscribers_.retain(|&subscriber| subscriber != remote);
+				}
+				let _ = subscribers.remove(&remote);
+			}
+		}
+	}
+}
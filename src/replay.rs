@@ -0,0 +1,172 @@
+use once_cell::sync::OnceCell;
+use std::{
+	collections::HashMap, fs, io::{BufRead, BufReader, Write}, path::{Path, PathBuf}, sync::Mutex, task::{Context, Poll, Waker}
+};
+
+use super::{seed, Pid};
+
+static REPLAY: OnceCell<Replay> = OnceCell::new();
+
+struct Replay {
+	mode: Mode,
+	state: Mutex<State>,
+}
+
+enum Mode {
+	/// Append the index of the receiver of each message received to this process's log.
+	Record(fs::File),
+	/// Hold up each receive until it's the next one in this process's log.
+	Replay(Vec<usize>),
+}
+
+struct State {
+	/// Receivers are identified by the order they were first created in, which unlike their remote's [Pid] is the same from run to run.
+	receivers: HashMap<Pid, usize>,
+	/// How many messages have been received.
+	received: usize,
+	/// Receives waiting for their turn.
+	waiting: Vec<Waker>,
+}
+
+fn log_path(dir: &Path) -> PathBuf {
+	dir.join(format!("{:016x}.log", seed()))
+}
+
+/// Get the seed of the initial process of the run recorded in `dir`.
+pub(crate) fn recorded_seed(dir: &Path) -> u64 {
+	fs::read_to_string(dir.join("seed"))
+		.ok()
+		.and_then(|seed| seed.trim().parse().ok())
+		.unwrap_or_else(|| {
+			panic!(
+				"CONSTELLATION_REPLAY: couldn't read the recorded seed from {}",
+				dir.join("seed").display()
+			)
+		})
+}
+
+/// Start recording to, or replaying from, this process's log in `dir`. This must be called once [`seed()`](seed) is set.
+pub(crate) fn init(record: Option<&Path>, replay: Option<&Path>, initial: bool) {
+	let mode = match (record, replay) {
+		(None, None) => return,
+		(Some(dir), None) => {
+			fs::create_dir_all(dir).unwrap_or_else(|e| {
+				panic!(
+					"CONSTELLATION_RECORD: couldn't create {}: {}",
+					dir.display(),
+					e
+				)
+			});
+			if initial {
+				fs::write(dir.join("seed"), seed().to_string()).unwrap_or_else(|e| {
+					panic!("CONSTELLATION_RECORD: couldn't write the seed: {}", e)
+				});
+			}
+			Mode::Record(
+				fs::File::create(log_path(dir)).unwrap_or_else(|e| {
+					panic!("CONSTELLATION_RECORD: couldn't create the log: {}", e)
+				}),
+			)
+		}
+		(None, Some(dir)) => {
+			let log = fs::File::open(log_path(dir)).unwrap_or_else(|e| {
+				panic!(
+					"CONSTELLATION_REPLAY: couldn't open {}: {}. Was the run recorded with the same processes spawned in the same order?",
+					log_path(dir).display(),
+					e
+				)
+			});
+			Mode::Replay(
+				BufReader::new(log)
+					.lines()
+					.map(|line| {
+						line.ok()
+							.and_then(|line| line.parse().ok())
+							.unwrap_or_else(|| {
+								panic!(
+									"CONSTELLATION_REPLAY: {} is corrupt",
+									log_path(dir).display()
+								)
+							})
+					})
+					.collect(),
+			)
+		}
+		(Some(_), Some(_)) => {
+			panic!("CONSTELLATION_RECORD and CONSTELLATION_REPLAY can't both be set")
+		}
+	};
+	let replay = Replay {
+		mode,
+		state: Mutex::new(State {
+			receivers: HashMap::new(),
+			received: 0,
+			waiting: Vec::new(),
+		}),
+	};
+	assert!(REPLAY.set(replay).is_ok());
+}
+
+/// Note that a [Receiver](super::Receiver) from `remote` has been created.
+pub(crate) fn register(remote: Pid) {
+	if let Some(replay) = REPLAY.get() {
+		let mut state = replay.state.lock().unwrap();
+		let index = state.receivers.len();
+		let _ = state.receivers.entry(remote).or_insert(index);
+	}
+}
+
+/// Whether it's the turn of the receiver from `remote` to receive. If it isn't, and `cx` is given, it's woken when the turn changes.
+///
+/// Once the log has been replayed, or if the receiver wasn't created with [`register()`], it's always its turn.
+pub(crate) fn is_turn(remote: Pid, cx: Option<&mut Context>) -> bool {
+	let replay = match REPLAY.get() {
+		Some(replay) => replay,
+		None => return true,
+	};
+	let log = match &replay.mode {
+		Mode::Record(_) => return true,
+		Mode::Replay(log) => log,
+	};
+	let mut state = replay.state.lock().unwrap();
+	let turn = match (state.receivers.get(&remote), log.get(state.received)) {
+		(Some(index), Some(next)) => index == next,
+		_ => true,
+	};
+	if let (false, Some(cx)) = (turn, cx) {
+		state.waiting.push(cx.waker().clone());
+	}
+	turn
+}
+
+/// Wait for the turn of the receiver from `remote`.
+pub(crate) async fn turn(remote: Pid) {
+	futures::future::poll_fn(|cx| {
+		if is_turn(remote, Some(cx)) {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	})
+	.await
+}
+
+/// Note that the receiver from `remote` has received a message.
+pub(crate) fn received(remote: Pid) {
+	if let Some(replay) = REPLAY.get() {
+		let mut state = replay.state.lock().unwrap();
+		let index = match state.receivers.get(&remote) {
+			Some(&index) => index,
+			None => return,
+		};
+		state.received += 1;
+		match &replay.mode {
+			Mode::Record(log) => writeln!(&*log, "{}", index).unwrap(),
+			Mode::Replay(_) => {
+				for waker in state.waiting.drain(..) {
+					waker.wake();
+				}
+			}
+		}
+	}
+}
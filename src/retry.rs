@@ -0,0 +1,50 @@
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+
+use super::{idle::Delay, try_spawn, Pid, Resources, TrySpawnError};
+
+/// How [`spawn_with_retry()`] retries a spawn that failed for lack of capacity.
+///
+/// The default policy makes up to 5 attempts, waiting around 100ms after the first, doubling after each one thereafter.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct RetryPolicy {
+	/// The most attempts to make, including the first. This must be at least 1.
+	pub max_attempts: usize,
+	/// How long to wait after the first failed attempt. It doubles after each subsequent one, and is jittered by up to half so that processes retrying at once don't all do so in lockstep.
+	pub backoff: Duration,
+}
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: 5,
+			backoff: Duration::from_millis(100),
+		}
+	}
+}
+
+/// Spawn a new process as with [`try_spawn()`], retrying according to `policy` if it can't be allocated immediately.
+///
+/// Only [`TrySpawnError::NoCapacity`] is retried, as the cluster may have capacity later; other errors are returned straight away. If the attempts are exhausted the last error is returned. As each attempt consumes a copy of `start`, it must be `Clone`.
+///
+/// This is an async fn.
+pub async fn spawn_with_retry<T: FnOnce(Pid) + Serialize + DeserializeOwned + Clone>(
+	resources: Resources, start: T, policy: RetryPolicy,
+) -> Result<Pid, TrySpawnError> {
+	assert_ne!(
+		policy.max_attempts, 0,
+		"spawn_with_retry() called with a max_attempts of 0"
+	);
+	let mut backoff = policy.backoff;
+	let mut attempt = 1;
+	loop {
+		match try_spawn(resources, start.clone()).await {
+			Err(TrySpawnError::NoCapacity) if attempt < policy.max_attempts => {
+				Delay::new(backoff.mul_f64(rand::thread_rng().gen_range(0.5, 1.0))).await;
+				backoff *= 2;
+				attempt += 1;
+			}
+			result => break result,
+		}
+	}
+}
@@ -0,0 +1,64 @@
+use serde::de::DeserializeOwned;
+use std::{
+	fmt, task::{Context, Poll}
+};
+
+use super::{ChannelError, Receiver};
+
+/// Wait for the first of several [Receiver]s, of possibly different types, to receive.
+///
+/// Each [`recv()`](Select::recv) adds a receiver along with a closure that maps what it receives to a common type `R`, typically an enum with a variant per receiver. [`wait()`](Select::wait) then resolves to what the closure for the first receiver to receive returns.
+///
+/// Only the receiver that fires has a message taken from it, so the others don't lose anything, and a `Select` that's dropped before it resolves hasn't received anything. If more than one receiver is ready, the one added first is chosen.
+pub struct Select<'a, R> {
+	arms: Vec<Box<dyn FnMut(&mut Context) -> Option<R> + 'a>>,
+}
+impl<'a, R> Select<'a, R> {
+	/// Create a `Select` with no receivers.
+	pub fn new() -> Self {
+		Self { arms: Vec::new() }
+	}
+
+	/// Add `receiver`, mapping what it receives to `R` with `f` should it be the first to receive.
+	#[must_use]
+	pub fn recv<T: DeserializeOwned + 'static, F: FnOnce(Result<T, ChannelError>) -> R + 'a>(
+		mut self, receiver: &'a Receiver<T>, f: F,
+	) -> Self {
+		let mut f = Some(f);
+		self.arms
+			.push(Box::new(move |cx| match receiver.poll_recv(cx) {
+				Poll::Ready(t) => Some(f.take().unwrap()(t)),
+				Poll::Pending => None,
+			}));
+		self
+	}
+
+	/// Wait for the first receiver to receive, returning what its closure returns.
+	///
+	/// This is an async fn.
+	pub async fn wait(mut self) -> R {
+		assert!(
+			!self.arms.is_empty(),
+			"Select::wait() called without any receivers"
+		);
+		futures::future::poll_fn(|cx| {
+			self.arms
+				.iter_mut()
+				.find_map(|arm| arm(cx))
+				.map_or(Poll::Pending, Poll::Ready)
+		})
+		.await
+	}
+}
+impl<R> Default for Select<'_, R> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<R> fmt::Debug for Select<'_, R> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Select")
+			.field("receivers", &self.arms.len())
+			.finish()
+	}
+}
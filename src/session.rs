@@ -0,0 +1,144 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+
+use super::{ChannelError, Pid, Receiver, Sender};
+
+/// The requesting end of a request/response protocol with a remote process, for use with a [`Responder`] at the other end.
+///
+/// The protocol is encoded in the types: [`request()`](Requester::request) consumes the `Requester` and returns an [`AwaitingResponse`], which must be consumed by receiving the response before the next request can be sent. Sending two requests without waiting for the response in between is therefore a compile error rather than a protocol violation at runtime.
+///
+/// This takes both the [Sender] and the [Receiver] to the remote process.
+pub struct Requester<Req: Serialize, Resp: DeserializeOwned> {
+	sender: Sender<Req>,
+	receiver: Receiver<Resp>,
+}
+impl<Req: Serialize, Resp: DeserializeOwned> Requester<Req, Resp> {
+	/// Create a new `Requester` with a remote [Pid].
+	pub fn new(remote: Pid) -> Self {
+		Self {
+			sender: Sender::new(remote),
+			receiver: Receiver::new(remote),
+		}
+	}
+
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.sender.remote_pid()
+	}
+
+	/// Send `request`, returning the handle with which to await its response.
+	///
+	/// This is an async fn.
+	pub async fn request(self, request: Req) -> AwaitingResponse<Req, Resp>
+	where
+		Req: 'static,
+	{
+		self.sender.send(request).await;
+		AwaitingResponse(self)
+	}
+}
+impl<Req: Serialize, Resp: DeserializeOwned> fmt::Debug for Requester<Req, Resp> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Requester")
+			.field("sender", &self.sender)
+			.field("receiver", &self.receiver)
+			.finish()
+	}
+}
+
+/// A [`Requester`] that has sent a request and must receive its response before sending another.
+#[must_use = "the response must be received before another request can be sent"]
+pub struct AwaitingResponse<Req: Serialize, Resp: DeserializeOwned>(Requester<Req, Resp>);
+impl<Req: Serialize, Resp: DeserializeOwned> AwaitingResponse<Req, Resp> {
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.0.remote_pid()
+	}
+
+	/// Receive the response, returning it along with the [`Requester`] with which to send the next request.
+	///
+	/// This is an async fn.
+	pub async fn response(self) -> Result<(Resp, Requester<Req, Resp>), ChannelError>
+	where
+		Resp: 'static,
+	{
+		let response = self.0.receiver.recv().await?;
+		Ok((response, self.0))
+	}
+}
+impl<Req: Serialize, Resp: DeserializeOwned> fmt::Debug for AwaitingResponse<Req, Resp> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("AwaitingResponse").field(&self.0).finish()
+	}
+}
+
+/// The responding end of a request/response protocol with a remote process, for use with a [`Requester`] at the other end.
+///
+/// [`request()`](Responder::request) consumes the `Responder` and returns a [`Responding`], which must be consumed by sending a response before the next request can be received.
+///
+/// This takes both the [Receiver] and the [Sender] to the remote process.
+pub struct Responder<Req: DeserializeOwned, Resp: Serialize> {
+	receiver: Receiver<Req>,
+	sender: Sender<Resp>,
+}
+impl<Req: DeserializeOwned, Resp: Serialize> Responder<Req, Resp> {
+	/// Create a new `Responder` with a remote [Pid].
+	pub fn new(remote: Pid) -> Self {
+		Self {
+			receiver: Receiver::new(remote),
+			sender: Sender::new(remote),
+		}
+	}
+
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.receiver.remote_pid()
+	}
+
+	/// Receive the next request, returning it along with the handle with which to respond to it.
+	///
+	/// This returns `Err` if the remote exits or the channel fails, for example because the [`Requester`] has been dropped.
+	///
+	/// This is an async fn.
+	pub async fn request(self) -> Result<(Req, Responding<Req, Resp>), ChannelError>
+	where
+		Req: 'static,
+	{
+		let request = self.receiver.recv().await?;
+		Ok((request, Responding(self)))
+	}
+}
+impl<Req: DeserializeOwned, Resp: Serialize> fmt::Debug for Responder<Req, Resp> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Responder")
+			.field("receiver", &self.receiver)
+			.field("sender", &self.sender)
+			.finish()
+	}
+}
+
+/// A [`Responder`] that has received a request and must respond to it before receiving another.
+#[must_use = "the request must be responded to before another can be received"]
+pub struct Responding<Req: DeserializeOwned, Resp: Serialize>(Responder<Req, Resp>);
+impl<Req: DeserializeOwned, Resp: Serialize> Responding<Req, Resp> {
+	/// Get the pid of the remote end of this channel.
+	pub fn remote_pid(&self) -> Pid {
+		self.0.remote_pid()
+	}
+
+	/// Send `response`, returning the [`Responder`] with which to receive the next request.
+	///
+	/// This is an async fn.
+	pub async fn respond(self, response: Resp) -> Responder<Req, Resp>
+	where
+		Resp: 'static,
+	{
+		self.0.sender.send(response).await;
+		self.0
+	}
+}
+impl<Req: DeserializeOwned, Resp: Serialize> fmt::Debug for Responding<Req, Resp> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Responding").field(&self.0).finish()
+	}
+}
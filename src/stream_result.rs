@@ -0,0 +1,57 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+
+use super::{spawn, FutureExt1, Pid, Receiver, Resources, Sender, SpawnError};
+
+/// The sending half of the result stream of a process spawned with [`spawn_stream_result()`], handed to the closure it runs.
+///
+/// Dropping it, as happens when the closure returns, ends the stream.
+pub struct StreamSender<T: Serialize + 'static>(Sender<Option<T>>);
+impl<T: Serialize + 'static> StreamSender<T> {
+	/// Get the pid of the process receiving the stream.
+	pub fn remote_pid(&self) -> Pid {
+		self.0.remote_pid()
+	}
+
+	/// Send the next item of the result.
+	///
+	/// This is an async fn.
+	pub async fn send(&self, t: T) {
+		self.0.send(Some(t)).await;
+	}
+}
+#[doc(hidden)] // noise
+impl<T: Serialize + 'static> Drop for StreamSender<T> {
+	fn drop(&mut self) {
+		self.0.send(None).block();
+	}
+}
+impl<T: Serialize + 'static> fmt::Debug for StreamSender<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("StreamSender").field(&self.0).finish()
+	}
+}
+
+/// Spawn a new process that runs `start`, streaming its result back item by item.
+///
+/// This is for a process that computes a large result, which it can send as it goes rather than buffering it all. `start` is handed a [`StreamSender`] to send the items with. On success the [Receiver] from the process is returned, which is a [`Stream`](futures::stream::Stream) of the items that ends once the `StreamSender` is dropped.
+///
+/// This is an async fn.
+pub async fn spawn_stream_result<
+	T: Serialize + DeserializeOwned + 'static,
+	F: FnOnce(Pid, StreamSender<T>) + Serialize + DeserializeOwned,
+>(
+	resources: Resources, start: F,
+) -> Result<Receiver<Option<T>>, SpawnError> {
+	let start: Vec<u8> = bincode::serialize(&start).unwrap();
+	let pid = spawn(
+		resources,
+		serde_closure::FnOnce!(move |parent| {
+			let start: Vec<u8> = start;
+			let start: F = bincode::deserialize(&start).unwrap();
+			start(parent, StreamSender(Sender::new(parent)));
+		}),
+	)
+	.await?;
+	Ok(Receiver::new(pid))
+}
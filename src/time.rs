@@ -0,0 +1,87 @@
+//! Logical clocks for ordering events across processes.
+//!
+//! Processes don't share a synchronized wall clock, so timestamps from different processes can't be compared. Instead each process keeps a [Lamport clock](https://en.wikipedia.org/wiki/Lamport_timestamps), a counter that is advanced by every local event and that messages carry between processes. If one event causally precedes another, by happening before it in the same process or by happening before a message that leads to it being sent, it has a lower [`LogicalClock`].
+//!
+//! Sending a [`Stamped`] value with [`Sender::send_stamped()`](crate::Sender::send_stamped) attaches the sender's clock, and receiving it with [`Receiver::recv_stamped()`](crate::Receiver::recv_stamped) merges it into the receiver's. Other channels don't carry a clock.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	fmt, sync::atomic::{AtomicU64, Ordering}
+};
+
+use super::{ChannelError, Receiver, Sender};
+
+static CLOCK: AtomicU64 = AtomicU64::new(0);
+
+/// A Lamport timestamp.
+///
+/// Timestamps from any processes can be compared, with ties broken by for example [Pid](crate::Pid) to give a total order consistent with causality.
+#[derive(
+	Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize, Debug,
+)]
+pub struct LogicalClock(pub u64);
+impl fmt::Display for LogicalClock {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+/// Get the current value of this process's clock.
+pub fn clock() -> LogicalClock {
+	LogicalClock(CLOCK.load(Ordering::SeqCst))
+}
+
+/// Advance this process's clock for a local event, returning the event's timestamp.
+pub fn tick() -> LogicalClock {
+	LogicalClock(CLOCK.fetch_add(1, Ordering::SeqCst) + 1)
+}
+
+/// Merge a timestamp received from another process into this process's clock, returning the timestamp of the receipt.
+pub fn merge(remote: LogicalClock) -> LogicalClock {
+	let mut current = CLOCK.load(Ordering::SeqCst);
+	loop {
+		let new = current.max(remote.0) + 1;
+		match CLOCK.compare_exchange_weak(current, new, Ordering::SeqCst, Ordering::SeqCst) {
+			Ok(_) => break LogicalClock(new),
+			Err(current_) => current = current_,
+		}
+	}
+}
+
+/// A value carrying the [`LogicalClock`] of its sender, for channels that propagate clocks.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Stamped<T> {
+	/// The sender's clock at the time of sending.
+	pub clock: LogicalClock,
+	/// The value sent.
+	pub value: T,
+}
+
+impl<T: Serialize> Sender<Stamped<T>> {
+	/// Send `t`, advancing this process's clock and attaching it.
+	///
+	/// This is an async fn.
+	pub async fn send_stamped(&self, t: T)
+	where
+		T: 'static,
+	{
+		self.send_with(|| Stamped {
+			clock: tick(),
+			value: t,
+		})
+		.await
+	}
+}
+
+impl<T: DeserializeOwned> Receiver<Stamped<T>> {
+	/// Receive a value, merging its sender's clock into this process's, and returning it along with the timestamp of its receipt.
+	///
+	/// This is an async fn.
+	pub async fn recv_stamped(&self) -> Result<(LogicalClock, T), ChannelError>
+	where
+		T: 'static,
+	{
+		let Stamped { clock, value } = self.recv().await?;
+		Ok((merge(clock), value))
+	}
+}
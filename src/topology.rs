@@ -0,0 +1,339 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, fmt, fs, io, path::Path};
+
+use super::{kill, spawn, FutureExt1, Pid, Receiver, Resources, Sender, SpawnError};
+use constellation_internal::{Cpu, Gpu, Mem};
+
+/// A multi-process topology described declaratively by a manifest.
+///
+/// The manifest is TOML that declares the roles, how many processes of each to spawn and with what resources, and the channels between them. It looks something like this:
+///
+/// ```toml
+/// [[role]]
+/// name = "source"
+///
+/// [[role]]
+/// name = "worker"
+/// count = 4
+/// mem = "20 MiB"
+/// cpu = 0.25
+///
+/// [[channel]]
+/// from = "source"
+/// to = "worker"
+/// ```
+///
+/// A channel from one role to another means each process of the first role can send to each process of the second. [`spawn()`](Topology::spawn) spawns the processes, and each is passed an [Instance] from which it can create its [Sender]s and [Receiver]s.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Topology {
+	roles: Vec<Role>,
+	channels: Vec<(String, String)>,
+}
+#[derive(Clone, PartialEq, Debug)]
+struct Role {
+	name: String,
+	count: usize,
+	resources: Resources,
+}
+impl Topology {
+	/// Parse a manifest from a string.
+	pub fn from_toml(toml: &str) -> Result<Self, TopologyError> {
+		#[derive(Deserialize)]
+		struct A {
+			#[serde(default)]
+			role: Vec<B>,
+			#[serde(default)]
+			channel: Vec<C>,
+		}
+		#[derive(Deserialize)]
+		struct B {
+			name: String,
+			count: Option<usize>,
+			mem: Option<Mem>,
+			cpu: Option<Cpu>,
+			gpu: Option<Gpu>,
+		}
+		#[derive(Deserialize)]
+		struct C {
+			from: String,
+			to: String,
+		}
+
+		let manifest: A = toml::from_str(toml).map_err(TopologyError::Toml)?;
+		let mut roles: Vec<Role> = Vec::with_capacity(manifest.role.len());
+		for role in manifest.role {
+			if roles.iter().any(|role_| role_.name == role.name) {
+				return Err(TopologyError::DuplicateRole(role.name));
+			}
+			let default = Resources::default();
+			roles.push(Role {
+				name: role.name,
+				count: role.count.unwrap_or(1),
+				resources: Resources {
+					mem: role.mem.unwrap_or(default.mem),
+					cpu: role.cpu.unwrap_or(default.cpu),
+					gpu: role.gpu.unwrap_or(default.gpu),
+				},
+			});
+		}
+		let mut channels = Vec::with_capacity(manifest.channel.len());
+		for channel in manifest.channel {
+			for role in &[&channel.from, &channel.to] {
+				if !roles.iter().any(|role_| &role_.name == *role) {
+					return Err(TopologyError::UnknownRole((*role).clone()));
+				}
+			}
+			let channel = (channel.from, channel.to);
+			if channels.contains(&channel) {
+				return Err(TopologyError::DuplicateChannel(channel.0, channel.1));
+			}
+			channels.push(channel);
+		}
+		Ok(Self { roles, channels })
+	}
+
+	/// Read and parse a manifest from a file.
+	pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, TopologyError> {
+		let toml = fs::read_to_string(path).map_err(TopologyError::Io)?;
+		Self::from_toml(&toml)
+	}
+
+	/// The names of the roles, in the order they're declared.
+	pub fn roles(&self) -> impl Iterator<Item = &str> {
+		self.roles.iter().map(|role| &*role.name)
+	}
+
+	/// Spawn the processes described by the manifest.
+	///
+	/// `start` is run in every process, and is passed an [Instance] describing its role and peers. It should look at [`Instance::role()`] to decide what to do.
+	///
+	/// Processes are spawned in the order their roles are declared. Each waits until all have been spawned, at which point it's passed its `Instance`. If any fails to spawn, those already spawned are [killed](kill) before they're passed their `Instance`, and the error returned, so either the whole topology is running or none of it is.
+	///
+	/// This is an async fn. On success it returns the [Pid]s of the spawned processes by role.
+	pub async fn spawn<F: FnOnce(Instance) + Serialize + DeserializeOwned>(
+		&self, start: F,
+	) -> Result<HashMap<String, Vec<Pid>>, SpawnError> {
+		let start: Vec<u8> = bincode::serialize(&start).unwrap();
+		let mut pids: HashMap<String, Vec<Pid>> = HashMap::with_capacity(self.roles.len());
+		for role in &self.roles {
+			let mut role_pids = Vec::with_capacity(role.count);
+			for _ in 0..role.count {
+				let start = start.clone();
+				let pid = spawn(
+					role.resources,
+					serde_closure::FnOnce!(move |parent| {
+						let start: Vec<u8> = start;
+						let instance = Receiver::<Instance>::new(parent).recv().block().unwrap();
+						let start: F = bincode::deserialize(&start).unwrap();
+						start(instance)
+					}),
+				)
+				.await;
+				match pid {
+					Ok(pid) => role_pids.push(pid),
+					Err(err) => {
+						for &pid in pids.values().flatten().chain(&role_pids) {
+							let _ = kill(pid).await;
+						}
+						return Err(err);
+					}
+				}
+			}
+			let _ = pids.insert(role.name.clone(), role_pids);
+		}
+		for role in &self.roles {
+			let outbound = self
+				.channels
+				.iter()
+				.filter(|(from, _)| *from == role.name)
+				.map(|(_, to)| to.clone())
+				.collect::<Vec<_>>();
+			let inbound = self
+				.channels
+				.iter()
+				.filter(|(_, to)| *to == role.name)
+				.map(|(from, _)| from.clone())
+				.collect::<Vec<_>>();
+			for (index, &pid) in pids[&role.name].iter().enumerate() {
+				let instance = Instance {
+					role: role.name.clone(),
+					index,
+					pid,
+					parent: super::pid(),
+					pids: pids.clone(),
+					outbound: outbound.clone(),
+					inbound: inbound.clone(),
+				};
+				Sender::<Instance>::new(pid).send(instance).await;
+			}
+		}
+		Ok(pids)
+	}
+}
+
+/// A process's place in a [Topology], passed to it on [`spawn()`](Topology::spawn).
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Instance {
+	role: String,
+	index: usize,
+	pid: Pid,
+	parent: Pid,
+	pids: HashMap<String, Vec<Pid>>,
+	outbound: Vec<String>,
+	inbound: Vec<String>,
+}
+impl Instance {
+	/// The name of this process's role.
+	pub fn role(&self) -> &str {
+		&self.role
+	}
+
+	/// The index of this process among those of its role.
+	pub fn index(&self) -> usize {
+		self.index
+	}
+
+	/// The [Pid] of the process that spawned the topology.
+	pub fn parent(&self) -> Pid {
+		self.parent
+	}
+
+	/// The [Pid]s of the processes of a role.
+	pub fn pids(&self, role: &str) -> &[Pid] {
+		self.pids
+			.get(role)
+			.unwrap_or_else(|| panic!("Instance::pids() called with unknown role {:?}", role))
+	}
+
+	/// Create a [Sender] to each process of a role that the manifest declares a channel to.
+	pub fn senders<T: Serialize>(&self, to: &str) -> Vec<Sender<T>> {
+		if !self.outbound.iter().any(|role| role == to) {
+			panic!(
+				"Instance::senders() called but the manifest has no channel from {:?} to {:?}",
+				self.role, to
+			);
+		}
+		self.peers(to).map(Sender::new).collect()
+	}
+
+	/// Create a [Receiver] from each process of a role that the manifest declares a channel from.
+	pub fn receivers<T: DeserializeOwned>(&self, from: &str) -> Vec<Receiver<T>> {
+		if !self.inbound.iter().any(|role| role == from) {
+			panic!(
+				"Instance::receivers() called but the manifest has no channel from {:?} to {:?}",
+				from, self.role
+			);
+		}
+		self.peers(from).map(Receiver::new).collect()
+	}
+
+	fn peers<'a>(&'a self, role: &str) -> impl Iterator<Item = Pid> + 'a {
+		self.pids(role)
+			.iter()
+			.cloned()
+			.filter(move |&pid| pid != self.pid)
+	}
+}
+
+/// An error returned when reading a [Topology] manifest.
+pub enum TopologyError {
+	/// The manifest couldn't be read.
+	Io(io::Error),
+	/// The manifest isn't valid TOML or is missing fields.
+	Toml(toml::de::Error),
+	/// Multiple roles were declared with the same name.
+	DuplicateRole(String),
+	/// A channel was declared to or from a role that isn't declared.
+	UnknownRole(String),
+	/// The same channel was declared multiple times.
+	DuplicateChannel(String, String),
+	#[doc(hidden)]
+	__Nonexhaustive,
+}
+impl fmt::Display for TopologyError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Io(err) => write!(f, "couldn't read manifest: {}", err),
+			Self::Toml(err) => write!(f, "couldn't parse manifest: {}", err),
+			Self::DuplicateRole(role) => write!(f, "role {:?} declared multiple times", role),
+			Self::UnknownRole(role) => write!(f, "channel refers to undeclared role {:?}", role),
+			Self::DuplicateChannel(from, to) => write!(
+				f,
+				"channel from {:?} to {:?} declared multiple times",
+				from, to
+			),
+			Self::__Nonexhaustive => unreachable!(),
+		}
+	}
+}
+impl fmt::Debug for TopologyError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(self, f)
+	}
+}
+impl Error for TopologyError {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn manifest_parsing() {
+		let topology = Topology::from_toml(
+			r#"
+			[[role]]
+			name = "source"
+
+			[[role]]
+			name = "worker"
+			count = 4
+			mem = "20 MiB"
+			cpu = 0.25
+
+			[[channel]]
+			from = "source"
+			to = "worker"
+			"#,
+		)
+		.unwrap();
+		assert_eq!(topology.roles().collect::<Vec<_>>(), ["source", "worker"]);
+		assert_eq!(topology.roles[0].count, 1);
+		assert_eq!(topology.roles[0].resources, Resources::default());
+		assert_eq!(topology.roles[1].count, 4);
+		assert_eq!(
+			topology.roles[1].resources,
+			Resources {
+				mem: 20 * Mem::MIB,
+				cpu: Cpu::CORE / 4,
+				gpu: Gpu::default()
+			}
+		);
+		assert_eq!(
+			topology.channels,
+			[(String::from("source"), String::from("worker"))]
+		);
+	}
+
+	#[test]
+	fn manifest_validation() {
+		match Topology::from_toml("[[role]]\nname = \"a\"\n[[role]]\nname = \"a\"\n") {
+			Err(TopologyError::DuplicateRole(role)) => assert_eq!(role, "a"),
+			res => panic!("{:?}", res),
+		}
+		match Topology::from_toml("[[role]]\nname = \"a\"\n[[channel]]\nfrom = \"a\"\nto = \"b\"\n")
+		{
+			Err(TopologyError::UnknownRole(role)) => assert_eq!(role, "b"),
+			res => panic!("{:?}", res),
+		}
+		match Topology::from_toml(
+			"[[role]]\nname = \"a\"\n[[channel]]\nfrom = \"a\"\nto = \"a\"\n[[channel]]\nfrom = \"a\"\nto = \"a\"\n",
+		) {
+			Err(TopologyError::DuplicateChannel(from, to)) => assert_eq!((&*from, &*to), ("a", "a")),
+			res => panic!("{:?}", res),
+		}
+		match Topology::from_toml("[[role]]\ncount = 1\n") {
+			Err(TopologyError::Toml(_)) => (),
+			res => panic!("{:?}", res),
+		}
+	}
+}
@@ -0,0 +1,69 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "0\nExited\n2\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "hello\nworld\n!\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use futures::StreamExt;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = AckedReceiver::<String>::new(parent);
+			let mut stream = Box::pin(receiver.ackable_stream());
+			for _ in 0..3 {
+				let (message, token) = stream.next().block().unwrap().unwrap();
+				println!("{}", message);
+				// Leave the second unacknowledged; dropping its token lets the third through
+				if message != "world" {
+					token.ack().block();
+				}
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = AckedSender::new(pid);
+	let first = sender.send_acked(String::from("hello")).block();
+	println!("{}", first.unwrap().seq());
+	let (second, third) = futures::future::join(
+		sender.send_acked(String::from("world")),
+		sender.send_acked(String::from("!")),
+	)
+	.block();
+	println!("{:?}", second.unwrap_err());
+	println!("{}", third.unwrap().seq());
+}
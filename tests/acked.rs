@@ -0,0 +1,67 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "0\n1\nExited\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "world\nhello\n!\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = AckedReceiver::<String>::new(parent);
+			let first = receiver.recv().block().unwrap();
+			let second = receiver.recv().block().unwrap();
+			// Acknowledge out of order
+			println!("{}", second.ack().block());
+			println!("{}", first.ack().block());
+			// Exit without acknowledging
+			let third = receiver.recv().block().unwrap();
+			println!("{}", *third);
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = AckedSender::new(pid);
+	let (first, second) = futures::future::join(
+		sender.send_acked(String::from("hello")),
+		sender.send_acked(String::from("world")),
+	)
+	.block();
+	println!("{}", first.unwrap().seq());
+	println!("{}", second.unwrap().seq());
+	let third = sender.send_acked(String::from("!")).block();
+	println!("{:?}", third.unwrap_err());
+}
@@ -0,0 +1,84 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "\\[Ok\\(\\(\\)\\), Ok\\(\\(\\)\\), Ok\\(\\(\\)\\)\\]\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "config v2\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "config v2\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "config v2\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pids = (0..3)
+		.map(|_| {
+			spawn(
+				Resources {
+					mem: 20 * Mem::MIB,
+					..Resources::default()
+				},
+				FnOnce!(|parent| {
+					let receiver = Receiver::<String>::new(parent);
+					println!("{}", receiver.recv().block().unwrap());
+				}),
+			)
+			.block()
+			.expect("spawn() failed to allocate process")
+		})
+		.collect::<Vec<_>>();
+	let results = broadcast(&pids, String::from("config v2")).block();
+	println!("{:?}", results);
+}
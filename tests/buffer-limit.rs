@@ -0,0 +1,58 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "6400\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			set_buffer_limit(Some(16), BufferLimitPolicy::Backpressure);
+			let receiver = Receiver::<Vec<u8>>::new(parent);
+			let len: usize = (0..100)
+				.map(|_| receiver.recv().block().unwrap().len())
+				.sum();
+			println!("{}", len);
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = Sender::<Vec<u8>>::new(pid);
+	for _ in 0..100 {
+		sender.send(vec![0; 64]).block();
+	}
+}
@@ -0,0 +1,83 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "cancelled\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [
+//=         {
+//=           "output": {
+//=             "2": [
+//=               "",
+//=               true
+//=             ],
+//=             "1": [
+//=               "cancelled\n",
+//=               true
+//=             ]
+//=           },
+//=           "children": [],
+//=           "exit": "Success"
+//=         }
+//=       ],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let token = cancellation_token();
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let pid = spawn(
+				Resources {
+					mem: 20 * Mem::MIB,
+					..Resources::default()
+				},
+				FnOnce!(|_parent| {
+					cancellation_token().cancelled().block();
+					println!("cancelled");
+				}),
+			)
+			.block()
+			.expect("spawn() failed to allocate process");
+			Sender::<Pid>::new(parent).send(pid).block();
+			cancellation_token().cancelled().block();
+			println!("cancelled");
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let _grandchild = Receiver::<Pid>::new(pid).recv().block().unwrap();
+	assert!(!token.is_cancelled());
+	token.cancel();
+	assert!(token.is_cancelled());
+}
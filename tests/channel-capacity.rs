@@ -0,0 +1,61 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "499500\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "499500\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<usize>::with_capacity(parent, 16);
+			let sender = Sender::<usize>::new(parent);
+			let sum = (0..1000)
+				.map(|_| receiver.recv().block().unwrap())
+				.sum::<usize>();
+			println!("{}", sum);
+			sender.send(sum).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = Sender::<usize>::with_capacity(pid, 64);
+	let receiver = Receiver::<usize>::new(pid);
+	for i in 0..1000 {
+		sender.send(i).block();
+	}
+	println!("{}", receiver.recv().block().unwrap());
+}
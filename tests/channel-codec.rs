@@ -0,0 +1,61 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "\\[\"pong\",3\\]\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "\\(\"ping\", 2\\)\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<(String, usize)>::new_with_codec(parent, Json);
+			let sender = Sender::<(String, usize)>::new_with_codec(parent, Json);
+			let (msg, n) = receiver.recv().block().unwrap();
+			println!("{:?}", (&msg, n));
+			sender.send((String::from("pong"), n + 1)).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = Sender::<(String, usize)>::new_with_codec(pid, Json);
+	// Receive the raw JSON, to check that it's what's on the wire
+	let receiver = Receiver::<Vec<u8>>::new(pid);
+	sender.send((String::from("ping"), 2)).block();
+	println!(
+		"{}",
+		String::from_utf8(receiver.recv().block().unwrap()).unwrap()
+	);
+}
@@ -0,0 +1,57 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "1048576\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<Vec<u8>>::with_compression(parent);
+			let sender = Sender::<usize>::new(parent);
+			let shard = receiver.recv().block().unwrap();
+			assert!(shard.iter().all(|&byte| byte == 7));
+			sender.send(shard.len()).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = Sender::<Vec<u8>>::with_compression(pid, Compression::None);
+	let receiver = Receiver::<usize>::new(pid);
+	sender.send(vec![7; 1 << 20]).block();
+	println!("{}", receiver.recv().block().unwrap());
+}
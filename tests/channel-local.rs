@@ -0,0 +1,37 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "499500\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::thread;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let (sender, receiver) = channel_local::<usize>();
+	assert_eq!(sender.remote_pid(), pid());
+	let thread = thread::spawn(move || {
+		for i in 0..1000 {
+			sender.send(i).block();
+		}
+	});
+	let mut sum = 0;
+	while let Ok(i) = receiver.recv().block() {
+		sum += i;
+	}
+	thread.join().unwrap();
+	println!("{}", sum);
+}
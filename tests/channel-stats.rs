@@ -0,0 +1,59 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "true\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{thread, time::Duration};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender = Sender::<usize>::new(parent);
+			thread::sleep(Duration::from_millis(500));
+			sender.send(42).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let receiver = Receiver::<usize>::new(pid);
+	assert_eq!(receiver.recv().block().unwrap(), 42);
+	let stats = channel_stats()
+		.into_iter()
+		.find(|stats| stats.remote == pid)
+		.unwrap();
+	println!("{}", stats.recv_blocked >= Duration::from_millis(400));
+}
@@ -0,0 +1,56 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "stdout: \"hello\\n\"\nstderr: \"oops\\n\"\nSuccess\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "oops\n",
+//=           true
+//=         ],
+//=         "1": [
+//=           "hello\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use futures::StreamExt;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let child = spawn_handle(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|_parent| {
+			println!("hello");
+			eprintln!("oops");
+		}),
+	)
+	.block()
+	.expect("spawn_handle() failed to allocate process");
+	let stdout = child.stdout().concat().block();
+	let stderr = child.stderr().concat().block();
+	println!("stdout: {:?}", String::from_utf8(stdout).unwrap());
+	println!("stderr: {:?}", String::from_utf8(stderr).unwrap());
+	println!("{:?}", child.join().block());
+}
@@ -0,0 +1,58 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "Success\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "\"hello world\"\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use futures::SinkExt;
+use std::io::Read;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let child = spawn_handle(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|_parent| {
+			let mut input = String::new();
+			let _ = std::io::stdin().read_to_string(&mut input).unwrap();
+			println!("{:?}", input);
+		}),
+	)
+	.block()
+	.expect("spawn_handle() failed to allocate process");
+	let mut stdin = Box::pin(child.stdin());
+	stdin.send(b"hello ".to_vec()).block().unwrap();
+	stdin.send(b"world".to_vec()).block().unwrap();
+	stdin.close().block().unwrap();
+	println!("{:?}", child.join().block());
+}
@@ -0,0 +1,95 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "false Some\\(3\\) None\nfalse None Some\\(9\\)\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": {
+//=         "Error": {
+//=           "Unix": {
+//=             "Status": 3
+//=           }
+//=         }
+//=       }
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": {
+//=         "Error": {
+//=           "Unix": {
+//=             "Signal": "SIGKILL"
+//=           }
+//=         }
+//=       }
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{process, thread, time::Duration};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let resources = Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	};
+	let failed = spawn_handle(resources, FnOnce!(|_parent| process::exit(3)))
+		.block()
+		.expect("spawn() failed to allocate process");
+	let status = failed.join().block();
+	println!(
+		"{} {:?} {:?}",
+		status.is_success(),
+		status.code(),
+		status.signal()
+	);
+	let killed = spawn_handle(
+		resources,
+		FnOnce!(|_parent| loop {
+			thread::sleep(Duration::from_secs(1));
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	killed.kill();
+	let status = killed.join().block();
+	println!(
+		"{} {:?} {:?}",
+		status.is_success(),
+		status.code(),
+		status.signal()
+	);
+}
@@ -0,0 +1,45 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "hello\nworld\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{
+	io::{Read, Write}, net::TcpListener, thread
+};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	// An external echo service speaking a big-endian u32 length-prefixed protocol
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let _ = thread::spawn(move || {
+		let (mut stream, _) = listener.accept().unwrap();
+		let mut len = [0; 4];
+		while stream.read_exact(&mut len).is_ok() {
+			let mut message = vec![0; u32::from_be_bytes(len) as usize];
+			stream.read_exact(&mut message).unwrap();
+			stream.write_all(&len).unwrap();
+			stream.write_all(&message).unwrap();
+		}
+	});
+	let mut connection = ExternalConnection::connect(addr, LengthPrefixed).unwrap();
+	for message in &["hello", "world"] {
+		connection.write_message(message.as_bytes()).unwrap();
+		let echo = connection.read_message().unwrap();
+		println!("{}", String::from_utf8(echo).unwrap());
+	}
+}
@@ -0,0 +1,82 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "a0 a1 a2 a3 a4 a5 b0 b1 b2\na0 a1 b0 a2 a3 b1 a4 a5 b2\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pids = (0..2)
+		.map(|_| {
+			spawn(
+				Resources {
+					mem: 20 * Mem::MIB,
+					..Resources::default()
+				},
+				FnOnce!(|parent| {
+					let receiver = Receiver::<()>::new(parent);
+					receiver.recv().block().unwrap();
+				}),
+			)
+			.block()
+			.expect("spawn() failed to allocate process")
+		})
+		.collect::<Vec<_>>();
+	for &policy in &[FanInPolicy::Drain, FanInPolicy::Fair] {
+		let mut fan_in = FanIn::new(policy);
+		for (i, (&pid, &(name, count))) in pids.iter().zip(&[("a", 6), ("b", 3)]).enumerate() {
+			let snapshot = (0..count).map(|j| format!("{}{}", name, j)).collect();
+			fan_in.add(Receiver::<String>::from_snapshot(pid, snapshot), 2 - i);
+		}
+		let received = (0..9)
+			.map(|_| fan_in.recv().block().unwrap().1.unwrap())
+			.collect::<Vec<_>>();
+		println!("{}", received.join(" "));
+	}
+	for &pid in &pids {
+		Sender::<()>::new(pid).send(()).block();
+	}
+}
@@ -0,0 +1,57 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "flushed\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "4950\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<u32>::new(parent);
+			let sum: u32 = (0..100).map(|_| receiver.recv().block().unwrap()).sum();
+			println!("{}", sum);
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = Sender::<u32>::new(pid);
+	for i in 0..100 {
+		sender.send(i).block();
+	}
+	flush_all().block();
+	println!("flushed");
+}
@@ -0,0 +1,64 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "true\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<()>::new(parent);
+			let sender = Sender::<bool>::new(parent);
+			receiver.recv().block().unwrap();
+			sender
+				.send(forwarded_connections().contains(&parent))
+				.block();
+			// Keep the connection open until the parent has looked at it
+			receiver.recv().block().unwrap();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = Sender::<()>::new(pid);
+	let receiver = Receiver::<bool>::new(pid);
+	sender.send(()).block();
+	let child_forwarded = receiver.recv().block().unwrap();
+	let forwarded = forwarded_connections().contains(&pid);
+	sender.send(()).block();
+	// The connection between the two was made by one and accepted by the other
+	println!("{}", child_forwarded != forwarded);
+}
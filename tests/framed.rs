@@ -0,0 +1,57 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "3 \\[1, 2, 3\\]\n0 \\[\\]\n5 \\[104, 101, 108, 108, 111\\]\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender = FramedSender::new(parent);
+			sender.write_message(&[1, 2, 3]).unwrap();
+			sender.write_message(&[]).unwrap();
+			sender.write_message(b"hello").unwrap();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let receiver = FramedReceiver::new(pid);
+	for _ in 0..3 {
+		let message = receiver.read_message().unwrap();
+		println!("{} {:?}", message.len(), message);
+	}
+}
@@ -0,0 +1,26 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "Err\\(AlreadyInitialised\\)\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	let resources = Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	};
+	init(resources);
+	init(resources);
+	println!("{:?}", try_init(resources));
+}
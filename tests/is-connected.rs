@@ -0,0 +1,34 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "true true\ntrue\nfalse\nfalse\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let (sender, receiver) = channel_local::<usize>();
+	println!("{} {}", sender.is_connected(), receiver.is_connected());
+	sender.send(1).block();
+	drop(sender);
+	println!("{}", receiver.is_connected());
+	assert_eq!(receiver.recv().block(), Ok(1));
+	println!("{}", receiver.is_connected());
+
+	let (sender, receiver) = channel_local::<usize>();
+	drop(receiver);
+	println!("{}", sender.is_connected());
+}
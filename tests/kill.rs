@@ -0,0 +1,57 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "Ok\\(\\(\\)\\)\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": {
+//=         "Error": {
+//=           "Unix": {
+//=             "Signal": "SIGKILL"
+//=           }
+//=         }
+//=       }
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{thread, time::Duration};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|_parent| loop {
+			thread::sleep(Duration::from_secs(1));
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	println!("{:?}", kill(pid).block());
+}
@@ -0,0 +1,61 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "1\n5 pong\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "3 ping\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::{
+	time::{tick, Stamped}, *
+};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	println!("{}", tick());
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<Stamped<String>>::new(parent);
+			let sender = Sender::<Stamped<String>>::new(parent);
+			let (clock, value) = receiver.recv_stamped().block().unwrap();
+			println!("{} {}", clock, value);
+			sender.send_stamped(String::from("pong")).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = Sender::<Stamped<String>>::new(pid);
+	let receiver = Receiver::<Stamped<String>>::new(pid);
+	sender.send_stamped(String::from("ping")).block();
+	let (clock, value) = receiver.recv_stamped().block().unwrap();
+	println!("{} {}", clock, value);
+}
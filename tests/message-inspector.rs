@@ -0,0 +1,82 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "3 3\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "4950\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::sync::{Arc, Mutex};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<u32>::new(parent);
+			let sender = Sender::<u32>::new(parent);
+			for _ in 0..3 {
+				let x = receiver.recv().block().unwrap();
+				sender.send(x * 2).block();
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let log = Arc::new(Mutex::new(Vec::new()));
+	let log_ = log.clone();
+	set_message_inspector(move |direction, remote, type_name, len| {
+		log_.lock()
+			.unwrap()
+			.push((direction, remote, type_name.to_owned(), len));
+	});
+	let sender = Sender::<u32>::new(pid);
+	let receiver = Receiver::<u32>::new(pid);
+	for i in 0..3 {
+		sender.send(i).block();
+		assert_eq!(receiver.recv().block().unwrap(), i * 2);
+	}
+	clear_message_inspector();
+	let log = log.lock().unwrap();
+	assert!(log
+		.iter()
+		.all(|(_, remote, type_name, len)| *remote == pid && type_name == "u32" && *len > 0));
+	let sent = log
+		.iter()
+		.filter(|(direction, ..)| *direction == Direction::Sent)
+		.count();
+	let received = log
+		.iter()
+		.filter(|(direction, ..)| *direction == Direction::Received)
+		.count();
+	println!("{} {}", sent, received);
+}
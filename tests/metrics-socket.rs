@@ -0,0 +1,36 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use std::{env, fs, io::Read, os::unix::net::UnixStream, process};
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let path = env::temp_dir().join(format!("constellation-metrics-{}.sock", process::id()));
+	serve_metrics(&path).unwrap();
+	let mut line = String::new();
+	let _ = UnixStream::connect(&path)
+		.unwrap()
+		.read_to_string(&mut line)
+		.unwrap();
+	fs::remove_file(&path).unwrap();
+	let metrics: serde_json::Value = serde_json::from_str(&line).unwrap();
+	assert_eq!(metrics["pid"], pid().to_string());
+	assert!(metrics["channels"].is_array());
+}
@@ -0,0 +1,76 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "42\nodd: 7\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn halve(resources: Resources, n: u32) -> OneshotResult<u32, String> {
+	oneshot_result(
+		resources,
+		FnOnce!(move |_parent| {
+			if n % 2 == 0 {
+				Ok(n / 2)
+			} else {
+				Err(format!("odd: {}", n))
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process")
+}
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let resources = Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	};
+	println!("{}", halve(resources, 84).block().unwrap());
+	match halve(resources, 7).block() {
+		Err(SpawnedError::Returned(err)) => println!("{}", err),
+		other => panic!("{:?}", other),
+	}
+}
@@ -0,0 +1,88 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": {
+//=         "Error": {
+//=           "Unix": {
+//=             "Signal": "SIGKILL"
+//=           }
+//=         }
+//=       }
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "hello 0\nhello 1\nhello 2\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let broker = Broker::spawn(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	})
+	.block()
+	.expect("spawn() failed to allocate process");
+	let subscriber = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(move |parent| {
+			let receiver = broker.subscribe::<String>("greetings").block();
+			let sender = Sender::<()>::new(parent);
+			sender.send(()).block();
+			for _ in 0..3 {
+				println!("{}", receiver.recv().block().unwrap());
+			}
+			sender.send(()).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let receiver = Receiver::<()>::new(subscriber);
+	receiver.recv().block().unwrap();
+	broker.publish("other", String::from("unseen")).block();
+	for i in 0..3 {
+		broker.publish("greetings", format!("hello {}", i)).block();
+	}
+	receiver.recv().block().unwrap();
+}
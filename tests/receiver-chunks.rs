@@ -0,0 +1,63 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "\\[0, 1\\]\n\\[2, 3\\]\n\\[4\\]\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use futures::stream::StreamExt;
+use std::time::Duration;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender = Sender::<usize>::new(parent);
+			for i in 0..5 {
+				sender.send(i).block();
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let receiver = Receiver::<usize>::new(pid);
+	let chunks = futures::executor::block_on(
+		receiver
+			.chunks(2, Duration::from_secs(60))
+			.collect::<Vec<_>>(),
+	);
+	for chunk in chunks {
+		println!("{:?}", chunk);
+	}
+}
@@ -0,0 +1,60 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "\\[0, 20, 40, 60, 80\\]\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use futures::stream::StreamExt;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender = Sender::<usize>::new(parent);
+			for i in 0..10 {
+				sender.send(i).block();
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let evens = Receiver::<usize>::new(pid)
+		.filter_map_recv(|i| if i % 2 == 0 { Some(i * 10) } else { None })
+		.take(5)
+		.map(Result::unwrap)
+		.collect::<Vec<_>>()
+		.block();
+	println!("{:?}", evens);
+}
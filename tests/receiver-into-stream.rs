@@ -0,0 +1,31 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "\\[Ok\\(1\\), Ok\\(2\\), Ok\\(3\\)\\]\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use futures::stream::StreamExt;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let (sender, receiver) = channel_local::<usize>();
+	for i in 1..=3 {
+		sender.send(i).block();
+	}
+	drop(sender);
+	let received = receiver.into_stream().collect::<Vec<_>>().block();
+	println!("{:?}", received);
+}
@@ -0,0 +1,42 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "Some\\(Ok\\(1\\)\\)\nSome\\(Ok\\(1\\)\\)\nOk\\(1\\)\nSome\\(Ok\\(2\\)\\)\nSome\\(Ok\\(2\\)\\)\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let (sender, receiver) = channel_local::<usize>();
+	assert!(receiver.peek().is_none());
+	sender.send(1).block();
+	sender.send(2).block();
+	println!(
+		"{:?}",
+		receiver.peek().map(|peek| peek().map(|peeked| *peeked))
+	);
+	println!(
+		"{:?}",
+		receiver.peek().map(|peek| peek().map(|peeked| *peeked))
+	);
+	println!("{:?}", receiver.recv().block());
+	println!(
+		"{:?}",
+		receiver.peek().map(|peek| peek().map(|peeked| *peeked))
+	);
+	println!("{:?}", receiver.try_recv().map(|recv| recv()));
+	assert!(receiver.peek().is_none());
+}
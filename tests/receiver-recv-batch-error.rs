@@ -0,0 +1,67 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "100\nExited\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender = Sender::<u32>::new(parent);
+			for i in 0..100 {
+				sender.send(i).block();
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	// Receiving ahead lets a batch drain up to the point the channel errors once the child exits
+	let receiver = Receiver::<u32>::with_capacity(pid, 16);
+	let mut received = Vec::new();
+	let err = loop {
+		match receiver.recv_batch_blocking(32) {
+			Ok(batch) => {
+				assert!(!batch.is_empty() && batch.len() <= 32);
+				received.extend(batch);
+			}
+			Err(err) => break err,
+		}
+	};
+	assert_eq!(received, (0..100).collect::<Vec<_>>());
+	println!("{}", received.len());
+	println!("{:?}", err);
+}
@@ -0,0 +1,61 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "10\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender = Sender::<u32>::new(parent);
+			for i in 0..10 {
+				sender.send(i).block();
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let receiver = Receiver::<u32>::new(pid);
+	let mut received = Vec::new();
+	while received.len() < 10 {
+		let batch = receiver.recv_batch_blocking(4).unwrap();
+		assert!(!batch.is_empty() && batch.len() <= 4);
+		received.extend(batch);
+	}
+	assert_eq!(received, (0..10).collect::<Vec<_>>());
+	println!("{}", received.len());
+}
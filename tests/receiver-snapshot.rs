@@ -0,0 +1,67 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "1\n\\[2, 3\\]\n2\n3\n4\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{thread, time::Duration};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender = Sender::<usize>::new(parent);
+			for i in 1..4 {
+				sender.send(i).block();
+			}
+			let receiver = Receiver::<()>::new(parent);
+			receiver.recv().block().unwrap();
+			sender.send(4).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let receiver = Receiver::<usize>::new(pid);
+	println!("{}", receiver.recv().block().unwrap());
+	thread::sleep(Duration::from_millis(500));
+	let snapshot = receiver.snapshot();
+	println!("{:?}", snapshot);
+	drop(receiver);
+	let receiver = Receiver::from_snapshot(pid, snapshot);
+	Sender::<()>::new(pid).send(()).block();
+	for _ in 0..3 {
+		println!("{}", receiver.recv().block().unwrap());
+	}
+}
@@ -0,0 +1,51 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "true\ntrue\ntrue\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let stage_a = Resources::builder()
+		.mem(20 * Mem::MIB)
+		.cpu(Cpu::CORE / 4)
+		.build();
+	let stage_b = Resources::builder()
+		.mem(100 * Mem::MIB)
+		.cpu(Cpu::CORE)
+		.gpu(Gpu::DEVICE)
+		.build();
+	let total = Resources::builder()
+		.mem(120 * Mem::MIB)
+		.cpu(Cpu::CORE + Cpu::CORE / 4)
+		.gpu(Gpu::DEVICE)
+		.build();
+	println!("{}", stage_a + stage_b == total);
+	println!(
+		"{}",
+		stage_b * 3
+			== Resources::builder()
+				.mem(300 * Mem::MIB)
+				.cpu(3 * Cpu::CORE)
+				.gpu(3 * Gpu::DEVICE)
+				.build()
+	);
+	println!(
+		"{}",
+		vec![stage_a, stage_b].into_iter().sum::<Resources>() == total
+	);
+}
@@ -0,0 +1,40 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "true\ntrue\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources::builder().mem(20 * Mem::MIB).build());
+	println!(
+		"{}",
+		resources()
+			== Resources {
+				mem: 20 * Mem::MIB,
+				..Resources::default()
+			}
+	);
+	println!(
+		"{}",
+		Resources::builder()
+			.mem(20 * Mem::MIB)
+			.cpu(Cpu::CORE / 4)
+			.gpu(Gpu::DEVICE)
+			.build() == Resources {
+			mem: 20 * Mem::MIB,
+			cpu: Cpu::CORE / 4,
+			gpu: Gpu::DEVICE,
+		}
+	);
+}
@@ -5,7 +5,7 @@
 //=       true
 //=     ],
 //=     "1": [
-//=       "Resources \\{ mem: Mem\\(20971520\\), cpu: Cpu\\(4096\\) \\}\n",
+//=       "Resources \\{ mem: Mem\\(20971520\\), cpu: Cpu\\(4096\\), gpu: Gpu\\(0\\) \\}\n",
 //=       true
 //=     ]
 //=   },
@@ -17,7 +17,7 @@
 //=           true
 //=         ],
 //=         "1": [
-//=           "hi Resources \\{ mem: Mem\\(20971520\\), cpu: Cpu\\(65\\) \\}\n",
+//=           "hi Resources \\{ mem: Mem\\(20971520\\), cpu: Cpu\\(65\\), gpu: Gpu\\(0\\) \\}\n",
 //=           true
 //=         ]
 //=       },
@@ -27,7 +27,7 @@
 //=     {
 //=       "output": {
 //=         "1": [
-//=           "hi Resources \\{ mem: Mem\\(20971521\\), cpu: Cpu\\(65\\) \\}\n",
+//=           "hi Resources \\{ mem: Mem\\(20971521\\), cpu: Cpu\\(65\\), gpu: Gpu\\(0\\) \\}\n",
 //=           true
 //=         ],
 //=         "2": [
@@ -45,7 +45,7 @@
 //=           true
 //=         ],
 //=         "1": [
-//=           "hi Resources \\{ mem: Mem\\(20971522\\), cpu: Cpu\\(65\\) \\}\n",
+//=           "hi Resources \\{ mem: Mem\\(20971522\\), cpu: Cpu\\(65\\), gpu: Gpu\\(0\\) \\}\n",
 //=           true
 //=         ]
 //=       },
@@ -59,7 +59,7 @@
 //=           true
 //=         ],
 //=         "1": [
-//=           "hi Resources \\{ mem: Mem\\(20971523\\), cpu: Cpu\\(65\\) \\}\n",
+//=           "hi Resources \\{ mem: Mem\\(20971523\\), cpu: Cpu\\(65\\), gpu: Gpu\\(0\\) \\}\n",
 //=           true
 //=         ]
 //=       },
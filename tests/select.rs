@@ -0,0 +1,102 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "a 1\nb b\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+enum Event {
+	A(Result<u32, ChannelError>),
+	B(Result<String, ChannelError>),
+}
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let a = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			Sender::<u32>::new(parent).send(1).block();
+			Receiver::<()>::new(parent).recv().block().unwrap();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let b = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			Receiver::<()>::new(parent).recv().block().unwrap();
+			Sender::<String>::new(parent)
+				.send(String::from("b"))
+				.block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let (a_receiver, b_receiver) = (Receiver::<u32>::new(a), Receiver::<String>::new(b));
+	let (a_sender, b_sender) = (Sender::<()>::new(a), Sender::<()>::new(b));
+	for _ in 0..2 {
+		let event = Select::new()
+			.recv(&a_receiver, Event::A)
+			.recv(&b_receiver, Event::B)
+			.wait()
+			.block();
+		match event {
+			Event::A(x) => {
+				println!("a {}", x.unwrap());
+				b_sender.send(()).block();
+			}
+			Event::B(x) => {
+				println!("b {}", x.unwrap());
+				a_sender.send(()).block();
+			}
+		}
+	}
+}
@@ -0,0 +1,39 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "1\n2\n3\nErr\\(Exited\\)\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use futures::{sink::SinkExt, stream::StreamExt};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let (mut sender, receiver) = channel_local::<usize>();
+	futures::executor::block_on(async {
+		sender
+			.send_all(&mut futures::stream::iter(vec![1, 2, 3]).map(Ok))
+			.await
+			.unwrap();
+		sender.close().await.unwrap();
+		// Closing again is a no-op
+		sender.close().await.unwrap();
+	});
+	for _ in 0..3 {
+		println!("{}", receiver.recv().block().unwrap());
+	}
+	println!("{:?}", receiver.recv().block());
+	drop(sender);
+}
@@ -0,0 +1,36 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "1\n2\n3\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use futures::{sink::SinkExt, stream::StreamExt};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let (mut sender, receiver) = channel_local::<usize>();
+	futures::executor::block_on(async {
+		futures::stream::iter(vec![1, 2, 3])
+			.map(Ok)
+			.forward(&mut sender)
+			.await
+			.unwrap();
+		sender.close().await.unwrap();
+	});
+	for _ in 0..3 {
+		println!("{}", receiver.recv().block().unwrap());
+	}
+}
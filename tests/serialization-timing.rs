@@ -0,0 +1,59 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "true\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::time::Duration;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	set_serialization_timing(true);
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender = Sender::<Vec<u64>>::new(parent);
+			sender.send((0..100_000).collect()).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let receiver = Receiver::<Vec<u64>>::new(pid);
+	assert_eq!(receiver.recv().block().unwrap().len(), 100_000);
+	let stats = channel_stats()
+		.into_iter()
+		.find(|stats| stats.remote == pid)
+		.unwrap();
+	println!("{}", stats.deserialize > Duration::new(0, 0));
+}
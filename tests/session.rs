@@ -0,0 +1,59 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "1\n2\n3\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let mut responder = Responder::<u32, u32>::new(parent);
+			for _ in 0..3 {
+				let (request, responding) = responder.request().block().unwrap();
+				responder = responding.respond(request + 1).block();
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let mut requester = Requester::<u32, u32>::new(pid);
+	for i in 0..3 {
+		let (response, requester_) = requester.request(i).block().response().block().unwrap();
+		println!("{}", response);
+		requester = requester_;
+	}
+}
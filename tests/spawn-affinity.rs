@@ -0,0 +1,53 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "true\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let _pid = spawn_with(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		SpawnOptions {
+			affinity: Some(NodeSelector::SameAs(pid())),
+			..SpawnOptions::default()
+		},
+		FnOnce!(|parent: Pid| {
+			println!("{}", pid().addr().ip() == parent.addr().ip());
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+}
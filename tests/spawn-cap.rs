@@ -0,0 +1,54 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "hi\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let cap = spawn_with_cap(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent: ChannelCap<String>| {
+			let (sender, receiver) = parent.into_channel();
+			let hi = receiver.recv().block().unwrap();
+			sender.send(hi).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let (sender, receiver) = cap.into_channel();
+	sender.send(String::from("hi")).block();
+	println!("{}", receiver.recv().block().unwrap());
+}
@@ -0,0 +1,74 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "init\nchild\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [
+//=         {
+//=           "output": {
+//=             "2": [
+//=               "",
+//=               true
+//=             ],
+//=             "1": [
+//=               "init\ngrandchild\n",
+//=               true
+//=             ]
+//=           },
+//=           "children": [],
+//=           "exit": "Success"
+//=         }
+//=       ],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	set_child_init(Fn!(|| println!("init")));
+	let _pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|_parent| {
+			println!("child");
+			let _pid = spawn(
+				Resources {
+					mem: 20 * Mem::MIB,
+					..Resources::default()
+				},
+				FnOnce!(|_parent| println!("grandchild")),
+			)
+			.block()
+			.expect("spawn() failed to allocate process");
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+}
@@ -0,0 +1,68 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "/\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{env, path::PathBuf};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let _pid = spawn_with(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		SpawnOptions {
+			cwd: Some(PathBuf::from("/")),
+			..SpawnOptions::default()
+		},
+		FnOnce!(|_parent| {
+			println!("{}", env::current_dir().unwrap().display());
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let err = spawn_with(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		SpawnOptions {
+			cwd: Some(PathBuf::from("/nonexistent")),
+			..SpawnOptions::default()
+		},
+		FnOnce!(|_parent| unreachable!()),
+	)
+	.block()
+	.unwrap_err();
+	assert_eq!(err, SpawnError::CwdNotFound);
+}
@@ -0,0 +1,63 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "Some\\(\"2\"\\) None\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{env, ffi::OsString};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	env::set_var("SPAWN_ENV_INHERITED", "1");
+	let _pid = spawn_with(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		SpawnOptions {
+			env: vec![
+				(OsString::from("SHARD"), OsString::from("1")),
+				(OsString::from("SHARD"), OsString::from("2")),
+			],
+			env_remove: vec![OsString::from("SPAWN_ENV_INHERITED")],
+			..SpawnOptions::default()
+		},
+		FnOnce!(|_parent| {
+			println!(
+				"{:?} {:?}",
+				env::var("SHARD").ok(),
+				env::var("SPAWN_ENV_INHERITED").ok()
+			);
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+}
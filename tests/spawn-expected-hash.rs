@@ -0,0 +1,77 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "verified\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use sha2::{Digest, Sha256};
+use std::{env, fs};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let binary = fs::read("/proc/self/exe").unwrap();
+	let mut hash = [0; 32];
+	hash.copy_from_slice(&Sha256::digest(&binary));
+	let _pid = spawn_with(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		SpawnOptions {
+			expected_hash: Some(hash),
+			..SpawnOptions::default()
+		},
+		FnOnce!(|_parent| {
+			println!("verified");
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	// The hash is only checked when running on a fabric
+	if env::var("CONSTELLATION").as_ref().map(|x| &**x) == Ok("fabric") {
+		let mut wrong = hash;
+		wrong[0] ^= 1;
+		let err = spawn_with(
+			Resources {
+				mem: 20 * Mem::MIB,
+				..Resources::default()
+			},
+			SpawnOptions {
+				expected_hash: Some(wrong),
+				..SpawnOptions::default()
+			},
+			FnOnce!(|_parent| unreachable!()),
+		)
+		.block()
+		.unwrap_err();
+		assert_eq!(err, SpawnError::BinaryHashMismatch);
+	}
+}
@@ -0,0 +1,85 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [
+//=         {
+//=           "output": {
+//=             "2": [
+//=               "",
+//=               true
+//=             ],
+//=             "1": [
+//=               "",
+//=               true
+//=             ]
+//=           },
+//=           "children": [],
+//=           "exit": {
+//=             "Error": {
+//=               "Unix": {
+//=                 "Signal": "SIGKILL"
+//=               }
+//=             }
+//=           }
+//=         }
+//=       ],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{thread, time::Duration};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let _pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|_parent| {
+			let _pid = spawn_with(
+				Resources {
+					mem: 20 * Mem::MIB,
+					..Resources::default()
+				},
+				SpawnOptions {
+					on_parent_exit: OnParentExit::Kill,
+					..SpawnOptions::default()
+				},
+				FnOnce!(|_parent| loop {
+					thread::sleep(Duration::from_secs(1));
+				}),
+			)
+			.block()
+			.expect("spawn() failed to allocate process");
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+}
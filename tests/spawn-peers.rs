@@ -0,0 +1,82 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "hello\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let receiver_pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let sender_pid: Pid = Receiver::new(parent).recv().block().unwrap();
+			let receiver = Receiver::<String>::new(sender_pid);
+			println!("{}", receiver.recv().block().unwrap());
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender_pid = spawn_with(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		SpawnOptions {
+			peers: vec![receiver_pid],
+			..SpawnOptions::default()
+		},
+		FnOnce!(move |_parent| {
+			let sender = Sender::<String>::new(receiver_pid);
+			sender.send(String::from("hello")).block();
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	Sender::<Pid>::new(receiver_pid).send(sender_pid).block();
+}
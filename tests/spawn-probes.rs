@@ -0,0 +1,61 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "ready 0\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{
+	sync::atomic::{AtomicBool, Ordering}, thread, time::Duration
+};
+
+static READY: AtomicBool = AtomicBool::new(false);
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let probed = spawn_with_probes(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		Probe::new(Fn!(|| READY.load(Ordering::SeqCst)))
+			.period(Duration::from_millis(100))
+			.failure_threshold(100),
+		Probe::new(Fn!(|| true)),
+		FnOnce!(|_parent| {
+			thread::sleep(Duration::from_millis(500));
+			READY.store(true, Ordering::SeqCst);
+			thread::sleep(Duration::from_millis(500));
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	println!("ready {}", probed.restarts());
+}
@@ -0,0 +1,59 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "true\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::fs::File;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let _pid = spawn_with(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		SpawnOptions {
+			rlimits: RLimits {
+				open_files: Some(32),
+				..RLimits::default()
+			},
+			..SpawnOptions::default()
+		},
+		FnOnce!(|_parent| {
+			let files = (0..32).map(|_| File::open("/dev/null")).collect::<Vec<_>>();
+			let err = files.iter().find_map(|file| file.as_ref().err()).unwrap();
+			println!("{}", err.raw_os_error() == Some(nix::libc::EMFILE));
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+}
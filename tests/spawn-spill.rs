@@ -0,0 +1,54 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "16777216 16777215\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 100 * Mem::MIB,
+		..Resources::default()
+	});
+	let data: Vec<u32> = (0..16 * 1024 * 1024).collect();
+	let _pid = spawn_with(
+		Resources {
+			mem: 200 * Mem::MIB,
+			..Resources::default()
+		},
+		SpawnOptions {
+			spill_arg: true,
+			..SpawnOptions::default()
+		},
+		FnOnce!(move |_parent| {
+			println!("{} {}", data.len(), data.last().unwrap());
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+}
@@ -149,6 +149,12 @@ fn parse_output(output: &process::Output) -> Result<Output, Option<serde_json::E
 					output.1 = true;
 				}
 			}
+			constellation_internal::DeployOutputEvent::Started(a, _) => {
+				if top.is_none() {
+					top = Some(a);
+					let _ = log.insert(a, (HashMap::new(), Vec::new(), None));
+				}
+			}
 			constellation_internal::DeployOutputEvent::Spawn(a, b) => {
 				if top.is_none() {
 					top = Some(a);
@@ -158,6 +164,7 @@ fn parse_output(output: &process::Output) -> Result<Output, Option<serde_json::E
 				let x = log.insert(b, (HashMap::new(), Vec::new(), None));
 				assert!(x.is_none());
 			}
+			constellation_internal::DeployOutputEvent::Paused(..) => (),
 			constellation_internal::DeployOutputEvent::Exit(a, b) => {
 				if top.is_none() {
 					top = Some(a);
@@ -165,6 +172,7 @@ fn parse_output(output: &process::Output) -> Result<Output, Option<serde_json::E
 				}
 				log.get_mut(&a).unwrap().2 = Some(b);
 			}
+			constellation_internal::DeployOutputEvent::Summary(_) => (),
 		}
 	}
 	let top = top.unwrap();
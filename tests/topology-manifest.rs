@@ -0,0 +1,107 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "\\[\\(\"source\", 1\\), \\(\"worker\", 2\\)\\]\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "hello from source 0\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     },
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "hello from source 0\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let topology = Topology::from_toml(
+		r#"
+		[[role]]
+		name = "source"
+		mem = "20 MiB"
+
+		[[role]]
+		name = "worker"
+		count = 2
+		mem = "20 MiB"
+
+		[[channel]]
+		from = "source"
+		to = "worker"
+		"#,
+	)
+	.unwrap();
+	let pids = topology
+		.spawn(FnOnce!(|instance: Instance| match instance.role() {
+			"source" => {
+				for sender in instance.senders::<String>("worker") {
+					sender
+						.send(format!("hello from source {}", instance.index()))
+						.block();
+				}
+			}
+			"worker" => {
+				for receiver in instance.receivers::<String>("source") {
+					println!("{}", receiver.recv().block().unwrap());
+				}
+			}
+			_ => unreachable!(),
+		}))
+		.block()
+		.expect("spawn() failed to allocate process");
+	let mut roles = pids
+		.iter()
+		.map(|(role, pids)| (role.clone(), pids.len()))
+		.collect::<Vec<_>>();
+	roles.sort();
+	println!("{:?}", roles);
+}
@@ -0,0 +1,40 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "CONSTELLATION_RESOURCES must be JSON-serialized Resources\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{env, process};
+
+fn main() {
+	let resources = Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	};
+	if env::var_os("TRY_INIT_BAD_ENV").is_some() {
+		// Report rather than panic on the malformed env var
+		match try_init(resources) {
+			Ok(()) => println!("initialised"),
+			Err(err) => println!("{}", err),
+		}
+		process::exit(0);
+	}
+	init(resources);
+	let output = process::Command::new("/proc/self/exe")
+		.env("TRY_INIT_BAD_ENV", "1")
+		.env("CONSTELLATION_RESOURCES", "bogus")
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	print!("{}", String::from_utf8(output.stdout).unwrap());
+}
@@ -0,0 +1,28 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "initialised\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::process;
+
+fn main() {
+	if let Err(err) = try_init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	}) {
+		eprintln!("couldn't initialise: {}", err);
+		process::exit(2);
+	}
+	println!("initialised");
+}
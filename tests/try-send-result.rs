@@ -0,0 +1,80 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "Some\\(Ok\\(\\(\\)\\)\\) Ok\\(1\\)\nSome\\(Err\\(Exited\\)\\)\nOk\\(\\(\\)\\)\nErr\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": {
+//=         "Error": {
+//=           "Unix": {
+//=             "Signal": "SIGKILL"
+//=           }
+//=         }
+//=       }
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::{thread, time::Duration};
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let (sender, receiver) = channel_local::<usize>();
+	let sent = sender.try_send().map(|send| send(1));
+	println!("{:?} {:?}", sent, receiver.recv().block());
+	drop(receiver);
+	let sent = sender.try_send().map(|send| send(2));
+	println!("{:?}", sent);
+
+	// A network channel to a process that has exited. One that exits of its own accord waits for its channels to be closed, so it's killed instead.
+	let child = spawn_handle(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<usize>::new(parent);
+			let _ = receiver.recv().block();
+			loop {
+				thread::sleep(Duration::from_secs(60));
+			}
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	let sender = Sender::<usize>::new(child.pid());
+	sender.send(1).block();
+	println!("{:?}", kill(child.pid()).block());
+	let _ = child.join().block();
+	// Sends can go through until the connection notices the process has gone
+	loop {
+		match sender.try_send().map(|send| send(2)) {
+			Some(Err(_)) => break,
+			Some(Ok(())) | None => thread::sleep(Duration::from_millis(10)),
+		}
+	}
+	println!("Err");
+}
@@ -0,0 +1,29 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "true\n",
+//=       true
+//=     ]
+//=   },
+//=   "children": [],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	update_resources(Resources {
+		mem: 30 * Mem::MIB,
+		..Resources::default()
+	})
+	.unwrap();
+	println!("{}", resources().mem == 30 * Mem::MIB);
+}
@@ -0,0 +1,56 @@
+//= {
+//=   "output": {
+//=     "2": [
+//=       "",
+//=       true
+//=     ],
+//=     "1": [
+//=       "",
+//=       true
+//=     ]
+//=   },
+//=   "children": [
+//=     {
+//=       "output": {
+//=         "2": [
+//=           "",
+//=           true
+//=         ],
+//=         "1": [
+//=           "reachable\n",
+//=           true
+//=         ]
+//=       },
+//=       "children": [],
+//=       "exit": "Success"
+//=     }
+//=   ],
+//=   "exit": "Success"
+//= }
+
+use constellation::*;
+use std::time::Duration;
+
+fn main() {
+	init(Resources {
+		mem: 20 * Mem::MIB,
+		..Resources::default()
+	});
+	let pid = spawn(
+		Resources {
+			mem: 20 * Mem::MIB,
+			..Resources::default()
+		},
+		FnOnce!(|parent| {
+			let receiver = Receiver::<String>::new(parent);
+			println!("{}", receiver.recv().block().unwrap());
+		}),
+	)
+	.block()
+	.expect("spawn() failed to allocate process");
+	pid.wait_reachable(Duration::from_secs(60))
+		.block()
+		.expect("wait_reachable() timed out");
+	let sender = Sender::<String>::new(pid);
+	sender.send(String::from("reachable")).block();
+}